@@ -0,0 +1,141 @@
+//! Benchmarks for the redstone engine, run on the same headless plot as `mchprs bench`.
+//!
+//! Each circuit is benchmarked with both the base redstone implementation and redpiler.
+//! The CPU benchmark uses the schematic in the `MCHPRS_BENCH_SCHEMATIC` environment variable
+//! and is skipped if it isn't set.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use mchprs_blocks::blocks::{
+    Block, ComparatorMode, Lever, LeverFace, RedstoneComparator, RedstoneRepeater,
+};
+use mchprs_blocks::{BlockDirection, BlockPos};
+use mchprs_core::bench::HeadlessPlot;
+use mchprs_redpiler::CompilerOptions;
+use mchprs_redstone::wire::make_cross;
+use mchprs_world::World;
+
+/// Place a block with a block of sandstone below it
+fn place_on_block(plot: &mut HeadlessPlot, pos: BlockPos, block: Block) {
+    let world = plot.world_mut();
+    world.set_block(pos - BlockPos::new(0, 1, 0), Block::Sandstone {});
+    world.set_block(pos, block);
+}
+
+/// Creates a lever at 0,1,0 and returns its position
+fn make_lever(plot: &mut HeadlessPlot) -> BlockPos {
+    let pos = BlockPos::new(0, 1, 0);
+    let lever = Lever {
+        face: LeverFace::Floor,
+        ..Default::default()
+    };
+    place_on_block(plot, pos, Block::Lever { lever });
+    pos
+}
+
+/// A lever powering 15 wires
+fn wire_line(plot: &mut HeadlessPlot) -> BlockPos {
+    for x in 1..=15 {
+        let wire = make_cross(0);
+        place_on_block(plot, BlockPos::new(x, 1, 0), Block::RedstoneWire { wire });
+    }
+    make_lever(plot)
+}
+
+/// A lever powering 100 repeaters in a line
+fn repeater_line(plot: &mut HeadlessPlot) -> BlockPos {
+    for x in 1..=100 {
+        let repeater = RedstoneRepeater {
+            delay: 1,
+            facing: BlockDirection::West,
+            ..Default::default()
+        };
+        place_on_block(
+            plot,
+            BlockPos::new(x, 1, 0),
+            Block::RedstoneRepeater { repeater },
+        );
+    }
+    make_lever(plot)
+}
+
+/// A lever powering 100 comparators in a line
+fn comparator_chain(plot: &mut HeadlessPlot) -> BlockPos {
+    for x in 1..=100 {
+        let comparator = RedstoneComparator {
+            facing: BlockDirection::West,
+            mode: ComparatorMode::Compare,
+            ..Default::default()
+        };
+        place_on_block(
+            plot,
+            BlockPos::new(x, 1, 0),
+            Block::RedstoneComparator { comparator },
+        );
+    }
+    make_lever(plot)
+}
+
+/// Benchmarks toggling the lever of a circuit and ticking until the signal has propagated
+fn bench_circuit(
+    c: &mut Criterion,
+    name: &str,
+    build: fn(&mut HeadlessPlot) -> BlockPos,
+    ticks: u64,
+) {
+    let mut group = c.benchmark_group(name);
+    for redpiler in [false, true] {
+        let mut plot = HeadlessPlot::empty();
+        let lever = build(&mut plot);
+        if redpiler {
+            plot.start_redpiler(CompilerOptions::default());
+        }
+        let id = if redpiler { "redpiler" } else { "redstone" };
+        group.bench_function(id, |b| {
+            b.iter(|| {
+                plot.use_block(lever);
+                plot.tickn(ticks);
+                plot.flush();
+            })
+        });
+    }
+    group.finish();
+}
+
+fn wire_propagation(c: &mut Criterion) {
+    bench_circuit(c, "wire_propagation", wire_line, 1);
+}
+
+fn repeater_lines(c: &mut Criterion) {
+    bench_circuit(c, "repeater_line", repeater_line, 110);
+}
+
+fn comparator_chains(c: &mut Criterion) {
+    bench_circuit(c, "comparator_chain", comparator_chain, 110);
+}
+
+fn cpu_schematic(c: &mut Criterion) {
+    let Ok(path) = std::env::var("MCHPRS_BENCH_SCHEMATIC") else {
+        eprintln!("MCHPRS_BENCH_SCHEMATIC is not set, skipping the CPU benchmark");
+        return;
+    };
+    let mut plot = HeadlessPlot::from_schematic(&path).expect("failed to load schematic");
+    plot.start_redpiler(CompilerOptions {
+        optimize: true,
+        ..Default::default()
+    });
+    c.bench_function("cpu_schematic", |b| {
+        b.iter(|| {
+            plot.tickn(1000);
+            plot.flush();
+        })
+    });
+}
+
+criterion_group!(
+    benches,
+    wire_propagation,
+    repeater_lines,
+    comparator_chains,
+    cpu_schematic
+);
+criterion_main!(benches);
@@ -2,6 +2,7 @@ use crate::items::Item;
 use mchprs_utils::{map, nbt_unwrap_val};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::io::Cursor;
 use std::str::FromStr;
 
 /// A single item in an inventory
@@ -13,17 +14,30 @@ pub struct InventoryEntry {
     pub nbt: Option<Vec<u8>>,
 }
 
-#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+/// Every row is a JSON text component
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SignBlockEntity {
     pub front_rows: [String; 4],
     pub back_rows: [String; 4],
 }
 
+impl Default for SignBlockEntity {
+    fn default() -> Self {
+        // The client can't parse an empty row, so it has to be an empty JSON string
+        let empty = || [(); 4].map(|_| "\"\"".to_string());
+        SignBlockEntity {
+            front_rows: empty(),
+            back_rows: empty(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
 pub enum ContainerType {
     Furnace,
     Barrel,
     Hopper,
+    TrappedChest,
 }
 
 impl FromStr for ContainerType {
@@ -34,6 +48,7 @@ impl FromStr for ContainerType {
             "barrel" => ContainerType::Barrel,
             "furnace" => ContainerType::Furnace,
             "hopper" => ContainerType::Hopper,
+            "trapped_chest" => ContainerType::TrappedChest,
             _ => return Err(()),
         })
     }
@@ -45,6 +60,7 @@ impl std::fmt::Display for ContainerType {
             ContainerType::Furnace => "minecraft:furnace",
             ContainerType::Barrel => "minecraft:barrel",
             ContainerType::Hopper => "minecraft:hopper",
+            ContainerType::TrappedChest => "minecraft:trapped_chest",
         })
     }
 }
@@ -55,6 +71,7 @@ impl ContainerType {
             ContainerType::Furnace => 3,
             ContainerType::Barrel => 27,
             ContainerType::Hopper => 5,
+            ContainerType::TrappedChest => 27,
         }
     }
 
@@ -64,6 +81,7 @@ impl ContainerType {
             ContainerType::Furnace => 14,
             ContainerType::Barrel => 2,
             ContainerType::Hopper => 16,
+            ContainerType::TrappedChest => 2,
         }
     }
 }
@@ -79,6 +97,12 @@ pub enum BlockEntity {
         ty: ContainerType,
     },
     Sign(Box<SignBlockEntity>),
+    Lectern {
+        comparator_override: u8,
+        book: InventoryEntry,
+        page: i32,
+        pages: i32,
+    },
 }
 
 impl BlockEntity {
@@ -90,64 +114,139 @@ impl BlockEntity {
                 ContainerType::Furnace => 0,
                 ContainerType::Barrel => 26,
                 ContainerType::Hopper => 17,
+                ContainerType::TrappedChest => 2,
             },
             BlockEntity::Sign(_) => 7,
+            BlockEntity::Lectern { .. } => 29,
+        }
+    }
+
+    /// Creates a container, with the signal strength comparators read from it
+    pub fn container(inventory: Vec<InventoryEntry>, ty: ContainerType) -> BlockEntity {
+        let fullness_sum: f32 = inventory
+            .iter()
+            .map(|entry| entry.count as f32 / Item::from_id(entry.id).max_stack_size() as f32)
+            .sum();
+        BlockEntity::Container {
+            comparator_override: (if fullness_sum > 0.0 { 1.0 } else { 0.0 }
+                + (fullness_sum / ty.num_slots() as f32) * 14.0)
+                .floor() as u8,
+            inventory,
+            ty,
+        }
+    }
+
+    /// Creates a container holding as much redstone dust as it can while comparators still read
+    /// `signal_strength` from it
+    pub fn container_with_signal(signal_strength: u8, ty: ContainerType) -> BlockEntity {
+        let slots = ty.num_slots() as u32;
+        let items_needed = match signal_strength {
+            0 => 0,
+            15 => slots * 64,
+            ss => ((32 * slots * ss as u32) as f32 / 7.0 - 1.0).ceil() as u32,
+        } as usize;
+        let mut inventory = Vec::new();
+        for (slot, items_added) in (0..items_needed).step_by(64).enumerate() {
+            let count = (items_needed - items_added).min(64);
+            inventory.push(InventoryEntry {
+                id: Item::Redstone {}.get_id(),
+                slot: slot as i8,
+                count: count as i8,
+                nbt: None,
+            });
         }
+        BlockEntity::Container {
+            comparator_override: signal_strength,
+            inventory,
+            ty,
+        }
+    }
+
+    /// Creates a lectern holding `book` open at `page`. Comparators read a signal from 1 to 15
+    /// depending on how far through the book the page is.
+    pub fn lectern(book: InventoryEntry, page: i32) -> BlockEntity {
+        let pages = book
+            .nbt
+            .as_ref()
+            .and_then(|tag| nbt::Blob::from_reader(&mut Cursor::new(tag)).ok())
+            .and_then(|tag| match tag.get("pages") {
+                Some(nbt::Value::List(pages)) => Some(pages.len() as i32),
+                _ => None,
+            })
+            .unwrap_or(0)
+            .max(1);
+        let page = page.clamp(0, pages - 1);
+        let progress = if pages > 1 {
+            page as f32 / (pages - 1) as f32
+        } else {
+            1.0
+        };
+        BlockEntity::Lectern {
+            comparator_override: (progress * 14.0).floor() as u8 + 1,
+            book,
+            page,
+            pages,
+        }
+    }
+
+    /// Reads an item saved the way vanilla saves them in containers and player inventories
+    pub fn load_item(
+        item_compound: &HashMap<String, nbt::Value>,
+        slot: i8,
+    ) -> Option<InventoryEntry> {
+        use nbt::Value;
+        let count = *nbt_unwrap_val!(item_compound.get("Count")?, Value::Byte);
+        let namespaced_name = nbt_unwrap_val!(
+            item_compound
+                .get("Id")
+                .or_else(|| item_compound.get("id"))?,
+            Value::String
+        );
+        let item_type = Item::from_name(namespaced_name.split(':').next_back()?);
+
+        let tag = match item_compound.get("tag") {
+            Some(nbt::Value::Compound(map)) => {
+                let mut blob = nbt::Blob::new();
+                for (k, v) in map {
+                    blob.insert(k, v.clone()).unwrap();
+                }
+
+                let mut data = Vec::new();
+                blob.to_writer(&mut data).unwrap();
+                Some(data)
+            }
+            _ => None,
+        };
+        Some(InventoryEntry {
+            slot,
+            count,
+            id: item_type.unwrap_or(Item::Redstone {}).get_id(),
+            nbt: tag,
+        })
     }
 
     fn load_container(slots_nbt: &[nbt::Value], ty: ContainerType) -> Option<BlockEntity> {
         use nbt::Value;
-        let num_slots = ty.num_slots();
-        let mut fullness_sum: f32 = 0.0;
         let mut inventory = Vec::new();
         for item in slots_nbt {
             let item_compound = nbt_unwrap_val!(item, Value::Compound);
-            let count = nbt_unwrap_val!(item_compound["Count"], Value::Byte);
             let slot = nbt_unwrap_val!(item_compound["Slot"], Value::Byte);
-            let namespaced_name = nbt_unwrap_val!(
-                item_compound
-                    .get("Id")
-                    .or_else(|| item_compound.get("id"))?,
-                Value::String
-            );
-            let item_type = Item::from_name(namespaced_name.split(':').next_back()?);
+            inventory.push(BlockEntity::load_item(item_compound, slot)?);
+        }
+        Some(BlockEntity::container(inventory, ty))
+    }
 
-            let mut blob = nbt::Blob::new();
-            for (k, v) in item_compound {
-                blob.insert(k, v.clone()).unwrap();
+    fn item_nbt(entry: &InventoryEntry) -> HashMap<String, nbt::Value> {
+        let mut nbt = map! {
+            "Count" => nbt::Value::Byte(entry.count),
+            "id" => nbt::Value::String("minecraft:".to_string() + Item::from_id(entry.id).get_name())
+        };
+        if let Some(tag) = &entry.nbt {
+            if let Ok(blob) = nbt::Blob::from_reader(&mut Cursor::new(tag)) {
+                nbt.insert("tag".to_owned(), nbt::Value::Compound(blob.content));
             }
-            let mut data = Vec::new();
-            blob.to_writer(&mut data).unwrap();
-
-            let tag = match item_compound.get("tag") {
-                Some(nbt::Value::Compound(map)) => {
-                    let mut blob = nbt::Blob::new();
-                    for (k, v) in map {
-                        blob.insert(k, v.clone()).unwrap();
-                    }
-
-                    let mut data = Vec::new();
-                    blob.to_writer(&mut data).unwrap();
-                    Some(data)
-                }
-                _ => None,
-            };
-            inventory.push(InventoryEntry {
-                slot,
-                count,
-                id: item_type.unwrap_or(Item::Redstone {}).get_id(),
-                nbt: tag,
-            });
-
-            fullness_sum += count as f32 / item_type.map_or(64, Item::max_stack_size) as f32;
         }
-        Some(BlockEntity::Container {
-            comparator_override: (if fullness_sum > 0.0 { 1.0 } else { 0.0 }
-                + (fullness_sum / num_slots as f32) * 14.0)
-                .floor() as u8,
-            inventory,
-            ty,
-        })
+        nbt
     }
 
     pub fn from_nbt(id: &str, nbt: &HashMap<String, nbt::Value>) -> Option<BlockEntity> {
@@ -168,6 +267,10 @@ impl BlockEntity {
                 nbt_unwrap_val!(&nbt["Items"], Value::List),
                 ContainerType::Hopper,
             ),
+            "trapped_chest" => BlockEntity::load_container(
+                nbt_unwrap_val!(&nbt["Items"], Value::List),
+                ContainerType::TrappedChest,
+            ),
             "sign" => {
                 let sign = if nbt.contains_key("Text1") {
                     // This is the pre-1.20 encoding
@@ -179,7 +282,7 @@ impl BlockEntity {
                             nbt_unwrap_val!(nbt["Text3"].clone(), Value::String),
                             nbt_unwrap_val!(nbt["Text4"].clone(), Value::String),
                         ],
-                        back_rows: Default::default(),
+                        back_rows: SignBlockEntity::default().back_rows,
                     }
                 } else {
                     let get_side = |side| {
@@ -200,6 +303,15 @@ impl BlockEntity {
                 };
                 Some(BlockEntity::Sign(Box::new(sign)))
             }
+            "lectern" => {
+                // Lecterns without a book don't need a block entity
+                let book = nbt_unwrap_val!(nbt.get("Book")?, Value::Compound);
+                let page = match nbt.get("Page") {
+                    Some(Value::Int(page)) => *page,
+                    _ => 0,
+                };
+                Some(BlockEntity::lectern(BlockEntity::load_item(book, 0)?, page))
+            }
             _ => None,
         }
     }
@@ -213,7 +325,7 @@ impl BlockEntity {
         match self {
             BlockEntity::Sign(sign) => Some({
                 let front = sign.front_rows.iter().map(|str| Value::String(str.clone()));
-                let back = sign.front_rows.iter().map(|str| Value::String(str.clone()));
+                let back = sign.back_rows.iter().map(|str| Value::String(str.clone()));
                 nbt::Blob::with_content(map! {
                     "is_waxed" => Value::Byte(0),
                     "front_text" => Value::Compound(map! {
@@ -238,15 +350,8 @@ impl BlockEntity {
             BlockEntity::Container { inventory, ty, .. } => Some({
                 let mut items = Vec::new();
                 for entry in inventory {
-                    let nbt = map! {
-                        "Count" => nbt::Value::Byte(entry.count),
-                        "id" => nbt::Value::String("minecraft:".to_string() + Item::from_id(entry.id).get_name()),
-                        "Slot" => nbt::Value::Byte(entry.slot)
-                    };
-                    // TODO: item nbt data in containers
-                    // if let Some(tag) = &entry.nbt {
-                    //     let blob = nbt::Blob::from_reader(&mut Cursor::new(tag)).unwrap();
-                    // }
+                    let mut nbt = BlockEntity::item_nbt(entry);
+                    nbt.insert("Slot".to_owned(), Value::Byte(entry.slot));
                     items.push(nbt::Value::Compound(nbt));
                 }
                 nbt::Blob::with_content(map! {
@@ -254,6 +359,135 @@ impl BlockEntity {
                     "Items" => Value::List(items)
                 })
             }),
+            BlockEntity::Lectern { book, page, .. } => Some({
+                nbt::Blob::with_content(map! {
+                    "Book" => Value::Compound(BlockEntity::item_nbt(book)),
+                    "Page" => Value::Int(*page),
+                    "id" => Value::String("minecraft:lectern".to_owned())
+                })
+            }),
         }
     }
 }
+
+#[test]
+fn sign_nbt_round_trip() {
+    let sign = SignBlockEntity {
+        front_rows: ["a", "b", "c", "d"].map(|s| s.to_string()),
+        back_rows: ["e", "f", "g", "h"].map(|s| s.to_string()),
+    };
+    let nbt = BlockEntity::Sign(Box::new(sign.clone()))
+        .to_nbt(false)
+        .unwrap();
+    match BlockEntity::from_nbt("minecraft:sign", &nbt.content) {
+        Some(BlockEntity::Sign(loaded)) => {
+            assert_eq!(loaded.front_rows, sign.front_rows);
+            assert_eq!(loaded.back_rows, sign.back_rows);
+        }
+        _ => panic!("sign did not load back"),
+    }
+}
+
+#[test]
+fn container_item_tag_round_trip() {
+    let mut tag = nbt::Blob::new();
+    tag.insert("CustomModelData", nbt::Value::Int(7)).unwrap();
+    let mut tag_data = Vec::new();
+    tag.to_writer(&mut tag_data).unwrap();
+
+    let container = BlockEntity::Container {
+        comparator_override: 1,
+        inventory: vec![InventoryEntry {
+            id: Item::Redstone {}.get_id(),
+            slot: 0,
+            count: 1,
+            nbt: Some(tag_data),
+        }],
+        ty: ContainerType::Barrel,
+    };
+    let nbt = container.to_nbt(false).unwrap();
+    match BlockEntity::from_nbt("minecraft:barrel", &nbt.content) {
+        Some(BlockEntity::Container { inventory, .. }) => {
+            let loaded =
+                nbt::Blob::from_reader(&mut Cursor::new(inventory[0].nbt.as_ref().unwrap()))
+                    .unwrap();
+            assert_eq!(loaded["CustomModelData"], nbt::Value::Int(7));
+        }
+        _ => panic!("container did not load back"),
+    }
+}
+
+#[test]
+fn container_comparator_override() {
+    let redstone = |slot, count| InventoryEntry {
+        id: Item::Redstone {}.get_id(),
+        slot,
+        count,
+        nbt: None,
+    };
+    let strength = |inventory| match BlockEntity::container(inventory, ContainerType::Barrel) {
+        BlockEntity::Container {
+            comparator_override,
+            ..
+        } => comparator_override,
+        _ => unreachable!(),
+    };
+    assert_eq!(strength(vec![]), 0);
+    assert_eq!(strength(vec![redstone(0, 1)]), 1);
+    assert_eq!(
+        strength((0..27).map(|slot| redstone(slot, 64)).collect()),
+        15
+    );
+}
+
+#[test]
+fn container_with_signal() {
+    use ContainerType::*;
+    for ty in [Furnace, Barrel, Hopper, TrappedChest] {
+        for ss in 0..=15 {
+            let BlockEntity::Container { inventory, .. } =
+                BlockEntity::container_with_signal(ss, ty)
+            else {
+                unreachable!()
+            };
+            // Reading the items gives the same signal strength
+            match BlockEntity::container(inventory, ty) {
+                BlockEntity::Container {
+                    comparator_override,
+                    ..
+                } => assert_eq!(comparator_override, ss, "{:?} with {}", ty, ss),
+                _ => unreachable!(),
+            }
+        }
+    }
+}
+
+#[test]
+fn lectern_comparator_override() {
+    let book = |pages: usize| {
+        let mut tag = nbt::Blob::new();
+        let pages = vec![nbt::Value::String("\"\"".to_string()); pages];
+        tag.insert("pages", nbt::Value::List(pages)).unwrap();
+        let mut data = Vec::new();
+        tag.to_writer(&mut data).unwrap();
+        InventoryEntry {
+            id: Item::WritableBook {}.get_id(),
+            slot: 0,
+            count: 1,
+            nbt: Some(data),
+        }
+    };
+    let strength = |book, page| match BlockEntity::lectern(book, page) {
+        BlockEntity::Lectern {
+            comparator_override,
+            ..
+        } => comparator_override,
+        _ => unreachable!(),
+    };
+    assert_eq!(strength(book(1), 0), 15);
+    assert_eq!(strength(book(15), 0), 1);
+    assert_eq!(strength(book(15), 7), 8);
+    assert_eq!(strength(book(15), 14), 15);
+    // Pages past the end of the book are clamped to the last page
+    assert_eq!(strength(book(3), 10), 15);
+}
@@ -98,6 +98,8 @@ impl Block {
                 | Block::Barrel { .. }
                 | Block::Furnace { .. }
                 | Block::Hopper { .. }
+                | Block::TrappedChest { .. }
+                | Block::Lectern { .. }
                 | Block::Sign { .. }
                 | Block::WallSign { .. }
         )
@@ -885,6 +887,89 @@ blocks! {
         solid: true,
         cube: true,
     },
+    TrappedChest {
+        props: {
+            facing: BlockDirection
+        },
+        get_id: facing.get_id() * 6 + 9120,
+        from_id_offset: 9119,
+        from_id(id): 9119..=9142 => {
+            facing: BlockDirection::from_id(id / 6)
+        },
+        from_names(_name): {
+            "trapped_chest" => {
+                facing: Default::default()
+            }
+        },
+        get_name: "trapped_chest",
+    },
+    SlimeBlock {
+        props: {},
+        get_id: 10364,
+        from_id(_id): 10364 => {},
+        from_names(_name): {
+            "slime_block" => {}
+        },
+        get_name: "slime_block",
+        solid: true,
+        cube: true,
+    },
+    HoneyBlock {
+        props: {},
+        get_id: 19445,
+        from_id(_id): 19445 => {},
+        from_names(_name): {
+            "honey_block" => {}
+        },
+        get_name: "honey_block",
+        transparent: true,
+    },
+    DaylightDetector {
+        props: {
+            inverted: bool,
+            power: u8
+        },
+        get_id: ((!inverted as u32) << 4) + power as u32 + 9191,
+        from_id_offset: 9191,
+        from_id(id): 9191..=9222 => {
+            inverted: (id >> 4) == 0,
+            power: (id & 0xF) as u8
+        },
+        from_names(_name): {
+            "daylight_detector" => {
+                inverted: false,
+                power: 0
+            }
+        },
+        get_name: "daylight_detector",
+    },
+    Lectern {
+        props: {
+            facing: BlockDirection,
+            has_book: bool,
+            powered: bool
+        },
+        get_id: {
+            (facing.get_id() << 2)
+                + ((!has_book as u32) << 1)
+                + !powered as u32
+                + 18450
+        },
+        from_id_offset: 18450,
+        from_id(id): 18450..=18465 => {
+            facing: BlockDirection::from_id(id >> 2),
+            has_book: (id & 0b10) == 0,
+            powered: (id & 1) == 0
+        },
+        from_names(_name): {
+            "lectern" => {
+                facing: Default::default(),
+                has_book: false,
+                powered: false
+            }
+        },
+        get_name: "lectern",
+    },
     Quartz {
         props: {},
         get_id: 9235,
@@ -469,6 +469,8 @@ impl Instrument {
             Block::Sign { .. }
             | Block::NoteBlock { .. }
             | Block::Barrel {}
+            | Block::TrappedChest { .. }
+            | Block::Lectern { .. }
             | Block::Composter { .. } => Instrument::Bass,
             Block::Clay {} => Instrument::Flute,
             Block::GoldBlock {} => Instrument::Bell,
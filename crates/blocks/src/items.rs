@@ -1,4 +1,5 @@
 use crate::block_entities::ContainerType;
+use crate::blocks::Block;
 use crate::BlockColorVariant;
 use mchprs_utils::map;
 
@@ -16,6 +17,7 @@ impl ItemStack {
             ContainerType::Barrel => Item::Barrel {},
             ContainerType::Hopper => Item::Hopper {},
             ContainerType::Furnace => Item::Furnace {},
+            ContainerType::TrappedChest => Item::TrappedChest {},
         };
         let slots = container_ty.num_slots() as u32;
 
@@ -329,6 +331,60 @@ items! {
         },
         block: true,
     },
+    TrappedChest {
+        props: {},
+        get_id: 677,
+        from_id(_id): 677 => {},
+        block: true,
+    },
+    Lectern {
+        props: {},
+        get_id: 669,
+        from_id(_id): 669 => {},
+        block: true,
+    },
+    SlimeBlock {
+        props: {},
+        get_id: 663,
+        from_id(_id): 663 => {},
+        block: true,
+    },
+    HoneyBlock {
+        props: {},
+        get_id: 664,
+        from_id(_id): 664 => {},
+        block: true,
+    },
+    DaylightDetector {
+        props: {},
+        get_id: 673,
+        from_id(_id): 673 => {},
+        block: true,
+    },
+    WritableBook {
+        props: {},
+        get_id: 1085,
+        from_id(_id): 1085 => {},
+        max_stack: 1,
+    },
+    WrittenBook {
+        props: {},
+        get_id: 1086,
+        from_id(_id): 1086 => {},
+        max_stack: 16,
+    },
+    Cauldron {
+        props: {},
+        get_id: 1002,
+        from_id(_id): 1002 => {},
+        block: true,
+    },
+    Composter {
+        props: {},
+        get_id: 1192,
+        from_id(_id): 1192 => {},
+        block: true,
+    },
     Barrel {
         props: {},
         get_id: 1193,
@@ -442,6 +498,27 @@ items! {
         get_id: 1003,
         from_id(_id): 1003 => {},
     },
+    Compass {
+        props: {},
+        get_id: 925,
+        from_id(_id): 925 => {},
+    },
+    ItemFrame {
+        props: {},
+        get_id: 1087,
+        from_id(_id): 1087 => {},
+    },
+    ArmorStand {
+        props: {},
+        get_id: 1116,
+        from_id(_id): 1116 => {},
+        max_stack: 16,
+    },
+    NameTag {
+        props: {},
+        get_id: 1122,
+        from_id(_id): 1122 => {},
+    },
     Unknown {
         props: {
             id: u32
@@ -457,6 +534,8 @@ impl Item {
             "snowball" => Some(Item::Snowball {}),
             "totem_of_undying" => Some(Item::TotemOfUndying {}),
             "milk_bucket" => Some(Item::MilkBucket {}),
+            "writable_book" => Some(Item::WritableBook {}),
+            "written_book" => Some(Item::WrittenBook {}),
             // Convert some common types of items to fix signal strength of containers
             "redstone" => Some(Item::Redstone {}),
             "stick" => Some(Item::Redstone {}),
@@ -470,7 +549,87 @@ impl Item {
             Item::Snowball {} => "snowball",
             Item::TotemOfUndying {} => "totem_of_undying",
             Item::MilkBucket {} => "milk_bucket",
+            Item::WritableBook {} => "writable_book",
+            Item::WrittenBook {} => "written_book",
             _ => "redstone",
         }
     }
+
+    /// Looks up an item by its name without the `minecraft:` namespace, as typed in `/give`.
+    /// Unlike [`Item::from_name`], this does not substitute items and only finds items that
+    /// the server knows about.
+    pub fn from_registry_name(name: &str) -> Option<Item> {
+        match name {
+            "wooden_axe" => Some(Item::WEWand {}),
+            "snowball" => Some(Item::Snowball {}),
+            "totem_of_undying" => Some(Item::TotemOfUndying {}),
+            "milk_bucket" => Some(Item::MilkBucket {}),
+            "ender_eye" => Some(Item::EnderEye {}),
+            "compass" => Some(Item::Compass {}),
+            "item_frame" => Some(Item::ItemFrame {}),
+            "armor_stand" => Some(Item::ArmorStand {}),
+            "name_tag" => Some(Item::NameTag {}),
+            "writable_book" => Some(Item::WritableBook {}),
+            "written_book" => Some(Item::WrittenBook {}),
+            "redstone" => Some(Item::Redstone {}),
+            _ => Block::from_name(name).and_then(Item::from_block),
+        }
+    }
+
+    /// Returns the item that places `block`, if there is one
+    pub fn from_block(block: Block) -> Option<Item> {
+        Some(match block {
+            Block::Stone {} => Item::Stone {},
+            Block::Glass {} => Item::Glass {},
+            Block::Glowstone {} => Item::Glowstone {},
+            Block::Sandstone {} => Item::Sandstone {},
+            Block::Sand {} => Item::Sand {},
+            Block::StoneBricks {} => Item::StoneBricks {},
+            Block::Clay {} => Item::Clay {},
+            Block::GoldBlock {} => Item::GoldBlock {},
+            Block::PackedIce {} => Item::PackedIce {},
+            Block::BoneBlock { .. } => Item::BoneBlock {},
+            Block::IronBlock {} => Item::IronBlock {},
+            Block::SoulSand {} => Item::SoulSand {},
+            Block::Pumpkin {} => Item::Pumpkin {},
+            Block::EmeraldBlock {} => Item::EmeraldBlock {},
+            Block::HayBlock { .. } => Item::HayBlock {},
+            Block::Terracotta {} => Item::Terracotta {},
+            Block::ColoredTerracotta { color } => Item::ColoredTerracotta { color },
+            Block::Concrete { color } => Item::Concrete { color },
+            Block::StainedGlass { color } => Item::StainedGlass { color },
+            Block::Wool { color } => Item::Wool { color },
+            Block::SmoothStoneSlab {} => Item::SmoothStoneSlab {},
+            Block::QuartzSlab {} => Item::QuartzSlab {},
+            Block::SeaPickle { .. } => Item::SeaPickle {},
+            Block::RedstoneWire { .. } => Item::Redstone {},
+            Block::RedstoneTorch { .. } | Block::RedstoneWallTorch { .. } => Item::RedstoneTorch {},
+            Block::RedstoneRepeater { .. } => Item::Repeater {},
+            Block::RedstoneComparator { .. } => Item::Comparator {},
+            Block::RedstoneBlock {} => Item::RedstoneBlock {},
+            Block::RedstoneLamp { .. } => Item::RedstoneLamp {},
+            Block::Lever { .. } => Item::Lever {},
+            Block::StoneButton { .. } => Item::StoneButton {},
+            Block::StonePressurePlate { .. } => Item::StonePressurePlate {},
+            Block::TripwireHook { .. } => Item::TripwireHook {},
+            Block::Target {} => Item::Target {},
+            Block::IronTrapdoor { .. } => Item::IronTrapdoor {},
+            Block::NoteBlock { .. } => Item::NoteBlock {},
+            Block::Barrel {} => Item::Barrel {},
+            Block::Furnace {} => Item::Furnace {},
+            Block::Hopper {} => Item::Hopper {},
+            Block::TrappedChest { .. } => Item::TrappedChest {},
+            Block::Lectern { .. } => Item::Lectern {},
+            Block::DaylightDetector { .. } => Item::DaylightDetector {},
+            Block::SlimeBlock {} => Item::SlimeBlock {},
+            Block::Cauldron { .. } => Item::Cauldron {},
+            Block::Composter { .. } => Item::Composter {},
+            Block::HoneyBlock {} => Item::HoneyBlock {},
+            Block::EndPortalFrame { .. } => Item::EndPortalFrame {},
+            Block::Sign { sign_type, .. } | Block::WallSign { sign_type, .. } => Item::Sign {
+                sign_type: sign_type.item_type(),
+            },
+            _ => return None,
+        })
+    }
 }
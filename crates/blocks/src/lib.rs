@@ -51,6 +51,27 @@ impl BlockPos {
     }
 }
 
+/// Parses coordinates in the form `x,y,z`
+impl FromStr for BlockPos {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let coords: Vec<&str> = s.split(',').collect();
+        let [x, y, z] = coords[..] else {
+            return Err(format!(
+                "expected coordinates in the form x,y,z but got `{}`",
+                s
+            ));
+        };
+        let parse = |c: &str| {
+            c.trim()
+                .parse::<i32>()
+                .map_err(|_| format!("invalid coordinate `{}`", c))
+        };
+        Ok(BlockPos::new(parse(x)?, parse(y)?, parse(z)?))
+    }
+}
+
 impl std::ops::Sub for BlockPos {
     type Output = BlockPos;
 
@@ -115,7 +136,7 @@ where
     }
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum BlockFace {
     Bottom,
     Top,
@@ -150,6 +171,18 @@ impl BlockFace {
         matches!(self, North | South | East | West)
     }
 
+    pub fn opposite(self) -> BlockFace {
+        use BlockFace::*;
+        match self {
+            Bottom => Top,
+            Top => Bottom,
+            North => South,
+            South => North,
+            West => East,
+            East => West,
+        }
+    }
+
     pub fn unwrap_direction(self) -> BlockDirection {
         match self {
             BlockFace::North => BlockDirection::North,
@@ -366,6 +399,18 @@ impl BlockFacing {
         }
     }
 
+    pub fn opposite(self) -> BlockFacing {
+        use BlockFacing::*;
+        match self {
+            North => South,
+            South => North,
+            East => West,
+            West => East,
+            Up => Down,
+            Down => Up,
+        }
+    }
+
     pub fn offset_pos(self, mut pos: BlockPos, n: i32) -> BlockPos {
         match self {
             BlockFacing::North => pos.z -= n,
@@ -447,6 +492,11 @@ impl SignType {
             _ => sign_type,
         })
     }
+
+    /// The inverse of [`SignType::from_item_type`], the ordering only differs for jungle and acacia
+    pub fn item_type(self) -> u32 {
+        SignType::from_item_type(self.0).0
+    }
 }
 
 impl BlockProperty for SignType {
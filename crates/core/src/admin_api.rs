@@ -0,0 +1,316 @@
+//! An HTTP API for managing the server from hosting panels and scripts.
+//!
+//! Every request has to carry the configured token in an `Authorization: Bearer <token>`
+//! header. Requests about players are answered by the server thread, and plot statistics
+//! come from the [metrics](crate::metrics) the plots report.
+
+use crate::config::AdminApiConfig;
+use crate::metrics;
+use crate::player::Gamemode;
+use crate::plot::database;
+use crate::server::Message;
+use crate::utils::HyphenatedUUID;
+use crate::worlds::{self, DEFAULT_WORLD};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+/// Requests larger than this are refused
+const MAX_BODY_LENGTH: usize = 64 * 1024;
+/// How long to wait for the server thread to answer a request
+const SERVER_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Serialize)]
+pub struct PlayerSummary {
+    pub uuid: HyphenatedUUID,
+    pub username: String,
+    pub world: String,
+    pub plot_x: i32,
+    pub plot_z: i32,
+    pub gamemode: Gamemode,
+}
+
+/// A request that has to be handled on the server thread
+#[derive(Debug)]
+pub enum AdminRequest {
+    ListPlayers(Sender<Vec<PlayerSummary>>),
+    /// Replies with `false` if the player isn't online
+    KickPlayer {
+        username: String,
+        reason: String,
+        reply: Sender<bool>,
+    },
+    /// Runs a command as if the player typed it. Replies with `false` if the player isn't online.
+    RunCommand {
+        username: String,
+        command: String,
+        reply: Sender<bool>,
+    },
+}
+
+#[derive(Deserialize)]
+struct KickBody {
+    reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct CommandBody {
+    command: String,
+}
+
+struct Request {
+    method: String,
+    path: String,
+    authorization: Option<String>,
+    body: Vec<u8>,
+}
+
+struct Response {
+    status: u16,
+    body: Value,
+}
+
+impl Response {
+    fn ok(body: Value) -> Response {
+        Response { status: 200, body }
+    }
+
+    fn error(status: u16, message: &str) -> Response {
+        Response {
+            status,
+            body: json!({ "error": message }),
+        }
+    }
+}
+
+fn read_request(stream: &TcpStream) -> io::Result<Request> {
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let path = parts.next().unwrap_or_default().to_string();
+
+    let mut authorization = None;
+    let mut content_length = 0;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        match name.trim().to_ascii_lowercase().as_str() {
+            "authorization" => authorization = Some(value.to_string()),
+            "content-length" => content_length = value.parse().unwrap_or(0),
+            _ => {}
+        }
+    }
+
+    if content_length > MAX_BODY_LENGTH {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "request body is too large",
+        ));
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+    Ok(Request {
+        method,
+        path,
+        authorization,
+        body,
+    })
+}
+
+fn write_response(mut stream: &TcpStream, response: Response) -> io::Result<()> {
+    let reason = match response.status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        _ => "Internal Server Error",
+    };
+    let body = response.body.to_string();
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        response.status,
+        reason,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+/// Compares the strings in constant time, so the token can't be guessed one byte at a time
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+fn plot_json(world: &str, plot_x: i32, plot_z: i32, stats: Option<&metrics::PlotMetrics>) -> Value {
+    let owner = database::get_plot_owner(world, plot_x, plot_z);
+    let owner_name = owner.clone().and_then(database::get_cached_username);
+    json!({
+        "world": world,
+        "x": plot_x,
+        "z": plot_z,
+        "owner": owner,
+        "owner_name": owner_name,
+        "loaded": stats.is_some(),
+        "stats": stats.map(|stats| json!({
+            "tick_duration_ms": stats.tick_duration.map(|d| d.as_secs_f64() * 1000.0),
+            "target_rtps": stats.target_rtps,
+            "achieved_rtps": stats.achieved_rtps,
+            "players": stats.players,
+            "memory_bytes": stats.memory_bytes,
+        })),
+    })
+}
+
+fn get_plot(world: &str, x: &str, z: &str) -> Result<Response, Response> {
+    if !worlds::exists(world) {
+        return Err(Response::error(404, "world does not exist"));
+    }
+    let (Ok(x), Ok(z)) = (x.parse::<i32>(), z.parse::<i32>()) else {
+        return Err(Response::error(400, "plot coordinates must be integers"));
+    };
+    let stats = metrics::plots()
+        .into_iter()
+        .find(|((plot_world, plot_x, plot_z), _)| {
+            plot_world == world && (*plot_x, *plot_z) == (x, z)
+        })
+        .map(|(_, stats)| stats);
+    Ok(Response::ok(plot_json(world, x, z, stats.as_ref())))
+}
+
+fn ask_server<T>(
+    server: &Sender<Message>,
+    request: impl FnOnce(Sender<T>) -> AdminRequest,
+) -> Result<T, Response> {
+    let (tx, rx) = mpsc::channel();
+    server
+        .send(Message::AdminRequest(request(tx)))
+        .map_err(|_| Response::error(500, "server is stopping"))?;
+    rx.recv_timeout(SERVER_TIMEOUT)
+        .map_err(|_| Response::error(500, "server did not respond"))
+}
+
+fn parse_body<T: for<'de> Deserialize<'de>>(body: &[u8]) -> Result<T, Response> {
+    serde_json::from_slice(body).map_err(|err| Response::error(400, &err.to_string()))
+}
+
+fn route(request: &Request, server: &Sender<Message>) -> Result<Response, Response> {
+    let path = request.path.split('?').next().unwrap_or_default();
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    match (request.method.as_str(), segments.as_slice()) {
+        ("GET", ["api", "players"]) => {
+            let players = ask_server(server, AdminRequest::ListPlayers)?;
+            Ok(Response::ok(json!(players)))
+        }
+        ("POST", ["api", "players", username, "kick"]) => {
+            let reason = if request.body.is_empty() {
+                None
+            } else {
+                parse_body::<KickBody>(&request.body)?.reason
+            };
+            let kicked = ask_server(server, |reply| AdminRequest::KickPlayer {
+                username: username.to_string(),
+                reason: reason.unwrap_or_else(|| "Kicked by an operator".to_string()),
+                reply,
+            })?;
+            if !kicked {
+                return Err(Response::error(404, "player is not online"));
+            }
+            Ok(Response::ok(json!({ "kicked": username })))
+        }
+        ("POST", ["api", "players", username, "command"]) => {
+            let body: CommandBody = parse_body(&request.body)?;
+            let ran = ask_server(server, |reply| AdminRequest::RunCommand {
+                username: username.to_string(),
+                command: body.command.trim_start_matches('/').to_string(),
+                reply,
+            })?;
+            if !ran {
+                return Err(Response::error(404, "player is not online"));
+            }
+            Ok(Response::ok(json!({ "queued": true })))
+        }
+        ("GET", ["api", "plots"]) => {
+            let mut plots = metrics::plots();
+            plots.sort_by(|(a, _), (b, _)| a.cmp(b));
+            let plots: Vec<Value> = plots
+                .iter()
+                .map(|((world, x, z), stats)| plot_json(world, *x, *z, Some(stats)))
+                .collect();
+            Ok(Response::ok(json!(plots)))
+        }
+        ("GET", ["api", "plots", x, z]) => get_plot(DEFAULT_WORLD, x, z),
+        ("GET", ["api", "plots", world, x, z]) => get_plot(world, x, z),
+        (
+            _,
+            ["api", "players"]
+            | ["api", "players", _, "kick" | "command"]
+            | ["api", "plots"]
+            | ["api", "plots", _, _]
+            | ["api", "plots", _, _, _],
+        ) => Err(Response::error(405, "method not allowed")),
+        _ => Err(Response::error(404, "not found")),
+    }
+}
+
+fn handle_connection(stream: TcpStream, token: &str, server: &Sender<Message>) -> io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let request = read_request(&stream)?;
+
+    let authorized = request
+        .authorization
+        .as_deref()
+        .and_then(|auth| auth.strip_prefix("Bearer "))
+        .is_some_and(|given| constant_time_eq(given.as_bytes(), token.as_bytes()));
+    let response = if authorized {
+        route(&request, server).unwrap_or_else(|err| err)
+    } else {
+        Response::error(401, "missing or invalid token")
+    };
+    write_response(&stream, response)
+}
+
+pub fn init(config: &AdminApiConfig, server: Sender<Message>) {
+    if config.token.is_empty() {
+        error!("The admin API needs a token to be set, it will not be started");
+        return;
+    }
+    let listener = match TcpListener::bind(&config.bind_address) {
+        Ok(listener) => listener,
+        Err(err) => {
+            error!(
+                "Failed to bind admin API to {}: {}",
+                config.bind_address, err
+            );
+            return;
+        }
+    };
+    info!("Serving the admin API on {}", config.bind_address);
+
+    let token = config.token.clone();
+    thread::Builder::new()
+        .name("admin-api".to_string())
+        .spawn(move || {
+            for stream in listener.incoming() {
+                let result = stream.and_then(|stream| handle_connection(stream, &token, &server));
+                if let Err(err) = result {
+                    warn!("Error serving admin API request: {}", err);
+                }
+            }
+        })
+        .unwrap();
+}
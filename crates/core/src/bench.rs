@@ -0,0 +1,250 @@
+//! Runs redstone in a plot without a server, for measuring the performance of the engine.
+
+use crate::interaction;
+use crate::plot::worldedit::{load_schematic_file, paste_clipboard, WorldEditClipboard};
+use crate::plot::{geometry, Entities, Plot, PlotGeometry};
+use crate::worlds::DEFAULT_WORLD;
+use anyhow::{bail, Result};
+use mchprs_blocks::BlockPos;
+use mchprs_redpiler::{Compiler, CompilerOptions};
+use mchprs_save_data::plot_data::{PlotData, TimeOfDay};
+use mchprs_world::memory::MemoryWorld;
+use mchprs_world::World;
+use rustc_hash::FxHashMap;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// A plot with no players or networking
+pub struct HeadlessPlot {
+    world: MemoryWorld,
+    geometry: PlotGeometry,
+    redpiler: Option<Compiler>,
+}
+
+impl HeadlessPlot {
+    /// Creates an empty plot at 0,0 with the size of the plots of the default world
+    pub fn empty() -> HeadlessPlot {
+        HeadlessPlot::at(geometry(DEFAULT_WORLD), 0, 0)
+    }
+
+    /// Creates an empty plot at `x`, `z`
+    fn at(geometry: PlotGeometry, x: i32, z: i32) -> HeadlessPlot {
+        let mut world = MemoryWorld::with_origin(
+            x << geometry.scale,
+            z << geometry.scale,
+            geometry.width,
+            geometry.width,
+            geometry.sections,
+        );
+        world.time_of_day = TimeOfDay::default().0;
+        HeadlessPlot {
+            world,
+            geometry,
+            redpiler: None,
+        }
+    }
+
+    /// Creates a plot at `x`, `z` from saved plot data. The plot gets the size the data was
+    /// saved with.
+    pub fn from_data(data: PlotData, x: i32, z: i32) -> HeadlessPlot {
+        let geometry = PlotGeometry::of_plot(&data).unwrap_or_else(|| geometry(DEFAULT_WORLD));
+        let mut plot = HeadlessPlot::at(geometry, x, z);
+        plot.load_data(data);
+        plot
+    }
+
+    /// Replaces everything in the plot with saved plot data
+    pub(crate) fn load_data(&mut self, data: PlotData) {
+        let (x, z) = (
+            self.world.x >> self.geometry.scale,
+            self.world.z >> self.geometry.scale,
+        );
+        // Plots keep their chunks in the same order as memory worlds
+        self.world.chunks = Plot::load_chunks(&self.geometry, data.chunk_data, x, z);
+        self.world.to_be_ticked = data.pending_ticks;
+        self.world.time_of_day = data.time_of_day.0;
+        // Nothing moves the entities without players, so only their signals are needed
+        let entities = Entities::load(&self.geometry, data.entities, x, z);
+        self.world.item_frames = entities.item_frame_overrides().collect();
+    }
+
+    /// Creates a plot with the schematic pasted at its lowest corner
+    pub fn from_schematic(path: impl AsRef<Path>) -> Result<HeadlessPlot> {
+        HeadlessPlot::from_clipboard(&load_schematic_file(path)?)
+    }
+
+    /// Creates a plot with the clipboard pasted at its lowest corner
+    pub(crate) fn from_clipboard(clipboard: &WorldEditClipboard) -> Result<HeadlessPlot> {
+        let geometry = geometry(DEFAULT_WORLD);
+        if clipboard.size_x as i32 > geometry.block_width
+            || clipboard.size_z as i32 > geometry.block_width
+            || clipboard.size_y as i32 > geometry.block_height
+        {
+            bail!(
+                "schematic of size {}x{}x{} does not fit in a plot",
+                clipboard.size_x,
+                clipboard.size_y,
+                clipboard.size_z
+            );
+        }
+
+        let mut plot = HeadlessPlot::at(geometry, 0, 0);
+        // Pasting subtracts the offset, so this puts the schematic at 0,0,0
+        let pos = BlockPos::new(clipboard.offset_x, clipboard.offset_y, clipboard.offset_z);
+        paste_clipboard(&mut plot.world, clipboard, pos, false, None);
+        Ok(plot)
+    }
+
+    pub fn world(&self) -> &MemoryWorld {
+        &self.world
+    }
+
+    pub fn world_mut(&mut self) -> &mut MemoryWorld {
+        &mut self.world
+    }
+
+    /// The lowest and highest corner of the plot
+    fn corners(&self) -> (BlockPos, BlockPos) {
+        let geometry = self.geometry;
+        let (x, z) = (self.world.x * 16, self.world.z * 16);
+        let first_pos = BlockPos::new(x, 0, z);
+        let second_pos = BlockPos::new(
+            x + geometry.block_width - 1,
+            geometry.block_height - 1,
+            z + geometry.block_width - 1,
+        );
+        (first_pos, second_pos)
+    }
+
+    /// Compiles the plot with redpiler, returning how long the compile took
+    pub fn start_redpiler(&mut self, options: CompilerOptions) -> Duration {
+        let start = Instant::now();
+        let mut compiler = Compiler::default();
+        let bounds = self.corners();
+        let ticks = self.world.to_be_ticked.drain(..).collect();
+        compiler.compile(&self.world, bounds, options, ticks, Default::default());
+        self.redpiler = Some(compiler);
+        start.elapsed()
+    }
+
+    pub fn is_redpiler_active(&self) -> bool {
+        self.redpiler.is_some()
+    }
+
+    /// Stops redpiler, writing its state and pending ticks back into the world
+    pub fn reset_redpiler(&mut self) {
+        if let Some(mut redpiler) = self.redpiler.take() {
+            let bounds = self.corners();
+            redpiler.reset(&mut self.world, bounds);
+        }
+    }
+
+    pub fn use_block(&mut self, pos: BlockPos) {
+        match &mut self.redpiler {
+            Some(redpiler) => redpiler.on_use_block(pos),
+            None => {
+                mchprs_redstone::on_use(self.world.get_block(pos), &mut self.world, pos);
+            }
+        }
+    }
+
+    pub fn set_pressure_plate(&mut self, pos: BlockPos, powered: bool) {
+        match &mut self.redpiler {
+            Some(redpiler) => redpiler.set_pressure_plate(pos, powered),
+            None => interaction::set_pressure_plate(&mut self.world, pos, powered),
+        }
+    }
+
+    /// Sets how many players have each container open and updates the trapped chests whose
+    /// count changed
+    pub fn set_container_viewers(&mut self, viewers: FxHashMap<BlockPos, u8>) {
+        let changed = interaction::changed_trapped_chests(
+            &self.world,
+            &self.world.container_viewers,
+            &viewers,
+        );
+        match &mut self.redpiler {
+            Some(redpiler) => {
+                for pos in changed {
+                    redpiler.set_trapped_chest(pos, viewers.get(&pos).copied().unwrap_or(0));
+                }
+                self.world.container_viewers = viewers;
+            }
+            None => {
+                self.world.container_viewers = viewers;
+                for pos in changed {
+                    interaction::update_trapped_chest(&mut self.world, pos);
+                }
+            }
+        }
+    }
+
+    pub fn tick(&mut self) {
+        if let Some(redpiler) = &mut self.redpiler {
+            redpiler.tick();
+            return;
+        }
+
+        self.world.tick(mchprs_redstone::tick);
+    }
+
+    pub fn tickn(&mut self, ticks: u64) {
+        if let Some(redpiler) = &mut self.redpiler {
+            redpiler.tickn(ticks);
+            return;
+        }
+        for _ in 0..ticks {
+            self.tick();
+        }
+    }
+
+    /// Writes the redpiler state back into the world
+    pub fn flush(&mut self) {
+        if let Some(redpiler) = &mut self.redpiler {
+            redpiler.flush(&mut self.world);
+        }
+    }
+}
+
+pub struct BenchOptions {
+    pub ticks: u64,
+    /// `None` to use the base redstone implementation
+    pub redpiler: Option<CompilerOptions>,
+    /// Blocks to use before ticking, relative to the lowest corner of the schematic
+    pub use_blocks: Vec<BlockPos>,
+}
+
+/// Loads the schematic, runs it for the amount of ticks and prints how fast it went
+pub fn run(schematic: &Path, options: BenchOptions) -> Result<()> {
+    let start = Instant::now();
+    let mut plot = HeadlessPlot::from_schematic(schematic)?;
+    println!("Loaded {} in {:?}", schematic.display(), start.elapsed());
+
+    match options.redpiler {
+        Some(redpiler_options) => {
+            let compile_time = plot.start_redpiler(redpiler_options);
+            println!("Compiled with redpiler in {:?}", compile_time);
+        }
+        None => println!("Using the base redstone implementation"),
+    }
+
+    for pos in options.use_blocks {
+        plot.use_block(pos);
+    }
+
+    let start = Instant::now();
+    plot.tickn(options.ticks);
+    plot.flush();
+    let elapsed = start.elapsed();
+
+    let secs = elapsed.as_secs_f64();
+    println!("Ran {} redstone ticks in {:?}", options.ticks, elapsed);
+    if options.ticks > 0 && secs > 0.0 {
+        println!(
+            "{:.0} rtps, {:.3} µs per tick",
+            options.ticks as f64 / secs,
+            secs * 1_000_000.0 / options.ticks as f64
+        );
+    }
+    Ok(())
+}
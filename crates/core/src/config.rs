@@ -1,4 +1,5 @@
 use crate::permissions::PermissionsConfig;
+use crate::utils::HyphenatedUUID;
 use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
 use std::fs;
@@ -74,7 +75,30 @@ gen_config! {
     luckperms: Option<PermissionsConfig> = None,
     block_in_hitbox: bool = true,
     auto_redpiler: bool = false,
-    velocity: Option<VelocityConfig> = None
+    plot_unload_time: i64 = 600,
+    plot_mspt_budget: i64 = 0,
+    plot_threads: i64 = 0,
+    plot_border: bool = false,
+    broadcast_kicks: bool = true,
+    keep_alive_interval: i64 = 10,
+    keep_alive_timeout: i64 = 30,
+    warp_keep_alive_timeout: i64 = 300,
+    navigation_tool: String = "compass".to_string(),
+    max_homes: i64 = 3,
+    home_limits: Option<Vec<HomeLimit>> = None,
+    cooldowns: Option<CooldownConfig> = None,
+    vanilla_playerdata: Option<String> = None,
+    plot_scale: i64 = 5,
+    world_height: i64 = 384,
+    velocity: Option<VelocityConfig> = None,
+    floodgate: Option<FloodgateConfig> = None,
+    metrics: Option<MetricsConfig> = None,
+    discord: Option<DiscordConfig> = None,
+    admin_api: Option<AdminApiConfig> = None,
+    web_map: Option<WebMapConfig> = None,
+    schedule: Option<Vec<ScheduledTask>> = None,
+    plot_template: Option<PlotTemplateConfig> = None,
+    worlds: Option<Vec<WorldConfig>> = None
 }
 
 #[derive(Serialize, Deserialize)]
@@ -82,3 +106,187 @@ pub struct VelocityConfig {
     pub enabled: bool,
     pub secret: String,
 }
+
+/// Bedrock players forwarded by a proxy running Geyser and Floodgate
+#[derive(Serialize, Deserialize)]
+pub struct FloodgateConfig {
+    pub enabled: bool,
+    /// The `username-prefix` from the Floodgate config
+    #[serde(default = "default_floodgate_prefix")]
+    pub username_prefix: String,
+}
+
+fn default_floodgate_prefix() -> String {
+    ".".to_string()
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct MetricsConfig {
+    pub enabled: bool,
+    pub bind_address: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct AdminApiConfig {
+    pub enabled: bool,
+    pub bind_address: String,
+    /// Requests have to send this in the `Authorization: Bearer <token>` header
+    pub token: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct WebMapConfig {
+    pub enabled: bool,
+    pub bind_address: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct DiscordConfig {
+    pub enabled: bool,
+    /// Chat is posted to the channel through this webhook
+    pub webhook_url: Option<String>,
+    /// The bot used to read messages from the channel
+    pub bot_token: Option<String>,
+    pub channel_id: Option<String>,
+    /// The format of messages from Discord in game chat
+    #[serde(default = "default_discord_format")]
+    pub format: String,
+}
+
+fn default_discord_format() -> String {
+    "&9[Discord] &7{username}&r: {message}".to_string()
+}
+
+/// Seconds players have to wait before using a heavy command again
+#[derive(Serialize, Deserialize)]
+pub struct CooldownConfig {
+    #[serde(default)]
+    pub paste: u64,
+    #[serde(default)]
+    pub plot_clear: u64,
+    #[serde(default)]
+    pub radvance: u64,
+}
+
+/// Lets players with `permission` set up to `max` homes
+#[derive(Serialize, Deserialize)]
+pub struct HomeLimit {
+    pub permission: String,
+    pub max: i64,
+}
+
+/// A plot world besides the default one, which is always called `world`
+#[derive(Serialize, Deserialize)]
+pub struct WorldConfig {
+    pub name: String,
+    /// The permission players need to switch to this world with `/world`
+    pub permission: Option<String>,
+    /// Overrides `auto_redpiler` for the plots in this world
+    pub auto_redpiler: Option<bool>,
+    /// Overrides `plot_unload_time` for the plots in this world
+    pub plot_unload_time: Option<i64>,
+    /// Overrides `plot_template` for the plots in this world
+    pub template: Option<PlotTemplateConfig>,
+    #[serde(default)]
+    pub generator: WorldGenerator,
+}
+
+/// How the template is used to generate new plots in a world
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum WorldGenerator {
+    /// A floor with a border around every plot
+    #[default]
+    Plots,
+    /// A floor without borders, so the plots make up one big area
+    Flat,
+    /// No floor at all
+    Void,
+}
+
+/// How new plots are generated in worlds without a `pTEMPLATE` plot
+#[derive(Serialize, Deserialize)]
+pub struct PlotTemplateConfig {
+    #[serde(default = "default_template_floor")]
+    pub floor: String,
+    /// The block around the edge of the plot
+    #[serde(default = "default_template_border")]
+    pub border: String,
+    /// How many blocks thick the floor is
+    #[serde(default = "default_template_layers")]
+    pub layers: i64,
+    /// Lines of this block are drawn in the top layer of the floor
+    pub grid: Option<String>,
+    /// How many blocks apart the grid lines are
+    #[serde(default = "default_template_grid_spacing")]
+    pub grid_spacing: i64,
+    /// A schematic in the `schems` folder that is placed on the floor in the corner of new plots
+    pub schematic: Option<String>,
+}
+
+impl Default for PlotTemplateConfig {
+    fn default() -> PlotTemplateConfig {
+        PlotTemplateConfig {
+            floor: default_template_floor(),
+            border: default_template_border(),
+            layers: default_template_layers(),
+            grid: None,
+            grid_spacing: default_template_grid_spacing(),
+            schematic: None,
+        }
+    }
+}
+
+fn default_template_floor() -> String {
+    "sandstone".to_string()
+}
+
+fn default_template_border() -> String {
+    "stone_bricks".to_string()
+}
+
+fn default_template_layers() -> i64 {
+    8
+}
+
+fn default_template_grid_spacing() -> i64 {
+    16
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ScheduledTask {
+    /// Runs the task at an interval, like `30m`
+    pub every: Option<String>,
+    /// Runs the task every day at a local time, like `04:00`
+    pub at: Option<String>,
+    /// The uuid of the player that commands run as
+    pub owner: Option<HyphenatedUUID>,
+    #[serde(flatten)]
+    pub action: TaskAction,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum TaskAction {
+    Announce {
+        message: String,
+    },
+    Backup {
+        /// The number of backups to keep, older ones are deleted
+        keep: Option<usize>,
+    },
+    /// Stops the server, to be started again by whatever is supervising it
+    Restart {
+        /// How many seconds before the restart players are warned
+        #[serde(default = "default_restart_warnings")]
+        warnings: Vec<u64>,
+    },
+    /// Runs a command as the owner of the task, like they typed it in chat
+    Command {
+        command: String,
+    },
+}
+
+fn default_restart_warnings() -> Vec<u64> {
+    vec![300, 60, 10]
+}
@@ -0,0 +1,146 @@
+//! Converts builds between schematic formats and plot save files without starting the server.
+
+use crate::bench::HeadlessPlot;
+use crate::plot::worldedit::{
+    create_clipboard, load_schematic_file, save_schematic_file, WorldEditClipboard,
+};
+use crate::plot::{PlotGeometry, PlotWorld};
+use anyhow::{bail, Context, Result};
+use mchprs_blocks::BlockPos;
+use mchprs_save_data::plot_data::{ChunkData, PlotData, TimeOfDay, Tps, WorldSendRate};
+use std::path::Path;
+use std::time::Instant;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Sponge,
+    Litematic,
+    /// A plot save file, like the ones in `world/plots`
+    Plot,
+}
+
+impl Format {
+    fn of(path: &Path) -> Format {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("schem" | "schematic") => Format::Sponge,
+            Some("litematic") => Format::Litematic,
+            _ => Format::Plot,
+        }
+    }
+}
+
+/// Returns the lowest and highest corner of the blocks in the world that aren't air
+fn block_bounds(world: &PlotWorld) -> Option<(BlockPos, BlockPos)> {
+    let mut bounds: Option<(BlockPos, BlockPos)> = None;
+    let width = world.geometry.width;
+    for (i, chunk) in world.chunks.iter().enumerate() {
+        let chunk_x = i as i32 / width;
+        let chunk_z = i as i32 % width;
+        for (section_y, section) in chunk.sections.iter().enumerate() {
+            if section.block_count() == 0 {
+                continue;
+            }
+            for y in 0..16 {
+                for z in 0..16 {
+                    for x in 0..16 {
+                        if section.get_block(x, y, z) == 0 {
+                            continue;
+                        }
+                        let pos = BlockPos::new(
+                            chunk_x * 16 + x as i32,
+                            section_y as i32 * 16 + y as i32,
+                            chunk_z * 16 + z as i32,
+                        );
+                        bounds = Some(match bounds {
+                            Some((min, max)) => (min.min(pos), max.max(pos)),
+                            None => (pos, pos),
+                        });
+                    }
+                }
+            }
+        }
+    }
+    bounds
+}
+
+/// Copies everything in a plot save file that isn't air, with the origin at its lowest corner
+fn load_plot_file(path: &Path) -> Result<WorldEditClipboard> {
+    let data = PlotData::load_from_file(path)
+        .with_context(|| format!("error loading plot save file at {}", path.display()))?;
+    // The plot can be from any world, so its size is worked out from the file
+    let Some(geometry) = PlotGeometry::of_plot(&data) else {
+        bail!(
+            "the plot has {} chunks, which is not the number of chunks of any plot scale",
+            data.chunk_data.len()
+        );
+    };
+    let chunks = data
+        .chunk_data
+        .into_iter()
+        .enumerate()
+        .map(|(i, c)| c.load(i as i32 / geometry.width, i as i32 % geometry.width))
+        .collect();
+    let mut world = PlotWorld {
+        x: 0,
+        z: 0,
+        geometry,
+        chunks,
+        to_be_ticked: Vec::new(),
+        packet_senders: Vec::new(),
+        map_feed: None,
+        container_viewers: Default::default(),
+        time_of_day: TimeOfDay::default().0,
+        entities: Default::default(),
+    };
+    let Some((min, max)) = block_bounds(&world) else {
+        bail!("the plot is empty");
+    };
+    Ok(create_clipboard(&mut world, min, min, max))
+}
+
+/// Saves the clipboard as a plot with its lowest corner at `0,0,0`
+fn save_plot_file(path: &Path, clipboard: &WorldEditClipboard) -> Result<()> {
+    let mut plot = HeadlessPlot::from_clipboard(clipboard)?;
+    let chunk_data = plot
+        .world_mut()
+        .chunks
+        .iter_mut()
+        .map(ChunkData::new)
+        .collect();
+    let data = PlotData {
+        tps: Tps::Limited(10),
+        world_send_rate: WorldSendRate::default(),
+        chunk_data,
+        pending_ticks: Vec::new(),
+        time_of_day: TimeOfDay::default(),
+        entities: Vec::new(),
+    };
+    data.save_to_file(path)?;
+    Ok(())
+}
+
+/// Converts the file at `input` into the format of `output`. The formats are picked by
+/// extension: `.schem` for Sponge schematics, `.litematic` for Litematica schematics, and
+/// anything else for plot save files.
+pub fn run(input: &Path, output: &Path) -> Result<()> {
+    let start = Instant::now();
+    let clipboard = match Format::of(input) {
+        Format::Plot => load_plot_file(input)?,
+        Format::Sponge | Format::Litematic => load_schematic_file(input)
+            .with_context(|| format!("error loading schematic at {}", input.display()))?,
+    };
+    match Format::of(output) {
+        Format::Plot => save_plot_file(output, &clipboard)?,
+        Format::Sponge | Format::Litematic => save_schematic_file(output, &clipboard)?,
+    }
+    println!(
+        "Converted {} to {} ({}x{}x{}) in {:?}",
+        input.display(),
+        output.display(),
+        clipboard.size_x,
+        clipboard.size_y,
+        clipboard.size_z,
+        start.elapsed()
+    );
+    Ok(())
+}
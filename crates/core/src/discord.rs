@@ -0,0 +1,198 @@
+//! Mirrors chat between the server and a Discord channel.
+//!
+//! Messages from the server are posted to the channel through a webhook. Messages from the
+//! channel are read with a bot token by polling the channel, which saves us from keeping a
+//! gateway connection open. The bot needs the message content intent to see what was said.
+
+use crate::config::DiscordConfig;
+use crate::events::{self, Event, EventResult};
+use crate::server::Message;
+use once_cell::sync::OnceCell;
+use reqwest::blocking::Client;
+use serde::Deserialize;
+use serde_json::json;
+use std::sync::mpsc::{self, Sender};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+const API_URL: &str = "https://discord.com/api/v10";
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+/// Discord messages longer than this are cut off before they are relayed into the game
+const MAX_MESSAGE_LENGTH: usize = 256;
+
+static OUTGOING: OnceCell<Mutex<Sender<Outgoing>>> = OnceCell::new();
+
+enum Outgoing {
+    Chat { username: String, message: String },
+    Notice(String),
+}
+
+#[derive(Deserialize)]
+struct DiscordUser {
+    username: String,
+    global_name: Option<String>,
+    #[serde(default)]
+    bot: bool,
+}
+
+#[derive(Deserialize)]
+struct DiscordMessage {
+    id: String,
+    content: String,
+    author: DiscordUser,
+    webhook_id: Option<String>,
+}
+
+fn send(message: Outgoing) {
+    if let Some(sender) = OUTGOING.get() {
+        let _ = sender.lock().unwrap().send(message);
+    }
+}
+
+/// Posts a chat message from a player to the Discord channel
+pub fn send_chat(username: &str, message: &str) {
+    send(Outgoing::Chat {
+        username: username.to_string(),
+        message: message.to_string(),
+    });
+}
+
+/// Usernames can contain underscores, which Discord would otherwise turn into italics
+fn escape_markdown(text: &str) -> String {
+    text.replace('_', "\\_")
+}
+
+fn post_webhook(client: &Client, url: &str, message: Outgoing) -> reqwest::Result<()> {
+    let body = match message {
+        Outgoing::Chat { username, message } => json!({
+            "username": username,
+            "content": message,
+            // Players shouldn't be able to ping @everyone from the game
+            "allowed_mentions": { "parse": [] },
+        }),
+        Outgoing::Notice(content) => json!({
+            "content": content,
+            "allowed_mentions": { "parse": [] },
+        }),
+    };
+    client.post(url).json(&body).send()?.error_for_status()?;
+    Ok(())
+}
+
+fn fetch_messages(
+    client: &Client,
+    token: &str,
+    channel_id: &str,
+    after: Option<&str>,
+) -> reqwest::Result<Vec<DiscordMessage>> {
+    let url = format!("{}/channels/{}/messages", API_URL, channel_id);
+    let mut request = client
+        .get(url)
+        .header("Authorization", format!("Bot {}", token));
+    request = match after {
+        Some(after) => request.query(&[("after", after), ("limit", "50")]),
+        // We only need the latest message to know where to start from
+        None => request.query(&[("limit", "1")]),
+    };
+    request.send()?.error_for_status()?.json()
+}
+
+/// Relays new messages in the channel to the server until the server stops
+fn poll_channel(client: Client, token: String, channel_id: String, server: Sender<Message>) {
+    let mut last_id = None;
+    loop {
+        match fetch_messages(&client, &token, &channel_id, last_id.as_deref()) {
+            Ok(mut messages) => {
+                // Discord returns the newest messages first
+                messages.sort_by_key(|message| message.id.parse::<u64>().unwrap_or(0));
+                let first_poll = last_id.is_none();
+                if let Some(newest) = messages.last() {
+                    last_id = Some(newest.id.clone());
+                } else if first_poll {
+                    last_id = Some("0".to_string());
+                }
+                if first_poll {
+                    // Don't relay messages sent before the server started
+                    thread::sleep(POLL_INTERVAL);
+                    continue;
+                }
+                for message in messages {
+                    // Skip bots and webhooks, which includes our own messages
+                    if message.author.bot || message.webhook_id.is_some() {
+                        continue;
+                    }
+                    let content = message.content.replace('\n', " ");
+                    let content: String = content.chars().take(MAX_MESSAGE_LENGTH).collect();
+                    if content.trim().is_empty() {
+                        continue;
+                    }
+                    let author = message
+                        .author
+                        .global_name
+                        .unwrap_or(message.author.username);
+                    if server.send(Message::DiscordChat(author, content)).is_err() {
+                        return;
+                    }
+                }
+            }
+            Err(err) => warn!("Failed to read messages from Discord: {}", err),
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+pub fn init(config: &DiscordConfig, server: Sender<Message>) {
+    let client = match Client::builder().timeout(Duration::from_secs(10)).build() {
+        Ok(client) => client,
+        Err(err) => {
+            error!("Failed to create the Discord client: {}", err);
+            return;
+        }
+    };
+
+    if let Some(webhook_url) = config.webhook_url.clone() {
+        let (tx, rx) = mpsc::channel();
+        let _ = OUTGOING.set(Mutex::new(tx));
+        let webhook_client = client.clone();
+        thread::Builder::new()
+            .name("discord-webhook".to_string())
+            .spawn(move || {
+                for message in rx {
+                    if let Err(err) = post_webhook(&webhook_client, &webhook_url, message) {
+                        warn!("Failed to send message to Discord: {}", err);
+                    }
+                }
+            })
+            .unwrap();
+
+        events::subscribe(|event| {
+            match event {
+                Event::PlayerJoin(player) => {
+                    send(Outgoing::Notice(format!(
+                        "**{}** joined the server",
+                        escape_markdown(&player.username)
+                    )));
+                }
+                Event::PlayerQuit(player) => {
+                    send(Outgoing::Notice(format!(
+                        "**{}** left the server",
+                        escape_markdown(&player.username)
+                    )));
+                }
+                _ => {}
+            }
+            EventResult::Continue
+        });
+    }
+
+    if let (Some(token), Some(channel_id)) = (config.bot_token.clone(), config.channel_id.clone()) {
+        thread::Builder::new()
+            .name("discord-bot".to_string())
+            .spawn(move || poll_channel(client, token, channel_id, server))
+            .unwrap();
+    }
+
+    info!("Discord bridge enabled");
+}
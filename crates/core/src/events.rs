@@ -0,0 +1,107 @@
+//! Game events that core modules publish and plugins and scripts subscribe to.
+//!
+//! Some events are published before the action that caused them happens, and can be
+//! cancelled by any subscriber to stop the action, for example to protect an area.
+//! Subscribers are called on the thread the event happened on, which is usually a plot thread.
+
+use crate::player::Player;
+use mchprs_blocks::blocks::Block;
+use mchprs_blocks::BlockPos;
+use std::sync::RwLock;
+
+/// The player an event is about
+#[derive(Debug, Clone)]
+pub struct PlayerInfo {
+    pub uuid: u128,
+    pub username: String,
+}
+
+impl PlayerInfo {
+    pub(crate) fn of(player: &Player) -> PlayerInfo {
+        PlayerInfo {
+            uuid: player.uuid,
+            username: player.username.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Event {
+    /// A player placed a block. This is published after the block is placed, and cancelling
+    /// it reverts the placement.
+    BlockPlace {
+        player: PlayerInfo,
+        pos: BlockPos,
+        block: Block,
+    },
+    /// A player is about to break a block
+    BlockBreak {
+        player: PlayerInfo,
+        pos: BlockPos,
+        block: Block,
+    },
+    PlayerJoin(PlayerInfo),
+    PlayerQuit(PlayerInfo),
+    PlayerEnterPlot {
+        player: PlayerInfo,
+        plot_x: i32,
+        plot_z: i32,
+    },
+    PlayerLeavePlot {
+        player: PlayerInfo,
+        plot_x: i32,
+        plot_z: i32,
+    },
+    /// A player is about to send a chat message
+    Chat {
+        player: PlayerInfo,
+        message: String,
+    },
+    /// A player is about to run a command
+    Command {
+        player: PlayerInfo,
+        command: String,
+        args: Vec<String>,
+    },
+}
+
+impl Event {
+    pub fn is_cancellable(&self) -> bool {
+        matches!(
+            self,
+            Event::BlockPlace { .. }
+                | Event::BlockBreak { .. }
+                | Event::Chat { .. }
+                | Event::Command { .. }
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventResult {
+    Continue,
+    /// Stops the action that caused the event. This does nothing for events that can't be
+    /// cancelled.
+    Cancel,
+}
+
+type Listener = Box<dyn Fn(&Event) -> EventResult + Send + Sync>;
+
+static LISTENERS: RwLock<Vec<Listener>> = RwLock::new(Vec::new());
+
+/// Calls `listener` for every event published from now on
+pub fn subscribe(listener: impl Fn(&Event) -> EventResult + Send + Sync + 'static) {
+    LISTENERS.write().unwrap().push(Box::new(listener));
+}
+
+/// Calls every subscriber with the event. Every subscriber sees the event, even if an earlier
+/// one cancelled it.
+pub(crate) fn publish(event: &Event) -> EventResult {
+    let mut result = EventResult::Continue;
+    for listener in LISTENERS.read().unwrap().iter() {
+        if listener(event) == EventResult::Cancel && event.is_cancellable() {
+            result = EventResult::Cancel;
+        }
+    }
+    result
+}
@@ -0,0 +1,82 @@
+//! Bedrock players join through Geyser, with Floodgate on the proxy standing in for the Java
+//! account they don't have. Floodgate makes their UUID out of their Xbox user id (XUID), leaving
+//! the upper half zero where a Java UUID has its version bits, and starts their name with a
+//! prefix so it can't be taken by a Java player. Velocity forwards them like any other player.
+
+use crate::config::CONFIG;
+use anyhow::Result;
+use mchprs_network::packets::PlayerProperty;
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+struct XuidResponse {
+    xuid: u64,
+}
+
+fn is_enabled() -> bool {
+    CONFIG
+        .floodgate
+        .as_ref()
+        .is_some_and(|config| config.enabled)
+}
+
+/// Whether the UUID is one Floodgate made for a Bedrock player
+pub fn is_bedrock_uuid(uuid: u128) -> bool {
+    is_enabled() && uuid != 0 && uuid >> 64 == 0
+}
+
+/// Returns the gamertag of the Bedrock player with this username, if it has the Floodgate prefix
+pub fn bedrock_gamertag(username: &str) -> Option<String> {
+    let config = CONFIG.floodgate.as_ref().filter(|config| config.enabled)?;
+    let gamertag = username.strip_prefix(config.username_prefix.as_str())?;
+    // Floodgate replaces the spaces in gamertags, which usernames can't have
+    Some(gamertag.replace('_', " "))
+}
+
+/// Looks up the UUID Floodgate gives the Bedrock player with this gamertag
+pub async fn lookup_uuid(gamertag: &str) -> Result<u128> {
+    let url = format!("https://api.geysermc.org/v2/xbox/xuid/{}", gamertag);
+    let client = reqwest::Client::new();
+    let res = client
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<XuidResponse>()
+        .await?;
+    Ok(res.xuid as u128)
+}
+
+/// Removes the skin of a Bedrock player that Floodgate hasn't gotten signed as a Java skin yet,
+/// which Java clients would refuse to show anyway
+pub fn java_properties(mut properties: Vec<PlayerProperty>) -> Vec<PlayerProperty> {
+    properties.retain(|property| {
+        property.name != "textures" || (!property.value.is_empty() && property.signature.is_some())
+    });
+    properties
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unsigned_skins_are_removed() {
+        let property = |name: &str, value: &str, signature: Option<&str>| PlayerProperty {
+            name: name.to_string(),
+            value: value.to_string(),
+            signature: signature.map(str::to_string),
+        };
+        let properties = java_properties(vec![
+            property("textures", "", None),
+            property("textures", "e30=", None),
+            property("textures", "e30=", Some("c2lnbmF0dXJl")),
+            property("floodgate", "1", None),
+        ]);
+        let kept: Vec<_> = properties
+            .iter()
+            .map(|p| (p.name.as_str(), p.signature.is_some()))
+            .collect();
+        assert_eq!(kept, [("textures", true), ("floodgate", false)]);
+    }
+}
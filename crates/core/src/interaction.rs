@@ -1,14 +1,18 @@
 use crate::config::CONFIG;
 use crate::player::Player;
-use crate::plot::{PlotWorld, PLOT_BLOCK_HEIGHT};
-use mchprs_blocks::block_entities::BlockEntity;
+use crate::plot::PlotWorld;
+use crate::utils;
+use mchprs_blocks::block_entities::{BlockEntity, ContainerType};
 use mchprs_blocks::blocks::*;
 use mchprs_blocks::items::{Item, ItemStack};
-use mchprs_blocks::{BlockFace, BlockPos, SignType};
+use mchprs_blocks::{BlockDirection, BlockFace, BlockPos, SignType};
 use mchprs_network::packets::clientbound::{COpenSignEditor, ClientBoundPacket};
 use mchprs_redstone as redstone;
-use mchprs_utils::nbt_unwrap_val;
+use mchprs_utils::{map, nbt_unwrap_val};
 use mchprs_world::World;
+use rustc_hash::FxHashMap;
+use serde_json::json;
+use tracing::warn;
 
 pub fn on_use(
     block: Block,
@@ -35,6 +39,27 @@ pub fn on_use(
             }
             ActionResult::Success
         }
+        // Using an empty hand fills them up a level at a time, until they are emptied again
+        Block::Cauldron { level } if item_in_hand.is_none() => {
+            world.set_block(
+                pos,
+                Block::Cauldron {
+                    level: (level + 1) % 4,
+                },
+            );
+            redstone::update_surrounding_blocks(world, pos);
+            ActionResult::Success
+        }
+        Block::Composter { level } if item_in_hand.is_none() => {
+            world.set_block(
+                pos,
+                Block::Composter {
+                    level: (level + 1) % 9,
+                },
+            );
+            redstone::update_surrounding_blocks(world, pos);
+            ActionResult::Success
+        }
         Block::EndPortalFrame { eye, facing } => {
             if let Some(Item::EnderEye {}) = item_in_hand {
                 if !eye {
@@ -45,18 +70,116 @@ pub fn on_use(
             }
             ActionResult::Pass
         }
-        b if b.has_block_entity() => {
-            // Open container
-            let block_entity = world.get_block_entity(pos);
-            if let Some(BlockEntity::Container { inventory, ty, .. }) = block_entity {
-                player.open_container(inventory, *ty);
+        Block::Sign { .. } | Block::WallSign { .. } => {
+            let open_sign_editor = COpenSignEditor {
+                pos_x: pos.x,
+                pos_y: pos.y,
+                pos_z: pos.z,
+                is_front_text: is_facing_front_text(block, pos, player),
+            }
+            .encode();
+            player.client.send_packet(&open_sign_editor);
+            ActionResult::Success
+        }
+        Block::Barrel { .. }
+        | Block::Furnace { .. }
+        | Block::Hopper { .. }
+        | Block::TrappedChest { .. } => {
+            match world.get_block_entity(pos) {
+                Some(BlockEntity::Container { inventory, ty, .. }) => {
+                    player.open_container(pos, inventory, *ty)
+                }
+                // Empty containers may not have any block entity data
+                _ => player.open_container(pos, &[], container_type(block)),
+            }
+            ActionResult::Success
+        }
+        Block::Lectern {
+            facing,
+            has_book: false,
+            powered,
+        } => {
+            if !matches!(
+                item_in_hand,
+                Some(Item::WritableBook {} | Item::WrittenBook {})
+            ) {
+                return ActionResult::Pass;
+            }
+            let Some(book) = &player.inventory[36 + player.selected_slot as usize] else {
+                return ActionResult::Pass;
+            };
+            let book = utils::stack_to_inventory_entry(0, book);
+            world.set_block_entity(pos, BlockEntity::lectern(book, 0));
+            world.set_block(
+                pos,
+                Block::Lectern {
+                    facing,
+                    has_book: true,
+                    powered,
+                },
+            );
+            redstone::update_surrounding_blocks(world, pos);
+            ActionResult::Success
+        }
+        Block::Lectern { .. } => {
+            if let Some(BlockEntity::Lectern { book, page, .. }) = world.get_block_entity(pos) {
+                player.open_lectern(pos, book, *page);
             }
             ActionResult::Success
         }
+        b if b.has_block_entity() => ActionResult::Success,
         _ => ActionResult::Pass,
     }
 }
 
+/// Whether [`on_use`] does something when the block is used with the item in hand, like opening
+/// a container, instead of letting the item be used on the block
+pub fn is_usable(block: Block, item_in_hand: Option<Item>) -> bool {
+    if redstone::is_usable(block) {
+        return true;
+    }
+    match block {
+        Block::SeaPickle { .. } | Block::Sign { .. } | Block::WallSign { .. } => true,
+        Block::Cauldron { .. } | Block::Composter { .. } => item_in_hand.is_none(),
+        Block::EndPortalFrame { eye, .. } => !eye && item_in_hand == Some(Item::EnderEye {}),
+        Block::Lectern {
+            has_book: false, ..
+        } => matches!(
+            item_in_hand,
+            Some(Item::WritableBook {} | Item::WrittenBook {})
+        ),
+        b => b.has_block_entity(),
+    }
+}
+
+pub fn container_type(block: Block) -> ContainerType {
+    match block {
+        Block::Furnace { .. } => ContainerType::Furnace,
+        Block::Hopper { .. } => ContainerType::Hopper,
+        Block::TrappedChest { .. } => ContainerType::TrappedChest,
+        _ => ContainerType::Barrel,
+    }
+}
+
+/// Returns whether the player is in front of the sign, which decides the side they edit
+fn is_facing_front_text(sign: Block, pos: BlockPos, player: &Player) -> bool {
+    let sign_yaw = match sign {
+        Block::Sign { rotation, .. } => rotation as f64 * 22.5,
+        Block::WallSign { facing, .. } => match facing {
+            BlockDirection::South => 0.0,
+            BlockDirection::West => 90.0,
+            BlockDirection::North => 180.0,
+            BlockDirection::East => 270.0,
+        },
+        _ => return true,
+    };
+    let dx = player.pos.x - (pos.x as f64 + 0.5);
+    let dz = player.pos.z - (pos.z as f64 + 0.5);
+    let player_yaw = dz.atan2(dx).to_degrees() - 90.0;
+    let difference = (player_yaw - sign_yaw).rem_euclid(360.0);
+    difference <= 90.0 || difference >= 270.0
+}
+
 pub fn get_state_for_placement(
     world: &impl World,
     pos: BlockPos,
@@ -152,7 +275,23 @@ pub fn get_state_for_placement(
             wire: redstone::wire::get_state_for_placement(world, pos),
         },
         Item::Barrel {} => Block::Barrel {},
+        Item::TrappedChest {} => Block::TrappedChest {
+            facing: context.player.get_direction().opposite(),
+        },
         Item::Target {} => Block::Target {},
+        Item::SlimeBlock {} => Block::SlimeBlock {},
+        Item::Cauldron {} => Block::Cauldron { level: 0 },
+        Item::Composter {} => Block::Composter { level: 0 },
+        Item::HoneyBlock {} => Block::HoneyBlock {},
+        Item::DaylightDetector {} => Block::DaylightDetector {
+            inverted: false,
+            power: redstone::daylight::get_power(world.time_of_day(), false),
+        },
+        Item::Lectern {} => Block::Lectern {
+            facing: context.player.get_direction().opposite(),
+            has_book: false,
+            powered: false,
+        },
         Item::StainedGlass { color } => Block::StainedGlass { color },
         Item::SmoothStoneSlab {} => Block::SmoothStoneSlab {},
         Item::QuartzSlab {} => Block::QuartzSlab {},
@@ -206,6 +345,75 @@ pub fn get_state_for_placement(
     }
 }
 
+/// Overrides the properties of a placed block with the item's `BlockStateTag`
+fn apply_block_state_tag(block: &mut Block, nbt: &Option<nbt::Blob>) {
+    let Some(nbt::Value::Compound(state)) = nbt.as_ref().and_then(|nbt| nbt.get("BlockStateTag"))
+    else {
+        return;
+    };
+    let props = state
+        .iter()
+        .filter_map(|(name, value)| match value {
+            nbt::Value::String(value) => Some((name.as_str(), value.as_str())),
+            _ => None,
+        })
+        .collect();
+    block.set_properties(props);
+}
+
+/// Returns the item for picking `block`. Properties that placing the item wouldn't restore,
+/// like the delay of a repeater, are kept in a `BlockStateTag`.
+pub fn pick_block_item(block: Block) -> Option<ItemStack> {
+    let item_type = Item::from_block(block)?;
+    let kept_props: &[&str] = match block {
+        Block::RedstoneRepeater { .. } => &["delay"],
+        Block::RedstoneComparator { .. } => &["mode"],
+        Block::NoteBlock { .. } => &["note"],
+        Block::SeaPickle { .. } => &["pickles"],
+        _ => &[],
+    };
+    let default_props = Block::from_name(block.get_name())
+        .map(|default| default.gen_properties())
+        .unwrap_or_default();
+    let mut props = block.gen_properties();
+    props.retain(|name, value| {
+        kept_props.contains(name) && default_props.get(name) != Some(&*value)
+    });
+    if props.is_empty() {
+        return Some(ItemStack {
+            item_type,
+            count: 1,
+            nbt: None,
+        });
+    }
+
+    let mut names: Vec<&str> = props.keys().copied().collect();
+    names.sort_unstable();
+    let lore = names
+        .iter()
+        .map(|name| {
+            let text = format!("{}: {}", name, props[name]);
+            nbt::Value::String(
+                json!({ "text": text, "color": "gray", "italic": false }).to_string(),
+            )
+        })
+        .collect();
+    let state = props
+        .into_iter()
+        .map(|(name, value)| (name.to_string(), nbt::Value::String(value)))
+        .collect();
+    Some(ItemStack {
+        item_type,
+        count: 1,
+        nbt: Some(nbt::Blob::with_content(map! {
+            "BlockStateTag" => nbt::Value::Compound(state),
+            "display" => nbt::Value::Compound(map! {
+                "Lore" => nbt::Value::List(lore)
+            })
+        })),
+    })
+}
+
 fn read_block_entity_tag(nbt: &nbt::Blob, block_id: &str) -> Option<BlockEntity> {
     if let nbt::Value::Compound(compound) = &nbt["BlockEntityTag"] {
         let id = match nbt.get("Id").or_else(|| nbt.get("id")) {
@@ -219,7 +427,7 @@ fn read_block_entity_tag(nbt: &nbt::Blob, block_id: &str) -> Option<BlockEntity>
 }
 
 pub fn place_in_world(
-    block: Block,
+    mut block: Block,
     world: &mut impl World,
     pos: BlockPos,
     nbt: &Option<nbt::Blob>,
@@ -227,6 +435,11 @@ pub fn place_in_world(
     if block.has_block_entity() {
         if let Some(nbt) = nbt {
             if let Some(block_entity) = read_block_entity_tag(nbt, block.get_name()) {
+                if let (Block::Lectern { has_book, .. }, BlockEntity::Lectern { .. }) =
+                    (&mut block, &block_entity)
+                {
+                    *has_book = true;
+                }
                 world.set_block_entity(pos, block_entity);
             }
         };
@@ -284,6 +497,39 @@ pub fn destroy(block: Block, world: &mut impl World, pos: BlockPos) {
     }
 }
 
+/// Powers or unpowers the pressure plate at `pos` when a player steps on or off it
+pub fn set_pressure_plate(world: &mut impl World, pos: BlockPos, powered: bool) {
+    match world.get_block(pos) {
+        Block::StonePressurePlate { .. } => {
+            world.set_block(pos, Block::StonePressurePlate { powered });
+            redstone::update_surrounding_blocks(world, pos);
+            redstone::update_surrounding_blocks(world, pos.offset(BlockFace::Bottom));
+        }
+        _ => warn!("Block at {} is not a pressure plate", pos),
+    }
+}
+
+/// The trapped chests that a different amount of players are looking into in `old` and `new`
+pub fn changed_trapped_chests(
+    world: &impl World,
+    old: &FxHashMap<BlockPos, u8>,
+    new: &FxHashMap<BlockPos, u8>,
+) -> Vec<BlockPos> {
+    old.keys()
+        .chain(new.keys())
+        .filter(|pos| old.get(pos) != new.get(pos))
+        .filter(|&&pos| matches!(world.get_block(pos), Block::TrappedChest { .. }))
+        .copied()
+        .collect()
+}
+
+/// Updates the blocks around a trapped chest after the amount of players looking into it changed
+pub fn update_trapped_chest(world: &mut impl World, pos: BlockPos) {
+    // Trapped chests strongly power the block below them
+    redstone::update_surrounding_blocks(world, pos);
+    redstone::update_surrounding_blocks(world, pos.offset(BlockFace::Bottom));
+}
+
 pub fn is_valid_position(block: Block, world: &impl World, pos: BlockPos) -> bool {
     if world.is_cursed() {
         return true;
@@ -420,8 +666,11 @@ pub fn use_item_on_block(
         return false;
     }
 
-    if can_place && (0..PLOT_BLOCK_HEIGHT).contains(&block_pos.y) {
-        let block = get_state_for_placement(world, block_pos, item.item_type, &ctx);
+    if can_place && (0..world.geometry.block_height).contains(&block_pos.y) {
+        let mut block = get_state_for_placement(world, block_pos, item.item_type, &ctx);
+        if block != (Block::Air {}) {
+            apply_block_state_tag(&mut block, &item.nbt);
+        }
 
         match block {
             Block::Sign { .. } | Block::WallSign { .. } => {
@@ -434,7 +683,7 @@ pub fn use_item_on_block(
                         pos_x: block_pos.x,
                         pos_y: block_pos.y,
                         pos_z: block_pos.z,
-                        // TODO: editing back text
+                        // The front of a sign always faces the player placing it
                         is_front_text: true,
                     }
                     .encode();
@@ -450,3 +699,22 @@ pub fn use_item_on_block(
         true
     }
 }
+
+#[test]
+fn containers_are_usable_with_items() {
+    let frame = Some(Item::ItemFrame {});
+    assert!(is_usable(Block::Barrel {}, frame));
+    assert!(is_usable(Block::Furnace {}, frame));
+    let chest = Block::TrappedChest {
+        facing: Default::default(),
+    };
+    assert!(is_usable(chest, frame));
+    assert!(!is_usable(Block::Stone {}, frame));
+    let lectern = Block::Lectern {
+        facing: Default::default(),
+        has_book: false,
+        powered: false,
+    };
+    assert!(!is_usable(lectern, frame));
+    assert!(is_usable(lectern, Some(Item::WritableBook {})));
+}
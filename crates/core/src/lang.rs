@@ -0,0 +1,225 @@
+//! Translations of the messages the server sends to players. The English messages are built
+//! into the server, and other languages are loaded from `lang/<locale>.toml`, named after the
+//! locale the client reports, like `de_de.toml`. These files can also replace English messages.
+//! Messages missing from a language are sent in English.
+
+use once_cell::sync::Lazy;
+use rustc_hash::FxHashMap;
+use std::fs;
+use std::path::Path;
+use tracing::{info, warn};
+
+pub const DEFAULT_LOCALE: &str = "en_us";
+/// The longest locale the vanilla server accepts from a client
+pub const MAX_LOCALE_LENGTH: usize = 16;
+
+const LANG_DIR: &str = "./lang";
+const EN_US: &str = include_str!("../lang/en_us.toml");
+
+type Catalog = FxHashMap<String, String>;
+
+static CATALOGS: Lazy<FxHashMap<String, Catalog>> = Lazy::new(load_catalogs);
+
+/// Creates a `Text` from a key in the message catalog, with the values of its placeholders:
+/// `text!("plot.claimed", x = plot_x, z = plot_z)`. A value can also be another `Text`, which
+/// is translated into the same language.
+macro_rules! text {
+    ($key:literal $(, $name:ident = $value:expr)* $(,)?) => {
+        $crate::lang::Text::Key {
+            key: $key,
+            args: vec![$((stringify!($name), $crate::lang::IntoArg::into_arg(&($value)))),*],
+        }
+    };
+}
+
+/// A value for a placeholder of a message
+pub trait IntoArg {
+    fn into_arg(self) -> Text;
+}
+
+impl<T: std::fmt::Display + ?Sized> IntoArg for &T {
+    fn into_arg(self) -> Text {
+        Text::Raw(self.to_string())
+    }
+}
+
+impl IntoArg for &Text {
+    fn into_arg(self) -> Text {
+        self.clone()
+    }
+}
+
+/// A message for a player, which is translated into their language when it is sent
+#[derive(Debug, Clone)]
+pub enum Text {
+    /// A message from the catalog
+    Key {
+        key: &'static str,
+        args: Vec<(&'static str, Text)>,
+    },
+    /// A message that isn't translated, like one from a plugin
+    Raw(String),
+}
+
+impl Text {
+    pub fn translate(&self, locale: &str) -> String {
+        match self {
+            Text::Key { key, args } => {
+                let mut message = lookup(locale, key).to_string();
+                for (name, value) in args {
+                    message = message.replace(&format!("{{{}}}", name), &value.translate(locale));
+                }
+                message
+            }
+            Text::Raw(message) => message.clone(),
+        }
+    }
+}
+
+impl From<&str> for Text {
+    fn from(message: &str) -> Text {
+        Text::Raw(message.to_string())
+    }
+}
+
+impl From<&String> for Text {
+    fn from(message: &String) -> Text {
+        Text::Raw(message.clone())
+    }
+}
+
+impl From<String> for Text {
+    fn from(message: String) -> Text {
+        Text::Raw(message)
+    }
+}
+
+/// Finds the message for `key` in a language, falling back to English and then to the key
+/// itself so a missing message can still be reported
+fn lookup<'a>(locale: &str, key: &'a str) -> &'a str {
+    [locale, DEFAULT_LOCALE]
+        .iter()
+        .find_map(|locale| CATALOGS.get(*locale)?.get(key))
+        .map(String::as_str)
+        .unwrap_or(key)
+}
+
+/// Turns nested tables into keys separated by dots
+fn flatten(prefix: &str, table: toml::Table, catalog: &mut Catalog) {
+    for (name, value) in table {
+        let key = if prefix.is_empty() {
+            name
+        } else {
+            format!("{}.{}", prefix, name)
+        };
+        match value {
+            toml::Value::String(message) => {
+                catalog.insert(key, message);
+            }
+            toml::Value::Table(table) => flatten(&key, table, catalog),
+            _ => warn!("The message {} is not a string", key),
+        }
+    }
+}
+
+fn parse_catalog(contents: &str) -> Result<Catalog, toml::de::Error> {
+    let mut catalog = Catalog::default();
+    flatten("", toml::from_str(contents)?, &mut catalog);
+    Ok(catalog)
+}
+
+fn load_catalogs() -> FxHashMap<String, Catalog> {
+    let mut catalogs = FxHashMap::default();
+    catalogs.insert(
+        DEFAULT_LOCALE.to_string(),
+        parse_catalog(EN_US).expect("the built in messages are invalid"),
+    );
+
+    let Ok(dir) = fs::read_dir(LANG_DIR) else {
+        return catalogs;
+    };
+    for entry in dir.flatten() {
+        let path = entry.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some("toml") {
+            continue;
+        }
+        let Some(locale) = path.file_stem().and_then(|stem| stem.to_str()) else {
+            continue;
+        };
+        match load_file(&path) {
+            Ok(messages) => {
+                info!("Loaded {} messages for {}", messages.len(), locale);
+                catalogs
+                    .entry(locale.to_lowercase())
+                    .or_default()
+                    .extend(messages);
+            }
+            Err(err) => warn!("There was an error loading {}: {}", path.display(), err),
+        }
+    }
+    catalogs
+}
+
+fn load_file(path: &Path) -> anyhow::Result<Catalog> {
+    Ok(parse_catalog(&fs::read_to_string(path)?)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use regex::Regex;
+    use std::path::PathBuf;
+
+    #[test]
+    fn placeholders() {
+        let text = text!("plot.claimed", x = 3, z = -2);
+        assert_eq!(text.translate("en_us"), "Claimed plot 3,-2");
+        // Languages without the message fall back to English
+        assert_eq!(text.translate("xx_xx"), "Claimed plot 3,-2");
+
+        let text = text!(
+            "operation.cooldown",
+            command = "/plot clear",
+            wait = text!("time.second")
+        );
+        assert_eq!(
+            text.translate("en_us"),
+            "You can use /plot clear again in 1 second."
+        );
+    }
+
+    fn source_files(dir: PathBuf, files: &mut Vec<PathBuf>) {
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                source_files(path, files);
+            } else if path.extension().is_some_and(|ext| ext == "rs") {
+                files.push(path);
+            }
+        }
+    }
+
+    /// Every message used in the code has to be in the English catalog
+    #[test]
+    fn messages_exist() {
+        let catalog = parse_catalog(EN_US).unwrap();
+        let key = Regex::new(r#"text!\(\s*"([^"]+)""#).unwrap();
+        let mut files = Vec::new();
+        source_files(
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src"),
+            &mut files,
+        );
+        for file in files {
+            let source = fs::read_to_string(&file).unwrap();
+            for captures in key.captures_iter(&source) {
+                let key = &captures[1];
+                assert!(
+                    catalog.contains_key(key),
+                    "{} uses the message {}, which is not in en_us.toml",
+                    file.display(),
+                    key
+                );
+            }
+        }
+    }
+}
@@ -1,14 +1,33 @@
 #![deny(rust_2018_idioms)]
 
+#[macro_use]
+mod lang;
 #[macro_use]
 mod utils;
+mod admin_api;
+pub mod bench;
 mod config;
+pub mod convert;
+mod discord;
+pub mod events;
+mod floodgate;
 mod interaction;
+mod metrics;
+pub mod midi;
 mod permissions;
 mod player;
 pub mod plot;
+pub mod plugin;
 mod profile;
+mod progress;
+pub mod replay;
+mod scheduler;
 pub mod server;
+mod snbt;
+mod spawn;
+mod vanilla_player;
+mod webmap;
+mod worlds;
 
 #[macro_use]
 extern crate bitflags;
@@ -0,0 +1,201 @@
+//! Server-wide statistics, optionally served over HTTP in the Prometheus text format.
+//!
+//! Plot threads report their own statistics about once a second, and the server thread
+//! keeps the global counters up to date. These are also used by the `/tps` command to
+//! show how other plots are doing.
+
+use crate::config::CONFIG;
+use rustc_hash::FxHashMap;
+use std::fmt::Write as _;
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+pub static PLAYERS_ONLINE: AtomicUsize = AtomicUsize::new(0);
+pub static PACKETS_RECEIVED: AtomicU64 = AtomicU64::new(0);
+
+/// The statistics of every loaded plot, by world and plot position
+static PLOTS: Mutex<Option<FxHashMap<(String, i32, i32), PlotMetrics>>> = Mutex::new(None);
+
+#[derive(Debug, Clone)]
+pub struct PlotMetrics {
+    /// The average tick duration of the last 10 seconds, or `None` if the plot hasn't ticked
+    pub tick_duration: Option<Duration>,
+    /// `None` if the plot is running at unlimited rtps
+    pub target_rtps: Option<u32>,
+    /// The rtps achieved over the last 10 seconds
+    pub achieved_rtps: f32,
+    pub players: usize,
+    /// A rough estimate of how much memory the plot world takes up, see [`memory_usage_shown`]
+    pub memory_bytes: Option<usize>,
+}
+
+/// Whether plots estimate their memory usage, which walks all of their chunks. Only the metrics
+/// endpoint and the admin API show it.
+pub fn memory_usage_shown() -> bool {
+    CONFIG.metrics.as_ref().is_some_and(|config| config.enabled)
+        || CONFIG
+            .admin_api
+            .as_ref()
+            .is_some_and(|config| config.enabled)
+}
+
+pub fn update_plot(world: &str, plot_x: i32, plot_z: i32, metrics: PlotMetrics) {
+    PLOTS
+        .lock()
+        .unwrap()
+        .get_or_insert_with(Default::default)
+        .insert((world.to_string(), plot_x, plot_z), metrics);
+}
+
+pub fn remove_plot(world: &str, plot_x: i32, plot_z: i32) {
+    if let Some(plots) = PLOTS.lock().unwrap().as_mut() {
+        plots.remove(&(world.to_string(), plot_x, plot_z));
+    }
+}
+
+/// Returns the statistics of every loaded plot, with the world and position of the plot
+pub fn plots() -> Vec<((String, i32, i32), PlotMetrics)> {
+    PLOTS
+        .lock()
+        .unwrap()
+        .iter()
+        .flatten()
+        .map(|(pos, metrics)| (pos.clone(), metrics.clone()))
+        .collect()
+}
+
+fn write_header(out: &mut String, name: &str, ty: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, ty);
+}
+
+fn render() -> String {
+    let mut out = String::new();
+
+    write_header(
+        &mut out,
+        "mchprs_players_online",
+        "gauge",
+        "Number of players connected to the server",
+    );
+    let _ = writeln!(
+        out,
+        "mchprs_players_online {}",
+        PLAYERS_ONLINE.load(Ordering::Relaxed)
+    );
+
+    write_header(
+        &mut out,
+        "mchprs_packets_received_total",
+        "counter",
+        "Number of packets received from players in plots",
+    );
+    let _ = writeln!(
+        out,
+        "mchprs_packets_received_total {}",
+        PACKETS_RECEIVED.load(Ordering::Relaxed)
+    );
+
+    let plots = plots();
+
+    write_header(
+        &mut out,
+        "mchprs_plots_loaded",
+        "gauge",
+        "Number of plots currently loaded",
+    );
+    let _ = writeln!(out, "mchprs_plots_loaded {}", plots.len());
+
+    // Gauges return `None` when there is no sample for a plot
+    let plot_gauges: [(&str, &str, fn(&PlotMetrics) -> Option<String>); 5] = [
+        (
+            "mchprs_plot_tick_duration_seconds",
+            "Average duration of a tick of a plot over the last 10 seconds",
+            |m| m.tick_duration.map(|d| d.as_secs_f64().to_string()),
+        ),
+        (
+            "mchprs_plot_target_rtps",
+            "Redstone ticks per second a plot is set to run at",
+            |m| {
+                Some(
+                    m.target_rtps
+                        .map_or("+Inf".to_string(), |tps| tps.to_string()),
+                )
+            },
+        ),
+        (
+            "mchprs_plot_achieved_rtps",
+            "Redstone ticks per second a plot achieved over the last 10 seconds",
+            |m| Some(m.achieved_rtps.to_string()),
+        ),
+        ("mchprs_plot_players", "Number of players in a plot", |m| {
+            Some(m.players.to_string())
+        }),
+        (
+            "mchprs_plot_memory_bytes",
+            "Estimated memory used by the world of a plot",
+            |m| m.memory_bytes.map(|bytes| bytes.to_string()),
+        ),
+    ];
+    for (name, help, value) in plot_gauges {
+        write_header(&mut out, name, "gauge", help);
+        for ((world, x, z), metrics) in &plots {
+            if let Some(value) = value(metrics) {
+                let _ = writeln!(
+                    out,
+                    "{}{{world=\"{}\",plot_x=\"{}\",plot_z=\"{}\"}} {}",
+                    name, world, x, z, value
+                );
+            }
+        }
+    }
+
+    out
+}
+
+fn handle_connection(mut stream: TcpStream) -> io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    // Every path gets the same response, so we don't care what the request is.
+    let mut buf = [0; 1024];
+    let _ = stream.read(&mut buf)?;
+
+    let body = render();
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+pub fn init(bind_address: &str) {
+    let listener = match TcpListener::bind(bind_address) {
+        Ok(listener) => listener,
+        Err(err) => {
+            error!(
+                "Failed to bind metrics endpoint to {}: {}",
+                bind_address, err
+            );
+            return;
+        }
+    };
+    info!("Serving metrics on {}", bind_address);
+
+    thread::Builder::new()
+        .name("metrics".to_string())
+        .spawn(move || {
+            for stream in listener.incoming() {
+                let result = stream.and_then(handle_connection);
+                if let Err(err) = result {
+                    warn!("Error serving metrics request: {}", err);
+                }
+            }
+        })
+        .unwrap();
+}
@@ -0,0 +1,391 @@
+//! Generates a note block contraption that plays a MIDI file, without starting the server.
+//!
+//! The song is quantized to redstone ticks at 10 rtps. A line of repeaters runs east from a
+//! button, with a block in the line for every tick with notes to play. From each of those blocks a
+//! repeater turns off the line into a wire, and the note blocks for that tick are on both sides of
+//! the block under the wire. The wires alternate between the north and south side of the line, so
+//! the note blocks of ticks right after each other never touch.
+
+use crate::plot::worldedit::{save_schematic_file, WorldEditClipboard};
+use anyhow::{bail, Context, Result};
+use mchprs_blocks::blocks::{
+    Block, ButtonFace, Instrument, RedstoneRepeater, RedstoneWire, RedstoneWireSide, StoneButton,
+};
+use mchprs_blocks::{BlockDirection, BlockPos};
+use mchprs_world::storage::PalettedBitBuffer;
+use midly::{MetaMessage, MidiMessage, Smf, Timing, TrackEventKind};
+use rustc_hash::FxHashMap;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::time::Instant;
+
+/// The length of a redstone tick at 10 rtps
+const TICK_MICROS: u64 = 100_000;
+/// The tempo of a MIDI file until it sets one, 120 beats per minute
+const DEFAULT_TEMPO: u64 = 500_000;
+/// The channel General MIDI uses for drums
+const PERCUSSION_CHANNEL: u8 = 9;
+/// Every 2 note blocks take a wire, and a wire can only be 15 long
+const MAX_NOTES_PER_TICK: usize = 30;
+
+const INSTRUMENT_Y: i32 = 0;
+const FLOOR_Y: i32 = 1;
+const LINE_Y: i32 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Note {
+    instrument: Instrument,
+    /// The number of clicks on the note block, 0 to 24
+    pitch: u32,
+}
+
+impl Note {
+    /// Picks the note block that plays a MIDI key, moving it by octaves into the range of the
+    /// instrument if needed
+    fn from_key(channel: u8, key: u8) -> Note {
+        if channel == PERCUSSION_CHANNEL {
+            let instrument = match key {
+                35 | 36 => Instrument::Basedrum,
+                37..=40 => Instrument::Snare,
+                _ => Instrument::Hat,
+            };
+            return Note {
+                instrument,
+                pitch: 12,
+            };
+        }
+
+        // Each instrument plays the 2 octaves from its lowest F#
+        let (instrument, lowest) = match key {
+            0..=53 => (Instrument::Bass, 30),
+            54..=78 => (Instrument::Harp, 54),
+            _ => (Instrument::Bell, 78),
+        };
+        let mut key = key as i32;
+        while key < lowest {
+            key += 12;
+        }
+        while key > lowest + 24 {
+            key -= 12;
+        }
+        Note {
+            instrument,
+            pitch: (key - lowest) as u32,
+        }
+    }
+
+    fn sort_key(&self) -> (u32, u32) {
+        (self.instrument.get_id(), self.pitch)
+    }
+}
+
+/// The block that goes under a note block to give it the instrument. Harp is the instrument of
+/// everything without another one, so it gets air.
+fn instrument_block(instrument: Instrument) -> Block {
+    match instrument {
+        Instrument::Basedrum => Block::Stone {},
+        Instrument::Snare => Block::Sand {},
+        Instrument::Hat => Block::Glass {},
+        Instrument::Bass => Block::Composter { level: 0 },
+        Instrument::Bell => Block::GoldBlock {},
+        _ => Block::Air {},
+    }
+}
+
+/// Converts a time in MIDI ticks to microseconds, following the changes of tempo before it
+fn micros_at(tick: u64, tempos: &[(u64, u64)], ticks_per_beat: u64) -> u64 {
+    let (mut micros, mut last_tick, mut tempo) = (0, 0, DEFAULT_TEMPO);
+    for &(at, new_tempo) in tempos {
+        if at >= tick {
+            break;
+        }
+        micros += (at - last_tick) * tempo / ticks_per_beat;
+        last_tick = at;
+        tempo = new_tempo;
+    }
+    micros + (tick - last_tick) * tempo / ticks_per_beat
+}
+
+/// Reads the notes of a song, by the redstone tick they are played at after the first one
+fn read_song(smf: &Smf<'_>) -> BTreeMap<u64, Vec<Note>> {
+    let mut tempos = Vec::new();
+    let mut notes = Vec::new();
+    for track in &smf.tracks {
+        let mut tick = 0;
+        for event in track {
+            tick += event.delta.as_int() as u64;
+            match event.kind {
+                TrackEventKind::Meta(MetaMessage::Tempo(tempo)) => {
+                    tempos.push((tick, tempo.as_int() as u64));
+                }
+                // A note on with no velocity is how many files turn notes off
+                TrackEventKind::Midi {
+                    channel,
+                    message: MidiMessage::NoteOn { key, vel },
+                } if vel.as_int() > 0 => {
+                    notes.push((tick, Note::from_key(channel.as_int(), key.as_int())))
+                }
+                _ => {}
+            }
+        }
+    }
+    tempos.sort_by_key(|&(tick, _)| tick);
+
+    let micros = |tick: u64| match smf.header.timing {
+        Timing::Metrical(ticks_per_beat) => {
+            micros_at(tick, &tempos, ticks_per_beat.as_int().max(1) as u64)
+        }
+        Timing::Timecode(fps, subframes) => {
+            (tick as f64 * 1_000_000.0 / (fps.as_f32() as f64 * subframes.max(1) as f64)) as u64
+        }
+    };
+    let mut song: BTreeMap<u64, Vec<Note>> = BTreeMap::new();
+    for (tick, note) in notes {
+        let redstone_tick = (micros(tick) + TICK_MICROS / 2) / TICK_MICROS;
+        song.entry(redstone_tick).or_default().push(note);
+    }
+    for notes in song.values_mut() {
+        notes.sort_by_key(Note::sort_key);
+        notes.dedup();
+    }
+    // Start right away, even if the song starts with silence
+    let first = song.keys().next().copied().unwrap_or(0);
+    song.into_iter()
+        .map(|(tick, notes)| (tick - first, notes))
+        .collect()
+}
+
+struct Contraption {
+    blocks: FxHashMap<BlockPos, Block>,
+    /// How many notes there were too many of in their tick to get a note block
+    dropped: usize,
+}
+
+impl Contraption {
+    fn place(&mut self, pos: BlockPos, block: Block) {
+        self.blocks.insert(pos, block);
+    }
+
+    /// Places a block in the line with the floor under it
+    fn place_on_floor(&mut self, pos: BlockPos, block: Block) {
+        self.place(BlockPos::new(pos.x, FLOOR_Y, pos.z), Block::Stone {});
+        self.place(pos, block);
+    }
+
+    fn build(song: &BTreeMap<u64, Vec<Note>>) -> Contraption {
+        let mut contraption = Contraption {
+            blocks: FxHashMap::default(),
+            dropped: 0,
+        };
+        let button = StoneButton {
+            face: ButtonFace::Floor,
+            facing: BlockDirection::East,
+            powered: false,
+        };
+        contraption.place_on_floor(BlockPos::new(0, LINE_Y, 0), Block::Stone {});
+        contraption.place(
+            BlockPos::new(0, LINE_Y + 1, 0),
+            Block::StoneButton { button },
+        );
+
+        // The x of the end of the line, and the tick it gets powered on after the button is pressed
+        let (mut x, mut powered_at) = (0, 0);
+        for (i, (&tick, notes)) in song.iter().enumerate() {
+            // Starting from the tick after the button, since there needs to be a repeater between
+            let mut delay = tick + 1 - powered_at;
+            while delay > 0 {
+                let repeater = RedstoneRepeater {
+                    delay: delay.min(4) as u8,
+                    facing: BlockDirection::West,
+                    ..Default::default()
+                };
+                x += 1;
+                contraption.place_on_floor(
+                    BlockPos::new(x, LINE_Y, 0),
+                    Block::RedstoneRepeater { repeater },
+                );
+                delay -= delay.min(4);
+            }
+            powered_at = tick + 1;
+            x += 1;
+            contraption.place_on_floor(BlockPos::new(x, LINE_Y, 0), Block::Stone {});
+
+            let (side, facing) = if i % 2 == 0 {
+                (-1, BlockDirection::South)
+            } else {
+                (1, BlockDirection::North)
+            };
+            let repeater = RedstoneRepeater {
+                facing,
+                ..Default::default()
+            };
+            contraption.place_on_floor(
+                BlockPos::new(x, LINE_Y, side),
+                Block::RedstoneRepeater { repeater },
+            );
+            let wire = RedstoneWire {
+                north: RedstoneWireSide::Side,
+                south: RedstoneWireSide::Side,
+                ..Default::default()
+            };
+            let kept = notes.len().min(MAX_NOTES_PER_TICK);
+            contraption.dropped += notes.len() - kept;
+            for wire_z in 0..kept.div_ceil(2) as i32 {
+                let z = side * (wire_z + 2);
+                contraption
+                    .place_on_floor(BlockPos::new(x, LINE_Y, z), Block::RedstoneWire { wire });
+            }
+            for (j, note) in notes[..kept].iter().enumerate() {
+                let z = side * (j as i32 / 2 + 2);
+                let note_x = if j % 2 == 0 { x - 1 } else { x + 1 };
+                contraption.place(
+                    BlockPos::new(note_x, INSTRUMENT_Y, z),
+                    instrument_block(note.instrument),
+                );
+                contraption.place(
+                    BlockPos::new(note_x, FLOOR_Y, z),
+                    Block::NoteBlock {
+                        instrument: note.instrument,
+                        note: note.pitch,
+                        powered: false,
+                    },
+                );
+            }
+        }
+        contraption
+    }
+
+    /// Copies the contraption into a clipboard with its lowest corner as the origin
+    fn to_clipboard(&self) -> WorldEditClipboard {
+        let min = self
+            .blocks
+            .keys()
+            .fold(BlockPos::new(0, 0, 0), |min, &pos| min.min(pos));
+        let max = self
+            .blocks
+            .keys()
+            .fold(BlockPos::new(0, 0, 0), |max, &pos| max.max(pos));
+        let size = max - min;
+        let (size_x, size_y, size_z) = (size.x as u32 + 1, size.y as u32 + 1, size.z as u32 + 1);
+        let mut data = PalettedBitBuffer::new((size_x * size_y * size_z) as usize, 9);
+        for (&pos, block) in &self.blocks {
+            let pos = pos - min;
+            let index = (pos.y as u32 * size_z + pos.z as u32) * size_x + pos.x as u32;
+            data.set_entry(index as usize, block.get_id());
+        }
+        WorldEditClipboard {
+            offset_x: 0,
+            offset_y: 0,
+            offset_z: 0,
+            size_x,
+            size_y,
+            size_z,
+            data,
+            block_entities: FxHashMap::default(),
+        }
+    }
+}
+
+/// Generates a note block contraption that plays the MIDI file at `input` and saves it as the
+/// schematic at `output`, in the format of its extension
+pub fn run(input: &Path, output: &Path) -> Result<()> {
+    let start = Instant::now();
+    if !matches!(
+        output.extension().and_then(|ext| ext.to_str()),
+        Some("schem" | "schematic" | "litematic")
+    ) {
+        bail!("the contraption can only be saved as a .schem or .litematic schematic");
+    }
+    let bytes = fs::read(input).with_context(|| format!("error reading {}", input.display()))?;
+    let smf = Smf::parse(&bytes)
+        .with_context(|| format!("error reading MIDI file at {}", input.display()))?;
+    let song = read_song(&smf);
+    let Some((&length, _)) = song.last_key_value() else {
+        bail!("the MIDI file has no notes");
+    };
+
+    let contraption = Contraption::build(&song);
+    let clipboard = contraption.to_clipboard();
+    save_schematic_file(output, &clipboard)?;
+    println!(
+        "Generated {} ({}x{}x{}) playing {} ticks of {} in {:?}",
+        output.display(),
+        clipboard.size_x,
+        clipboard.size_y,
+        clipboard.size_z,
+        length + 1,
+        input.display(),
+        start.elapsed()
+    );
+    if contraption.dropped > 0 {
+        println!(
+            "{} notes were left out for being played with more than {} others",
+            contraption.dropped,
+            MAX_NOTES_PER_TICK - 1
+        );
+    }
+    println!("Press the button on the west end to play it at 10 rtps.");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bench::HeadlessPlot;
+    use mchprs_world::World;
+
+    #[test]
+    fn notes_play_on_their_tick() {
+        let c = Note::from_key(0, 66);
+        let chord = [
+            c,
+            Note::from_key(0, 42),
+            Note::from_key(PERCUSSION_CHANNEL, 36),
+        ];
+        let song = BTreeMap::from([(0, vec![c]), (1, chord.to_vec()), (6, vec![c])]);
+        let contraption = Contraption::build(&song);
+        let note_blocks: Vec<BlockPos> = contraption
+            .blocks
+            .iter()
+            .filter(|(_, block)| matches!(block, Block::NoteBlock { .. }))
+            .map(|(&pos, _)| pos)
+            .collect();
+        let north = contraption.blocks.keys().map(|pos| pos.z).min().unwrap();
+        let mut plot = HeadlessPlot::from_clipboard(&contraption.to_clipboard()).unwrap();
+
+        plot.use_block(BlockPos::new(0, LINE_Y + 1, -north));
+        let mut played = Vec::new();
+        let mut was_powered = Vec::new();
+        for tick in 0..12 {
+            plot.tick();
+            let mut notes = Vec::new();
+            for &pos in &note_blocks {
+                let pos = BlockPos::new(pos.x, pos.y, pos.z - north);
+                let Block::NoteBlock {
+                    instrument,
+                    note,
+                    powered: true,
+                } = plot.world().get_block(pos)
+                else {
+                    continue;
+                };
+                if !was_powered.contains(&pos) {
+                    was_powered.push(pos);
+                    notes.push(Note {
+                        instrument,
+                        pitch: note,
+                    });
+                }
+            }
+            if !notes.is_empty() {
+                notes.sort_by_key(Note::sort_key);
+                played.push((tick, notes));
+            }
+        }
+
+        let mut chord = chord.to_vec();
+        chord.sort_by_key(Note::sort_key);
+        assert_eq!(played, [(1, vec![c]), (2, chord), (7, vec![c])]);
+    }
+}
@@ -1,8 +1,14 @@
 use crate::config::CONFIG;
+use crate::lang::{Text, DEFAULT_LOCALE};
 use crate::permissions::{self, PlayerPermissionsCache};
 use crate::plot::worldedit::{WorldEditClipboard, WorldEditUndo};
-use crate::plot::PLOT_SCALE;
+use crate::plot::Operation;
+use crate::plot::{geometry, PlotGeometry};
+use crate::spawn;
 use crate::utils::{self, HyphenatedUUID};
+use crate::vanilla_player::VanillaPlayer;
+use crate::worlds::{self, DEFAULT_WORLD};
+use anyhow::{Context, Result};
 use byteorder::{BigEndian, ReadBytesExt};
 use mchprs_blocks::block_entities::{ContainerType, InventoryEntry};
 use mchprs_blocks::items::{Item, ItemStack};
@@ -11,17 +17,27 @@ use mchprs_network::packets::clientbound::*;
 use mchprs_network::packets::{PacketEncoder, PlayerProperty, SlotData};
 use mchprs_network::{PlayerConn, PlayerPacketSender};
 use mchprs_text::{ColorCode, TextComponent, TextComponentBuilder};
+use rustc_hash::FxHashMap;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fmt;
-use std::fs::{self, OpenOptions};
-use std::io::{Cursor, Write};
+use std::fs::{self, File};
+use std::io::{Cursor, ErrorKind, Write};
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicU32, Ordering};
-use std::time::{Instant, SystemTime};
-use tracing::{error, warn};
+use std::time::{Duration, Instant, SystemTime};
+use tracing::{info, warn};
 
 pub type EntityId = u32;
 static ENTITY_ID_COUNTER: AtomicU32 = AtomicU32::new(0);
 
+/// How often the HUD is refreshed, which must be often enough that the action bar doesn't fade
+const HUD_UPDATE_INTERVAL: Duration = Duration::from_millis(250);
+/// How long a teleport can go unconfirmed before it is sent again, which is 20 ticks in vanilla
+const TELEPORT_RESEND_INTERVAL: Duration = Duration::from_secs(1);
+/// The number of hotbars that can be saved, the same as the creative saved toolbars
+pub const NUM_SAVED_TOOLBARS: usize = 9;
+
 #[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 pub enum Gamemode {
     Creative,
@@ -53,6 +69,233 @@ pub struct PlayerData {
     gamemode: Gamemode,
 }
 
+/// The hotbars saved with `/toolbar save`. These are written after the `PlayerData` in the
+/// player file so that files saved by older versions still load.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SavedToolbars {
+    toolbars: Vec<Option<Vec<InventoryEntry>>>,
+}
+
+fn player_file(uuid: u128) -> PathBuf {
+    PathBuf::from(format!("./world/players/{:032x}", uuid))
+}
+
+/// Everything in a player file, read before the player is created
+#[derive(Debug)]
+pub struct SavedPlayer {
+    data: PlayerData,
+    toolbars: SavedToolbars,
+    world: Option<String>,
+    homes: BTreeMap<String, Home>,
+    preferences: Preferences,
+}
+
+impl SavedPlayer {
+    /// Reads the player file of `uuid`, returning `None` if the player has never joined. A file
+    /// that can't be deserialized is backed up and the player starts over, but any other error
+    /// is returned, since saving the player later would overwrite the file.
+    pub fn load(uuid: u128, username: &str) -> Result<Option<SavedPlayer>> {
+        let path = player_file(uuid);
+        let data = match fs::read(&path) {
+            Ok(data) => data,
+            Err(err) if err.kind() == ErrorKind::NotFound => {
+                return Ok(SavedPlayer::import_vanilla(uuid, username))
+            }
+            Err(err) => {
+                return Err(err).with_context(|| format!("failed to read {}", path.display()))
+            }
+        };
+        let mut reader = Cursor::new(&data);
+        let player_data: PlayerData = match bincode::deserialize_from(&mut reader) {
+            Ok(data) => data,
+            Err(_) => {
+                warn!("There was an error loading the player data for {}, player data will be backed up and reset.", username);
+                let backup = path.with_extension("bak");
+                fs::rename(&path, &backup).with_context(|| {
+                    format!("failed to back up player data to {}", backup.display())
+                })?;
+                return Ok(None);
+            }
+        };
+        let has_more = |reader: &Cursor<&Vec<u8>>| reader.position() < data.len() as u64;
+
+        // Player files from before saved toolbars end after the player data
+        let toolbars = if has_more(&reader) {
+            bincode::deserialize_from(&mut reader).unwrap_or_else(|_| {
+                warn!(
+                    "There was an error loading the saved toolbars of {}",
+                    username
+                );
+                Default::default()
+            })
+        } else {
+            Default::default()
+        };
+
+        // The world is saved after the toolbars, and is missing from older player files
+        let world = if has_more(&reader) {
+            bincode::deserialize_from::<_, String>(&mut reader)
+                .ok()
+                .filter(|world| worlds::exists(world))
+        } else {
+            None
+        };
+        let homes = if has_more(&reader) {
+            bincode::deserialize_from(&mut reader).unwrap_or_else(|_| {
+                warn!("There was an error loading the homes of {}", username);
+                Default::default()
+            })
+        } else {
+            Default::default()
+        };
+        let preferences = if has_more(&reader) {
+            bincode::deserialize_from(&mut reader).unwrap_or_else(|_| {
+                warn!("There was an error loading the preferences of {}", username);
+                Default::default()
+            })
+        } else {
+            Default::default()
+        };
+
+        Ok(Some(SavedPlayer {
+            data: player_data,
+            toolbars,
+            world,
+            homes,
+            preferences,
+        }))
+    }
+
+    /// Converts the player file from the vanilla server in `vanilla_playerdata` for a player who
+    /// has never joined this server. A file that can't be read is left alone, and the player
+    /// starts over like any other new player.
+    fn import_vanilla(uuid: u128, username: &str) -> Option<SavedPlayer> {
+        let dir = CONFIG.vanilla_playerdata.as_ref()?;
+        let path = Path::new(dir).join(format!("{}.dat", HyphenatedUUID(uuid)));
+        let player = match VanillaPlayer::read(&path) {
+            Ok(player) => player?,
+            Err(err) => {
+                warn!(
+                    "Failed to import the vanilla player data of {}: {:?}",
+                    username, err
+                );
+                return None;
+            }
+        };
+        info!("Imported the vanilla player data of {}", username);
+        Some(SavedPlayer {
+            data: PlayerData {
+                on_ground: player.on_ground,
+                flying: player.flying,
+                motion: [0.0, 0.0, 0.0],
+                position: player.position,
+                rotation: [player.pitch, player.yaw],
+                inventory: player.inventory,
+                selected_item_slot: player.selected_slot,
+                fly_speed: player.fly_speed,
+                walk_speed: player.walk_speed,
+                gamemode: if player.spectator {
+                    Gamemode::Spectator
+                } else {
+                    Gamemode::Creative
+                },
+            },
+            toolbars: Default::default(),
+            world: None,
+            homes: Default::default(),
+            preferences: Default::default(),
+        })
+    }
+}
+
+/// The preferences set with `/toggle`. These are written after the homes in the player file.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Preferences {
+    /// Whether sounds like note blocks are played to the player
+    pub sounds: bool,
+    /// Whether the selection is sent to the worldedit CUI mod
+    pub cui: bool,
+    /// Whether server announcements are shown in chat
+    pub broadcasts: bool,
+    /// Whether the position HUD is shown in the action bar
+    pub hud: bool,
+}
+
+impl Default for Preferences {
+    fn default() -> Preferences {
+        Preferences {
+            sounds: true,
+            cui: true,
+            broadcasts: true,
+            hud: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Preference {
+    Sounds,
+    Cui,
+    Broadcasts,
+    Hud,
+}
+
+impl Preference {
+    pub const ALL: [Preference; 4] = [
+        Preference::Sounds,
+        Preference::Cui,
+        Preference::Broadcasts,
+        Preference::Hud,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Preference::Sounds => "sounds",
+            Preference::Cui => "cui",
+            Preference::Broadcasts => "broadcasts",
+            Preference::Hud => "hud",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Preference> {
+        Preference::ALL
+            .into_iter()
+            .find(|preference| preference.name() == name)
+    }
+}
+
+impl Preferences {
+    pub fn get(&self, preference: Preference) -> bool {
+        match preference {
+            Preference::Sounds => self.sounds,
+            Preference::Cui => self.cui,
+            Preference::Broadcasts => self.broadcasts,
+            Preference::Hud => self.hud,
+        }
+    }
+
+    pub fn get_mut(&mut self, preference: Preference) -> &mut bool {
+        match preference {
+            Preference::Sounds => &mut self.sounds,
+            Preference::Cui => &mut self.cui,
+            Preference::Broadcasts => &mut self.broadcasts,
+            Preference::Hud => &mut self.hud,
+        }
+    }
+}
+
+/// A named position set with `/sethome`. The homes are written after the world in the player
+/// file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Home {
+    pub world: String,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+    pub yaw: f32,
+    pub pitch: f32,
+}
+
 impl Default for PlayerData {
     fn default() -> PlayerData {
         PlayerData {
@@ -113,9 +356,16 @@ impl PlayerPos {
         (self.x.floor() as i32 >> 4, self.z.floor() as i32 >> 4)
     }
 
-    pub fn plot_pos(self) -> (i32, i32) {
+    /// The plot the position is in, in a world with `geometry`
+    pub fn plot_pos(self, geometry: &PlotGeometry) -> (i32, i32) {
         let (chunk_x, chunk_z) = self.chunk_pos();
-        (chunk_x >> PLOT_SCALE, chunk_z >> PLOT_SCALE)
+        let scale = geometry.scale;
+        (chunk_x >> scale, chunk_z >> scale)
+    }
+
+    pub fn distance(self, other: PlayerPos) -> f64 {
+        let (dx, dy, dz) = (self.x - other.x, self.y - other.y, self.z - other.z);
+        (dx * dx + dy * dy + dz * dz).sqrt()
     }
 }
 
@@ -134,6 +384,8 @@ pub struct Player {
     /// The selected slot of the player's hotbar (1-9)
     pub selected_slot: u32,
     pub pos: PlayerPos,
+    /// The name of the plot world the player is in
+    pub world: String,
     /// The last X chunk the player was in. This is used for updated view position.
     pub last_chunk_x: i32,
     /// The last Z chunk the player was in. This is used for updated view position.
@@ -151,10 +403,21 @@ pub struct Player {
     pub gamemode: Gamemode,
     pub entity_id: EntityId,
     pub client: PlayerConn,
+    /// The language the client is set to, like `en_us`
+    pub locale: String,
     /// The last time the keep alive packet was received.
     pub last_keep_alive_received: Instant,
     /// The last time the keep alive packet was sent.
     last_keep_alive_sent: Instant,
+    /// The id of the last teleport sent to the client
+    last_teleport_id: i32,
+    /// The teleport the client hasn't confirmed yet, and when it was sent. Movement isn't
+    /// trusted until it is, since the client sent it before it was teleported.
+    awaiting_teleport: Option<(i32, Instant)>,
+    /// The last time the player's plot was busy tick warping, see `keep_alive_while_warping`
+    last_warp: Option<Instant>,
+    /// The last time the player teleported with the navigation tool
+    pub last_navigation: Option<Instant>,
     /// The worldedit first position.
     pub first_position: Option<BlockPos>,
     /// The worldedit second position.
@@ -165,8 +428,31 @@ pub struct Player {
     /// Each entry stores the plot coords and the clipboard
     pub worldedit_undo: Vec<WorldEditUndo>,
     pub worldedit_redo: Vec<WorldEditUndo>,
+    /// The last worldedit command that changed the world, which `//` runs again
+    pub last_worldedit_command: Option<String>,
     /// Commands are stored so they can be handled after packets
     pub command_queue: Vec<String>,
+    /// The commands of wasm extensions that were declared to the client with the built-in ones
+    pub extension_commands: Vec<&'static str>,
+    /// Whether the stats sidebar is shown instead of the redpiler status
+    pub stats_sidebar: bool,
+    pub preferences: Preferences,
+    /// The position of the container the player has open
+    pub open_container_pos: Option<BlockPos>,
+    /// The hotbars saved with `/toolbar save`
+    pub saved_toolbars: Vec<Option<Vec<InventoryEntry>>>,
+    pub homes: BTreeMap<String, Home>,
+    /// Where the player was before switching to spectator, which they are taken back to when
+    /// they switch back
+    pub spectator_return: Option<Home>,
+    /// The heavy command the player is running, see `plot::operation`
+    pub operation: Option<Operation>,
+    pub operation_started: Instant,
+    /// Whether the operation is done, but commands sent while it ran might not be handled yet
+    pub operation_finished: bool,
+    /// When the player last used each heavy command
+    pub operation_cooldowns: FxHashMap<Operation, Instant>,
+    last_hud_update: Instant,
     permissions_cache: Option<PlayerPermissionsCache>,
 }
 
@@ -179,6 +465,34 @@ impl fmt::Debug for Player {
     }
 }
 
+fn suitable_hotbar_slot(inventory: &[Option<ItemStack>], selected_slot: u32) -> usize {
+    (0..9)
+        .map(|i| 36 + (selected_slot as usize + i) % 9)
+        .find(|&slot| inventory[slot].is_none())
+        .unwrap_or(36 + selected_slot as usize)
+}
+
+fn is_pick_slot(
+    inventory: &[Option<ItemStack>],
+    selected_slot: u32,
+    slot: usize,
+    item: Item,
+) -> bool {
+    let holds_item = |slot: usize| {
+        inventory[slot]
+            .as_ref()
+            .is_some_and(|stack| stack.item_type == item && stack.nbt.is_none())
+    };
+    let pick_slot = match (36..45).chain(9..36).find(|&slot| holds_item(slot)) {
+        Some(slot @ 36..=44) => slot,
+        _ => suitable_hotbar_slot(inventory, selected_slot),
+    };
+    slot == pick_slot
+        && inventory[slot]
+            .as_ref()
+            .is_none_or(|stack| stack.item_type == item)
+}
+
 impl Player {
     pub fn generate_offline_uuid(username: &str) -> u128 {
         Cursor::new(md5::compute(format!("OfflinePlayer:{}", username)).0)
@@ -191,6 +505,7 @@ impl Player {
 
     fn from_data(
         player_data: PlayerData,
+        saved_toolbars: SavedToolbars,
         uuid: u128,
         username: String,
         properties: Vec<PlayerProperty>,
@@ -199,15 +514,31 @@ impl Player {
         // Load inventory
         let mut inventory: Vec<Option<ItemStack>> = vec![None; 46];
         for entry in player_data.inventory {
-            let nbt = entry
-                .nbt
-                .map(|data| nbt::Blob::from_reader(&mut Cursor::new(data)).unwrap());
+            if !(0..inventory.len() as i8).contains(&entry.slot) || entry.count <= 0 {
+                warn!(
+                    "The item in slot {} of {} has an invalid slot or count, it will be dropped",
+                    entry.slot, username
+                );
+                continue;
+            }
+            let nbt = entry.nbt.and_then(|data| {
+                nbt::Blob::from_reader(&mut Cursor::new(data))
+                    .map_err(|err| {
+                        warn!(
+                            "The item in slot {} of {} has invalid nbt, it will be dropped: {}",
+                            entry.slot, username, err
+                        )
+                    })
+                    .ok()
+            });
             inventory[entry.slot as usize] = Some(ItemStack {
                 item_type: Item::from_id(entry.id),
                 count: entry.count as u8,
                 nbt,
             });
         }
+        let mut saved_toolbars = saved_toolbars.toolbars;
+        saved_toolbars.resize(NUM_SAVED_TOOLBARS, None);
         let permissions_cache = CONFIG
             .luckperms
             .is_some()
@@ -224,6 +555,8 @@ impl Player {
                 y: player_data.position[1],
                 z: player_data.position[2],
             },
+            world: DEFAULT_WORLD.to_string(),
+            locale: DEFAULT_LOCALE.to_string(),
             pitch: player_data.rotation[0],
             yaw: player_data.rotation[1],
             last_chunk_x: 0,
@@ -239,74 +572,94 @@ impl Player {
             fly_speed: player_data.fly_speed,
             last_keep_alive_received: Instant::now(),
             last_keep_alive_sent: Instant::now(),
+            last_teleport_id: 0,
+            awaiting_teleport: None,
+            last_warp: None,
+            last_navigation: None,
             first_position: None,
             second_position: None,
             worldedit_clipboard: None,
             worldedit_undo: Vec::new(),
             worldedit_redo: Vec::new(),
+            last_worldedit_command: None,
             command_queue: Vec::new(),
+            extension_commands: Vec::new(),
+            stats_sidebar: false,
+            preferences: Default::default(),
+            open_container_pos: None,
+            saved_toolbars,
+            homes: BTreeMap::new(),
+            spectator_return: None,
+            operation: None,
+            operation_started: Instant::now(),
+            operation_finished: false,
+            operation_cooldowns: FxHashMap::default(),
+            last_hud_update: Instant::now(),
             permissions_cache,
         }
     }
 
-    /// This will load the player from the file. If the file does not exist,
-    /// It will be created.
+    /// Creates the player from what was saved when they last left, or at the spawn if they have
+    /// never joined before.
     pub fn load_player(
+        saved: Option<SavedPlayer>,
         uuid: u128,
         username: String,
         properties: Vec<PlayerProperty>,
         client: PlayerConn,
     ) -> Player {
-        let filename = format!("./world/players/{:032x}", uuid);
-        if let Ok(data) = fs::read(&filename) {
-            let player_data: PlayerData = match bincode::deserialize(&data) {
-                Ok(data) => data,
-                Err(_) => {
-                    warn!("There was an error loading the player data for {}, player data will be backed up and reset.", username);
-                    if let Err(err) = fs::rename(&filename, filename.clone() + ".bak") {
-                        error!("Failed to back up player data: {}", err);
-                    }
-                    return Player::from_data(
-                        Default::default(),
-                        uuid,
-                        username,
-                        properties,
-                        client,
-                    );
-                }
-            };
-
-            Player::from_data(player_data, uuid, username, properties, client)
-        } else {
-            Player::from_data(Default::default(), uuid, username, properties, client)
+        let Some(saved) = saved else {
+            return Player::first_join(uuid, username, properties, client);
+        };
+        let mut player = Player::from_data(
+            saved.data,
+            saved.toolbars,
+            uuid,
+            username,
+            properties,
+            client,
+        );
+        if let Some(world) = saved.world {
+            player.world = world;
         }
+        player.homes = saved.homes;
+        player.preferences = saved.preferences;
+        player
     }
 
-    /// Saves the player to `./world/players/{uuid}`. This will create
-    /// the file if it does not already exist.
-    pub fn save(&self) {
-        let mut file = OpenOptions::new()
-            .write(true)
-            .create(true)
-            .open(format!("./world/players/{:032x}", self.uuid))
-            .unwrap();
+    /// Creates a player without any saved data at the spawn
+    fn first_join(
+        uuid: u128,
+        username: String,
+        properties: Vec<PlayerProperty>,
+        client: PlayerConn,
+    ) -> Player {
+        let mut player = Player::from_data(
+            Default::default(),
+            Default::default(),
+            uuid,
+            username,
+            properties,
+            client,
+        );
+        let spawn = spawn::get();
+        player.pos = PlayerPos::new(spawn.x, spawn.y, spawn.z);
+        player.yaw = spawn.yaw;
+        player.pitch = spawn.pitch;
+        player.world = spawn.world;
+        player
+    }
+
+    /// Saves the player to `./world/players/{uuid}`. Like plots, the file is written next to
+    /// the old one first, so the old file is kept if the save fails.
+    pub fn save(&self) -> Result<()> {
         let mut inventory: Vec<InventoryEntry> = Vec::new();
         for (slot, item_option) in self.inventory.iter().enumerate() {
             if let Some(item) = item_option {
-                let nbt = item.nbt.clone().map(|blob| {
-                    let mut data = Vec::new();
-                    blob.to_writer(&mut data).unwrap();
-                    data
-                });
-                inventory.push(InventoryEntry {
-                    count: item.count as i8,
-                    id: item.item_type.get_id(),
-                    slot: slot as i8,
-                    nbt,
-                });
+                inventory.push(utils::stack_to_inventory_entry(slot as i8, item));
             }
         }
-        let data = bincode::serialize(&PlayerData {
+        let mut data = bincode::serialize(&PlayerData {
             fly_speed: self.fly_speed,
             flying: self.flying,
             gamemode: self.gamemode,
@@ -317,18 +670,151 @@ impl Player {
             rotation: [self.pitch, self.yaw],
             selected_item_slot: self.selected_slot as i32,
             walk_speed: self.walk_speed,
-        })
-        .unwrap();
-        file.write_all(&data).unwrap();
+        })?;
+        let saved_toolbars = SavedToolbars {
+            toolbars: self.saved_toolbars.clone(),
+        };
+        bincode::serialize_into(&mut data, &saved_toolbars)?;
+        bincode::serialize_into(&mut data, &self.world)?;
+        bincode::serialize_into(&mut data, &self.homes)?;
+        bincode::serialize_into(&mut data, &self.preferences)?;
+
+        let path = player_file(self.uuid);
+        let tmp_path = path.with_extension("tmp");
+        let mut file = File::create(&tmp_path)
+            .with_context(|| format!("failed to create {}", tmp_path.display()))?;
+        file.write_all(&data)
+            .and_then(|_| file.sync_data())
+            .with_context(|| format!("failed to write {}", tmp_path.display()))?;
+        fs::rename(&tmp_path, &path)
+            .with_context(|| format!("failed to replace {}", path.display()))?;
+        Ok(())
+    }
+
+    /// Puts the item in the first empty slot, looking through the hotbar before the rest of the
+    /// inventory. Returns false if the inventory is full.
+    pub fn give_item(&mut self, item: ItemStack) -> bool {
+        let Some(slot) = (36..45)
+            .chain(9..36)
+            .find(|&slot| self.inventory[slot].is_none())
+        else {
+            return false;
+        };
+        self.set_inventory_slot(slot as u32, Some(item));
+        true
+    }
+
+    /// Picks `item` into the hotbar like vanilla does. A stack that is already in the hotbar is
+    /// selected and one in the rest of the inventory is swapped into the hotbar. Otherwise the
+    /// item goes into a free hotbar slot, or replaces the selected item, which is moved into
+    /// the inventory if there is room.
+    pub fn pick_item(&mut self, item: ItemStack) {
+        let matching = (36..45).chain(9..36).find(|&slot| {
+            self.inventory[slot]
+                .as_ref()
+                .is_some_and(|stack| stack.item_type == item.item_type && stack.nbt == item.nbt)
+        });
+        let hotbar_slot = match matching {
+            Some(slot @ 36..=44) => slot,
+            Some(slot) => {
+                let hotbar_slot = self.suitable_hotbar_slot();
+                self.inventory.swap(slot, hotbar_slot);
+                hotbar_slot
+            }
+            None => {
+                let hotbar_slot = self.suitable_hotbar_slot();
+                if self.inventory[hotbar_slot].is_some() {
+                    if let Some(free) = (36..45)
+                        .chain(9..36)
+                        .find(|&slot| self.inventory[slot].is_none())
+                    {
+                        self.inventory[free] = self.inventory[hotbar_slot].take();
+                    }
+                }
+                self.inventory[hotbar_slot] = Some(item);
+                hotbar_slot
+            }
+        };
+        self.selected_slot = hotbar_slot as u32 - 36;
+        self.send_inventory();
+    }
+
+    /// Returns the first empty hotbar slot starting from the selected one, or the selected slot
+    fn suitable_hotbar_slot(&self) -> usize {
+        suitable_hotbar_slot(&self.inventory, self.selected_slot)
+    }
+
+    /// Whether a creative client setting `slot` to the plain `item` is how it picks a block. It
+    /// picks into the hotbar slot that already holds the item, or else the suitable hotbar slot,
+    /// and only a slot that is empty or holds the same item can be a pick.
+    pub fn is_pick_slot(&self, slot: usize, item: Item) -> bool {
+        is_pick_slot(&self.inventory, self.selected_slot, slot, item)
+    }
+
+    /// Sends the whole inventory and the selected slot to the client
+    pub fn send_inventory(&self) {
+        let slot_data: Vec<Option<SlotData>> = self
+            .inventory
+            .iter()
+            .map(|op| op.as_ref().map(utils::encode_slot_data))
+            .collect();
+        let window_items = CSetContainerContent {
+            window_id: 0,
+            state_id: 0,
+            slot_data,
+            carried_item: None,
+        }
+        .encode();
+        self.client.send_packet(&window_items);
+
+        let held_item_change = CSetHeldItem {
+            slot: self.selected_slot as i8,
+        }
+        .encode();
+        self.client.send_packet(&held_item_change);
+    }
+
+    /// Saves the player's hotbar as toolbar `index`
+    pub fn save_toolbar(&mut self, index: usize) {
+        let toolbar = self.inventory[36..45]
+            .iter()
+            .enumerate()
+            .filter_map(|(slot, item)| {
+                item.as_ref()
+                    .map(|item| utils::stack_to_inventory_entry(slot as i8, item))
+            })
+            .collect();
+        self.saved_toolbars[index] = Some(toolbar);
+    }
+
+    /// Replaces the player's hotbar with toolbar `index`. Returns false if nothing was saved there.
+    pub fn load_toolbar(&mut self, index: usize) -> bool {
+        let Some(toolbar) = self.saved_toolbars[index].clone() else {
+            return false;
+        };
+        let mut hotbar: [Option<ItemStack>; 9] = Default::default();
+        for entry in &toolbar {
+            if let Some(item) = hotbar.get_mut(entry.slot as usize) {
+                *item = Some(utils::inventory_entry_to_stack(entry));
+            }
+        }
+        for (slot, item) in hotbar.into_iter().enumerate() {
+            self.set_inventory_slot(36 + slot as u32, item);
+        }
+        true
     }
 
     /// Manages keep alives and packet reading. Return true if the view position should be updated.
     pub fn update(&mut self) -> bool {
-        if self.last_keep_alive_received.elapsed().as_secs() > 30 {
-            self.kick("Timed out.".into());
+        if self.timed_out() {
+            self.kick(text!("player.timed_out").translate(&self.locale).into());
         }
-        if self.last_keep_alive_sent.elapsed().as_secs() > 10 {
-            self.send_keep_alive();
+        self.send_keep_alive_if_due();
+        if self
+            .awaiting_teleport
+            .is_some_and(|(_, sent)| sent.elapsed() >= TELEPORT_RESEND_INTERVAL)
+        {
+            self.sync_position(self.pos, self.yaw, self.pitch, 0);
         }
 
         // Prevent from locking player position at Infinity or NaN
@@ -338,10 +824,66 @@ impl Player {
             self.pos.z = 128.0;
         }
 
+        if self.preferences.hud && self.last_hud_update.elapsed() > HUD_UPDATE_INTERVAL {
+            self.update_hud();
+        }
+
         let (chunk_x, chunk_z) = self.pos.chunk_pos();
         chunk_x != self.last_chunk_x || chunk_z != self.last_chunk_z
     }
 
+    fn update_hud(&mut self) {
+        self.last_hud_update = Instant::now();
+        let pos = self.pos.block_pos();
+        let (plot_x, plot_z) = self.pos.plot_pos(&geometry(&self.world));
+        let mut text = text!(
+            "hud.position",
+            x = pos.x,
+            y = pos.y,
+            z = pos.z,
+            facing = self.get_direction(),
+            plot_x = plot_x,
+            plot_z = plot_z
+        )
+        .translate(&self.locale);
+        if let (Some(first), Some(second)) = (self.first_position, self.second_position) {
+            let size = first.max(second) - first.min(second);
+            let volume = (size.x as i64 + 1) * (size.y as i64 + 1) * (size.z as i64 + 1);
+            text += &text!("hud.selection", volume = volume).translate(&self.locale);
+        }
+        self.send_action_bar(TextComponent {
+            extra: TextComponent::from_legacy_text(&text),
+            ..Default::default()
+        });
+    }
+
+    /// Whether the client stopped answering keep alive packets. Packets aren't handled while the
+    /// plot is tick warping, so right after a warp the longer `warp_keep_alive_timeout` is used.
+    fn timed_out(&self) -> bool {
+        let idle = self.last_keep_alive_received.elapsed().as_secs();
+        let timeout = CONFIG.keep_alive_timeout as u64;
+        let warped = self
+            .last_warp
+            .is_some_and(|last_warp| last_warp.elapsed().as_secs() <= timeout);
+        if warped {
+            idle > timeout.max(CONFIG.warp_keep_alive_timeout as u64)
+        } else {
+            idle > timeout
+        }
+    }
+
+    fn send_keep_alive_if_due(&mut self) {
+        if self.last_keep_alive_sent.elapsed().as_secs() >= CONFIG.keep_alive_interval as u64 {
+            self.send_keep_alive();
+        }
+    }
+
+    /// Keeps the client connected while the plot is too busy tick warping to handle its packets
+    pub fn keep_alive_while_warping(&mut self) {
+        self.last_warp = Some(Instant::now());
+        self.send_keep_alive_if_due();
+    }
+
     /// Sends the keep alive packet to the client and updates `last_keep_alive_sent`
     pub fn send_keep_alive(&mut self) {
         let keep_alive = CKeepAlive {
@@ -383,25 +925,58 @@ impl Player {
         }
     }
 
+    /// Whether the client still has to confirm the last teleport. Until it does, the movement it
+    /// sends should be ignored.
+    pub fn is_awaiting_teleport(&self) -> bool {
+        self.awaiting_teleport.is_some()
+    }
+
+    /// Handles the client confirming a teleport. Confirmations of teleports that were sent
+    /// again since are ignored.
+    pub fn confirm_teleport(&mut self, teleport_id: i32) {
+        if self
+            .awaiting_teleport
+            .is_some_and(|(awaiting, _)| awaiting == teleport_id)
+        {
+            self.awaiting_teleport = None;
+        }
+    }
+
     pub fn teleport(&mut self, pos: PlayerPos) {
+        // Pitch and yaw are relative
+        self.sync_position(pos, 0.0, 0.0, 0x08 | 0x10);
+    }
+
+    /// Teleports the player and turns them to look in the direction of `yaw` and `pitch`
+    pub fn teleport_facing(&mut self, pos: PlayerPos, yaw: f32, pitch: f32) {
+        if self.sync_position(pos, yaw, pitch, 0) {
+            self.yaw = yaw;
+            self.pitch = pitch;
+        }
+    }
+
+    fn sync_position(&mut self, pos: PlayerPos, yaw: f32, pitch: f32, flags: u8) -> bool {
         // Prevent from teleporting to Infinity or NaN
         if !pos.x.is_finite() || !pos.y.is_finite() || !pos.z.is_finite() {
-            self.send_error_message("We just saved you from a game crash, don't try it again!");
-            return;
+            self.send_error_message(text!("player.invalid_position"));
+            return false;
         }
 
+        self.last_teleport_id = self.last_teleport_id.wrapping_add(1);
+        self.awaiting_teleport = Some((self.last_teleport_id, Instant::now()));
         let player_position_and_look = CSynchronizePlayerPosition {
             x: pos.x,
             y: pos.y,
             z: pos.z,
-            yaw: 0f32,
-            pitch: 0f32,
-            flags: 0x08 | 0x10, // pitch and yaw are relative
-            teleport_id: 0,
+            yaw,
+            pitch,
+            flags,
+            teleport_id: self.last_teleport_id,
         }
         .encode();
         self.pos = pos;
         self.client.send_packet(&player_position_and_look);
+        true
     }
 
     /// Sends the `ChatMessage` packet containing the raw text component
@@ -415,6 +990,16 @@ impl Player {
         self.client.send_packet(&chat_message);
     }
 
+    /// Shows the text component above the player's hotbar
+    pub fn send_action_bar(&self, message: TextComponent) {
+        let action_bar = CSystemChatMessage {
+            content: message,
+            overlay: true,
+        }
+        .encode();
+        self.client.send_packet(&action_bar);
+    }
+
     /// Sends a raw chat message to the player
     pub fn send_chat_message(&self, message: &[TextComponent]) {
         self.send_raw_chat(TextComponent {
@@ -424,37 +1009,44 @@ impl Player {
     }
 
     pub fn send_no_permission_message(&self) {
-        self.send_error_message("You do not have permission to perform this action.");
+        self.send_error_message(text!("player.no_permission"));
     }
 
     /// Sends the player a light purple system message (`message` is not in json format)
-    pub fn send_worldedit_message(&self, message: &str) {
+    pub fn send_worldedit_message(&self, message: impl Into<Text>) {
         self.send_raw_system_message(
-            TextComponentBuilder::new(message.to_string())
+            TextComponentBuilder::new(message.into().translate(&self.locale))
                 .color_code(ColorCode::LightPurple)
                 .finish(),
         );
     }
 
     pub fn worldedit_set_first_position(&mut self, pos: BlockPos) {
-        self.send_worldedit_message(&format!(
-            "First position set to ({}, {}, {})",
-            pos.x, pos.y, pos.z
+        self.send_worldedit_message(text!(
+            "worldedit.first_position",
+            x = pos.x,
+            y = pos.y,
+            z = pos.z
         ));
         self.first_position = Some(pos);
         self.worldedit_send_cui(&format!("p|0|{}|{}|{}|0", pos.x, pos.y, pos.z));
     }
 
     pub fn worldedit_set_second_position(&mut self, pos: BlockPos) {
-        self.send_worldedit_message(&format!(
-            "Second position set to ({}, {}, {})",
-            pos.x, pos.y, pos.z
+        self.send_worldedit_message(text!(
+            "worldedit.second_position",
+            x = pos.x,
+            y = pos.y,
+            z = pos.z
         ));
         self.second_position = Some(pos);
         self.worldedit_send_cui(&format!("p|1|{}|{}|{}|0", pos.x, pos.y, pos.z));
     }
 
     pub fn worldedit_send_cui(&self, message: &str) {
+        if !self.preferences.cui {
+            return;
+        }
         let cui_plugin_message = CPlayPluginMessage {
             channel: String::from("worldedit:cui"),
             data: Vec::from(message.as_bytes()),
@@ -463,6 +1055,17 @@ impl Player {
         self.client.send_packet(&cui_plugin_message);
     }
 
+    /// Sends the current selection to the worldedit CUI mod, after it was turned back on
+    pub fn worldedit_resend_cui(&self) {
+        self.worldedit_send_cui("s|cuboid");
+        if let Some(pos) = self.first_position {
+            self.worldedit_send_cui(&format!("p|0|{}|{}|{}|0", pos.x, pos.y, pos.z));
+        }
+        if let Some(pos) = self.second_position {
+            self.worldedit_send_cui(&format!("p|1|{}|{}|{}|0", pos.x, pos.y, pos.z));
+        }
+    }
+
     /// Sends the player the disconnect packet, it is still up to the player to end the network
     /// stream.
     pub fn kick(&self, reason: TextComponent) {
@@ -490,6 +1093,19 @@ impl Player {
         self.client.send_packet(&change_game_state);
     }
 
+    /// The most homes the player can set. Players get the highest limit of the permissions
+    /// in `home_limits` that they have.
+    pub fn max_homes(&self) -> usize {
+        CONFIG
+            .home_limits
+            .iter()
+            .flatten()
+            .filter(|limit| self.has_permission(&limit.permission))
+            .map(|limit| limit.max)
+            .fold(CONFIG.max_homes, i64::max)
+            .max(0) as usize
+    }
+
     pub fn has_permission(&self, node: &str) -> bool {
         if let Some(cache) = &self.permissions_cache {
             if let Some(val) = cache.get_node_val(node) {
@@ -504,7 +1120,13 @@ impl Player {
         }
     }
 
-    pub fn open_container(&self, inventory: &[InventoryEntry], container_type: ContainerType) {
+    pub fn open_container(
+        &mut self,
+        pos: BlockPos,
+        inventory: &[InventoryEntry],
+        container_type: ContainerType,
+    ) {
+        self.open_container_pos = Some(pos);
         let mut slots: Vec<Option<SlotData>> =
             (0..container_type.num_slots()).map(|_| None).collect();
         for entry in inventory {
@@ -530,6 +1152,85 @@ impl Player {
         self.client.send_packet(&window_items);
     }
 
+    /// Shows the sky at `time_of_day` ticks, without the client advancing it on its own
+    pub fn send_time_of_day(&self, time_of_day: u32) {
+        // A negative time stops the daylight cycle, so midnight has to be sent as a full day
+        let time_of_day = if time_of_day == 0 {
+            24000
+        } else {
+            time_of_day as i64
+        };
+        let time_update = UpdateTime {
+            world_age: 0,
+            time_of_day: -time_of_day,
+        }
+        .encode();
+        self.client.send_packet(&time_update);
+    }
+
+    /// Shows a world border around a square centered on `x, z`. The client won't let the
+    /// player walk or fly out of it.
+    pub fn send_world_border(&self, x: f64, z: f64, diameter: f64) {
+        let world_border = CInitializeWorldBorder {
+            x,
+            z,
+            old_diameter: diameter,
+            new_diameter: diameter,
+            speed: 0,
+            portal_teleport_boundary: 29999984,
+            warning_blocks: 0,
+            warning_time: 0,
+        }
+        .encode();
+        self.client.send_packet(&world_border);
+    }
+
+    /// Puts the world border back at the edge of the world
+    pub fn reset_world_border(&self) {
+        let world_border = CInitializeWorldBorder {
+            x: 0.0,
+            z: 0.0,
+            old_diameter: 59999968.0,
+            new_diameter: 59999968.0,
+            speed: 0,
+            portal_teleport_boundary: 29999984,
+            warning_blocks: 5,
+            warning_time: 15,
+        }
+        .encode();
+        self.client.send_packet(&world_border);
+    }
+
+    pub fn open_lectern(&mut self, pos: BlockPos, book: &InventoryEntry, page: i32) {
+        self.open_container_pos = Some(pos);
+        let open_window = COpenScreen {
+            window_id: 1,
+            window_type: 17,
+            window_title: "Lectern".into(),
+        }
+        .encode();
+        self.client.send_packet(&open_window);
+
+        let window_items = CSetContainerContent {
+            window_id: 1,
+            state_id: 0,
+            slot_data: vec![Some(utils::encode_slot_data(
+                &utils::inventory_entry_to_stack(book),
+            ))],
+            carried_item: None,
+        }
+        .encode();
+        self.client.send_packet(&window_items);
+
+        let set_page = CSetContainerProperty {
+            window_id: 1,
+            property: 0,
+            value: page as i16,
+        }
+        .encode();
+        self.client.send_packet(&set_page);
+    }
+
     pub fn set_inventory_slot(&mut self, slot: u32, item: Option<ItemStack>) {
         let set_slot = CSetContainerSlot {
             window_id: 0,
@@ -612,6 +1313,11 @@ impl Player {
 pub trait PacketSender {
     fn send_packet(&self, data: &PacketEncoder);
 
+    /// The language messages are translated into
+    fn locale(&self) -> &str {
+        DEFAULT_LOCALE
+    }
+
     /// Sends the `ChatMessage` packet containing the raw json data.
     /// Position 1: system message (chat box)
     fn send_raw_system_message(&self, message: TextComponent) {
@@ -624,18 +1330,18 @@ pub trait PacketSender {
     }
 
     /// Sends the player a red system message (`message` is not in json format)
-    fn send_error_message(&self, message: &str) {
+    fn send_error_message(&self, message: impl Into<Text>) {
         self.send_raw_system_message(
-            TextComponentBuilder::new(message.to_string())
+            TextComponentBuilder::new(message.into().translate(self.locale()))
                 .color_code(ColorCode::Red)
                 .finish(),
         );
     }
 
     /// Sends the player a yellow system message (`message` is not in json format)
-    fn send_system_message(&self, message: &str) {
+    fn send_system_message(&self, message: impl Into<Text>) {
         self.send_raw_system_message(
-            TextComponentBuilder::new(message.to_string())
+            TextComponentBuilder::new(message.into().translate(self.locale()))
                 .color_code(ColorCode::Yellow)
                 .finish(),
         )
@@ -652,4 +1358,75 @@ impl PacketSender for Player {
     fn send_packet(&self, data: &PacketEncoder) {
         self.client.send_packet(data);
     }
+
+    fn locale(&self) -> &str {
+        &self.locale
+    }
+}
+
+/// Lets other threads send messages to a player in their language
+#[derive(Debug)]
+pub struct MessageSender {
+    packets: PlayerPacketSender,
+    locale: String,
+}
+
+impl MessageSender {
+    pub fn new(player: &Player) -> MessageSender {
+        MessageSender {
+            packets: PlayerPacketSender::new(&player.client),
+            locale: player.locale.clone(),
+        }
+    }
+}
+
+impl PacketSender for MessageSender {
+    fn send_packet(&self, data: &PacketEncoder) {
+        self.packets.send_packet(data);
+    }
+
+    fn locale(&self) -> &str {
+        &self.locale
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inventory(items: &[(usize, Item)]) -> Vec<Option<ItemStack>> {
+        let mut inventory = vec![None; 46];
+        for &(slot, item_type) in items {
+            inventory[slot] = Some(ItemStack {
+                item_type,
+                count: 1,
+                nbt: None,
+            });
+        }
+        inventory
+    }
+
+    #[test]
+    fn pick_slots() {
+        let repeater = Item::Repeater {};
+        assert!(is_pick_slot(&inventory(&[]), 0, 36, repeater));
+        assert!(is_pick_slot(&inventory(&[]), 4, 40, repeater));
+
+        let holding = inventory(&[(36, Item::Stone {}), (38, repeater)]);
+        assert!(is_pick_slot(&holding, 0, 38, repeater));
+        assert!(!is_pick_slot(&holding, 0, 37, repeater));
+
+        let full: Vec<_> = (36..45).map(|slot| (slot, Item::Stone {})).collect();
+        assert!(!is_pick_slot(&inventory(&full), 0, 36, repeater));
+    }
+
+    #[test]
+    fn creative_slot_sets_are_not_picks() {
+        // Moving a repeater out of the creative inventory into a slot the client doesn't pick into
+        let repeater = Item::Repeater {};
+        assert!(!is_pick_slot(&inventory(&[]), 0, 40, repeater));
+        let holding = inventory(&[(36, repeater)]);
+        assert!(!is_pick_slot(&holding, 0, 37, repeater));
+        assert!(!is_pick_slot(&inventory(&[]), 0, 12, repeater));
+    }
 }
@@ -1,22 +1,81 @@
-use super::{database, worldedit, Plot, PlotWorld};
-use crate::player::{Gamemode, PacketSender, PlayerPos};
-use crate::plot::data::sleep_time_for_tps;
+use super::profiler::Profiler;
+use super::screen::{self, ScreenFormat};
+use super::{data, database, geometry, worldedit, Operation, Plot, PlotWorld, ASYNC_RT};
+use crate::events::{Event, EventResult, PlayerInfo};
+use crate::lang::Text;
+use crate::metrics;
+use crate::player::{
+    Gamemode, Home, MessageSender, PacketSender, PlayerPos, Preference, NUM_SAVED_TOOLBARS,
+};
+use crate::plugin;
 use crate::profile::PlayerProfile;
-use crate::server::Message;
-use mchprs_blocks::items::ItemStack;
+use crate::progress::ProgressBar;
+use crate::server::{Message, NearQuery};
+use crate::snbt;
+use crate::spawn::{self, Spawn};
+use crate::worlds;
+use mchprs_blocks::items::{Item, ItemStack};
 use mchprs_network::packets::clientbound::{
     CCommands, CCommandsNode as Node, CDeclareCommandsNodeParser as Parser, ClientBoundPacket,
 };
 use mchprs_network::packets::PacketEncoder;
-use mchprs_network::PlayerPacketSender;
-use mchprs_redpiler::CompilerOptions;
+use mchprs_redpiler::{Compiler, CompilerOptions};
 use mchprs_save_data::plot_data::{Tps, WorldSendRate};
 use mchprs_text::TextComponent;
 use once_cell::sync::Lazy;
+use std::fs;
 use std::ops::Add;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
-use std::time::Instant;
-use tracing::{debug, info, warn};
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{debug, error, info, warn};
+
+const REDPILER_EXPORT_DIR: &str = "./redpiler_exports";
+
+/// Whether `name` can be used for a warp or home
+fn is_valid_location_name(name: &str) -> bool {
+    name.len() <= 32
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+/// Parses an item in the form `<item>[nbt] [count]`, like `barrel{BlockEntityTag:{...}} 2`
+fn parse_item_argument(input: &str) -> Result<ItemStack, Text> {
+    let input = input.trim_start();
+    let name_end = input
+        .find(|c: char| c == '{' || c.is_whitespace())
+        .unwrap_or(input.len());
+    let (name, rest) = input.split_at(name_end);
+    let name = name.strip_prefix("minecraft:").unwrap_or(name);
+    let item_type =
+        Item::from_registry_name(name).ok_or_else(|| text!("give.unknown_item", item = name))?;
+
+    let (nbt, rest) = if rest.starts_with('{') {
+        let (nbt, rest) =
+            snbt::parse_compound(rest).map_err(|err| text!("give.invalid_nbt", error = err))?;
+        (Some(nbt), rest)
+    } else {
+        (None, rest)
+    };
+
+    let max_count = item_type.max_stack_size();
+    let count = match rest.trim() {
+        "" => 1,
+        count => count
+            .parse()
+            .ok()
+            .filter(|count| (1..=max_count).contains(count))
+            .ok_or_else(|| text!("give.invalid_count", max = max_count))?,
+    };
+    Ok(ItemStack {
+        item_type,
+        count: count as u8,
+        nbt,
+    })
+}
 
 // Parses a relative or absolute coordinate relative to a reference coordinate
 fn parse_relative_coord<F: FromStr + Add + Add<Output = F>>(
@@ -32,10 +91,274 @@ fn parse_relative_coord<F: FromStr + Add + Add<Output = F>>(
     }
 }
 
+/// Picks a color code for a tick duration based on how much of the tick budget it uses
+fn mspt_color(mspt: Duration, target_rtps: Option<u32>) -> char {
+    let Some(tps) = target_rtps.filter(|tps| *tps != 0) else {
+        return 'a';
+    };
+    let budget = Duration::from_secs(1) / tps;
+    if mspt < budget / 2 {
+        'a'
+    } else if mspt < budget {
+        'e'
+    } else {
+        'c'
+    }
+}
+
+/// Picks a color code for the achieved rtps based on how close it is to the target
+fn rtps_color(achieved: f32, target_rtps: Option<u32>) -> char {
+    let Some(tps) = target_rtps.filter(|tps| *tps != 0) else {
+        return 'a';
+    };
+    let ratio = achieved / tps as f32;
+    if ratio >= 0.95 {
+        'a'
+    } else if ratio >= 0.8 {
+        'e'
+    } else {
+        'c'
+    }
+}
+
+fn as_ms(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
 impl Plot {
+    /// Sends the tick statistics of this plot and of the whole server for `/tps`
+    fn send_tps_report(&self, player: usize) {
+        let target_rtps = match self.tps {
+            Tps::Limited(tps) => Some(tps),
+            Tps::Unlimited => None,
+        };
+        let mut lines = Vec::new();
+
+        let plots = metrics::plots();
+        lines.push(text!(
+            "tps.server",
+            plots = plots.len(),
+            players = metrics::PLAYERS_ONLINE.load(Ordering::Relaxed)
+        ));
+        let ticking: Vec<_> = plots
+            .iter()
+            .filter_map(|((_, x, z), m)| m.tick_duration.map(|d| ((*x, *z), d, m.target_rtps)))
+            .collect();
+        if let Some(&((x, z), slowest, slowest_target)) =
+            ticking.iter().max_by_key(|(_, duration, _)| *duration)
+        {
+            let average =
+                ticking.iter().map(|(_, d, _)| *d).sum::<Duration>() / ticking.len() as u32;
+            lines.push(text!(
+                "tps.plots",
+                average = format!("{:.2}", as_ms(average)),
+                color = mspt_color(slowest, slowest_target),
+                slowest = format!("{:.2}", as_ms(slowest)),
+                x = x,
+                z = z
+            ));
+        }
+
+        lines.push(text!("tps.plot", x = self.world.x, z = self.world.z));
+        match self.timings.tick_duration_report() {
+            Some(report) => lines.push(text!(
+                "tps.plot_mspt",
+                average_color = mspt_color(report.average, target_rtps),
+                average = format!("{:.2}", as_ms(report.average)),
+                max_color = mspt_color(report.max, target_rtps),
+                max = format!("{:.2}", as_ms(report.max))
+            )),
+            None => lines.push(text!("tps.plot_no_mspt")),
+        }
+        match self.timings.generate_report() {
+            Some(report) => lines.push(text!(
+                "tps.plot_rtps",
+                color = rtps_color(report.ten_s, target_rtps),
+                rtps = format!("{:.1}", report.ten_s),
+                target = self.tps
+            )),
+            None => lines.push(text!("tps.plot_no_rtps", target = self.tps)),
+        }
+
+        let player = &self.players[player];
+        for line in lines {
+            let line = line.translate(&player.locale);
+            player.send_chat_message(&TextComponent::from_legacy_text(&line));
+        }
+    }
+
+    /// Handles a command that starts with `/profile`
+    fn handle_profile_command(&mut self, player: usize, command: &str) {
+        match command {
+            "start" => {
+                self.profiler = Some(Profiler::start());
+                self.players[player].send_system_message(text!("profile.started"));
+                if self.redpiler.is_active() {
+                    self.players[player].send_system_message(text!("profile.redpiler_active"));
+                }
+            }
+            "stop" => match &mut self.profiler {
+                Some(profiler) if profiler.is_running() => {
+                    profiler.stop();
+                    self.players[player].send_system_message(text!("profile.stopped"));
+                }
+                _ => self.players[player].send_error_message(text!("profile.not_running")),
+            },
+            "report" => {
+                let Some(profiler) = &self.profiler else {
+                    self.players[player].send_error_message(text!("profile.none"));
+                    return;
+                };
+                let player = &self.players[player];
+                for line in profiler.report() {
+                    let line = line.translate(&player.locale);
+                    player.send_chat_message(&TextComponent::from_legacy_text(&line));
+                }
+            }
+            _ => self.players[player].send_error_message(text!("profile.usage")),
+        }
+    }
+
+    /// Handles a command that starts with `/record`
+    fn handle_record_command(&mut self, player: usize, command: &str) {
+        match command {
+            "start" => {
+                if self.is_recording() {
+                    self.players[player].send_error_message(text!("record.already_recording"));
+                    return;
+                }
+                self.start_recording();
+                self.players[player].send_system_message(text!("record.started"));
+            }
+            "stop" => match self.stop_recording() {
+                Some(Ok(path)) => self.players[player]
+                    .send_system_message(text!("record.saved", path = path.display())),
+                Some(Err(err)) => {
+                    error!("Failed to save recording: {:?}", err);
+                    self.players[player].send_error_message(text!("record.error"));
+                }
+                None => self.players[player].send_error_message(text!("record.not_recording")),
+            },
+            _ => self.players[player].send_error_message(text!("record.usage")),
+        }
+    }
+
+    /// Handles a command that starts with `/screen`
+    fn handle_screen_command(&mut self, player: usize, args: &[&str]) {
+        match args {
+            ["capture", x1, z1, x2, z2, rest @ ..] if rest.len() <= 2 => {
+                if self.is_capturing_screen() {
+                    self.players[player].send_error_message(text!("screen.already_capturing"));
+                    return;
+                }
+                let pos = self.players[player].pos.block_pos();
+                let coords = [(x1, pos.x), (z1, pos.z), (x2, pos.x), (z2, pos.z)]
+                    .map(|(coord, ref_coord)| parse_relative_coord(coord, ref_coord).ok());
+                let [Some(x1), Some(z1), Some(x2), Some(z2)] = coords else {
+                    self.players[player].send_error_message(text!("screen.invalid_coords"));
+                    return;
+                };
+                let (first, second) = self.world.get_corners();
+                let in_plot = |x: i32, z: i32| {
+                    (first.x..=second.x).contains(&x) && (first.z..=second.z).contains(&z)
+                };
+                if !in_plot(x1, z1) || !in_plot(x2, z2) {
+                    self.players[player].send_error_message(text!("screen.outside_plot"));
+                    return;
+                }
+                if x1.abs_diff(x2) >= screen::MAX_SCREEN_SIZE as u32
+                    || z1.abs_diff(z2) >= screen::MAX_SCREEN_SIZE as u32
+                {
+                    self.players[player].send_error_message(text!(
+                        "screen.too_large",
+                        max = screen::MAX_SCREEN_SIZE
+                    ));
+                    return;
+                }
+
+                let mut ticks = screen::DEFAULT_CAPTURE_TICKS;
+                let mut format = ScreenFormat::Gif;
+                for arg in rest {
+                    if let Some(parsed) = ScreenFormat::parse(arg) {
+                        format = parsed;
+                        continue;
+                    }
+                    match arg.parse::<u64>() {
+                        Ok(parsed) if (1..=screen::MAX_CAPTURE_TICKS).contains(&parsed) => {
+                            ticks = parsed
+                        }
+                        _ => {
+                            self.players[player].send_error_message(text!(
+                                "screen.invalid_ticks",
+                                max = screen::MAX_CAPTURE_TICKS
+                            ));
+                            return;
+                        }
+                    }
+                }
+
+                if !self.start_screen_capture(player, (x1, z1), (x2, z2), ticks, format) {
+                    self.players[player].send_error_message(text!("screen.no_lamps"));
+                    return;
+                }
+                self.players[player].send_system_message(text!("screen.started", ticks = ticks));
+            }
+            ["stop"] => {
+                if !self.stop_screen_capture() {
+                    self.players[player].send_error_message(text!("screen.not_capturing"));
+                }
+            }
+            _ => self.players[player].send_error_message(text!("screen.usage")),
+        }
+    }
+
+    /// Teleports the player to `pos` in `world`. Returns true if they left the plot for another
+    /// world, in which case packets should stop being handled.
+    fn teleport_to_world(
+        &mut self,
+        player: usize,
+        world: String,
+        pos: PlayerPos,
+        yaw: f32,
+        pitch: f32,
+    ) -> bool {
+        self.players[player].teleport_facing(pos, yaw, pitch);
+        if world == self.world_name {
+            return false;
+        }
+        self.players[player].world = world;
+        let uuid = self.players[player].uuid;
+        let player = self.leave_plot(uuid);
+        let _ = self.message_sender.send(Message::PlayerLeavePlot(player));
+        true
+    }
+
+    /// Flips one of the player's `/toggle` preferences, which are saved with the player
+    fn toggle_preference(&mut self, player: usize, preference: Preference) {
+        let player_idx = player;
+        let player = &mut self.players[player_idx];
+        let value = player.preferences.get_mut(preference);
+        *value = !*value;
+        let enabled = *value;
+        match preference {
+            Preference::Sounds => self.world.packet_senders[player_idx].sounds_enabled = enabled,
+            Preference::Cui if enabled => player.worldedit_resend_cui(),
+            // Clear the action bar right away instead of waiting for it to fade
+            Preference::Hud if !enabled => player.send_action_bar(Default::default()),
+            _ => {}
+        }
+        let name = preference.name();
+        if enabled {
+            player.send_system_message(text!("toggle.enabled", preference = name));
+        } else {
+            player.send_system_message(text!("toggle.disabled", preference = name));
+        }
+    }
+
     /// Handles a command that starts with `/plot` or `/p`
     fn handle_plot_command(&mut self, player: usize, command: &str, args: &[&str]) {
-        let (plot_x, plot_z) = self.players[player].pos.plot_pos();
+        let geometry = self.world.geometry;
+        let (plot_x, plot_z) = self.players[player].pos.plot_pos(&geometry);
 
         let permission_node = match command {
             "info" | "i" => "plots.info",
@@ -46,8 +369,10 @@ impl Plot {
             "teleport" | "tp" => "plots.visit",
             "lock" | "unlock" => "plots.lock",
             "sel" | "select" => "plots.select",
+            "set" => "plots.set",
+            "clear" => "plots.clear",
             _ => {
-                self.players[player].send_error_message("Invalid argument for /plot");
+                self.players[player].send_error_message(text!("plot.invalid_argument"));
                 return;
             }
         };
@@ -58,18 +383,18 @@ impl Plot {
 
         match command {
             "info" | "i" => {
-                if let Some(owner) = database::get_plot_owner(plot_x, plot_z) {
-                    self.players[player].send_system_message(&format!(
-                        "Plot owner is: {}",
-                        database::get_cached_username(owner.clone()).unwrap_or(owner)
+                if let Some(owner) = database::get_plot_owner(&self.world_name, plot_x, plot_z) {
+                    self.players[player].send_system_message(text!(
+                        "plot.info.owner",
+                        owner = database::get_cached_username(owner.clone()).unwrap_or(owner)
                     ));
                 } else {
-                    self.players[player].send_system_message("Plot is not owned by anyone.");
+                    self.players[player].send_system_message(text!("plot.info.unowned"));
                 }
             }
             "claim" | "c" => {
-                if database::is_claimed(plot_x, plot_z).unwrap() {
-                    self.players[player].send_system_message("Plot is already claimed!");
+                if database::is_claimed(&self.world_name, plot_x, plot_z).unwrap() {
+                    self.players[player].send_system_message(text!("plot.claim.already_claimed"));
                 } else {
                     self.claim_plot(plot_x, plot_z, player);
                 }
@@ -77,7 +402,7 @@ impl Plot {
             "auto" | "a" => {
                 let mut start = (0, 0);
                 for _ in 0..i32::MAX {
-                    if database::is_claimed(start.0, start.1).unwrap() {
+                    if database::is_claimed(&self.world_name, start.0, start.1).unwrap() {
                         start = Plot::get_next_plot(start.0, start.1);
                     } else {
                         self.claim_plot(start.0, start.1, player);
@@ -86,12 +411,12 @@ impl Plot {
                 }
             }
             "middle" => {
-                let center = Plot::get_center(plot_x, plot_z);
+                let center = Plot::get_center(&geometry, plot_x, plot_z);
                 self.players[player].teleport(PlayerPos::new(center.0, 64.0, center.1));
             }
             "visit" | "v" => {
                 if !(1..=2).contains(&args.len()) {
-                    self.players[player].send_error_message("Invalid number of arguments!");
+                    self.players[player].send_error_message(text!("command.invalid_arguments"));
                     return;
                 }
 
@@ -99,7 +424,7 @@ impl Plot {
                     match args[1].parse::<usize>() {
                         Ok(idx) => idx.wrapping_sub(1),
                         Err(_) => {
-                            self.players[player].send_error_message("Unable to parse index");
+                            self.players[player].send_error_message(text!("command.invalid_index"));
                             return;
                         }
                     }
@@ -107,23 +432,23 @@ impl Plot {
                     0
                 };
 
-                let plots = database::get_owned_plots(args[0]);
+                let plots = database::get_owned_plots(&self.world_name, args[0]);
                 if !plots.is_empty() {
                     if let Some(&(plot_x, plot_z)) = plots.get(idx) {
-                        let center = Plot::get_center(plot_x, plot_z);
+                        let center = Plot::get_center(&geometry, plot_x, plot_z);
                         self.players[player].teleport(PlayerPos::new(center.0, 64.0, center.1));
                     } else {
                         self.players[player]
-                            .send_system_message(&format!("Plot range (1, {}).", plots.len()));
+                            .send_system_message(text!("plot.visit.range", max = plots.len()));
                     }
                 } else {
                     self.players[player]
-                        .send_system_message(&format!("{} does not own any plots.", args[0]));
+                        .send_system_message(text!("plot.visit.no_plots", player = args[0]));
                 }
             }
             "teleport" | "tp" => {
                 if args.len() != 2 {
-                    self.players[player].send_error_message("Invalid number of arguments!");
+                    self.players[player].send_error_message(text!("command.invalid_arguments"));
                     return;
                 }
 
@@ -132,34 +457,34 @@ impl Plot {
                 if let Ok(x_arg) = parse_relative_coord(args[0], plot_x) {
                     new_plot_x = x_arg;
                 } else {
-                    self.players[player].send_error_message("Unable to parse x coordinate!");
+                    self.players[player].send_error_message(text!("command.invalid_x"));
                     return;
                 }
                 if let Ok(z_arg) = parse_relative_coord(args[1], plot_z) {
                     new_plot_z = z_arg;
                 } else {
-                    self.players[player].send_error_message("Unable to parse z coordinate!");
+                    self.players[player].send_error_message(text!("command.invalid_z"));
                     return;
                 }
 
-                let center = Plot::get_center(new_plot_x, new_plot_z);
+                let center = Plot::get_center(&geometry, new_plot_x, new_plot_z);
                 self.players[player].teleport(PlayerPos::new(center.0, 64.0, center.1));
             }
             "lock" => {
                 if self.locked_players.insert(self.players[player].entity_id) {
+                    self.send_plot_border(&self.players[player]);
                     let PlotWorld { x, z, .. } = self.world;
-                    let res = format!("Locked to plot ({}, {}). Use '/p unlock' to unlock.", x, z);
-                    self.players[player].send_system_message(&res);
+                    self.players[player].send_system_message(text!("plot.locked", x = x, z = z));
                 } else {
-                    self.players[player]
-                        .send_system_message("You are already locked to this plot.");
+                    self.players[player].send_system_message(text!("plot.already_locked"));
                 }
             }
             "unlock" => {
                 if self.locked_players.remove(&self.players[player].entity_id) {
-                    self.players[player].send_system_message("You are now unlocked.");
+                    self.players[player].reset_world_border();
+                    self.players[player].send_system_message(text!("plot.unlocked"));
                 } else {
-                    self.players[player].send_system_message("You are not locked to this plot.");
+                    self.players[player].send_system_message(text!("plot.not_locked"));
                 }
             }
             "select" | "sel" => {
@@ -167,28 +492,86 @@ impl Plot {
                 self.players[player].worldedit_set_first_position(corners.0);
                 self.players[player].worldedit_set_second_position(corners.1);
             }
-            _ => self.players[player].send_error_message("Invalid argument for /plot"),
+            "clear" => {
+                if !self.can_interact(player) {
+                    self.players[player].send_error_message(text!("plot.clear.not_owner"));
+                    return;
+                }
+                // Clearing can't be undone, so it has to be confirmed
+                if args != ["confirm"] {
+                    self.players[player].send_system_message(text!("plot.clear.confirm"));
+                    return;
+                }
+                if !self.start_operation(player, Operation::PlotClear) {
+                    return;
+                }
+                match data::empty_plot(&self.world_name) {
+                    Ok(plot_data) => {
+                        self.replace_world(plot_data);
+                        self.players[player].send_system_message(text!("plot.clear.done"));
+                    }
+                    Err(err) => {
+                        error!("{:?}", err);
+                        self.players[player].send_error_message(text!("plot.clear.error"));
+                    }
+                }
+                self.finish_operation(player);
+            }
+            "set" => match args {
+                ["time", ticks] => match ticks.parse::<u32>() {
+                    Ok(ticks) => {
+                        let ticks = ticks % 24000;
+                        self.set_time_of_day(ticks);
+                        self.record_snapshot();
+                        self.players[player]
+                            .send_system_message(text!("plot.set_time.done", ticks = ticks));
+                    }
+                    Err(_) => {
+                        self.players[player].send_error_message(text!("command.invalid_ticks"))
+                    }
+                },
+                _ => self.players[player].send_error_message(text!("plot.set_time.usage")),
+            },
+            _ => self.players[player].send_error_message(text!("plot.invalid_argument")),
         }
     }
 
+    /// Compiles the logic graph of the plot with the current redpiler options and writes it to
+    /// a file, returning the path and the number of nodes
+    fn export_redpiler_graph(&mut self, format: &str) -> anyhow::Result<(PathBuf, usize)> {
+        let mut options = self.redpiler.current_flags().cloned().unwrap_or_default();
+        // Don't also write the binary export
+        options.export = false;
+        if self.redpiler.is_active() {
+            self.redpiler.flush(&mut self.world);
+        }
+        let nodes = Compiler::compile_graph(&self.world, self.world.get_corners(), &options);
+        let contents = match format {
+            "dot" => redpiler_graph::to_dot(&nodes),
+            _ => serde_json::to_string_pretty(&nodes)?,
+        };
+
+        fs::create_dir_all(REDPILER_EXPORT_DIR)?;
+        let path = Path::new(REDPILER_EXPORT_DIR)
+            .join(format!("plot_{}_{}.{}", self.world.x, self.world.z, format));
+        fs::write(&path, contents)?;
+        Ok((path, nodes.len()))
+    }
+
     /// Handles a command that starts with `/redpiler` or `/rp`
     fn handle_redpiler_command(&mut self, player: usize, command: &str, args: &[&str]) {
         match command {
             "compile" | "c" => {
-                let start_time = Instant::now();
                 let args = args.join(" ");
                 let options = CompilerOptions::parse(&args);
 
                 if options.optimize {
-                    let msg = "Redpiler optimization is highly unstable and can break builds. Use with caution!";
-                    warn!("{}", msg);
-                    self.players[player].send_system_message(msg);
+                    warn!("Redpiler optimization is highly unstable and can break builds. Use with caution!");
+                    self.players[player].send_system_message(text!("redpiler.optimize_warning"));
                 }
 
                 self.reset_redpiler();
                 self.start_redpiler(options);
-
-                debug!("Compile took {:?}", start_time.elapsed());
             }
             "inspect" | "i" => {
                 let player = &self.players[player];
@@ -200,7 +583,7 @@ impl Plot {
                     10.0,
                 );
                 let Some(pos) = pos else {
-                    player.send_error_message("Trace failed");
+                    player.send_error_message(text!("redpiler.inspect.trace_failed"));
                     return;
                 };
                 self.redpiler.inspect(pos);
@@ -208,7 +591,29 @@ impl Plot {
             "reset" | "r" => {
                 self.reset_redpiler();
             }
-            _ => self.players[player].send_error_message("Invalid argument for /redpiler"),
+            "export" | "e" => {
+                let format = match args {
+                    ["dot"] => "dot",
+                    ["json"] => "json",
+                    _ => {
+                        self.players[player].send_error_message(text!("redpiler.export.usage"));
+                        return;
+                    }
+                };
+                match self.export_redpiler_graph(format) {
+                    Ok((path, node_count)) => self.players[player].send_system_message(text!(
+                        "redpiler.export.done",
+                        nodes = node_count,
+                        path = path.display()
+                    )),
+                    Err(err) => {
+                        error!("Failed to export redpiler graph: {}", err);
+                        self.players[player]
+                            .send_error_message(text!("redpiler.export.error", error = err));
+                    }
+                }
+            }
+            _ => self.players[player].send_error_message(text!("redpiler.invalid_argument")),
         }
     }
 
@@ -226,19 +631,55 @@ impl Plot {
             args.join(" ")
         );
 
+        let event = Event::Command {
+            player: PlayerInfo::of(&self.players[player]),
+            command: command.to_string(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+        };
+        if self.publish_event(event) == EventResult::Cancel {
+            return false;
+        }
+
         // Handle worldedit commands
         if worldedit::execute_command(self, player, command, &mut args) {
             // If the command was handled, there is no need to continue;
             return false;
         }
 
+        if plugin::execute_command(self, player, command, &args) {
+            return false;
+        }
+
+        #[cfg(feature = "wasm")]
+        if self.extensions.has_command(command) {
+            use mchprs_world::World;
+
+            self.reset_redpiler();
+            let can_build = self.can_interact(player);
+            self.extensions.run_command(
+                &mut self.world,
+                &self.players[player],
+                can_build,
+                command,
+                &args,
+            );
+            self.record_snapshot();
+            self.world.flush_block_changes();
+            return false;
+        }
+
+        #[cfg(feature = "lua")]
+        if self.run_script_command(command, &args) {
+            return false;
+        }
+
         match command {
             "whitelist" => match args.as_slice() {
                 ["add", username] => {
                     let username = username.to_string();
                     let sender = self.message_sender.clone();
-                    let packet_sender = PlayerPacketSender::new(&self.players[player].client);
-                    self.async_rt.spawn(async move {
+                    let packet_sender = MessageSender::new(&self.players[player]);
+                    ASYNC_RT.spawn(async move {
                         match PlayerProfile::lookup_by_username(&username).await {
                             Ok(profile) => sender
                                 .send(Message::WhitelistAdd(
@@ -256,8 +697,8 @@ impl Plot {
                 ["remove", username] => {
                     let username = username.to_string();
                     let sender = self.message_sender.clone();
-                    let packet_sender = PlayerPacketSender::new(&self.players[player].client);
-                    self.async_rt.spawn(async move {
+                    let packet_sender = MessageSender::new(&self.players[player]);
+                    ASYNC_RT.spawn(async move {
                         match PlayerProfile::lookup_by_username(&username).await {
                             Ok(profile) => sender
                                 .send(Message::WhitelistRemove(profile.uuid.0, packet_sender))
@@ -269,8 +710,7 @@ impl Plot {
                     });
                 }
                 _ => {
-                    self.players[player]
-                        .send_error_message("Usage: /whitelist [add | remove] (username)");
+                    self.players[player].send_error_message(text!("whitelist.usage"));
                     return false;
                 }
             },
@@ -278,15 +718,21 @@ impl Plot {
                 if args.is_empty() {
                     let report = self.timings.generate_report();
                     if let Some(report) = report {
+                        let text = text!(
+                            "rtps.report",
+                            ten_s = format!("{:.1}", report.ten_s),
+                            one_m = format!("{:.1}", report.one_m),
+                            five_m = format!("{:.1}", report.five_m),
+                            fifteen_m = format!("{:.1}", report.fifteen_m),
+                            rtps = self.tps
+                        );
                         self.players[player].send_chat_message(&TextComponent::from_legacy_text(
-                            &format!(
-                            "&6RTPS from last 10s, 1m, 5m, 15m: &a{:.1}, {:.1}, {:.1}, {:.1} ({})",
-                            report.ten_s, report.one_m, report.five_m, report.fifteen_m, self.tps
-                        ),
+                            &text.translate(&self.players[player].locale),
                         ));
                     } else {
+                        let text = text!("rtps.no_data", rtps = self.tps);
                         self.players[player].send_chat_message(&TextComponent::from_legacy_text(
-                            &format!("&6No timings data. &a({})", self.tps),
+                            &text.translate(&self.players[player].locale),
                         ));
                     }
 
@@ -298,48 +744,214 @@ impl Plot {
                 } else if !args[0].is_empty() && "unlimited".starts_with(args[0]) {
                     Tps::Unlimited
                 } else {
-                    self.players[player].send_error_message("Unable to parse rtps!");
+                    self.players[player].send_error_message(text!("rtps.invalid"));
                     return false;
                 };
 
-                self.sleep_time = sleep_time_for_tps(tps);
-                self.timings.set_tps(tps);
-                self.tps = tps;
-                self.reset_timings();
-                self.players[player].send_system_message("The rtps was successfully set.");
+                self.set_tps(tps);
+                self.players[player].send_system_message(text!("rtps.set"));
+            }
+            "tps" | "lag" => self.send_tps_report(player),
+            #[cfg(feature = "lua")]
+            "script" => self.handle_script_command(player, &args),
+            "profile" => {
+                if args.is_empty() {
+                    self.players[player].send_error_message(text!("profile.usage"));
+                    return false;
+                }
+                self.handle_profile_command(player, args[0]);
+            }
+            "record" => {
+                if !self.players[player].has_permission("mchprs.record") {
+                    self.players[player].send_no_permission_message();
+                    return false;
+                }
+                if args.is_empty() {
+                    self.players[player].send_error_message(text!("record.usage"));
+                    return false;
+                }
+                self.handle_record_command(player, args[0]);
+            }
+            "screen" => {
+                if !self.players[player].has_permission("mchprs.screen") {
+                    self.players[player].send_no_permission_message();
+                    return false;
+                }
+                self.handle_screen_command(player, &args);
             }
             "radv" | "radvance" => {
                 if args.is_empty() {
-                    self.players[player]
-                        .send_error_message("Please specify a number of ticks to advance.");
+                    self.players[player].send_error_message(text!("radvance.usage"));
                     return false;
                 }
                 let ticks = if let Ok(ticks) = args[0].parse::<u32>() {
                     ticks
                 } else {
-                    self.players[player].send_error_message("Unable to parse ticks!");
+                    self.players[player].send_error_message(text!("command.invalid_ticks"));
                     return false;
                 };
+                if !self.start_operation(player, Operation::TickWarp) {
+                    return false;
+                }
                 let start_time = Instant::now();
-                self.tickn(ticks as u64);
+                let mut progress =
+                    ProgressBar::new(&self.players[player], text!("progress.radvance"));
+                // Tick in steps so the progress bar can be updated
+                let step = (ticks / 100).max(1);
+                let mut ticks_done = 0;
+                while ticks_done < ticks {
+                    let n = step.min(ticks - ticks_done);
+                    self.tickn(n as u64);
+                    ticks_done += n;
+                    progress.set_progress(ticks_done as f32 / ticks as f32);
+                    for player in &mut self.players {
+                        player.keep_alive_while_warping();
+                    }
+                }
+                drop(progress);
 
                 if self.redpiler.is_active() {
                     self.redpiler.flush(&mut self.world);
                 }
-                self.players[player].send_system_message(&format!(
-                    "Plot has been advanced by {} ticks ({:?})",
-                    ticks,
-                    start_time.elapsed()
+                self.finish_operation(player);
+                self.players[player].send_system_message(text!(
+                    "radvance.done",
+                    ticks = ticks,
+                    time = format!("{:?}", start_time.elapsed())
                 ));
             }
+            "hud" => self.toggle_preference(player, Preference::Hud),
+            "toggle" => {
+                let Some(preference) = args.first().and_then(|name| Preference::from_name(name))
+                else {
+                    let player = &self.players[player];
+                    let state = |preference| {
+                        if player.preferences.get(preference) {
+                            text!("toggle.on")
+                        } else {
+                            text!("toggle.off")
+                        }
+                        .translate(&player.locale)
+                    };
+                    player.send_error_message(text!("toggle.usage"));
+                    player.send_system_message(text!(
+                        "toggle.status",
+                        sounds = state(Preference::Sounds),
+                        cui = state(Preference::Cui),
+                        broadcasts = state(Preference::Broadcasts),
+                        hud = state(Preference::Hud)
+                    ));
+                    return false;
+                };
+                self.toggle_preference(player, preference);
+            }
+            "give" | "item" | "i" => {
+                if args.is_empty() {
+                    let usage = match command {
+                        "give" => text!("give.usage"),
+                        _ => text!("give.item_usage"),
+                    };
+                    self.players[player].send_error_message(usage);
+                    return false;
+                }
+                // The receiver can be left out, in which case the first argument is the item
+                let first = args[0].split('{').next().unwrap_or_default();
+                let first = first.strip_prefix("minecraft:").unwrap_or(first);
+                let receiver = if command == "give" && Item::from_registry_name(first).is_none() {
+                    Some(args.remove(0))
+                } else {
+                    None
+                };
+
+                let item = match parse_item_argument(&args.join(" ")) {
+                    Ok(item) => item,
+                    Err(err) => {
+                        self.players[player].send_error_message(err);
+                        return false;
+                    }
+                };
+                let receiver = match receiver {
+                    Some(name) if !name.eq_ignore_ascii_case(&self.players[player].username) => {
+                        name
+                    }
+                    _ => {
+                        if !self.players[player].give_item(item) {
+                            self.players[player].send_error_message(text!("player.inventory_full"));
+                        }
+                        return false;
+                    }
+                };
+                let receiver_idx = self
+                    .players
+                    .iter()
+                    .position(|p| p.username.eq_ignore_ascii_case(receiver));
+                match receiver_idx {
+                    Some(receiver_idx) => {
+                        if !self.players[receiver_idx].give_item(item) {
+                            self.players[player].send_error_message(text!(
+                                "give.inventory_full",
+                                player = self.players[receiver_idx].username
+                            ));
+                        }
+                    }
+                    None => {
+                        let packet_sender = MessageSender::new(&self.players[player]);
+                        let _ = self.message_sender.send(Message::GiveItem(
+                            receiver.to_string(),
+                            item,
+                            packet_sender,
+                        ));
+                    }
+                }
+            }
+            "toolbar" => {
+                if args.len() != 2 {
+                    self.players[player].send_error_message(text!("toolbar.usage"));
+                    return false;
+                }
+                let index = match args[1].parse::<usize>() {
+                    Ok(number @ 1..=NUM_SAVED_TOOLBARS) => number - 1,
+                    _ => {
+                        self.players[player].send_error_message(text!(
+                            "toolbar.invalid_number",
+                            max = NUM_SAVED_TOOLBARS
+                        ));
+                        return false;
+                    }
+                };
+                match args[0] {
+                    "save" => {
+                        self.players[player].save_toolbar(index);
+                        self.players[player]
+                            .send_system_message(text!("toolbar.saved", number = index + 1));
+                    }
+                    "load" => {
+                        if !self.players[player].load_toolbar(index) {
+                            self.players[player]
+                                .send_error_message(text!("toolbar.not_saved", number = index + 1));
+                        }
+                    }
+                    _ => self.players[player].send_error_message(text!("toolbar.usage")),
+                }
+            }
+            "togglesidebar" => {
+                let enabled = !self.players[player].stats_sidebar;
+                self.players[player].stats_sidebar = enabled;
+                if enabled {
+                    self.scoreboard.show_stats(&self.players[player]);
+                    self.update_stats_sidebars();
+                    self.players[player].send_system_message(text!("stats.enabled"));
+                } else {
+                    self.scoreboard.hide_stats(&self.players[player]);
+                    self.players[player].send_system_message(text!("stats.disabled"));
+                }
+            }
             "toggleautorp" => {
                 self.auto_redpiler = !self.auto_redpiler;
                 if self.auto_redpiler {
-                    self.players[player]
-                        .send_system_message("Automatic redpiler compilation has been enabled.");
+                    self.players[player].send_system_message(text!("redpiler.auto.enabled"));
                 } else {
-                    self.players[player]
-                        .send_system_message("Automatic redpiler compilation has been disabled.");
+                    self.players[player].send_system_message(text!("redpiler.auto.disabled"));
                 }
             }
             "teleport" | "tp" => {
@@ -351,27 +963,31 @@ impl Plot {
                     if let Ok(x_arg) = parse_relative_coord(args[0], player_pos.x) {
                         x = x_arg;
                     } else {
-                        self.players[player].send_error_message("Unable to parse x coordinate!");
+                        self.players[player].send_error_message(text!("command.invalid_x"));
                         return false;
                     }
                     if let Ok(y_arg) = parse_relative_coord(args[1], player_pos.y) {
                         y = y_arg;
                     } else {
-                        self.players[player].send_error_message("Unable to parse y coordinate!");
+                        self.players[player].send_error_message(text!("command.invalid_y"));
                         return false;
                     }
                     if let Ok(z_arg) = parse_relative_coord(args[2], player_pos.z) {
                         z = z_arg;
                     } else {
-                        self.players[player].send_error_message("Unable to parse z coordinate!");
+                        self.players[player].send_error_message(text!("command.invalid_z"));
                         return false;
                     }
-                    self.players[player]
-                        .send_system_message(&format!("Teleporting to ({}, {}, {})", x, y, z));
+                    self.players[player].send_system_message(text!(
+                        "tp.teleporting_to_pos",
+                        x = x,
+                        y = y,
+                        z = z
+                    ));
                     self.players[player].teleport(PlayerPos::new(x, y, z));
                 } else if args.len() == 1 {
                     self.players[player]
-                        .send_system_message(&format!("Teleporting to {}", args[0]));
+                        .send_system_message(text!("tp.teleporting", target = args[0]));
                     let uuid = self.players[player].uuid;
                     let player = self.leave_plot(uuid);
                     let _ = self
@@ -379,107 +995,405 @@ impl Plot {
                         .send(Message::PlayerTeleportOther(player, args[0].to_string()));
                     return true;
                 } else {
-                    self.players[player]
-                        .send_error_message("Invalid number of arguments for teleport command!");
+                    self.players[player].send_error_message(text!("tp.invalid_arguments"));
                 }
             }
             "stop" => {
                 let _ = self.message_sender.send(Message::Shutdown);
             }
-            "plot" | "p" => {
+            "world" => {
                 if args.is_empty() {
-                    self.players[player].send_error_message("Invalid number of arguments!");
+                    let names: Vec<&str> = worlds::names().collect();
+                    self.players[player].send_system_message(text!(
+                        "world.list",
+                        world = self.world_name,
+                        worlds = names.join(", ")
+                    ));
                     return false;
                 }
-                let command = args.remove(0);
-                self.handle_plot_command(player, command, &args);
-            }
-            "redpiler" | "rp" => {
-                if args.is_empty() {
-                    self.players[player].send_error_message("Invalid number of arguments!");
+                let world = args[0];
+                if !worlds::exists(world) {
+                    self.players[player].send_error_message(text!("world.not_found"));
                     return false;
                 }
-                let command = args.remove(0);
-                self.handle_redpiler_command(player, command, &args);
-            }
-            "speed" => {
-                if args.len() != 1 {
-                    self.players[player].send_error_message("/speed <0-10>");
+                if world == self.world_name {
+                    self.players[player].send_error_message(text!("world.already_in_world"));
                     return false;
                 }
-                if let Ok(speed_arg) = args[0].parse::<f32>() {
-                    if speed_arg < 0.0 {
-                        self.players[player]
-                            .send_error_message("Silly child, you can't have a negative flyspeed!");
-                        return false;
-                    }
-                    if speed_arg > 10.0 {
-                        self.players[player].send_error_message(
-                            "For performance reasons player speed cannot be higher than 10.",
-                        );
+                if let Some(permission) = worlds::permission(world) {
+                    if !self.players[player].has_permission(permission) {
+                        self.players[player].send_no_permission_message();
                         return false;
                     }
-                    if speed_arg.is_nan() {
-                        self.players[player]
-                            .send_error_message("You can't set your speed to NaN or -NaN.");
-                        return false;
-                    }
-                    self.players[player].fly_speed = speed_arg;
-                    self.players[player].update_player_abilities();
-                    let username = self.players[player].username.clone();
-                    self.players[player].send_system_message(&format!(
-                        "Set flying speed to {} for {}",
-                        speed_arg, username
-                    ));
-                } else {
-                    self.players[player].send_error_message("Unable to parse speed value");
                 }
+                self.players[player].send_system_message(text!("world.switching", world = world));
+                let center = Plot::get_center(&geometry(world), 0, 0);
+                self.players[player].world = world.to_string();
+                self.players[player].teleport(PlayerPos::new(center.0, 64.0, center.1));
+                let uuid = self.players[player].uuid;
+                let player = self.leave_plot(uuid);
+                let _ = self.message_sender.send(Message::PlayerLeavePlot(player));
+                return true;
             }
-            "gmsp" => self.change_player_gamemode(player, Gamemode::Spectator),
-            "gmc" => self.change_player_gamemode(player, Gamemode::Creative),
-            "gamemode" => {
-                if args.is_empty() {
-                    self.players[player].send_error_message("Invalid number of arguments!");
+            "spawn" => {
+                let spawn = spawn::get();
+                self.players[player].send_system_message(text!("spawn.teleporting"));
+                let pos = PlayerPos::new(spawn.x, spawn.y, spawn.z);
+                return self.teleport_to_world(player, spawn.world, pos, spawn.yaw, spawn.pitch);
+            }
+            "setspawn" => {
+                if !self.players[player].has_permission("mchprs.setspawn") {
+                    self.players[player].send_no_permission_message();
                     return false;
                 }
-                let name = args.remove(0);
-                let gamemode = match name {
-                    "creative" | "1" => Gamemode::Creative,
-                    "spectator" | "3" => Gamemode::Spectator,
-                    _ => {
-                        self.players[player].send_error_message("Unknown gamemode");
-                        return false;
-                    }
+                let PlayerPos { x, y, z } = self.players[player].pos;
+                let spawn = Spawn {
+                    world: self.world_name.clone(),
+                    x,
+                    y,
+                    z,
+                    yaw: self.players[player].yaw,
+                    pitch: self.players[player].pitch,
                 };
-                self.change_player_gamemode(player, gamemode);
+                match spawn::set(spawn) {
+                    Ok(()) => self.players[player].send_system_message(text!(
+                        "spawn.set",
+                        x = format!("{:.1}", x),
+                        y = format!("{:.1}", y),
+                        z = format!("{:.1}", z),
+                        world = self.world_name
+                    )),
+                    Err(err) => {
+                        error!("Failed to save the spawn: {}", err);
+                        self.players[player].send_error_message(text!("spawn.save_error"));
+                    }
+                }
             }
-            "container" => {
-                if args.len() != 2 {
-                    self.players[player].send_error_message("Usage: /container [type] [power]");
+            "warp" => {
+                let [name] = args.as_slice() else {
+                    self.players[player].send_error_message(text!("warp.usage"));
+                    return false;
+                };
+                let Some(warp) = database::get_warp(name) else {
+                    self.players[player].send_error_message(text!("warp.not_found"));
+                    return false;
+                };
+                if !worlds::exists(&warp.world) {
+                    self.players[player].send_error_message(text!("warp.world_missing"));
                     return false;
                 }
-
+                if let Some(permission) = worlds::permission(&warp.world) {
+                    if !self.players[player].has_permission(permission) {
+                        self.players[player].send_no_permission_message();
+                        return false;
+                    }
+                }
+                self.players[player].send_system_message(text!("warp.warping", name = name));
+                let pos = PlayerPos::new(warp.x, warp.y, warp.z);
+                return self.teleport_to_world(player, warp.world, pos, warp.yaw, warp.pitch);
+            }
+            "warps" => {
+                let names = database::get_warp_names();
+                if names.is_empty() {
+                    self.players[player].send_system_message(text!("warp.none"));
+                } else {
+                    self.players[player]
+                        .send_system_message(text!("warp.list", warps = names.join(", ")));
+                }
+            }
+            "setwarp" => {
+                if !self.players[player].has_permission("mchprs.setwarp") {
+                    self.players[player].send_no_permission_message();
+                    return false;
+                }
+                let [name] = args.as_slice() else {
+                    self.players[player].send_error_message(text!("warp.set_usage"));
+                    return false;
+                };
+                if !is_valid_location_name(name) {
+                    self.players[player].send_error_message(text!("warp.invalid_name"));
+                    return false;
+                }
+                let PlayerPos { x, y, z } = self.players[player].pos;
+                let warp = database::Warp {
+                    world: self.world_name.clone(),
+                    x,
+                    y,
+                    z,
+                    yaw: self.players[player].yaw,
+                    pitch: self.players[player].pitch,
+                };
+                database::set_warp(name, &warp);
+                self.players[player].send_system_message(text!("warp.set", name = name));
+            }
+            "delwarp" => {
+                if !self.players[player].has_permission("mchprs.delwarp") {
+                    self.players[player].send_no_permission_message();
+                    return false;
+                }
+                let [name] = args.as_slice() else {
+                    self.players[player].send_error_message(text!("warp.delete_usage"));
+                    return false;
+                };
+                if database::delete_warp(name) {
+                    self.players[player].send_system_message(text!("warp.deleted", name = name));
+                } else {
+                    self.players[player].send_error_message(text!("warp.not_found"));
+                }
+            }
+            "home" => {
+                let name = args.first().copied().unwrap_or("home");
+                let Some(home) = self.players[player].homes.get(name).cloned() else {
+                    self.players[player].send_error_message(text!("home.not_found"));
+                    return false;
+                };
+                if !worlds::exists(&home.world) {
+                    self.players[player].send_error_message(text!("home.world_missing"));
+                    return false;
+                }
+                if let Some(permission) = worlds::permission(&home.world) {
+                    if !self.players[player].has_permission(permission) {
+                        self.players[player].send_no_permission_message();
+                        return false;
+                    }
+                }
+                self.players[player].send_system_message(text!("home.teleporting", name = name));
+                let pos = PlayerPos::new(home.x, home.y, home.z);
+                return self.teleport_to_world(player, home.world, pos, home.yaw, home.pitch);
+            }
+            "homes" => {
+                let player = &self.players[player];
+                if player.homes.is_empty() {
+                    player.send_system_message(text!("home.none"));
+                } else {
+                    let names: Vec<&str> = player.homes.keys().map(String::as_str).collect();
+                    player.send_system_message(text!(
+                        "home.list",
+                        count = names.len(),
+                        max = player.max_homes(),
+                        homes = names.join(", ")
+                    ));
+                }
+            }
+            "sethome" => {
+                let name = args.first().copied().unwrap_or("home");
+                if !is_valid_location_name(name) {
+                    self.players[player].send_error_message(text!("home.invalid_name"));
+                    return false;
+                }
+                let max_homes = self.players[player].max_homes();
+                if !self.players[player].homes.contains_key(name)
+                    && self.players[player].homes.len() >= max_homes
+                {
+                    self.players[player].send_error_message(text!("home.limit", max = max_homes));
+                    return false;
+                }
+                let PlayerPos { x, y, z } = self.players[player].pos;
+                let home = Home {
+                    world: self.world_name.clone(),
+                    x,
+                    y,
+                    z,
+                    yaw: self.players[player].yaw,
+                    pitch: self.players[player].pitch,
+                };
+                self.players[player].homes.insert(name.to_string(), home);
+                self.players[player].send_system_message(text!("home.set", name = name));
+            }
+            "delhome" => {
+                let name = args.first().copied().unwrap_or("home");
+                if self.players[player].homes.remove(name).is_some() {
+                    self.players[player].send_system_message(text!("home.deleted", name = name));
+                } else {
+                    self.players[player].send_error_message(text!("home.not_found"));
+                }
+            }
+            "kick" | "kickall" => {
+                if !self.players[player].has_permission("mchprs.kick") {
+                    self.players[player].send_no_permission_message();
+                    return false;
+                }
+                let target = if command == "kick" {
+                    if args.is_empty() {
+                        self.players[player].send_error_message(text!("kick.usage"));
+                        return false;
+                    }
+                    Some(args.remove(0).to_string())
+                } else {
+                    None
+                };
+                let reason = (!args.is_empty()).then(|| args.join(" "));
+                let packet_sender = MessageSender::new(&self.players[player]);
+                let _ = self.message_sender.send(Message::Kick {
+                    target,
+                    reason,
+                    staff_uuid: self.players[player].uuid,
+                    staff_name: self.players[player].username.clone(),
+                    sender: packet_sender,
+                });
+            }
+            "list" => {
+                let packet_sender = MessageSender::new(&self.players[player]);
+                let _ = self
+                    .message_sender
+                    .send(Message::ListPlayers(packet_sender));
+            }
+            "seen" => {
+                let [username] = args.as_slice() else {
+                    self.players[player].send_error_message(text!("seen.usage"));
+                    return false;
+                };
+                let packet_sender = MessageSender::new(&self.players[player]);
+                let _ = self
+                    .message_sender
+                    .send(Message::Seen(username.to_string(), packet_sender));
+            }
+            "near" => {
+                let uuid = self.players[player].uuid;
+                let pos = self.players[player].pos;
+                let Some(radius) = args.first() else {
+                    let nearby = self.players_near(uuid, pos, f64::INFINITY);
+                    let player = &self.players[player];
+                    if nearby.is_empty() {
+                        player.send_system_message(text!("near.alone"));
+                    }
+                    for (other, distance) in nearby {
+                        player.send_system_message(text!(
+                            "near.player",
+                            player = other.username,
+                            distance = distance.round(),
+                            x = self.world.x,
+                            z = self.world.z
+                        ));
+                    }
+                    return false;
+                };
+                let radius = match radius.parse::<f64>() {
+                    Ok(radius) if radius > 0.0 && radius.is_finite() => radius,
+                    _ => {
+                        self.players[player].send_error_message(text!("near.invalid_radius"));
+                        return false;
+                    }
+                };
+                self.players[player].send_system_message(text!("near.header", radius = radius));
+                // Every plot in the world answers, including this one
+                let query = NearQuery {
+                    uuid,
+                    world: self.world_name.clone(),
+                    pos,
+                    radius,
+                    sender: Arc::new(MessageSender::new(&self.players[player])),
+                };
+                let _ = self.message_sender.send(Message::Near(query));
+            }
+            "whois" => {
+                if !self.players[player].has_permission("mchprs.whois") {
+                    self.players[player].send_no_permission_message();
+                    return false;
+                }
+                let [username] = args.as_slice() else {
+                    self.players[player].send_error_message(text!("whois.usage"));
+                    return false;
+                };
+                let packet_sender = MessageSender::new(&self.players[player]);
+                let _ = self
+                    .message_sender
+                    .send(Message::WhoIs(username.to_string(), packet_sender));
+            }
+            "plot" | "p" => {
+                if args.is_empty() {
+                    self.players[player].send_error_message(text!("command.invalid_arguments"));
+                    return false;
+                }
+                let command = args.remove(0);
+                self.handle_plot_command(player, command, &args);
+            }
+            "redpiler" | "rp" => {
+                if args.is_empty() {
+                    self.players[player].send_error_message(text!("command.invalid_arguments"));
+                    return false;
+                }
+                let command = args.remove(0);
+                self.handle_redpiler_command(player, command, &args);
+            }
+            "speed" => {
+                if args.len() != 1 {
+                    self.players[player].send_error_message(text!("speed.usage"));
+                    return false;
+                }
+                if let Ok(speed_arg) = args[0].parse::<f32>() {
+                    if speed_arg < 0.0 {
+                        self.players[player].send_error_message(text!("speed.negative"));
+                        return false;
+                    }
+                    if speed_arg > 10.0 {
+                        self.players[player].send_error_message(text!("speed.too_high"));
+                        return false;
+                    }
+                    if speed_arg.is_nan() {
+                        self.players[player].send_error_message(text!("speed.nan"));
+                        return false;
+                    }
+                    self.players[player].fly_speed = speed_arg;
+                    self.players[player].update_player_abilities();
+                    let username = self.players[player].username.clone();
+                    self.players[player].send_system_message(text!(
+                        "speed.set",
+                        speed = speed_arg,
+                        player = username
+                    ));
+                } else {
+                    self.players[player].send_error_message(text!("speed.invalid"));
+                }
+            }
+            "gmsp" => self.change_player_gamemode(player, Gamemode::Spectator),
+            "gmc" => self.change_player_gamemode(player, Gamemode::Creative),
+            "gm" => {
+                let gamemode = match self.players[player].gamemode {
+                    Gamemode::Creative => Gamemode::Spectator,
+                    Gamemode::Spectator => Gamemode::Creative,
+                };
+                self.change_player_gamemode(player, gamemode);
+            }
+            "gamemode" => {
+                if args.is_empty() {
+                    self.players[player].send_error_message(text!("command.invalid_arguments"));
+                    return false;
+                }
+                let name = args.remove(0);
+                let gamemode = match name {
+                    "creative" | "1" => Gamemode::Creative,
+                    "spectator" | "3" => Gamemode::Spectator,
+                    _ => {
+                        self.players[player].send_error_message(text!("gamemode.unknown"));
+                        return false;
+                    }
+                };
+                self.change_player_gamemode(player, gamemode);
+            }
+            "container" => {
+                if args.len() != 2 {
+                    self.players[player].send_error_message(text!("container.usage"));
+                    return false;
+                }
+
                 let power = if let Ok(p) = args[1].parse() {
                     p
                 } else {
-                    self.players[player].send_error_message("Unable to parse power!");
+                    self.players[player].send_error_message(text!("container.invalid_power"));
                     return false;
                 };
 
                 let container_ty = match args[0].parse() {
                     Ok(ty) => ty,
                     Err(()) => {
-                        self.players[player].send_error_message(
-                            "Container type must be one of [barrel, furnace, hopper]",
-                        );
+                        self.players[player].send_error_message(text!("container.invalid_type"));
                         return false;
                     }
                 };
 
                 if !(1..=15).contains(&power) {
-                    self.players[player].send_error_message(
-                        "Container power must be greater than 0 and lower than 15!",
-                    );
+                    self.players[player].send_error_message(text!("container.power_out_of_range"));
                     return false;
                 }
 
@@ -489,35 +1403,92 @@ impl Plot {
             }
             "worldsendrate" | "wsr" => {
                 if args.len() != 1 {
-                    self.players[player].send_error_message("Usage: /worldsendrate <hertz>");
+                    self.players[player].send_error_message(text!("worldsendrate.usage"));
                     return false;
                 }
 
                 let Ok(hertz) = args[0].parse::<u32>() else {
-                    self.players[player].send_error_message("Unable to parse send rate!");
+                    self.players[player].send_error_message(text!("worldsendrate.invalid"));
                     return false;
                 };
                 if hertz == 0 {
-                    self.players[player].send_error_message("The world send rate cannot be 0!");
+                    self.players[player].send_error_message(text!("worldsendrate.zero"));
                     return false;
                 }
                 if hertz > 1000 {
-                    self.players[player]
-                        .send_error_message("The world send rate cannot go higher than 1000!");
+                    self.players[player].send_error_message(text!("worldsendrate.too_high"));
                     return false;
                 }
 
                 self.world_send_rate = WorldSendRate(hertz);
                 self.reset_timings();
-                self.players[player]
-                    .send_system_message("The world send rate was successfully set.");
+                self.players[player].send_system_message(text!("worldsendrate.set"));
             }
-            _ => self.players[player].send_error_message("Command not found!"),
+            _ => self.players[player].send_error_message(text!("command.not_found")),
         }
         false
     }
 }
 
+/// The commands `handle_command` matches itself, including their aliases
+const BUILTIN_COMMANDS: &[&str] = &[
+    "whitelist",
+    "rtps",
+    "tps",
+    "lag",
+    "script",
+    "profile",
+    "record",
+    "screen",
+    "radv",
+    "radvance",
+    "hud",
+    "toggle",
+    "give",
+    "item",
+    "i",
+    "toolbar",
+    "togglesidebar",
+    "toggleautorp",
+    "teleport",
+    "tp",
+    "stop",
+    "world",
+    "spawn",
+    "setspawn",
+    "warp",
+    "warps",
+    "setwarp",
+    "delwarp",
+    "home",
+    "homes",
+    "sethome",
+    "delhome",
+    "kick",
+    "kickall",
+    "list",
+    "seen",
+    "near",
+    "whois",
+    "plot",
+    "p",
+    "redpiler",
+    "rp",
+    "speed",
+    "gmsp",
+    "gmc",
+    "gm",
+    "gamemode",
+    "container",
+    "worldsendrate",
+    "wsr",
+];
+
+/// Whether a command is one of the server's own, which plugins can't register
+pub(crate) fn is_builtin_command(name: &str) -> bool {
+    BUILTIN_COMMANDS.contains(&name) || worldedit::is_command(name)
+}
+
 bitflags! {
     pub struct CommandFlags: u32 {
         const ROOT = 0x0;
@@ -533,14 +1504,20 @@ bitflags! {
 // For more information, see https://wiki.vg/Command_Data
 /// The `DeclareCommands` packet that is sent when the player joins.
 /// This is used for command autocomplete.
-pub static DECLARE_COMMANDS: Lazy<PacketEncoder> = Lazy::new(|| {
+pub static DECLARE_COMMANDS: Lazy<PacketEncoder> = Lazy::new(|| declare_commands(&[]));
+
+/// Builds the `DeclareCommands` packet, with `extra_commands` added like plugin commands. Plots
+/// send this when they have commands of their own.
+pub fn declare_commands(extra_commands: &[&'static str]) -> PacketEncoder {
     let mut packet = CCommands {
         nodes: vec![
             // 0: Root Node
             Node {
                 flags: CommandFlags::ROOT.bits() as i8,
                 children: vec![
-                    1, 4, 5, 6, 8, 10, 11, 13, 18, 30, 34, 41, 43, 44, 45, 49, 51,
+                    1, 4, 5, 6, 8, 10, 11, 13, 18, 30, 34, 41, 43, 44, 45, 49, 51, 52, 53, 54, 58,
+                    59, 64, 68, 72, 73, 77, 80, 81, 82, 84, 85, 86, 87, 89, 90, 91, 92, 93, 95, 96,
+                    99, 100, 105, 106, 108, 111, 118,
                 ],
                 redirect_node: None,
                 name: None,
@@ -658,7 +1635,7 @@ pub static DECLARE_COMMANDS: Lazy<PacketEncoder> = Lazy::new(|| {
             // 13: /plot
             Node {
                 flags: (CommandFlags::LITERAL).bits() as i8,
-                children: vec![14, 15, 16, 17, 19, 20, 21, 22, 24, 25, 27, 28, 29],
+                children: vec![14, 15, 16, 17, 19, 20, 21, 22, 24, 25, 27, 28, 29, 74, 79],
                 redirect_node: None,
                 name: Some("plot"),
                 parser: None,
@@ -847,7 +1824,7 @@ pub static DECLARE_COMMANDS: Lazy<PacketEncoder> = Lazy::new(|| {
             // 34: /container
             Node {
                 flags: (CommandFlags::LITERAL).bits() as i8,
-                children: vec![35, 36, 37],
+                children: vec![35, 36, 37, 63],
                 redirect_node: None,
                 name: Some("container"),
                 parser: None,
@@ -940,7 +1917,7 @@ pub static DECLARE_COMMANDS: Lazy<PacketEncoder> = Lazy::new(|| {
             // 44: /redpiler
             Node {
                 flags: CommandFlags::LITERAL.bits() as i8,
-                children: vec![46, 47, 48], // Children are compile, inspect, reset
+                children: vec![46, 47, 48, 60], // Children are compile, inspect, reset, export
                 redirect_node: None,
                 name: Some("redpiler"),
                 parser: None,
@@ -1009,9 +1986,683 @@ pub static DECLARE_COMMANDS: Lazy<PacketEncoder> = Lazy::new(|| {
                 parser: None,
                 suggestions_type: None,
             },
-        ],
-        root_index: 0,
-    };
-    super::worldedit::add_command_completions(&mut packet.nodes);
-    packet.encode()
-});
+            // 52: /tps
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("tps"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 53: /lag
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("lag"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 54: /profile
+            Node {
+                flags: CommandFlags::LITERAL.bits() as i8,
+                children: vec![55, 56, 57], // Children are start, stop, report
+                redirect_node: None,
+                name: Some("profile"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 55: /profile start
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("start"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 56: /profile stop
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("stop"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 57: /profile report
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("report"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 58: /togglesidebar
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("togglesidebar"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 59: /hud
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("hud"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 60: /redpiler export
+            Node {
+                flags: CommandFlags::LITERAL.bits() as i8,
+                children: vec![61, 62], // Children are dot, json
+                redirect_node: None,
+                name: Some("export"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 61: /redpiler export dot
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("dot"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 62: /redpiler export json
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("json"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 63: /container trapped_chest
+            Node {
+                flags: (CommandFlags::LITERAL).bits() as i8,
+                children: vec![38],
+                redirect_node: None,
+                name: Some("trapped_chest"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 64: /toolbar
+            Node {
+                flags: CommandFlags::LITERAL.bits() as i8,
+                children: vec![65, 66], // Children are save, load
+                redirect_node: None,
+                name: Some("toolbar"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 65: /toolbar save
+            Node {
+                flags: CommandFlags::LITERAL.bits() as i8,
+                children: vec![67],
+                redirect_node: None,
+                name: Some("save"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 66: /toolbar load
+            Node {
+                flags: CommandFlags::LITERAL.bits() as i8,
+                children: vec![67],
+                redirect_node: None,
+                name: Some("load"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 67: /toolbar [save|load] [number]
+            Node {
+                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("number"),
+                parser: Some(Parser::Integer(1, NUM_SAVED_TOOLBARS as i32)),
+                suggestions_type: None,
+            },
+            // 68: /give
+            Node {
+                flags: CommandFlags::LITERAL.bits() as i8,
+                children: vec![69, 70],
+                redirect_node: None,
+                name: Some("give"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 69: /give [player]
+            Node {
+                flags: CommandFlags::ARGUMENT.bits() as i8,
+                children: vec![70],
+                redirect_node: None,
+                name: Some("player"),
+                parser: Some(Parser::Entity(3)), // Only allow one player
+                suggestions_type: None,
+            },
+            // 70: /give [player] <item>
+            Node {
+                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![71],
+                redirect_node: None,
+                name: Some("item"),
+                parser: Some(Parser::ItemStack),
+                suggestions_type: None,
+            },
+            // 71: /give [player] <item> [count]
+            Node {
+                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("count"),
+                parser: Some(Parser::Integer(1, 64)),
+                suggestions_type: None,
+            },
+            // 72: /item
+            Node {
+                flags: CommandFlags::LITERAL.bits() as i8,
+                children: vec![70],
+                redirect_node: None,
+                name: Some("item"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 73: /i
+            Node {
+                flags: (CommandFlags::REDIRECT | CommandFlags::LITERAL).bits() as i8,
+                children: vec![],
+                redirect_node: Some(72), // Redirect to /item
+                name: Some("i"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 74: /plot set
+            Node {
+                flags: (CommandFlags::LITERAL).bits() as i8,
+                children: vec![75],
+                redirect_node: None,
+                name: Some("set"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 75: /plot set time
+            Node {
+                flags: (CommandFlags::LITERAL).bits() as i8,
+                children: vec![76],
+                redirect_node: None,
+                name: Some("time"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 76: /plot set time [ticks]
+            Node {
+                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("ticks"),
+                parser: Some(Parser::Integer(0, i32::MAX)),
+                suggestions_type: None,
+            },
+            // 77: /world
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![78],
+                redirect_node: None,
+                name: Some("world"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 78: /world [name]
+            Node {
+                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("name"),
+                parser: Some(Parser::String(0)),
+                suggestions_type: None,
+            },
+            // 79: /plot clear
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![123],
+                redirect_node: None,
+                name: Some("clear"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 80: /spawn
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("spawn"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 81: /setspawn
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("setspawn"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 82: /warp
+            Node {
+                flags: CommandFlags::LITERAL.bits() as i8,
+                children: vec![83],
+                redirect_node: None,
+                name: Some("warp"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 83: /warp [name], /setwarp [name], /delwarp [name]
+            Node {
+                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("name"),
+                parser: Some(Parser::String(0)),
+                suggestions_type: None,
+            },
+            // 84: /warps
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("warps"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 85: /setwarp
+            Node {
+                flags: CommandFlags::LITERAL.bits() as i8,
+                children: vec![83],
+                redirect_node: None,
+                name: Some("setwarp"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 86: /delwarp
+            Node {
+                flags: CommandFlags::LITERAL.bits() as i8,
+                children: vec![83],
+                redirect_node: None,
+                name: Some("delwarp"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 87: /home
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![88],
+                redirect_node: None,
+                name: Some("home"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 88: /home [name], /sethome [name], /delhome [name]
+            Node {
+                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("name"),
+                parser: Some(Parser::String(0)),
+                suggestions_type: None,
+            },
+            // 89: /sethome
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![88],
+                redirect_node: None,
+                name: Some("sethome"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 90: /delhome
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![88],
+                redirect_node: None,
+                name: Some("delhome"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 91: /homes
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("homes"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 92: /list
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("list"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 93: /seen
+            Node {
+                flags: CommandFlags::LITERAL.bits() as i8,
+                children: vec![94],
+                redirect_node: None,
+                name: Some("seen"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 94: /seen [player], /whois [player]
+            Node {
+                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("player"),
+                parser: Some(Parser::Entity(3)), // Only allow one player
+                suggestions_type: None,
+            },
+            // 95: /whois
+            Node {
+                flags: CommandFlags::LITERAL.bits() as i8,
+                children: vec![94],
+                redirect_node: None,
+                name: Some("whois"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 96: /kick
+            Node {
+                flags: CommandFlags::LITERAL.bits() as i8,
+                children: vec![97],
+                redirect_node: None,
+                name: Some("kick"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 97: /kick [player]
+            Node {
+                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![98],
+                redirect_node: None,
+                name: Some("player"),
+                parser: Some(Parser::Entity(3)), // Only allow one player
+                suggestions_type: None,
+            },
+            // 98: /kick [player] [reason], /kickall [reason]
+            Node {
+                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("reason"),
+                parser: Some(Parser::String(2)), // Greedy
+                suggestions_type: None,
+            },
+            // 99: /kickall
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![98],
+                redirect_node: None,
+                name: Some("kickall"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 100: /toggle
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![101, 102, 103, 104],
+                redirect_node: None,
+                name: Some("toggle"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 101: /toggle sounds
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("sounds"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 102: /toggle cui
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("cui"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 103: /toggle broadcasts
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("broadcasts"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 104: /toggle hud
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("hud"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 105: /gm
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("gm"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 106: /near
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![107],
+                redirect_node: None,
+                name: Some("near"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 107: /near [radius]
+            Node {
+                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("radius"),
+                parser: Some(Parser::Integer(1, i32::MAX)),
+                suggestions_type: None,
+            },
+            // 108: /record
+            Node {
+                flags: CommandFlags::LITERAL.bits() as i8,
+                children: vec![109, 110], // Children are start, stop
+                redirect_node: None,
+                name: Some("record"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 109: /record start
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("start"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 110: /record stop
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("stop"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 111: /screen
+            Node {
+                flags: CommandFlags::LITERAL.bits() as i8,
+                children: vec![112, 117], // Children are capture, stop
+                redirect_node: None,
+                name: Some("screen"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 112: /screen capture
+            Node {
+                flags: CommandFlags::LITERAL.bits() as i8,
+                children: vec![113],
+                redirect_node: None,
+                name: Some("capture"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 113: /screen capture [from]
+            Node {
+                flags: CommandFlags::ARGUMENT.bits() as i8,
+                children: vec![114],
+                redirect_node: None,
+                name: Some("from"),
+                parser: Some(Parser::Vec2),
+                suggestions_type: None,
+            },
+            // 114: /screen capture [from] [to]
+            Node {
+                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![115],
+                redirect_node: None,
+                name: Some("to"),
+                parser: Some(Parser::Vec2),
+                suggestions_type: None,
+            },
+            // 115: /screen capture [from] [to] [ticks]
+            Node {
+                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![116],
+                redirect_node: None,
+                name: Some("ticks"),
+                parser: Some(Parser::Integer(1, screen::MAX_CAPTURE_TICKS as i32)),
+                suggestions_type: None,
+            },
+            // 116: /screen capture [from] [to] [ticks] [format]
+            Node {
+                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("format"),
+                parser: Some(Parser::String(0)),
+                suggestions_type: None,
+            },
+            // 117: /screen stop
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("stop"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 118: /script
+            Node {
+                flags: CommandFlags::LITERAL.bits() as i8,
+                children: vec![119, 121, 122], // Children are load, unload, status
+                redirect_node: None,
+                name: Some("script"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 119: /script load
+            Node {
+                flags: CommandFlags::LITERAL.bits() as i8,
+                children: vec![120],
+                redirect_node: None,
+                name: Some("load"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 120: /script load [name]
+            Node {
+                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("name"),
+                parser: Some(Parser::String(0)),
+                suggestions_type: None,
+            },
+            // 121: /script unload
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("unload"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 122: /script status
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("status"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 123: /plot clear confirm
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("confirm"),
+                parser: None,
+                suggestions_type: None,
+            },
+        ],
+        root_index: 0,
+    };
+    super::worldedit::add_command_completions(&mut packet.nodes);
+    plugin::add_command_completions(&mut packet.nodes);
+    for &name in extra_commands {
+        add_free_form_command(&mut packet.nodes, name);
+    }
+    packet.encode()
+}
+
+/// Adds a command that takes any arguments, for commands that parse their own
+pub(crate) fn add_free_form_command(nodes: &mut Vec<Node>, name: &'static str) {
+    let command_idx = nodes.len() as i32;
+    nodes[0].children.push(command_idx);
+    nodes.push(Node {
+        flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+        children: vec![command_idx + 1],
+        redirect_node: None,
+        name: Some(name),
+        parser: None,
+        suggestions_type: None,
+    });
+    nodes.push(Node {
+        flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
+        children: Vec::new(),
+        redirect_node: None,
+        name: Some("args"),
+        parser: Some(Parser::String(2)),
+        suggestions_type: None,
+    });
+}
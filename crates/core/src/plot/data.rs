@@ -1,8 +1,16 @@
-use super::{Plot, PlotWorld, PLOT_WIDTH};
-use anyhow::{Context, Result};
-use mchprs_save_data::plot_data::{ChunkData, PlotData, Tps, WorldSendRate};
+use super::worldedit::{load_schematic, parse_block, paste_clipboard, WorldEditClipboard};
+use super::{geometry, PlotGeometry, PlotWorld};
+use crate::config::{PlotTemplateConfig, WorldGenerator};
+use crate::worlds;
+use anyhow::{bail, Context, Result};
+use mchprs_blocks::blocks::Block;
+use mchprs_blocks::BlockPos;
+use mchprs_save_data::plot_data::{ChunkData, PlotData, TimeOfDay, Tps, WorldSendRate};
+use mchprs_world::storage::Chunk;
 use once_cell::sync::Lazy;
+use rustc_hash::FxHashMap;
 use std::path::Path;
+use std::sync::Mutex;
 use std::time::Duration;
 
 // TODO: where to put this?
@@ -21,42 +29,207 @@ pub fn sleep_time_for_tps(tps: Tps) -> Duration {
 
 pub fn load_plot(path: impl AsRef<Path>) -> Result<PlotData> {
     let path = path.as_ref();
-    if path.exists() {
-        Ok(PlotData::load_from_file(path)
-            .with_context(|| format!("error loading plot save file at {}", path.display()))?)
-    } else {
-        Ok(EMPTY_PLOT.clone())
-    }
+    PlotData::load_from_file(path)
+        .with_context(|| format!("error loading plot save file at {}", path.display()))
 }
 
-pub fn empty_plot() -> PlotData {
-    EMPTY_PLOT.clone()
+/// The blocks new plots are generated with
+struct PlotTemplate {
+    /// The size of the plots of the world
+    geometry: PlotGeometry,
+    floor: Block,
+    border: Block,
+    layers: i32,
+    /// A block and the spacing of the lines drawn with it in the top layer of the floor
+    grid: Option<(Block, i32)>,
+    schematic: Option<WorldEditClipboard>,
 }
 
-static EMPTY_PLOT: Lazy<PlotData> = Lazy::new(|| {
-    let template_path = Path::new("./world/plots/pTEMPLATE");
-    if template_path.exists() {
-        PlotData::load_from_file(template_path).expect("failed to read template plot")
-    } else {
+fn template_block(name: &str) -> Result<Block> {
+    match parse_block(name) {
+        Some(Block::Air {}) if !name.ends_with("air") => bail!("unknown block {}", name),
+        Some(block) => Ok(block),
+        None => bail!("unknown block {}", name),
+    }
+}
+
+impl PlotTemplate {
+    fn from_config(
+        config: &PlotTemplateConfig,
+        generator: WorldGenerator,
+        geometry: PlotGeometry,
+    ) -> Result<PlotTemplate> {
+        let height = geometry.block_height as i64;
+        if !(0..height).contains(&config.layers) {
+            bail!("the floor must have between 0 and {} layers", height - 1);
+        }
+        let grid = match &config.grid {
+            Some(grid) if config.grid_spacing > 0 => {
+                Some((template_block(grid)?, config.grid_spacing as i32))
+            }
+            Some(_) => bail!("the grid spacing must be positive"),
+            None => None,
+        };
+        let schematic = match &config.schematic {
+            Some(file_name) => Some(
+                load_schematic(file_name)
+                    .with_context(|| format!("error loading template schematic {}", file_name))?,
+            ),
+            None => None,
+        };
+        let floor = template_block(&config.floor)?;
+        let border = match generator {
+            WorldGenerator::Plots => template_block(&config.border)?,
+            WorldGenerator::Flat | WorldGenerator::Void => floor,
+        };
+        let layers = match generator {
+            WorldGenerator::Void => 0,
+            WorldGenerator::Plots | WorldGenerator::Flat => config.layers as i32,
+        };
+        Ok(PlotTemplate {
+            geometry,
+            floor,
+            border,
+            layers,
+            grid,
+            schematic,
+        })
+    }
+
+    fn generate_chunk(&self, x: i32, z: i32) -> Chunk {
+        let mut chunk = Chunk::empty(x, z, self.geometry.sections);
+        let width = self.geometry.block_width;
+        for ry in 0..self.layers {
+            for rx in 0..16 {
+                for rz in 0..16 {
+                    // The position in the plot
+                    let block_x = ((x << 4) | rx).rem_euclid(width);
+                    let block_z = ((z << 4) | rz).rem_euclid(width);
+
+                    let on_border = block_x == 0
+                        || block_z == 0
+                        || block_x == width - 1
+                        || block_z == width - 1;
+                    let block = match self.grid {
+                        _ if on_border => self.border,
+                        Some((grid, spacing))
+                            if ry == self.layers - 1
+                                && (block_x % spacing == 0 || block_z % spacing == 0) =>
+                        {
+                            grid
+                        }
+                        _ => self.floor,
+                    };
+                    chunk.set_block(rx as u32, ry as u32, rz as u32, block.get_id());
+                }
+            }
+        }
+        chunk
+    }
+
+    fn generate(&self) -> PlotData {
         let mut chunks = Vec::new();
-        for chunk_x in 0..PLOT_WIDTH {
-            for chunk_z in 0..PLOT_WIDTH {
-                chunks.push(Plot::generate_chunk(8, chunk_x, chunk_z));
+        let width = self.geometry.width;
+        for chunk_x in 0..width {
+            for chunk_z in 0..width {
+                chunks.push(self.generate_chunk(chunk_x, chunk_z));
             }
         }
         let mut world = PlotWorld {
             x: 0,
             z: 0,
+            geometry: self.geometry,
             chunks,
             to_be_ticked: Vec::new(),
             packet_senders: Vec::new(),
+            map_feed: None,
+            container_viewers: Default::default(),
+            time_of_day: TimeOfDay::default().0,
+            entities: Default::default(),
         };
+        if let Some(schematic) = &self.schematic {
+            // The lowest corner of the schematic goes on top of the floor
+            let pos = BlockPos::new(
+                schematic.offset_x,
+                schematic.offset_y + self.layers,
+                schematic.offset_z,
+            );
+            paste_clipboard(&mut world, schematic, pos, true, None);
+        }
         let chunk_data: Vec<ChunkData> = world.chunks.iter_mut().map(ChunkData::new).collect();
         PlotData {
             tps: Tps::Limited(10),
             world_send_rate: WorldSendRate::default(),
             chunk_data,
             pending_ticks: Vec::new(),
+            time_of_day: TimeOfDay::default(),
+            entities: Vec::new(),
         }
     }
-});
+}
+
+/// New plots of every world, so they are only generated once
+static EMPTY_PLOTS: Lazy<Mutex<FxHashMap<String, PlotData>>> = Lazy::new(Default::default);
+
+/// Returns a new plot for `world`. This is a copy of the `pTEMPLATE` plot in the folder of the
+/// world if there is one, and is generated from the plot template and generator in the config
+/// otherwise.
+pub fn empty_plot(world: &str) -> Result<PlotData> {
+    let mut empty_plots = EMPTY_PLOTS.lock().unwrap();
+    if let Some(data) = empty_plots.get(world) {
+        return Ok(data.clone());
+    }
+
+    let template_path = worlds::plots_dir(world).join("pTEMPLATE");
+    let data = if template_path.exists() {
+        load_plot(template_path)?
+    } else {
+        let config = worlds::template(world);
+        PlotTemplate::from_config(config, worlds::generator(world), geometry(world))
+            .with_context(|| format!("invalid plot template for world {}", world))?
+            .generate()
+    };
+    empty_plots.insert(world.to_string(), data.clone());
+    Ok(data)
+}
+
+#[test]
+fn template_grid() {
+    let template = PlotTemplate {
+        geometry: PlotGeometry::default(),
+        floor: Block::Sandstone {},
+        border: Block::StoneBricks {},
+        layers: 2,
+        grid: Some((Block::RedstoneBlock {}, 16)),
+        schematic: None,
+    };
+    let chunk = template.generate_chunk(0, 0);
+    let block = |x, y, z| Block::from_id(chunk.get_block(x, y, z));
+    assert_eq!(block(0, 1, 5), Block::StoneBricks {});
+    assert_eq!(block(5, 1, 5), Block::Sandstone {});
+
+    let chunk = template.generate_chunk(1, 0);
+    let block = |x, y, z| Block::from_id(chunk.get_block(x, y, z));
+    assert_eq!(block(0, 1, 5), Block::RedstoneBlock {});
+    // The grid is only in the top layer, and the border goes over it
+    assert_eq!(block(0, 0, 5), Block::Sandstone {});
+    assert_eq!(block(0, 1, 0), Block::StoneBricks {});
+    assert_eq!(block(0, 2, 5), Block::Air {});
+}
+
+#[test]
+fn flat_and_void_generators() {
+    let config = PlotTemplateConfig::default();
+    let flat =
+        PlotTemplate::from_config(&config, WorldGenerator::Flat, PlotGeometry::default()).unwrap();
+    let chunk = flat.generate_chunk(0, 0);
+    assert_eq!(
+        Block::from_id(chunk.get_block(0, 7, 0)),
+        Block::Sandstone {}
+    );
+
+    let void =
+        PlotTemplate::from_config(&config, WorldGenerator::Void, PlotGeometry::default()).unwrap();
+    let chunk = void.generate_chunk(0, 0);
+    assert_eq!(Block::from_id(chunk.get_block(0, 0, 0)), Block::Air {});
+}
@@ -10,7 +10,7 @@ fn lock<'a>() -> MutexGuard<'a, Connection> {
     CONN.lock().unwrap()
 }
 
-pub fn get_plot_owner(plot_x: i32, plot_z: i32) -> Option<String> {
+pub fn get_plot_owner(world: &str, plot_x: i32, plot_z: i32) -> Option<String> {
     lock()
         .query_row(
             "SELECT
@@ -22,10 +22,11 @@ pub fn get_plot_owner(plot_x: i32, plot_z: i32) -> Option<String> {
             JOIN
                 user ON user.id = userplot.user_id
             WHERE
-                plot_x=?1
-                AND plot_z=?2
+                world=?1
+                AND plot_x=?2
+                AND plot_z=?3
                 AND is_owner=TRUE",
-            params![plot_x, plot_z],
+            params![world, plot_x, plot_z],
             |row| row.get::<_, String>(0),
         )
         .ok()
@@ -46,7 +47,7 @@ pub fn get_cached_username(uuid: String) -> Option<String> {
         .ok()
 }
 
-pub fn get_owned_plots(player: &str) -> Vec<(i32, i32)> {
+pub fn get_owned_plots(world: &str, player: &str) -> Vec<(i32, i32)> {
     let conn = lock();
     let mut stmt = conn
         .prepare_cached(
@@ -59,31 +60,32 @@ pub fn get_owned_plots(player: &str) -> Vec<(i32, i32)> {
                 JOIN
                     user ON user.id = userplot.user_id
                 WHERE
-                    name=?1
+                    world=?1
+                    AND name=?2
                     AND is_owner=TRUE",
         )
         .unwrap();
-    stmt.query_map(params![player], |row| Ok((row.get(0)?, row.get(1)?)))
+    stmt.query_map(params![world, player], |row| Ok((row.get(0)?, row.get(1)?)))
         .unwrap()
         .map(Result::unwrap)
         .collect()
 }
 
-pub fn is_claimed(plot_x: i32, plot_z: i32) -> Option<bool> {
+pub fn is_claimed(world: &str, plot_x: i32, plot_z: i32) -> Option<bool> {
     lock()
         .query_row(
-            "SELECT EXISTS(SELECT * FROM plot WHERE plot_x = ?1 AND plot_z = ?2)",
-            params![plot_x, plot_z],
+            "SELECT EXISTS(SELECT * FROM plot WHERE world = ?1 AND plot_x = ?2 AND plot_z = ?3)",
+            params![world, plot_x, plot_z],
             |row| row.get::<_, bool>(0),
         )
         .ok()
 }
 
-pub fn claim_plot(plot_x: i32, plot_z: i32, uuid: &str) {
+pub fn claim_plot(world: &str, plot_x: i32, plot_z: i32, uuid: &str) {
     let conn = lock();
     conn.execute(
-        "INSERT INTO plot(plot_x, plot_z) VALUES(?1, ?2)",
-        params![plot_x, plot_z],
+        "INSERT INTO plot(world, plot_x, plot_z) VALUES(?1, ?2, ?3)",
+        params![world, plot_x, plot_z],
     )
     .unwrap();
 
@@ -99,6 +101,108 @@ pub fn claim_plot(plot_x: i32, plot_z: i32, uuid: &str) {
     .unwrap();
 }
 
+/// A public teleport point set with `/setwarp`
+pub struct Warp {
+    pub world: String,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+    pub yaw: f32,
+    pub pitch: f32,
+}
+
+pub fn get_warp(name: &str) -> Option<Warp> {
+    lock()
+        .query_row(
+            "SELECT world, x, y, z, yaw, pitch FROM warp WHERE name = ?1",
+            params![name],
+            |row| {
+                Ok(Warp {
+                    world: row.get(0)?,
+                    x: row.get(1)?,
+                    y: row.get(2)?,
+                    z: row.get(3)?,
+                    yaw: row.get(4)?,
+                    pitch: row.get(5)?,
+                })
+            },
+        )
+        .ok()
+}
+
+pub fn get_warp_names() -> Vec<String> {
+    let conn = lock();
+    let mut stmt = conn
+        .prepare_cached("SELECT name FROM warp ORDER BY name")
+        .unwrap();
+    stmt.query_map([], |row| row.get(0))
+        .unwrap()
+        .map(Result::unwrap)
+        .collect()
+}
+
+/// Creates the warp, or moves it if there already is one called `name`
+pub fn set_warp(name: &str, warp: &Warp) {
+    lock()
+        .execute(
+            "INSERT INTO warp(name, world, x, y, z, yaw, pitch)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+                ON CONFLICT (name) DO UPDATE SET
+                    world = ?2, x = ?3, y = ?4, z = ?5, yaw = ?6, pitch = ?7",
+            params![name, warp.world, warp.x, warp.y, warp.z, warp.yaw, warp.pitch],
+        )
+        .unwrap();
+}
+
+/// Returns false if there was no warp called `name`
+pub fn delete_warp(name: &str) -> bool {
+    lock()
+        .execute("DELETE FROM warp WHERE name = ?1", params![name])
+        .unwrap()
+        > 0
+}
+
+/// When a player last joined and left, in seconds since the unix epoch
+pub struct LastSeen {
+    pub username: String,
+    pub last_login: Option<i64>,
+    pub last_logout: Option<i64>,
+}
+
+pub fn get_last_seen(username: &str) -> Option<LastSeen> {
+    lock()
+        .query_row(
+            "SELECT name, last_login, last_logout FROM user WHERE name = ?1 COLLATE NOCASE",
+            params![username],
+            |row| {
+                Ok(LastSeen {
+                    username: row.get(0)?,
+                    last_login: row.get(1)?,
+                    last_logout: row.get(2)?,
+                })
+            },
+        )
+        .ok()
+}
+
+pub fn set_last_login(uuid: &str, time: i64) {
+    lock()
+        .execute(
+            "UPDATE user SET last_login = ?2 WHERE uuid = ?1",
+            params![uuid, time],
+        )
+        .unwrap();
+}
+
+pub fn set_last_logout(uuid: &str, time: i64) {
+    lock()
+        .execute(
+            "UPDATE user SET last_logout = ?2 WHERE uuid = ?1",
+            params![uuid, time],
+        )
+        .unwrap();
+}
+
 pub fn ensure_user(uuid: &str, name: &str) {
     lock()
         .execute(
@@ -110,6 +214,24 @@ pub fn ensure_user(uuid: &str, name: &str) {
         .unwrap();
 }
 
+/// Adds a column to a table that was created before the column existed
+fn add_missing_column(conn: &Connection, table: &str, column: &str, definition: &str) {
+    let exists: bool = conn
+        .query_row(
+            "SELECT EXISTS(SELECT * FROM pragma_table_info(?1) WHERE name = ?2)",
+            params![table, column],
+            |row| row.get(0),
+        )
+        .unwrap();
+    if !exists {
+        conn.execute(
+            &format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition),
+            [],
+        )
+        .unwrap();
+    }
+}
+
 pub fn init() {
     let conn = lock();
 
@@ -117,22 +239,30 @@ pub fn init() {
         "CREATE TABLE IF NOT EXISTS user(
             id INTEGER PRIMARY KEY AUTOINCREMENT,
             uuid BLOB(16) UNIQUE NOT NULL,
-            name VARCHAR(16) NOT NULL
+            name VARCHAR(16) NOT NULL,
+            last_login INTEGER,
+            last_logout INTEGER
         )",
         [],
     )
     .unwrap();
+    add_missing_column(&conn, "user", "last_login", "INTEGER");
+    add_missing_column(&conn, "user", "last_logout", "INTEGER");
 
     conn.execute(
         "CREATE TABLE IF NOT EXISTS plot(
             id INTEGER PRIMARY KEY AUTOINCREMENT,
             plot_x INTEGER NOT NULL,
-            plot_z INTEGER NOT NULL
+            plot_z INTEGER NOT NULL,
+            world TEXT NOT NULL DEFAULT 'world'
         )",
         [],
     )
     .unwrap();
 
+    // Databases from before multiple worlds only have plots in the default world
+    add_missing_column(&conn, "plot", "world", "TEXT NOT NULL DEFAULT 'world'");
+
     conn.execute(
         "CREATE TABLE IF NOT EXISTS userplot(
             user_id INTEGER NOT NULL,
@@ -144,4 +274,18 @@ pub fn init() {
         [],
     )
     .unwrap();
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS warp(
+            name TEXT PRIMARY KEY,
+            world TEXT NOT NULL,
+            x REAL NOT NULL,
+            y REAL NOT NULL,
+            z REAL NOT NULL,
+            yaw REAL NOT NULL,
+            pitch REAL NOT NULL
+        )",
+        [],
+    )
+    .unwrap();
 }
@@ -0,0 +1,541 @@
+//! Item frames and armor stands, which players put up to label their builds. Neither of them moves
+//! or ticks, so they only change when a player places, uses or hits one.
+//!
+//! Like in vanilla, a comparator reading through a block reads the item frame on the other side of
+//! it, with a signal of one more than the rotation of the item in the frame. Redpiler reads these
+//! signals when it compiles, so changing a frame resets it.
+
+use super::{Plot, PlotGeometry};
+use crate::player::{PacketSender, PlayerPos};
+use mchprs_blocks::items::{Item, ItemStack};
+use mchprs_blocks::{BlockFace, BlockPos};
+use mchprs_network::packets::clientbound::{
+    CRemoveEntities, CSetEntityMetadata, CSetEntityMetadataEntry, CSpawnEntity, ClientBoundPacket,
+};
+use mchprs_network::packets::{PacketEncoder, PacketEncoderExt, SlotData};
+use mchprs_save_data::plot_data::EntityData;
+use mchprs_text::TextComponentBuilder;
+use mchprs_world::World;
+use rustc_hash::FxHashMap;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// Every entity is sent to every player that enters the plot
+pub const MAX_ENTITIES: usize = 1024;
+/// The longest name an anvil gives a name tag
+const MAX_NAME_LENGTH: usize = 50;
+
+const ARMOR_STAND_TYPE: i32 = 2;
+const ITEM_FRAME_TYPE: i32 = 57;
+
+/// Players take their entity ids from the bottom, counting up from 0
+static NEXT_ENTITY_ID: AtomicU32 = AtomicU32::new(1 << 30);
+
+#[derive(Debug, Clone)]
+pub enum Entity {
+    ItemFrame {
+        /// The block the frame is in, next to the block it hangs on
+        pos: BlockPos,
+        facing: BlockFace,
+        item: Option<Item>,
+        /// How many times the item was turned, from 0 to 7
+        rotation: u8,
+    },
+    ArmorStand {
+        pos: PlayerPos,
+        yaw: f32,
+        name: Option<String>,
+    },
+}
+
+impl Entity {
+    fn spawn_packet(&self, id: u32) -> CSpawnEntity {
+        let (entity_type, pos, yaw, data) = match *self {
+            Entity::ItemFrame { pos, facing, .. } => {
+                let center =
+                    PlayerPos::new(pos.x as f64 + 0.5, pos.y as f64 + 0.5, pos.z as f64 + 0.5);
+                (ITEM_FRAME_TYPE, center, 0.0, facing as i32)
+            }
+            Entity::ArmorStand { pos, yaw, .. } => (ARMOR_STAND_TYPE, pos, yaw, 0),
+        };
+        CSpawnEntity {
+            entity_id: id as i32,
+            // Clients only use it to tell entities apart
+            entity_uuid: id as u128,
+            entity_type,
+            x: pos.x,
+            y: pos.y,
+            z: pos.z,
+            pitch: 0.0,
+            yaw,
+            head_yaw: yaw,
+            data,
+            velocity_x: 0,
+            velocity_y: 0,
+            velocity_z: 0,
+        }
+    }
+
+    fn metadata_packet(&self, id: u32) -> CSetEntityMetadata {
+        let metadata = match self {
+            Entity::ItemFrame { item, rotation, .. } => {
+                let mut slot = Vec::new();
+                slot.write_slot_data(&item.map(|item| SlotData {
+                    item_id: item.get_id() as i32,
+                    item_count: 1,
+                    nbt: None,
+                }));
+                let mut rotation_value = Vec::new();
+                rotation_value.write_varint(*rotation as i32);
+                vec![
+                    CSetEntityMetadataEntry {
+                        index: 8,
+                        metadata_type: 7,
+                        value: slot,
+                    },
+                    CSetEntityMetadataEntry {
+                        index: 9,
+                        metadata_type: 1,
+                        value: rotation_value,
+                    },
+                ]
+            }
+            Entity::ArmorStand { name, .. } => {
+                let mut custom_name = Vec::new();
+                custom_name.write_bool(name.is_some());
+                if let Some(name) = name {
+                    custom_name
+                        .write_text_component(&TextComponentBuilder::new(name.clone()).finish());
+                }
+                vec![
+                    CSetEntityMetadataEntry {
+                        index: 2,
+                        metadata_type: 6,
+                        value: custom_name,
+                    },
+                    CSetEntityMetadataEntry {
+                        index: 3,
+                        metadata_type: 8,
+                        value: vec![name.is_some() as u8],
+                    },
+                    // No gravity, since the server doesn't move entities
+                    CSetEntityMetadataEntry {
+                        index: 5,
+                        metadata_type: 8,
+                        value: vec![1],
+                    },
+                ]
+            }
+        };
+        CSetEntityMetadata {
+            entity_id: id as i32,
+            metadata,
+        }
+    }
+
+    /// The signal comparators read from the item frame. Like in vanilla, an empty frame still
+    /// gives a signal of 0 instead of letting the comparator read the block behind it.
+    fn comparator_output(&self) -> Option<u8> {
+        match *self {
+            Entity::ItemFrame { item, rotation, .. } => Some(item.map_or(0, |_| rotation % 8 + 1)),
+            Entity::ArmorStand { .. } => None,
+        }
+    }
+}
+
+/// The entities in a plot, by entity id
+#[derive(Default)]
+pub struct Entities {
+    entities: BTreeMap<u32, Entity>,
+    /// The id of the item frame at each position and facing, for comparators to look up
+    frames: FxHashMap<(BlockPos, BlockFace), u32>,
+}
+
+impl Entities {
+    /// Loads the entities of the plot at `x`, `z` from its save file
+    pub fn load(geometry: &PlotGeometry, data: Vec<EntityData>, x: i32, z: i32) -> Entities {
+        let width = geometry.block_width;
+        let (offset_x, offset_z) = (x * width, z * width);
+        let mut entities = Entities::default();
+        for entity in data {
+            entities.insert(match entity {
+                EntityData::ItemFrame {
+                    pos,
+                    facing,
+                    item,
+                    rotation,
+                } => Entity::ItemFrame {
+                    pos: BlockPos::new(pos.x + offset_x, pos.y, pos.z + offset_z),
+                    facing,
+                    item: item.map(Item::from_id),
+                    rotation: rotation % 8,
+                },
+                EntityData::ArmorStand { x, y, z, yaw, name } => Entity::ArmorStand {
+                    pos: PlayerPos::new(x + offset_x as f64, y, z + offset_z as f64),
+                    yaw,
+                    name,
+                },
+            });
+        }
+        entities
+    }
+
+    /// Returns the entities of the plot at `x`, `z` the way they are saved
+    pub fn save(&self, geometry: &PlotGeometry, x: i32, z: i32) -> Vec<EntityData> {
+        let width = geometry.block_width;
+        let (offset_x, offset_z) = (x * width, z * width);
+        self.entities
+            .values()
+            .map(|entity| match entity {
+                Entity::ItemFrame {
+                    pos,
+                    facing,
+                    item,
+                    rotation,
+                } => EntityData::ItemFrame {
+                    pos: BlockPos::new(pos.x - offset_x, pos.y, pos.z - offset_z),
+                    facing: *facing,
+                    item: item.map(Item::get_id),
+                    rotation: *rotation,
+                },
+                Entity::ArmorStand { pos, yaw, name } => EntityData::ArmorStand {
+                    x: pos.x - offset_x as f64,
+                    y: pos.y,
+                    z: pos.z - offset_z as f64,
+                    yaw: *yaw,
+                    name: name.clone(),
+                },
+            })
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+
+    pub fn ids(&self) -> impl Iterator<Item = u32> + '_ {
+        self.entities.keys().copied()
+    }
+
+    pub fn get(&self, id: u32) -> Option<&Entity> {
+        self.entities.get(&id)
+    }
+
+    /// Adds the entity with a new id, which is returned. An item frame replaces the one already
+    /// in its place.
+    pub fn insert(&mut self, entity: Entity) -> u32 {
+        let id = NEXT_ENTITY_ID.fetch_add(1, Ordering::Relaxed);
+        if let Entity::ItemFrame { pos, facing, .. } = entity {
+            if let Some(old) = self.frames.insert((pos, facing), id) {
+                self.entities.remove(&old);
+            }
+        }
+        self.entities.insert(id, entity);
+        id
+    }
+
+    /// Changes the entity, which has to stay where it is
+    pub fn update(&mut self, id: u32, entity: Entity) {
+        if let Some(old) = self.entities.get_mut(&id) {
+            *old = entity;
+        }
+    }
+
+    pub fn remove(&mut self, id: u32) -> Option<Entity> {
+        let entity = self.entities.remove(&id)?;
+        if let Entity::ItemFrame { pos, facing, .. } = entity {
+            self.frames.remove(&(pos, facing));
+        }
+        Some(entity)
+    }
+
+    pub fn has_item_frame(&self, pos: BlockPos, facing: BlockFace) -> bool {
+        self.frames.contains_key(&(pos, facing))
+    }
+
+    pub fn item_frame_override(&self, pos: BlockPos, facing: BlockFace) -> Option<u8> {
+        let id = self.frames.get(&(pos, facing))?;
+        self.entities[id].comparator_output()
+    }
+
+    /// Returns what [`Entities::item_frame_override`] returns for every item frame
+    pub fn item_frame_overrides(&self) -> impl Iterator<Item = ((BlockPos, BlockFace), u8)> + '_ {
+        self.frames.iter().filter_map(|(&key, id)| {
+            let signal = self.entities[id].comparator_output()?;
+            Some((key, signal))
+        })
+    }
+
+    /// The packets that show every entity to a player
+    pub fn spawn_packets(&self) -> Vec<PacketEncoder> {
+        self.entities
+            .iter()
+            .flat_map(|(&id, entity)| {
+                [
+                    entity.spawn_packet(id).encode(),
+                    entity.metadata_packet(id).encode(),
+                ]
+            })
+            .collect()
+    }
+}
+
+/// Returns the name given to a name tag in an anvil, without its formatting
+fn name_tag_name(item: &ItemStack) -> Option<String> {
+    if item.item_type != (Item::NameTag {}) {
+        return None;
+    }
+    let nbt::Value::Compound(display) = item.nbt.as_ref()?.content.get("display")? else {
+        return None;
+    };
+    let nbt::Value::String(json) = display.get("Name")? else {
+        return None;
+    };
+    let name = match serde_json::from_str(json) {
+        Ok(component) => plain_text(&component),
+        Err(_) => json.clone(),
+    };
+    let name: String = name.chars().take(MAX_NAME_LENGTH).collect();
+    (!name.is_empty()).then_some(name)
+}
+
+fn plain_text(component: &Value) -> String {
+    match component {
+        Value::String(text) => text.clone(),
+        Value::Array(components) => components.iter().map(plain_text).collect(),
+        Value::Object(component) => {
+            let mut text = component
+                .get("text")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string();
+            if let Some(Value::Array(extra)) = component.get("extra") {
+                text.extend(extra.iter().map(plain_text));
+            }
+            text
+        }
+        _ => String::new(),
+    }
+}
+
+impl Plot {
+    fn broadcast_packet(&self, packet: &PacketEncoder) {
+        for player in &self.players {
+            player.client.send_packet(packet);
+        }
+    }
+
+    /// Puts an item frame or armor stand where the player used the item, returning false if the
+    /// item isn't one of them
+    pub(super) fn place_entity(
+        &mut self,
+        player: usize,
+        item: &ItemStack,
+        block_pos: BlockPos,
+        block_face: BlockFace,
+    ) -> bool {
+        let pos = block_pos.offset(block_face);
+        let entity = match item.item_type {
+            Item::ItemFrame {} => {
+                if !self.world.get_block(block_pos).is_solid()
+                    || self.world.entities.has_item_frame(pos, block_face)
+                {
+                    return true;
+                }
+                Entity::ItemFrame {
+                    pos,
+                    facing: block_face,
+                    item: None,
+                    rotation: 0,
+                }
+            }
+            Item::ArmorStand {} => {
+                if block_face == BlockFace::Bottom
+                    || !self.world.get_block(pos).can_place_block_in()
+                {
+                    return true;
+                }
+                // Armor stands face the player, snapped to 45 degrees like in vanilla
+                let yaw = ((self.players[player].yaw - 180.0) / 45.0).round() * 45.0;
+                Entity::ArmorStand {
+                    pos: PlayerPos::new(pos.x as f64 + 0.5, pos.y as f64, pos.z as f64 + 0.5),
+                    yaw,
+                    name: None,
+                }
+            }
+            _ => return false,
+        };
+        if !Plot::in_plot_bounds(
+            &self.world.geometry,
+            self.world.x,
+            self.world.z,
+            pos.x,
+            pos.z,
+        ) || !(0..self.world.geometry.block_height).contains(&pos.y)
+        {
+            return true;
+        }
+        if self.world.entities.len() >= MAX_ENTITIES {
+            self.players[player]
+                .send_error_message(text!("plot.entity_limit", limit = MAX_ENTITIES));
+            return true;
+        }
+
+        let id = self.world.entities.insert(entity.clone());
+        self.broadcast_packet(&entity.spawn_packet(id).encode());
+        self.broadcast_packet(&entity.metadata_packet(id).encode());
+        self.record_snapshot();
+        true
+    }
+
+    /// Uses or hits one of the plot's entities. Using an empty item frame puts the held item in
+    /// it and using a full one turns its item, while hitting takes the item out first and the
+    /// frame after that. Naming an armor stand works with a name tag from an anvil.
+    pub(super) fn interact_with_entity(&mut self, player: usize, id: u32, attack: bool) {
+        let Some(mut entity) = self.world.entities.get(id).cloned() else {
+            // Most likely another player
+            return;
+        };
+        if !self.can_interact(player) {
+            self.players[player].send_no_permission_message();
+            return;
+        }
+        if let Entity::ItemFrame { .. } = entity {
+            match self.redpiler.current_flags() {
+                Some(flags) if flags.io_only => {
+                    self.players[player].send_error_message(text!("redpiler.io_only"));
+                    return;
+                }
+                _ => {}
+            }
+            self.reset_redpiler();
+        }
+
+        let selected_slot = self.players[player].selected_slot as usize;
+        let item_in_hand = self.players[player].inventory[selected_slot + 36].clone();
+        let removed = match &mut entity {
+            Entity::ItemFrame { item, rotation, .. } => match (*item, attack) {
+                (Some(_), true) => {
+                    *item = None;
+                    *rotation = 0;
+                    false
+                }
+                (Some(_), false) => {
+                    *rotation = (*rotation + 1) % 8;
+                    false
+                }
+                (None, true) => true,
+                (None, false) => match item_in_hand {
+                    Some(stack) => {
+                        *item = Some(stack.item_type);
+                        false
+                    }
+                    None => return,
+                },
+            },
+            Entity::ArmorStand { .. } if attack => true,
+            Entity::ArmorStand { name, .. } => {
+                match item_in_hand.as_ref().and_then(name_tag_name) {
+                    Some(new_name) => *name = Some(new_name),
+                    None => return,
+                }
+                false
+            }
+        };
+
+        if removed {
+            self.world.entities.remove(id);
+            self.broadcast_packet(
+                &CRemoveEntities {
+                    entity_ids: vec![id as i32],
+                }
+                .encode(),
+            );
+        } else {
+            self.broadcast_packet(&entity.metadata_packet(id).encode());
+            self.world.entities.update(id, entity.clone());
+        }
+        if let Entity::ItemFrame { pos, facing, .. } = entity {
+            mchprs_redstone::update_surrounding_blocks(
+                &mut self.world,
+                pos.offset(facing.opposite()),
+            );
+            self.world.flush_block_changes();
+        }
+        self.record_snapshot();
+    }
+
+    /// Shows the plot's entities to a player that entered it
+    pub(super) fn send_entities(&self, player: usize) {
+        for packet in self.world.entities.spawn_packets() {
+            self.players[player].client.send_packet(&packet);
+        }
+    }
+
+    /// Replaces the plot's entities, like when it is cleared
+    pub(super) fn replace_entities(&mut self, entities: Entities) {
+        let old = CRemoveEntities {
+            entity_ids: self.world.entities.ids().map(|id| id as i32).collect(),
+        }
+        .encode();
+        self.broadcast_packet(&old);
+        self.world.entities = entities;
+        for packet in self.world.entities.spawn_packets() {
+            self.broadcast_packet(&packet);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bench::HeadlessPlot;
+    use mchprs_blocks::block_entities::BlockEntity;
+    use mchprs_blocks::blocks::{Block, ComparatorMode, RedstoneComparator};
+    use mchprs_blocks::BlockDirection;
+
+    #[test]
+    fn comparator_reads_item_frame_through_block() {
+        let mut plot = HeadlessPlot::empty();
+        let world = plot.world_mut();
+        let comparator_pos = BlockPos::new(3, 1, 1);
+        for x in 0..=3 {
+            world.set_block(BlockPos::new(x, 0, 1), Block::Stone {});
+        }
+        world.set_block(BlockPos::new(2, 1, 1), Block::Stone {});
+        let mut entities = Entities::default();
+        entities.insert(Entity::ItemFrame {
+            pos: BlockPos::new(1, 1, 1),
+            facing: BlockFace::West,
+            item: Some(Item::Redstone {}),
+            rotation: 2,
+        });
+        world.item_frames = entities.item_frame_overrides().collect();
+        let comparator = Block::RedstoneComparator {
+            comparator: RedstoneComparator::new(
+                BlockDirection::West,
+                ComparatorMode::Compare,
+                false,
+            ),
+        };
+        world.set_block(comparator_pos, comparator);
+        world.set_block_entity(
+            comparator_pos,
+            BlockEntity::Comparator { output_strength: 0 },
+        );
+        mchprs_redstone::update(comparator, world, comparator_pos);
+        for _ in 0..4 {
+            plot.tick();
+        }
+
+        assert!(matches!(
+            plot.world().get_block_entity(comparator_pos),
+            Some(BlockEntity::Comparator { output_strength: 3 })
+        ));
+    }
+}
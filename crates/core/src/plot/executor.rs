@@ -0,0 +1,129 @@
+//! Runs the loaded plots on a fixed number of worker threads, instead of a thread for each plot.
+//!
+//! The plots that aren't being updated wait in one queue with the time they should be updated
+//! next, and any idle worker takes the plot that is due first. A plot that takes long to update
+//! only holds up the worker that is updating it, the other plots are picked up by the other
+//! workers. Plots don't share anything with each other, players are moved between them and
+//! broadcasts reach them as messages through the server.
+
+use super::Plot;
+use crate::config::CONFIG;
+use once_cell::sync::Lazy;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Condvar, Mutex};
+use std::thread;
+use std::time::Instant;
+use tracing::{error, info};
+
+/// Loads a plot, or returns `None` if it couldn't be loaded
+type LoadPlot = Box<dyn FnOnce() -> Option<Plot> + Send>;
+
+#[derive(Default)]
+struct Queue {
+    /// Plots that haven't been loaded yet
+    loads: Vec<LoadPlot>,
+    /// The plots that no worker is updating right now, each with when it should be updated next
+    plots: Vec<(Instant, Plot)>,
+}
+
+static QUEUE: Lazy<Mutex<Queue>> = Lazy::new(|| {
+    let threads = match usize::try_from(CONFIG.plot_threads) {
+        Ok(0) | Err(_) => thread::available_parallelism().map_or(1, |threads| threads.get()),
+        Ok(threads) => threads,
+    };
+    info!("Running plots on {} threads", threads);
+    for i in 0..threads {
+        thread::Builder::new()
+            .name(format!("plots-{}", i))
+            .spawn(run_worker)
+            .unwrap();
+    }
+    Default::default()
+});
+
+/// Wakes up the idle workers when there is a plot to load or one was put back into the queue
+static WORK: Condvar = Condvar::new();
+
+/// Loads a plot with `load` and runs it until it stops running
+pub(super) fn spawn(load: impl FnOnce() -> Option<Plot> + Send + 'static) {
+    QUEUE.lock().unwrap().loads.push(Box::new(load));
+    WORK.notify_one();
+}
+
+/// Drops a plot that stopped running or panicked. Dropping it sends its players away, saves it
+/// and tells the server it was unloaded, which may well panic too if the plot was broken.
+fn unload(plot: Plot) {
+    let (x, z) = (plot.world.x, plot.world.z);
+    if panic::catch_unwind(AssertUnwindSafe(move || drop(plot))).is_err() {
+        error!("Plot {},{} panicked while it was being unloaded", x, z);
+    }
+}
+
+enum Work {
+    Load(LoadPlot),
+    Update(Plot),
+}
+
+/// Loads or updates a single plot, returning it with when it should be updated next if it is
+/// still running
+fn run(work: Work) -> Option<(Instant, Plot)> {
+    let mut plot = match work {
+        Work::Load(load) => {
+            return match panic::catch_unwind(AssertUnwindSafe(load)) {
+                Ok(plot) => plot.map(|plot| (Instant::now(), plot)),
+                Err(_) => {
+                    error!("A plot panicked while it was being loaded");
+                    None
+                }
+            };
+        }
+        Work::Update(plot) => plot,
+    };
+    match panic::catch_unwind(AssertUnwindSafe(|| plot.step())) {
+        Ok(Some(wait)) => Some((Instant::now() + wait, plot)),
+        result => {
+            if result.is_err() {
+                error!("Plot {},{} panicked", plot.world.x, plot.world.z);
+            }
+            unload(plot);
+            None
+        }
+    }
+}
+
+fn run_worker() {
+    let _guard = super::ASYNC_RT.enter();
+    let mut queue = QUEUE.lock().unwrap();
+    loop {
+        let work = match queue.loads.pop() {
+            Some(load) => Work::Load(load),
+            None => {
+                let next = (0..queue.plots.len()).min_by_key(|&i| queue.plots[i].0);
+                let now = Instant::now();
+                match next {
+                    Some(i) if queue.plots[i].0 <= now => {
+                        Work::Update(queue.plots.swap_remove(i).1)
+                    }
+                    Some(i) => {
+                        let wait = queue.plots[i].0 - now;
+                        queue = WORK.wait_timeout(queue, wait).unwrap().0;
+                        continue;
+                    }
+                    None => {
+                        queue = WORK.wait(queue).unwrap();
+                        continue;
+                    }
+                }
+            }
+        };
+        drop(queue);
+
+        let plot = run(work);
+        queue = QUEUE.lock().unwrap();
+        if let Some(plot) = plot {
+            queue.plots.push(plot);
+            // Another worker may be sleeping until a later plot is due
+            WORK.notify_one();
+        }
+    }
+}
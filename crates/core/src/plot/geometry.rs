@@ -0,0 +1,218 @@
+//! The size of plots and the height of a world. These are picked when the world is created
+//! and saved with it, because plots saved with one size can't be loaded with another.
+
+use crate::worlds;
+use anyhow::{bail, Context, Result};
+use mchprs_save_data::plot_data::PlotData;
+use once_cell::sync::OnceCell;
+use rustc_hash::FxHashMap;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+/// The client refuses dimensions that reach higher than this with a minimum height of 0
+const MAX_BLOCK_HEIGHT: i64 = 2032;
+const MAX_PLOT_SCALE: i64 = 8;
+
+/// The geometry of every world, by the name of the world
+static GEOMETRIES: OnceCell<FxHashMap<String, PlotGeometry>> = OnceCell::new();
+
+/// What is saved in `meta.toml` in the folder of a world
+#[derive(Serialize, Deserialize)]
+struct WorldMeta {
+    plot_scale: u32,
+    plot_sections: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlotGeometry {
+    /// The width of a plot (2^n)
+    pub scale: u32,
+    /// The width of a plot counted in chunks
+    pub width: i32,
+    /// The plot width in blocks
+    pub block_width: i32,
+    pub num_chunks: usize,
+    /// The height of the world in sections
+    pub sections: usize,
+    /// The plot height in blocks
+    pub block_height: i32,
+}
+
+impl PlotGeometry {
+    pub fn new(scale: u32, sections: usize) -> PlotGeometry {
+        let width = 1 << scale;
+        PlotGeometry {
+            scale,
+            width,
+            block_width: width * 16,
+            num_chunks: (width * width) as usize,
+            sections,
+            block_height: sections as i32 * 16,
+        }
+    }
+
+    /// Checks the size from the config and turns it into a geometry
+    fn from_config(plot_scale: i64, world_height: i64) -> Result<PlotGeometry> {
+        if !(0..=MAX_PLOT_SCALE).contains(&plot_scale) {
+            bail!("plot_scale must be between 0 and {}", MAX_PLOT_SCALE);
+        }
+        if world_height % 16 != 0 || !(16..=MAX_BLOCK_HEIGHT).contains(&world_height) {
+            bail!(
+                "world_height must be a multiple of 16 between 16 and {}",
+                MAX_BLOCK_HEIGHT
+            );
+        }
+        Ok(PlotGeometry::new(
+            plot_scale as u32,
+            (world_height / 16) as usize,
+        ))
+    }
+
+    /// Works out the geometry a plot was saved with from its chunks, or returns `None` if they
+    /// don't fit any geometry
+    pub fn of_plot(data: &PlotData) -> Option<PlotGeometry> {
+        let chunks = data.chunk_data.len();
+        let sections = data.chunk_data.first()?.sections.len();
+        // Plots are squares with a width of a power of two
+        let scale = chunks.trailing_zeros() / 2;
+        let geometry = PlotGeometry::new(scale, sections);
+        (scale as i64 <= MAX_PLOT_SCALE && geometry.num_chunks == chunks && sections > 0)
+            .then_some(geometry)
+    }
+
+    fn load(path: &Path) -> Result<PlotGeometry> {
+        let meta: WorldMeta = toml::from_str(&fs::read_to_string(path)?)?;
+        Ok(PlotGeometry::new(meta.plot_scale, meta.plot_sections))
+    }
+
+    fn save(&self, path: &Path) -> Result<()> {
+        let meta = WorldMeta {
+            plot_scale: self.scale,
+            plot_sections: self.sections,
+        };
+        fs::write(path, toml::to_string(&meta)?)?;
+        Ok(())
+    }
+}
+
+impl Default for PlotGeometry {
+    /// The size worlds had before it could be configured
+    fn default() -> PlotGeometry {
+        PlotGeometry::new(5, 24)
+    }
+}
+
+fn meta_path(world: &str) -> PathBuf {
+    worlds::dir(world).join("meta.toml")
+}
+
+/// Returns the geometry of `world`. Outside of the server, like when benchmarking or
+/// converting, this is read from the world folder if there is one.
+pub fn geometry(world: &str) -> PlotGeometry {
+    match GEOMETRIES
+        .get()
+        .and_then(|geometries| geometries.get(world))
+    {
+        Some(geometry) => *geometry,
+        None => PlotGeometry::load(&meta_path(world)).unwrap_or_default(),
+    }
+}
+
+/// Loads the geometry of one world. `old` is the size the world has if it is from before its
+/// size was saved.
+fn init_world(world: &str, configured: PlotGeometry, old: PlotGeometry) -> Result<PlotGeometry> {
+    fs::create_dir_all(worlds::dir(world))?;
+    let path = meta_path(world);
+    let geometry = if path.exists() {
+        PlotGeometry::load(&path).with_context(|| format!("error loading {}", path.display()))?
+    } else if worlds::plots_dir(world).exists() {
+        old.save(&path)?;
+        old
+    } else {
+        configured.save(&path)?;
+        info!(
+            "Created world {} with plot_scale = {} and world_height = {}",
+            world, configured.scale, configured.block_height
+        );
+        configured
+    };
+    if geometry != configured {
+        warn!(
+            "World {} was created with plot_scale = {} and world_height = {}, the size in the config is ignored",
+            world, geometry.scale, geometry.block_height
+        );
+    }
+    Ok(geometry)
+}
+
+/// Loads the geometry of every world on server start. New worlds are created with the size
+/// from the config, while the size of existing worlds can't be changed.
+pub fn init(plot_scale: i64, world_height: i64) -> Result<()> {
+    let configured = PlotGeometry::from_config(plot_scale, world_height)?;
+    // The default world is from before the size was saved if it has no size, so it has the old
+    // size. The other worlds then shared the size of the default world.
+    let default = init_world(worlds::DEFAULT_WORLD, configured, PlotGeometry::default())?;
+    // Players see every world as the same dimension, so they all have to be as high
+    let configured = PlotGeometry::new(configured.scale, default.sections);
+    let mut geometries = FxHashMap::default();
+    geometries.insert(worlds::DEFAULT_WORLD.to_string(), default);
+    for world in worlds::names().filter(|&world| world != worlds::DEFAULT_WORLD) {
+        let geometry = init_world(world, configured, default)?;
+        if geometry.sections != default.sections {
+            bail!(
+                "world {} is {} blocks high, but every world has to be as high as the default world ({} blocks)",
+                world,
+                geometry.block_height,
+                default.block_height
+            );
+        }
+        geometries.insert(world.to_string(), geometry);
+    }
+    if GEOMETRIES.set(geometries).is_err() {
+        bail!("the world geometry was already loaded");
+    }
+    Ok(())
+}
+
+#[test]
+fn config_geometry() {
+    let geometry = PlotGeometry::from_config(5, 384).unwrap();
+    assert_eq!(geometry, PlotGeometry::default());
+    assert_eq!(geometry.block_width, 512);
+    assert_eq!(geometry.num_chunks, 1024);
+    assert!(PlotGeometry::from_config(5, 390).is_err());
+    assert!(PlotGeometry::from_config(5, 4096).is_err());
+    assert!(PlotGeometry::from_config(9, 384).is_err());
+}
+
+#[test]
+fn geometry_of_plot() {
+    use mchprs_save_data::plot_data::{ChunkData, Tps};
+
+    let plot = |chunks: usize, sections: usize| PlotData {
+        tps: Tps::Limited(10),
+        world_send_rate: Default::default(),
+        chunk_data: vec![
+            ChunkData {
+                sections: vec![None; sections],
+                block_entities: Default::default(),
+            };
+            chunks
+        ],
+        pending_ticks: Vec::new(),
+        time_of_day: Default::default(),
+        entities: Vec::new(),
+    };
+    assert_eq!(
+        PlotGeometry::of_plot(&plot(64, 16)),
+        Some(PlotGeometry::new(3, 16))
+    );
+    assert_eq!(
+        PlotGeometry::of_plot(&plot(1, 24)),
+        Some(PlotGeometry::new(0, 24))
+    );
+    assert_eq!(PlotGeometry::of_plot(&plot(32, 24)), None);
+    assert_eq!(PlotGeometry::of_plot(&plot(0, 24)), None);
+}
@@ -0,0 +1,494 @@
+//! Lua scripts for automating a plot, such as generating test inputs for a circuit.
+//!
+//! Scripts are loaded from `./scripts` with `/script load`, and only act on the plot they were
+//! loaded in. Positions are relative to the lowest corner of the plot. Scripts get a `plot`
+//! table with these functions:
+//!
+//! - `plot.get_block(x, y, z)`: Returns the name of a block and a table of its properties.
+//! - `plot.set_block(x, y, z, name, [properties])`: Sets a block. This resets redpiler.
+//! - `plot.use_block(x, y, z)`: Uses a block, like flipping a lever or pressing a button.
+//! - `plot.after(ticks, callback)`: Runs the callback after a number of redstone ticks.
+//! - `plot.command(name, callback)`: Registers `/name`. The callback is called with the
+//!   arguments of the command as strings.
+//! - `plot.on(event, callback)`: Calls the callback with a table describing the event every
+//!   time it happens in the plot. Returning `false` from the callback cancels the event if it
+//!   can be cancelled. The events are `block_place`, `block_break`, `chat`, `command`,
+//!   `player_enter` and `player_leave`.
+//! - `plot.print(message)`: Sends a message with `&` color codes to everyone in the plot.
+
+use super::recording::Input;
+use super::Plot;
+use crate::events::{Event, EventResult};
+use crate::lang::Text;
+use crate::player::PacketSender;
+use mchprs_blocks::blocks::Block;
+use mchprs_blocks::BlockPos;
+use mchprs_text::TextComponent;
+use mchprs_world::World;
+use mlua::{Function, HookTriggers, Lua, LuaOptions, RegistryKey, StdLib, Table, Value, Variadic};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+const SCRIPTS_DIR: &str = "./scripts";
+const MAX_MEMORY: usize = 64 * 1024 * 1024;
+/// How long a script may run every time it is called before it gets stopped
+const CALL_TIME_LIMIT: Duration = Duration::from_secs(1);
+
+pub struct PlotScript {
+    name: String,
+    lua: Lua,
+    /// When the call that is currently running has to be stopped
+    deadline: Arc<Mutex<Option<Instant>>>,
+    /// The number of redstone ticks since the script was loaded
+    current_tick: u64,
+    /// Callbacks sorted by the tick they should run at
+    scheduled: Vec<(u64, RegistryKey)>,
+    commands: HashMap<String, RegistryKey>,
+    /// Event handlers by the name of the event
+    handlers: HashMap<String, Vec<RegistryKey>>,
+}
+
+impl PlotScript {
+    fn new(name: &str) -> mlua::Result<PlotScript> {
+        // Scripts don't get access to the filesystem or the rest of the OS
+        let libs = StdLib::TABLE | StdLib::STRING | StdLib::MATH | StdLib::UTF8;
+        let lua = Lua::new_with(libs, LuaOptions::default())?;
+        lua.set_memory_limit(MAX_MEMORY)?;
+
+        let deadline = Arc::new(Mutex::new(None));
+        let hook_deadline = deadline.clone();
+        lua.set_hook(
+            HookTriggers::new().every_nth_instruction(10_000),
+            move |_, _| match *hook_deadline.lock().unwrap() {
+                Some(deadline) if Instant::now() > deadline => Err(mlua::Error::RuntimeError(
+                    "script took too long to run".to_string(),
+                )),
+                _ => Ok(()),
+            },
+        );
+
+        Ok(PlotScript {
+            name: name.to_string(),
+            lua,
+            deadline,
+            current_tick: 0,
+            scheduled: Vec::new(),
+            commands: HashMap::new(),
+            handlers: HashMap::new(),
+        })
+    }
+}
+
+/// Callbacks registered by a script during a call, which are added to it afterwards
+#[derive(Default)]
+struct Registrations {
+    scheduled: Vec<(u64, RegistryKey)>,
+    commands: Vec<(String, RegistryKey)>,
+    handlers: Vec<(String, RegistryKey)>,
+}
+
+const EVENT_NAMES: &[&str] = &[
+    "block_place",
+    "block_break",
+    "chat",
+    "command",
+    "player_enter",
+    "player_leave",
+];
+
+fn event_name(event: &Event) -> Option<&'static str> {
+    Some(match event {
+        Event::BlockPlace { .. } => "block_place",
+        Event::BlockBreak { .. } => "block_break",
+        Event::Chat { .. } => "chat",
+        Event::Command { .. } => "command",
+        Event::PlayerEnterPlot { .. } => "player_enter",
+        Event::PlayerLeavePlot { .. } => "player_leave",
+        // These happen outside of plots
+        Event::PlayerJoin(_) | Event::PlayerQuit(_) => return None,
+    })
+}
+
+/// Builds the table an event handler is called with. `origin` is the lowest corner of the plot.
+fn event_table<'lua>(
+    lua: &'lua Lua,
+    event: &Event,
+    origin: (i32, i32),
+) -> mlua::Result<Table<'lua>> {
+    let table = lua.create_table()?;
+    match event {
+        Event::BlockPlace { player, pos, block } | Event::BlockBreak { player, pos, block } => {
+            table.set("player", player.username.as_str())?;
+            table.set("x", pos.x - origin.0)?;
+            table.set("y", pos.y)?;
+            table.set("z", pos.z - origin.1)?;
+            table.set("block", block.get_name())?;
+        }
+        Event::Chat { player, message } => {
+            table.set("player", player.username.as_str())?;
+            table.set("message", message.as_str())?;
+        }
+        Event::Command {
+            player,
+            command,
+            args,
+        } => {
+            table.set("player", player.username.as_str())?;
+            table.set("command", command.as_str())?;
+            table.set("args", args.clone())?;
+        }
+        Event::PlayerEnterPlot { player, .. }
+        | Event::PlayerLeavePlot { player, .. }
+        | Event::PlayerJoin(player)
+        | Event::PlayerQuit(player) => {
+            table.set("player", player.username.as_str())?;
+        }
+    }
+    Ok(table)
+}
+
+impl Plot {
+    /// Converts a position relative to the plot into a world position
+    fn script_pos(&self, x: i32, y: i32, z: i32) -> mlua::Result<BlockPos> {
+        let geometry = self.world.geometry;
+        if !(0..geometry.block_width).contains(&x)
+            || !(0..geometry.block_width).contains(&z)
+            || !(0..geometry.block_height).contains(&y)
+        {
+            return Err(mlua::Error::RuntimeError(format!(
+                "position {} {} {} is outside of the plot",
+                x, y, z
+            )));
+        }
+        Ok(BlockPos::new(
+            self.world.x * geometry.block_width + x,
+            y,
+            self.world.z * geometry.block_width + z,
+        ))
+    }
+
+    fn script_use_block(&mut self, pos: BlockPos) {
+        self.record(Input::Use { pos });
+        let block = self.world.get_block(pos);
+        let lever_or_button = matches!(block, Block::Lever { .. } | Block::StoneButton { .. });
+        if self.redpiler.is_active() && lever_or_button {
+            self.redpiler.on_use_block(pos);
+            return;
+        }
+        self.reset_redpiler();
+        mchprs_redstone::on_use(block, &mut self.world, pos);
+    }
+
+    /// Calls into the script with its api set up for this plot
+    fn call_script(
+        &mut self,
+        script: &mut PlotScript,
+        f: impl FnOnce(&Lua) -> mlua::Result<()>,
+    ) -> mlua::Result<()> {
+        // Make sure the script sees the current state of the world
+        if self.redpiler.is_active() {
+            self.redpiler.flush(&mut self.world);
+        }
+
+        let current_tick = script.current_tick;
+        let registrations = RefCell::new(Registrations::default());
+        let plot = RefCell::new(self);
+        *script.deadline.lock().unwrap() = Some(Instant::now() + CALL_TIME_LIMIT);
+        let lua = &script.lua;
+        let result = lua.scope(|scope| {
+            let api = lua.create_table()?;
+            api.set(
+                "get_block",
+                scope.create_function(|_, (x, y, z): (i32, i32, i32)| {
+                    let plot = plot.borrow();
+                    let block = plot.world.get_block(plot.script_pos(x, y, z)?);
+                    Ok((block.get_name(), block.properties()))
+                })?,
+            )?;
+            api.set(
+                "set_block",
+                scope.create_function(
+                    |_,
+                     (x, y, z, name, props): (
+                        i32,
+                        i32,
+                        i32,
+                        String,
+                        Option<HashMap<String, String>>,
+                    )| {
+                        let mut plot = plot.borrow_mut();
+                        let pos = plot.script_pos(x, y, z)?;
+                        let Some(mut block) =
+                            Block::from_name(name.trim_start_matches("minecraft:"))
+                        else {
+                            return Err(mlua::Error::RuntimeError(format!(
+                                "unknown block: {}",
+                                name
+                            )));
+                        };
+                        if let Some(props) = &props {
+                            block.set_properties(
+                                props
+                                    .iter()
+                                    .map(|(k, v)| (k.as_str(), v.as_str()))
+                                    .collect(),
+                            );
+                        }
+                        plot.reset_redpiler();
+                        plot.record(Input::SetBlock {
+                            pos,
+                            block: block.get_id(),
+                        });
+                        plot.world.set_block(pos, block);
+                        Ok(())
+                    },
+                )?,
+            )?;
+            api.set(
+                "use_block",
+                scope.create_function(|_, (x, y, z): (i32, i32, i32)| {
+                    let mut plot = plot.borrow_mut();
+                    let pos = plot.script_pos(x, y, z)?;
+                    plot.script_use_block(pos);
+                    Ok(())
+                })?,
+            )?;
+            api.set(
+                "after",
+                scope.create_function(|lua, (ticks, callback): (u64, Function)| {
+                    let key = lua.create_registry_value(callback)?;
+                    registrations
+                        .borrow_mut()
+                        .scheduled
+                        .push((current_tick + ticks.max(1), key));
+                    Ok(())
+                })?,
+            )?;
+            api.set(
+                "command",
+                scope.create_function(|lua, (name, callback): (String, Function)| {
+                    let key = lua.create_registry_value(callback)?;
+                    registrations.borrow_mut().commands.push((name, key));
+                    Ok(())
+                })?,
+            )?;
+            api.set(
+                "on",
+                scope.create_function(|lua, (name, callback): (String, Function)| {
+                    if !EVENT_NAMES.contains(&name.as_str()) {
+                        return Err(mlua::Error::RuntimeError(format!(
+                            "unknown event: {}",
+                            name
+                        )));
+                    }
+                    let key = lua.create_registry_value(callback)?;
+                    registrations.borrow_mut().handlers.push((name, key));
+                    Ok(())
+                })?,
+            )?;
+            api.set(
+                "print",
+                scope.create_function(|_, message: String| {
+                    let message = TextComponent::from_legacy_text(&message);
+                    for player in &plot.borrow().players {
+                        player.send_chat_message(&message);
+                    }
+                    Ok(())
+                })?,
+            )?;
+            lua.globals().set("plot", api)?;
+            f(lua)
+        });
+        *script.deadline.lock().unwrap() = None;
+
+        let plot = plot.into_inner();
+        let registrations = registrations.into_inner();
+        script.scheduled.extend(registrations.scheduled);
+        script.scheduled.sort_by_key(|(tick, _)| *tick);
+        for (name, key) in registrations.commands {
+            if let Some(old) = script.commands.insert(name, key) {
+                let _ = script.lua.remove_registry_value(old);
+            }
+        }
+        for (name, key) in registrations.handlers {
+            script.handlers.entry(name).or_default().push(key);
+        }
+        plot.world.flush_block_changes();
+        result
+    }
+
+    fn send_script_error(&self, name: &str, err: &mlua::Error) {
+        debug!(
+            "Script {} in plot {}, {} failed: {}",
+            name, self.world.x, self.world.z, err
+        );
+        for player in &self.players {
+            player.send_error_message(text!("script.error", error = err));
+        }
+    }
+
+    fn load_script(&mut self, name: &str) -> Result<(), Text> {
+        let valid_name = !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        if !valid_name {
+            return Err(text!("script.invalid_name"));
+        }
+        let path = Path::new(SCRIPTS_DIR).join(format!("{}.lua", name));
+        let source =
+            fs::read_to_string(&path).map_err(|_| text!("script.not_found", name = name))?;
+
+        let mut script = PlotScript::new(name).map_err(|err| Text::from(err.to_string()))?;
+        // Unload the previous script before running the new one
+        self.script = None;
+        self.call_script(&mut script, |lua| lua.load(&source).set_name(name).exec())
+            .map_err(|err| text!("script.error", error = err))?;
+        self.script = Some(script);
+        Ok(())
+    }
+
+    /// Advances the script's clock, running callbacks that became due
+    pub(super) fn advance_script(&mut self, ticks: u64) {
+        let Some(mut script) = self.script.take() else {
+            return;
+        };
+        script.current_tick += ticks;
+        let due = script
+            .scheduled
+            .iter()
+            .take_while(|(tick, _)| *tick <= script.current_tick)
+            .count();
+        let callbacks: Vec<_> = script.scheduled.drain(..due).map(|(_, key)| key).collect();
+        for key in callbacks {
+            let result = self.call_script(&mut script, |lua| {
+                lua.registry_value::<Function>(&key)?.call(())
+            });
+            let _ = script.lua.remove_registry_value(key);
+            if let Err(err) = result {
+                self.send_script_error(&script.name, &err);
+            }
+        }
+        self.script = Some(script);
+    }
+
+    /// Returns the number of ticks until the next scheduled callback
+    pub(super) fn ticks_until_script_callback(&self) -> Option<u64> {
+        let script = self.script.as_ref()?;
+        let (tick, _) = script.scheduled.first()?;
+        Some(tick.saturating_sub(script.current_tick))
+    }
+
+    /// Runs a command registered by the script. Returns `false` if there is no such command.
+    pub(super) fn run_script_command(&mut self, command: &str, args: &[&str]) -> bool {
+        let Some(mut script) = self.script.take() else {
+            return false;
+        };
+        // The command is taken out while it runs, so that it can register itself again
+        let Some(key) = script.commands.remove(command) else {
+            self.script = Some(script);
+            return false;
+        };
+
+        let args: Variadic<String> = args.iter().map(|arg| arg.to_string()).collect();
+        let result = self.call_script(&mut script, |lua| {
+            lua.registry_value::<Function>(&key)?.call(args)
+        });
+        if script.commands.contains_key(command) {
+            let _ = script.lua.remove_registry_value(key);
+        } else {
+            script.commands.insert(command.to_string(), key);
+        }
+        if let Err(err) = result {
+            self.send_script_error(&script.name, &err);
+        }
+        self.script = Some(script);
+        true
+    }
+
+    /// Calls the script's handlers for an event that happened in this plot
+    pub(super) fn script_event(&mut self, event: &Event) -> EventResult {
+        let Some(name) = event_name(event) else {
+            return EventResult::Continue;
+        };
+        let Some(mut script) = self.script.take() else {
+            return EventResult::Continue;
+        };
+        let Some(handlers) = script.handlers.remove(name) else {
+            self.script = Some(script);
+            return EventResult::Continue;
+        };
+
+        let origin = (
+            self.world.x * self.world.geometry.block_width,
+            self.world.z * self.world.geometry.block_width,
+        );
+        let mut result = EventResult::Continue;
+        for key in &handlers {
+            let call_result = self.call_script(&mut script, |lua| {
+                let table = event_table(lua, event, origin)?;
+                let value: Value = lua.registry_value::<Function>(key)?.call(table)?;
+                if matches!(value, Value::Boolean(false)) {
+                    result = EventResult::Cancel;
+                }
+                Ok(())
+            });
+            if let Err(err) = call_result {
+                self.send_script_error(&script.name, &err);
+            }
+        }
+        // Handlers registered while the event was running are kept after the existing ones
+        let mut handlers = handlers;
+        handlers.extend(script.handlers.remove(name).unwrap_or_default());
+        script.handlers.insert(name.to_string(), handlers);
+        self.script = Some(script);
+        result
+    }
+
+    pub(super) fn handle_script_command(&mut self, player: usize, args: &[&str]) {
+        let is_owner = self.owner == Some(self.players[player].uuid);
+        if !is_owner && !self.players[player].has_permission("plots.script.other") {
+            self.players[player].send_no_permission_message();
+            return;
+        }
+
+        match args {
+            ["load", name] => match self.load_script(name) {
+                Ok(()) => {
+                    self.players[player].send_system_message(text!("script.loaded", name = name))
+                }
+                Err(err) => self.players[player].send_error_message(err),
+            },
+            ["unload"] => match self.script.take() {
+                Some(script) => self.players[player]
+                    .send_system_message(text!("script.unloaded", name = script.name)),
+                None => self.players[player].send_error_message(text!("script.not_loaded")),
+            },
+            ["status"] => match &self.script {
+                Some(script) => {
+                    let mut commands: Vec<_> = script.commands.keys().cloned().collect();
+                    commands.sort();
+                    let player = &self.players[player];
+                    let commands = if commands.is_empty() {
+                        text!("script.no_commands").translate(&player.locale)
+                    } else {
+                        commands.join(", ")
+                    };
+                    player.send_system_message(text!(
+                        "script.status",
+                        name = script.name,
+                        callbacks = script.scheduled.len(),
+                        commands = commands
+                    ));
+                }
+                None => self.players[player].send_system_message(text!("script.not_loaded")),
+            },
+            _ => self.players[player].send_error_message(text!("script.usage")),
+        }
+    }
+}
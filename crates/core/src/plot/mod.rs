@@ -1,18 +1,34 @@
 pub mod commands;
 mod data;
 pub mod database;
+mod entities;
+mod executor;
+pub mod geometry;
+#[cfg(feature = "lua")]
+mod lua;
 mod monitor;
+mod navigation;
+mod operation;
 mod packet_handlers;
+mod profiler;
+pub mod recording;
 mod scoreboard;
+mod screen;
+#[cfg(feature = "wasm")]
+mod wasm;
 pub mod worldedit;
 
 use crate::config::CONFIG;
+use crate::events::{self, Event, EventResult, PlayerInfo};
 use crate::interaction;
 use crate::interaction::UseOnBlockContext;
-use crate::player::{EntityId, Gamemode, PacketSender, Player, PlayerPos};
+use crate::metrics::{self, PlotMetrics};
+use crate::player::{EntityId, Gamemode, Home, PacketSender, Player, PlayerPos};
 use crate::server::{BroadcastMessage, Message, PrivMessage};
 use crate::utils::HyphenatedUUID;
-use anyhow::Error;
+use crate::webmap::{self, MapFeed};
+use crate::worlds;
+use anyhow::{Context, Error};
 use bus::BusReader;
 use mchprs_blocks::block_entities::BlockEntity;
 use mchprs_blocks::blocks::Block;
@@ -20,46 +36,57 @@ use mchprs_blocks::items::Item;
 use mchprs_blocks::{BlockFace, BlockPos};
 use mchprs_network::packets::clientbound::*;
 use mchprs_network::packets::serverbound::SUseItemOn;
+use mchprs_network::packets::PacketEncoder;
 use mchprs_network::PlayerPacketSender;
 use mchprs_redpiler::{Compiler, CompilerOptions};
-use mchprs_save_data::plot_data::{ChunkData, PlotData, Tps, WorldSendRate};
+use mchprs_save_data::plot_data::{ChunkData, PlotData, TimeOfDay, Tps, WorldSendRate};
 use mchprs_text::TextComponent;
-use mchprs_world::storage::Chunk;
+use mchprs_world::memory::MemoryWorld;
+use mchprs_world::storage::{Chunk, ChunkSection};
 use mchprs_world::{TickEntry, TickPriority, World};
 use monitor::TimingsMonitor;
+use navigation::Click;
+use once_cell::sync::Lazy;
+use rustc_hash::FxHashMap;
 use scoreboard::RedpilerState;
 use std::cmp::Ordering;
 use std::collections::HashSet;
-use std::path::Path;
+use std::mem;
+use std::panic;
 use std::sync::mpsc::{Receiver, Sender};
-use std::thread;
 use std::time::{Duration, Instant};
 use tokio::runtime::Runtime;
-use tracing::{debug, error, warn};
+use tokio::task::JoinHandle;
+use tracing::{debug, debug_span, error, info, trace_span};
 
 use self::data::sleep_time_for_tps;
+pub use self::entities::Entities;
+pub use self::geometry::{geometry, PlotGeometry};
+pub(crate) use self::operation::format_wait;
+pub use self::operation::Operation;
+use self::profiler::Profiler;
+use self::recording::{Input, Recording};
 use self::scoreboard::Scoreboard;
+use self::screen::ScreenCapture;
 
-/// The width of a plot (2^n)
-pub const PLOT_SCALE: u32 = 5;
+/// How many seconds in a row a plot has to go over the MSPT budget before it is throttled
+const MSPT_BUDGET_STRIKES: u32 = 5;
 
-/// The width of a plot counted in chunks
-pub const PLOT_WIDTH: i32 = 2i32.pow(PLOT_SCALE);
-/// The plot width in blocks
-pub const PLOT_BLOCK_WIDTH: i32 = PLOT_WIDTH * 16;
-pub const NUM_CHUNKS: usize = PLOT_WIDTH.pow(2) as usize;
+/// When more blocks than this changed in a chunk since the last world send, the whole chunk is
+/// sent again instead of the changes. A full section of changes is already much bigger than the
+/// chunk data, and it replaces up to one packet for each section.
+const CHUNK_RESEND_CHANGES: usize = 2048;
 
-/// The height of the world in sections (Default: 24, Max: 127)
-pub const PLOT_SECTIONS: usize = 24;
-/// The plot height in blocks
-pub const PLOT_BLOCK_HEIGHT: i32 = PLOT_SECTIONS as i32 * 16;
-
-const ERROR_IO_ONLY: &str = "This plot cannot be interacted with while redpiler is active with `--io-only`. To stop redpiler, run `/redpiler reset`.";
+/// Runs the async work started by plots, like looking up players for the whitelist
+static ASYNC_RT: Lazy<Runtime> = Lazy::new(|| Runtime::new().unwrap());
 
 pub struct Plot {
     pub world: PlotWorld,
     pub players: Vec<Player>,
     pub redpiler: Compiler,
+    /// The redpiler compile that is running on the blocking pool. The plot doesn't tick or
+    /// handle packets until it is done, so the world stays the way it was compiled.
+    compiling: Option<JoinHandle<Compiler>>,
 
     // Thread communication
     message_receiver: BusReader<BroadcastMessage>,
@@ -75,6 +102,10 @@ pub struct Plot {
     lag_time: Duration,
     last_nspt: Option<Duration>,
     timings: TimingsMonitor,
+    /// The last time this plot's statistics were reported to the metrics and stats sidebars
+    last_stats_update: Instant,
+    /// How many seconds in a row this plot has gone over the MSPT budget
+    budget_strikes: u32,
     /// The last time a player was in this plot
     last_player_time: Instant,
     /// The last time the world changes were sent to the player
@@ -87,55 +118,136 @@ pub struct Plot {
     /// If true, the plot will remain running even if no players are on for a long time.
     always_running: bool,
     auto_redpiler: bool,
+    /// The name of the plot world this plot is in
+    world_name: String,
 
     owner: Option<u128>,
-    async_rt: Runtime,
     scoreboard: Scoreboard,
+    /// The last profiler started with `/profile`, kept after stopping so it can be reported
+    profiler: Option<Profiler>,
+    /// Started with `/record`
+    recording: Option<Recording>,
+    /// Started with `/screen capture`
+    screen_capture: Option<ScreenCapture>,
+    #[cfg(feature = "wasm")]
+    extensions: wasm::Extensions,
+    /// The script loaded with `/script load`
+    #[cfg(feature = "lua")]
+    script: Option<lua::PlotScript>,
+}
+
+/// Sends packets to a player in the plot, for when the world is borrowed without the players
+pub struct PlotPacketSender {
+    sender: PlayerPacketSender,
+    /// Whether the player wants to hear sounds, set with `/toggle sounds`
+    pub sounds_enabled: bool,
+}
+
+impl PlotPacketSender {
+    fn new(player: &Player) -> PlotPacketSender {
+        PlotPacketSender {
+            sender: PlayerPacketSender::new(&player.client),
+            sounds_enabled: player.preferences.sounds,
+        }
+    }
+
+    pub fn send_packet(&self, data: &PacketEncoder) {
+        self.sender.send_packet(data);
+    }
 }
 
 pub struct PlotWorld {
     pub x: i32,
     pub z: i32,
+    /// The geometry of the world the plot is in
+    pub geometry: PlotGeometry,
     pub chunks: Vec<Chunk>,
     pub to_be_ticked: Vec<TickEntry>,
-    pub packet_senders: Vec<PlayerPacketSender>,
+    pub packet_senders: Vec<PlotPacketSender>,
+    /// Only present while someone is watching the plot on the web map
+    pub(crate) map_feed: Option<MapFeed>,
+    /// How many players have each container open, used by trapped chests
+    pub(crate) container_viewers: FxHashMap<BlockPos, u8>,
+    pub(crate) time_of_day: u32,
+    pub(crate) entities: Entities,
 }
 
 impl PlotWorld {
     fn get_chunk_index_for_chunk(&self, chunk_x: i32, chunk_z: i32) -> usize {
-        let local_x = chunk_x - self.x * PLOT_WIDTH;
-        let local_z = chunk_z - self.z * PLOT_WIDTH;
-        (local_x * PLOT_WIDTH + local_z).unsigned_abs() as usize
+        let width = self.geometry.width;
+        let local_x = chunk_x - self.x * width;
+        let local_z = chunk_z - self.z * width;
+        (local_x * width + local_z).unsigned_abs() as usize
     }
 
     fn get_chunk_index_for_block(&self, block_x: i32, block_z: i32) -> Option<usize> {
-        let chunk_x = (block_x - (self.x * PLOT_BLOCK_WIDTH)) >> 4;
-        let chunk_z = (block_z - (self.z * PLOT_BLOCK_WIDTH)) >> 4;
-        if chunk_x >= PLOT_WIDTH || chunk_z >= PLOT_WIDTH {
+        let geometry = self.geometry;
+        let chunk_x = (block_x - (self.x * geometry.block_width)) >> 4;
+        let chunk_z = (block_z - (self.z * geometry.block_width)) >> 4;
+        if chunk_x >= geometry.width || chunk_z >= geometry.width {
             return None;
         }
-        Some(((chunk_x << PLOT_SCALE) + chunk_z).unsigned_abs() as usize)
+        Some(((chunk_x << geometry.scale) + chunk_z).unsigned_abs() as usize)
     }
 
-    fn flush_block_changes(&mut self) {
-        for packet in self.chunks.iter_mut().flat_map(|c| c.multi_blocks()) {
-            let encoded = packet.encode();
-            for player in &self.packet_senders {
-                player.send_packet(&encoded);
-            }
+    /// Estimates the memory used by the chunks of this world, not counting allocator overhead
+    fn memory_usage(&self) -> usize {
+        let chunks: usize = self
+            .chunks
+            .iter()
+            .map(|chunk| {
+                let sections: usize = chunk
+                    .sections
+                    .iter()
+                    .map(|section| section.data().len() * 8 + section.palette().len() * 4)
+                    .sum();
+                sections
+                    + chunk.sections.len() * std::mem::size_of::<ChunkSection>()
+                    + chunk.block_entities.len() * std::mem::size_of::<(BlockPos, BlockEntity)>()
+            })
+            .sum();
+        chunks + self.to_be_ticked.len() * std::mem::size_of::<TickEntry>()
+    }
+
+    /// The trapped chests that `viewers` has a different amount of players looking into
+    fn changed_trapped_chests(&self, viewers: &FxHashMap<BlockPos, u8>) -> Vec<BlockPos> {
+        interaction::changed_trapped_chests(self, &self.container_viewers, viewers)
+    }
+
+    /// Sets how many players have each container open and updates the trapped chests whose
+    /// count changed
+    pub(crate) fn set_container_viewers(&mut self, viewers: FxHashMap<BlockPos, u8>) {
+        let changed = self.changed_trapped_chests(&viewers);
+        self.container_viewers = viewers;
+        for pos in changed {
+            interaction::update_trapped_chest(self, pos);
         }
-        for chunk in &mut self.chunks {
-            chunk.reset_multi_blocks();
+    }
+
+    /// Copies the blocks and pending ticks of the plot, and everything else redstone reads, into
+    /// a world that can be moved to another thread
+    fn snapshot(&self) -> MemoryWorld {
+        let width = self.geometry.width;
+        MemoryWorld {
+            x: self.x * width,
+            z: self.z * width,
+            width,
+            depth: width,
+            chunks: self.chunks.clone(),
+            to_be_ticked: self.to_be_ticked.clone(),
+            time_of_day: self.time_of_day,
+            item_frames: self.entities.item_frame_overrides().collect(),
+            container_viewers: self.container_viewers.clone(),
         }
     }
 
     pub fn get_corners(&self) -> (BlockPos, BlockPos) {
-        const W: i32 = PLOT_BLOCK_WIDTH;
-        let first_pos = BlockPos::new(self.x * W, 0, self.z * W);
+        let w = self.geometry.block_width;
+        let first_pos = BlockPos::new(self.x * w, 0, self.z * w);
         let second_pos = BlockPos::new(
-            (self.x + 1) * W - 1,
-            PLOT_BLOCK_HEIGHT - 1,
-            (self.z + 1) * W - 1,
+            (self.x + 1) * w - 1,
+            self.geometry.block_height - 1,
+            (self.z + 1) * w - 1,
         );
         (first_pos, second_pos)
     }
@@ -150,7 +262,7 @@ impl World for PlotWorld {
         };
 
         // Check to see if block is within height limit
-        if pos.y >= PLOT_BLOCK_HEIGHT || pos.y < 0 {
+        if pos.y >= self.geometry.block_height || pos.y < 0 {
             return false;
         }
 
@@ -220,6 +332,45 @@ impl World for PlotWorld {
         self.chunks.get_mut(chunk_idx)
     }
 
+    fn flush_block_changes(&mut self) {
+        for chunk in &mut self.chunks {
+            let (chunk_x, chunk_z) = (chunk.x, chunk.z);
+            let mut changes = 0;
+            let packets: Vec<_> = chunk
+                .multi_blocks()
+                .map(|packet| {
+                    changes += packet.records.len();
+                    packet.encode()
+                })
+                .collect();
+            if packets.is_empty() {
+                continue;
+            }
+
+            if changes > CHUNK_RESEND_CHANGES {
+                let chunk_data = chunk.encode_packet();
+                for player in &self.packet_senders {
+                    player.send_packet(&chunk_data);
+                }
+            } else {
+                for packet in &packets {
+                    for player in &self.packet_senders {
+                        player.send_packet(packet);
+                    }
+                }
+            }
+            if let Some(feed) = &mut self.map_feed {
+                feed.record_chunk_change(
+                    chunk_x - self.x * self.geometry.width,
+                    chunk_z - self.z * self.geometry.width,
+                );
+            }
+        }
+        for chunk in &mut self.chunks {
+            chunk.reset_multi_blocks();
+        }
+    }
+
     fn schedule_tick(&mut self, pos: BlockPos, delay: u32, priority: TickPriority) {
         self.to_be_ticked.push(TickEntry {
             pos,
@@ -232,6 +383,18 @@ impl World for PlotWorld {
         self.to_be_ticked.iter().any(|e| e.pos == pos)
     }
 
+    fn container_viewers(&self, pos: BlockPos) -> u8 {
+        self.container_viewers.get(&pos).copied().unwrap_or(0)
+    }
+
+    fn time_of_day(&self) -> u32 {
+        self.time_of_day
+    }
+
+    fn item_frame_override(&self, pos: BlockPos, facing: BlockFace) -> Option<u8> {
+        self.entities.item_frame_override(pos, facing)
+    }
+
     fn play_sound(
         &mut self,
         pos: BlockPos,
@@ -260,15 +423,43 @@ impl World for PlotWorld {
         .encode();
 
         for player in &self.packet_senders {
-            player.send_packet(&sound_effect_data);
+            if player.sounds_enabled {
+                player.send_packet(&sound_effect_data);
+            }
         }
     }
 }
 
 impl Plot {
     fn tickn(&mut self, ticks: u64) {
+        if self.is_capturing_screen() {
+            // Every tick is captured, so they can't be run all at once
+            for _ in 0..ticks {
+                self.tick();
+            }
+            return;
+        }
+
+        #[cfg(feature = "lua")]
+        if self.redpiler.is_active() && self.script.is_some() {
+            // Stop at every scheduled callback so that it runs at the right tick
+            let mut remaining = ticks;
+            while remaining > 0 {
+                let n = self
+                    .ticks_until_script_callback()
+                    .map_or(remaining, |due| due.clamp(1, remaining));
+                self.timings.tickn(n);
+                self.record_ticks(n);
+                self.redpiler.tickn(n);
+                self.advance_script(n);
+                remaining -= n;
+            }
+            return;
+        }
+
         if self.redpiler.is_active() {
             self.timings.tickn(ticks);
+            self.record_ticks(ticks);
             self.redpiler.tickn(ticks);
             return;
         }
@@ -279,7 +470,16 @@ impl Plot {
     }
 
     fn tick(&mut self) {
+        self.tick_redstone();
+        #[cfg(feature = "lua")]
+        self.advance_script(1);
+        self.capture_screen();
+    }
+
+    fn tick_redstone(&mut self) {
+        let _span = trace_span!("redstone_tick").entered();
         self.timings.tick();
+        self.record_ticks(1);
         if self.redpiler.is_active() {
             self.redpiler.tick();
             return;
@@ -291,9 +491,22 @@ impl Plot {
         for pending in &mut self.world.to_be_ticked {
             pending.ticks_left = pending.ticks_left.saturating_sub(1);
         }
+        let profiler = self.profiler.as_mut().filter(|p| p.is_running());
+        let Some(profiler) = profiler else {
+            while self.world.to_be_ticked.first().map_or(1, |e| e.ticks_left) == 0 {
+                let entry = self.world.to_be_ticked.remove(0);
+                mchprs_redstone::tick(self.world.get_block(entry.pos), &mut self.world, entry.pos);
+            }
+            return;
+        };
+
+        profiler.tick();
         while self.world.to_be_ticked.first().map_or(1, |e| e.ticks_left) == 0 {
             let entry = self.world.to_be_ticked.remove(0);
-            mchprs_redstone::tick(self.world.get_block(entry.pos), &mut self.world, entry.pos);
+            let block = self.world.get_block(entry.pos);
+            let start = Instant::now();
+            mchprs_redstone::tick(block, &mut self.world, entry.pos);
+            profiler.record(block, entry.pos, start.elapsed());
         }
     }
 
@@ -326,8 +539,46 @@ impl Plot {
         }
     }
 
+    /// The players in this plot within `radius` of `pos`, closest first, leaving out the player
+    /// with `uuid`
+    fn players_near(&self, uuid: u128, pos: PlayerPos, radius: f64) -> Vec<(&Player, f64)> {
+        let mut players: Vec<_> = self
+            .players
+            .iter()
+            .filter(|player| player.uuid != uuid)
+            .map(|player| (player, player.pos.distance(pos)))
+            .filter(|&(_, distance)| distance <= radius)
+            .collect();
+        players.sort_by(|(_, a), (_, b)| a.total_cmp(b));
+        players
+    }
+
     fn change_player_gamemode(&mut self, player_idx: usize, gamemode: Gamemode) {
-        self.players[player_idx].set_gamemode(gamemode);
+        let player = &mut self.players[player_idx];
+        match (player.gamemode, gamemode) {
+            (Gamemode::Creative, Gamemode::Spectator) => {
+                player.spectator_return = Some(Home {
+                    world: player.world.clone(),
+                    x: player.pos.x,
+                    y: player.pos.y,
+                    z: player.pos.z,
+                    yaw: player.yaw,
+                    pitch: player.pitch,
+                });
+                player.set_gamemode(gamemode);
+            }
+            (Gamemode::Spectator, Gamemode::Creative) => {
+                player.set_gamemode(gamemode);
+                // Players that went to another world while spectating stay where they are
+                if let Some(back) = player.spectator_return.take() {
+                    if back.world == player.world {
+                        let pos = PlayerPos::new(back.x, back.y, back.z);
+                        player.teleport_facing(pos, back.yaw, back.pitch);
+                    }
+                }
+            }
+            _ => player.set_gamemode(gamemode),
+        }
         let _ = self.message_sender.send(Message::PlayerUpdateGamemode(
             self.players[player_idx].uuid,
             gamemode,
@@ -352,24 +603,12 @@ impl Plot {
     }
 
     fn set_pressure_plate(&mut self, pos: BlockPos, powered: bool) {
+        self.record(Input::PressurePlate { pos, powered });
         if self.redpiler.is_active() {
             self.redpiler.set_pressure_plate(pos, powered);
             return;
         }
-
-        let block = self.world.get_block(pos);
-        match block {
-            Block::StonePressurePlate { .. } => {
-                self.world
-                    .set_block(pos, Block::StonePressurePlate { powered });
-                mchprs_redstone::update_surrounding_blocks(&mut self.world, pos);
-                mchprs_redstone::update_surrounding_blocks(
-                    &mut self.world,
-                    pos.offset(BlockFace::Bottom),
-                );
-            }
-            _ => warn!("Block at {} is not a pressure plate", pos),
-        }
+        interaction::set_pressure_plate(&mut self.world, pos, powered);
     }
 
     fn are_players_on_block(&mut self, pos: BlockPos) -> bool {
@@ -382,7 +621,7 @@ impl Plot {
     }
 
     fn enter_plot(&mut self, player: Player) {
-        self.save();
+        self.save_or_notify();
         let spawn_player = player.spawn_packet().encode();
         let metadata = player.metadata_packet().encode();
         let entity_equipment = player.equippment_packet();
@@ -404,16 +643,37 @@ impl Plot {
             player.client.send_packet(&other_metadata);
         }
 
-        player.send_system_message(&format!(
-            "Entering plot ({}, {})",
-            self.world.x, self.world.z
-        ));
+        player.send_system_message(text!("plot.entering", x = self.world.x, z = self.world.z));
         self.world
             .packet_senders
-            .push(PlayerPacketSender::new(&player.client));
+            .push(PlotPacketSender::new(&player));
         self.scoreboard.add_player(&player);
+        player.send_time_of_day(self.world.time_of_day);
+        if CONFIG.plot_border && self.owner == Some(player.uuid) {
+            self.locked_players.insert(player.entity_id);
+            self.send_plot_border(&player);
+        }
+        let event = Event::PlayerEnterPlot {
+            player: PlayerInfo::of(&player),
+            plot_x: self.world.x,
+            plot_z: self.world.z,
+        };
         self.players.push(player);
         self.update_view_pos_for_player(self.players.len() - 1, true);
+        self.send_entities(self.players.len() - 1);
+        #[cfg(feature = "wasm")]
+        self.declare_extension_commands(self.players.len() - 1);
+        self.publish_event(event);
+    }
+
+    /// Publishes an event on the event bus, and to the script loaded in the plot
+    fn publish_event(&mut self, event: Event) -> EventResult {
+        let result = events::publish(&event);
+        #[cfg(feature = "lua")]
+        if self.script_event(&event) == EventResult::Cancel && event.is_cancellable() {
+            return EventResult::Cancel;
+        }
+        result
     }
 
     fn get_chunk_distance(x1: i32, z1: i32, x2: i32, z2: i32) -> u32 {
@@ -434,10 +694,20 @@ impl Plot {
             // let unload_chunk = CUnloadChunk { chunk_x, chunk_z }.encode();
             // self.players[player_idx].client.send_packet(&unload_chunk);
         } else if !was_loaded && should_be_loaded {
-            if !Plot::chunk_in_plot_bounds(self.world.x, self.world.z, chunk_x, chunk_z) {
+            if !Plot::chunk_in_plot_bounds(
+                &self.world.geometry,
+                self.world.x,
+                self.world.z,
+                chunk_x,
+                chunk_z,
+            ) {
                 self.players[player_idx]
                     .client
-                    .send_packet(&Chunk::encode_empty_packet(chunk_x, chunk_z, PLOT_SECTIONS));
+                    .send_packet(&Chunk::encode_empty_packet(
+                        chunk_x,
+                        chunk_z,
+                        self.world.geometry.sections,
+                    ));
             } else {
                 let chunk_data = self.world.chunks
                     [self.world.get_chunk_index_for_chunk(chunk_x, chunk_z)]
@@ -489,6 +759,17 @@ impl Plot {
         self.players[player_idx].last_chunk_z = chunk_z;
     }
 
+    /// Returns whether the player may change blocks in this plot
+    pub(crate) fn can_interact(&self, player: usize) -> bool {
+        let player = &self.players[player];
+        match self.owner {
+            Some(owner) => {
+                owner == player.uuid || player.has_permission("plots.admin.interact.other")
+            }
+            None => player.has_permission("plots.admin.interact.unowned"),
+        }
+    }
+
     fn handle_use_item_impl(&mut self, use_item_on: &SUseItemOn, player: usize) {
         let block_pos = BlockPos::new(use_item_on.x, use_item_on.y, use_item_on.z);
         let block_face = BlockFace::from_id(use_item_on.face as u32);
@@ -509,8 +790,19 @@ impl Plot {
             self.players[player].inventory[45].clone()
         };
 
-        if !Plot::in_plot_bounds(self.world.x, self.world.z, block_pos.x, block_pos.z) {
-            self.players[player].send_system_message("Can't interact with blocks outside of plot");
+        if use_item_on.hand == 0 && self.use_navigation_tool(player, Click::Right) {
+            cancel(self);
+            return;
+        }
+
+        if !Plot::in_plot_bounds(
+            &self.world.geometry,
+            self.world.x,
+            self.world.z,
+            block_pos.x,
+            block_pos.z,
+        ) {
+            self.players[player].send_system_message(text!("plot.interact_outside"));
             cancel(self);
             return;
         }
@@ -529,14 +821,7 @@ impl Plot {
             }
         }
 
-        if let Some(owner) = self.owner {
-            let player = &mut self.players[player];
-            if owner != player.uuid && !player.has_permission("plots.admin.interact.other") {
-                player.send_no_permission_message();
-                cancel(self);
-                return;
-            }
-        } else if !self.players[player].has_permission("plots.admin.interact.unowned") {
+        if !self.can_interact(player) {
             self.players[player].send_no_permission_message();
             cancel(self);
             return;
@@ -546,6 +831,7 @@ impl Plot {
             let block = self.world.get_block(block_pos);
             let lever_or_button = matches!(block, Block::Lever { .. } | Block::StoneButton { .. });
             if lever_or_button && !self.players[player].crouching {
+                self.record(Input::Use { pos: block_pos });
                 self.redpiler.on_use_block(block_pos);
                 self.redpiler.flush(&mut self.world);
                 self.world.flush_block_changes();
@@ -553,7 +839,7 @@ impl Plot {
             } else {
                 match self.redpiler.current_flags() {
                     Some(flags) if flags.io_only => {
-                        self.players[player].send_error_message(ERROR_IO_ONLY);
+                        self.players[player].send_error_message(text!("redpiler.io_only"));
                         cancel(self);
                         return;
                     }
@@ -563,7 +849,20 @@ impl Plot {
             }
         }
 
+        if let Some(item) = &item_in_hand {
+            let used_block = self.world.get_block(block_pos);
+            let usable = interaction::is_usable(used_block, Some(item.item_type));
+            if (self.players[player].crouching || !usable)
+                && self.place_entity(player, item, block_pos, block_face)
+            {
+                return;
+            }
+        }
+
         if let Some(item) = item_in_hand {
+            let place_pos = block_pos.offset(block_face);
+            let old_block = self.world.get_block(place_pos);
+            let used_block = self.world.get_block(block_pos);
             let cancelled = interaction::use_item_on_block(
                 &item,
                 &mut self.world,
@@ -577,12 +876,39 @@ impl Plot {
             if cancelled {
                 cancel(self);
             }
+            let new_block = self.world.get_block(place_pos);
+            if new_block != old_block {
+                let event = Event::BlockPlace {
+                    player: PlayerInfo::of(&self.players[player]),
+                    pos: place_pos,
+                    block: new_block,
+                };
+                if self.publish_event(event) == EventResult::Cancel {
+                    self.world.set_block(place_pos, old_block);
+                    self.world.delete_block_entity(place_pos);
+                }
+            }
+            if self.is_recording() {
+                let placed = self.world.get_block(place_pos);
+                if placed != old_block {
+                    self.record(Input::Place {
+                        pos: place_pos,
+                        block: placed.get_id(),
+                        block_entity: self.world.get_block_entity(place_pos).cloned(),
+                    });
+                } else if !self.players[player].crouching {
+                    self.record_use(block_pos, used_block);
+                }
+            }
+            self.update_container_viewers();
             self.world.flush_block_changes();
             return;
         }
 
         let block = self.world.get_block(block_pos);
-        if !self.players[player].crouching {
+        // When the client doesn't expect anything to happen, like for cauldrons, it tries again
+        // with the off hand, which shouldn't use the block twice
+        if !self.players[player].crouching && use_item_on.hand == 0 {
             interaction::on_use(
                 block,
                 &mut self.world,
@@ -590,15 +916,109 @@ impl Plot {
                 block_pos,
                 None,
             );
+            self.record_use(block_pos, block);
+            self.update_container_viewers();
             self.world.flush_block_changes();
         }
     }
 
+    /// Counts the players looking into each container and updates the trapped chests whose
+    /// count changed.
+    pub(crate) fn update_container_viewers(&mut self) {
+        let mut viewers: FxHashMap<BlockPos, u8> = FxHashMap::default();
+        for pos in self.players.iter().filter_map(|p| p.open_container_pos) {
+            let count = viewers.entry(pos).or_default();
+            *count = count.saturating_add(1);
+        }
+        if viewers == self.world.container_viewers {
+            return;
+        }
+
+        if self.is_recording() {
+            let viewers = viewers.iter().map(|(&pos, &count)| (pos, count)).collect();
+            self.record(Input::ContainerViewers(viewers));
+        }
+        if self.redpiler.is_active() {
+            for pos in self.world.changed_trapped_chests(&viewers) {
+                let count = viewers.get(&pos).copied().unwrap_or(0);
+                self.redpiler.set_trapped_chest(pos, count);
+            }
+            self.world.container_viewers = viewers;
+            return;
+        }
+        self.world.set_container_viewers(viewers);
+    }
+
+    /// Sets the time of day and updates the daylight detectors in the plot for it
+    pub(crate) fn set_time_of_day(&mut self, time_of_day: u32) {
+        self.world.time_of_day = time_of_day;
+        for player in &self.players {
+            player.send_time_of_day(time_of_day);
+        }
+
+        let mut detectors = Vec::new();
+        for chunk in &self.world.chunks {
+            for (section_y, section) in chunk.sections.iter().enumerate() {
+                if section.block_count() == 0 {
+                    continue;
+                }
+                for y in 0..16 {
+                    for z in 0..16 {
+                        for x in 0..16 {
+                            let block = Block::from_id(section.get_block(x, y, z));
+                            if let Block::DaylightDetector { inverted, power } = block {
+                                let pos = BlockPos::new(
+                                    chunk.x * 16 + x as i32,
+                                    section_y as i32 * 16 + y as i32,
+                                    chunk.z * 16 + z as i32,
+                                );
+                                detectors.push((pos, inverted, power));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut reset = false;
+        for (pos, inverted, power) in detectors {
+            let new_power = mchprs_redstone::daylight::get_power(time_of_day, inverted);
+            if new_power == power {
+                continue;
+            }
+            if !reset {
+                self.reset_redpiler();
+                reset = true;
+            }
+            self.world.set_block(
+                pos,
+                Block::DaylightDetector {
+                    inverted,
+                    power: new_power,
+                },
+            );
+            mchprs_redstone::update_surrounding_blocks(&mut self.world, pos);
+        }
+        self.world.flush_block_changes();
+    }
+
     fn handle_player_digging(&mut self, block_pos: BlockPos, player: usize) {
         let block = self.world.get_block(block_pos);
 
-        if !Plot::in_plot_bounds(self.world.x, self.world.z, block_pos.x, block_pos.z) {
-            self.players[player].send_system_message("Can't break blocks outside of plot");
+        if self.holding_navigation_tool(player) {
+            self.send_block_change(block_pos, block.get_id());
+            self.use_navigation_tool(player, Click::Left);
+            return;
+        }
+
+        if !Plot::in_plot_bounds(
+            &self.world.geometry,
+            self.world.x,
+            self.world.z,
+            block_pos.x,
+            block_pos.z,
+        ) {
+            self.players[player].send_system_message(text!("plot.break_outside"));
             return;
         }
 
@@ -636,16 +1056,27 @@ impl Plot {
 
         match self.redpiler.current_flags() {
             Some(flags) if flags.io_only => {
-                self.players[player].send_error_message(ERROR_IO_ONLY);
+                self.players[player].send_error_message(text!("redpiler.io_only"));
                 self.send_block_change(block_pos, block.get_id());
                 return;
             }
             _ => {}
         }
 
+        let event = Event::BlockBreak {
+            player: PlayerInfo::of(&self.players[player]),
+            pos: block_pos,
+            block,
+        };
+        if self.publish_event(event) == EventResult::Cancel {
+            self.send_block_change(block_pos, block.get_id());
+            return;
+        }
+
         self.reset_redpiler();
 
         interaction::destroy(block, &mut self.world, block_pos);
+        self.record(Input::Break { pos: block_pos });
         self.world.flush_block_changes();
 
         let effect = CWorldEvent {
@@ -685,42 +1116,63 @@ impl Plot {
         let bounds = self.world.get_corners();
         // TODO: use monitor
         let monitor = Default::default();
-        let ticks = self.world.to_be_ticked.drain(..).collect();
-
-        let mut players_need_updates = HashSet::new();
-        thread::scope(|s| {
-            let handle = s.spawn(|| {
-                self.redpiler
-                    .compile(&self.world, bounds, options, ticks, monitor)
-            });
-            while !handle.is_finished() {
-                // We'll update the players so that they don't time out.
-                for player_idx in 0..self.players.len() {
-                    if self.players[player_idx].update() {
-                        // Unforunately we can't update a players view position
-                        // since we don't have access to the world, but we can
-                        // save the players that need updating for later.
-                        players_need_updates.insert(player_idx);
-                    }
-                }
-                thread::sleep(Duration::from_millis(20));
-            }
-        });
+        // The compile gets a copy of the world so that it doesn't hold up the plot's worker.
+        // The ticks stay in the plot until the compile is done, in case it gets discarded.
+        let mut world = self.world.snapshot();
+        self.compiling = Some(ASYNC_RT.spawn_blocking(move || {
+            let _span = debug_span!("redpiler_compile").entered();
+            let mut redpiler = Compiler::default();
+            let ticks = mem::take(&mut world.to_be_ticked);
+            redpiler.compile(&world, bounds, options, ticks, monitor);
+            redpiler
+        }));
+    }
 
-        // Now that we have ownership of the world again, we can update player view positions
-        for player_idx in players_need_updates {
-            self.update_view_pos_for_player(player_idx, false);
+    /// Switches to the compiled redpiler once the compile started by [`Plot::start_redpiler`]
+    /// is done
+    fn poll_redpiler(&mut self) {
+        if !self
+            .compiling
+            .as_ref()
+            .is_some_and(|handle| handle.is_finished())
+        {
+            return;
         }
-
+        let handle = self.compiling.take().unwrap();
+        self.redpiler = match ASYNC_RT.block_on(handle) {
+            Ok(redpiler) => redpiler,
+            Err(err) => panic::resume_unwind(err.into_panic()),
+        };
+        self.world.to_be_ticked.clear();
         self.scoreboard
             .set_redpiler_state(&self.players, RedpilerState::Running);
 
         self.reset_timings();
     }
 
+    /// Keeps the players alive while redpiler compiles. Packets aren't handled while compiling,
+    /// so it counts as a warp for the keep alive timeout.
+    fn update_while_compiling(&mut self) {
+        self.handle_messages();
+        self.poll_redpiler();
+        for player_idx in 0..self.players.len() {
+            self.players[player_idx].keep_alive_while_warping();
+            if self.players[player_idx].update() {
+                self.update_view_pos_for_player(player_idx, false);
+            }
+        }
+    }
+
     /// Redpiler needs to reset implicitly in the case of any block changes done by a player. This
     /// can be
-    fn reset_redpiler(&mut self) {
+    pub(crate) fn reset_redpiler(&mut self) {
+        if self.compiling.take().is_some() {
+            debug!("Discarding redpiler compile");
+            self.scoreboard
+                .set_redpiler_state(&self.players, RedpilerState::Stopped);
+            self.scoreboard
+                .set_redpiler_options(&self.players, &Default::default());
+        }
         if self.redpiler.is_active() {
             debug!("Discarding redpiler");
             let bounds = self.world.get_corners();
@@ -751,13 +1203,18 @@ impl Plot {
         let player = self.players.remove(player_idx);
 
         let destroy_other_entities = CRemoveEntities {
-            entity_ids: self.players.iter().map(|p| p.entity_id as i32).collect(),
+            entity_ids: self
+                .players
+                .iter()
+                .map(|p| p.entity_id as i32)
+                .chain(self.world.entities.ids().map(|id| id as i32))
+                .collect(),
         }
         .encode();
         player.client.send_packet(&destroy_other_entities);
 
-        let chunk_offset_x = self.world.x << PLOT_SCALE;
-        let chunk_offset_z = self.world.z << PLOT_SCALE;
+        let chunk_offset_x = self.world.x << self.world.geometry.scale;
+        let chunk_offset_z = self.world.z << self.world.geometry.scale;
         for chunk in &self.world.chunks {
             player.client.send_packet(
                 &CUnloadChunk {
@@ -768,33 +1225,69 @@ impl Plot {
             );
         }
         self.destroy_entity(player.entity_id);
-        self.locked_players.remove(&player.entity_id);
+        if self.locked_players.remove(&player.entity_id) {
+            player.reset_world_border();
+        }
         self.scoreboard.remove_player(&player);
+        if player.open_container_pos.is_some() {
+            self.update_container_viewers();
+            self.world.flush_block_changes();
+        }
+        self.publish_event(Event::PlayerLeavePlot {
+            player: PlayerInfo::of(&player),
+            plot_x: self.world.x,
+            plot_z: self.world.z,
+        });
         player
     }
 
-    fn chunk_in_plot_bounds(plot_x: i32, plot_z: i32, chunk_x: i32, chunk_z: i32) -> bool {
-        let (x, z) = (chunk_x >> PLOT_SCALE, chunk_z >> PLOT_SCALE);
+    /// Keeps a player that is locked to the plot inside of it
+    fn send_plot_border(&self, player: &Player) {
+        let geometry = &self.world.geometry;
+        let (x, z) = Plot::get_center(geometry, self.world.x, self.world.z);
+        player.send_world_border(x, z, geometry.block_width as f64);
+    }
+
+    fn chunk_in_plot_bounds(
+        geometry: &PlotGeometry,
+        plot_x: i32,
+        plot_z: i32,
+        chunk_x: i32,
+        chunk_z: i32,
+    ) -> bool {
+        let scale = geometry.scale;
+        let (x, z) = (chunk_x >> scale, chunk_z >> scale);
         plot_x == x && plot_z == z
     }
 
-    fn in_plot_bounds(plot_x: i32, plot_z: i32, x: i32, z: i32) -> bool {
-        Plot::chunk_in_plot_bounds(plot_x, plot_z, x >> 4, z >> 4)
+    pub(crate) fn in_plot_bounds(
+        geometry: &PlotGeometry,
+        plot_x: i32,
+        plot_z: i32,
+        x: i32,
+        z: i32,
+    ) -> bool {
+        Plot::chunk_in_plot_bounds(geometry, plot_x, plot_z, x >> 4, z >> 4)
     }
 
     pub fn claim_plot(&mut self, plot_x: i32, plot_z: i32, player: usize) {
         let player = &mut self.players[player];
-        database::claim_plot(plot_x, plot_z, &format!("{:032x}", player.uuid));
-        let center = Plot::get_center(plot_x, plot_z);
+        database::claim_plot(
+            &self.world_name,
+            plot_x,
+            plot_z,
+            &format!("{:032x}", player.uuid),
+        );
+        let center = Plot::get_center(&self.world.geometry, plot_x, plot_z);
         player.teleport(PlayerPos::new(center.0, 64.0, center.1));
-        player.send_system_message(&format!("Claimed plot {},{}", plot_x, plot_z));
+        player.send_system_message(text!("plot.claimed", x = plot_x, z = plot_z));
     }
 
-    pub fn get_center(plot_x: i32, plot_z: i32) -> (f64, f64) {
-        const WIDTH: f64 = PLOT_BLOCK_WIDTH as f64;
+    pub fn get_center(geometry: &PlotGeometry, plot_x: i32, plot_z: i32) -> (f64, f64) {
+        let width = geometry.block_width as f64;
         (
-            plot_x as f64 * WIDTH + WIDTH / 2.0,
-            plot_z as f64 * WIDTH + WIDTH / 2.0,
+            plot_x as f64 * width + width / 2.0,
+            plot_z as f64 * width + width / 2.0,
         )
     }
 
@@ -834,13 +1327,20 @@ impl Plot {
         for player_idx in 0..self.players.len() {
             let player_idx = player_idx - removal_offset;
             let commands: Vec<String> = self.players[player_idx].command_queue.drain(..).collect();
+            let finished_operation = self.players[player_idx].operation_finished;
+            let mut left = false;
             for command in commands {
                 let mut args: Vec<&str> = command.split(' ').collect();
                 let command = args.remove(0);
                 if self.handle_command(player_idx, command, args) {
                     removal_offset += 1;
+                    left = true;
+                    break;
                 }
             }
+            if finished_operation && !left {
+                self.release_operation(player_idx);
+            }
         }
     }
 
@@ -852,6 +1352,12 @@ impl Plot {
                         player.send_chat_message(&message);
                     }
                 }
+                BroadcastMessage::Notice(message) => {
+                    for player in self.players.iter().filter(|p| p.preferences.broadcasts) {
+                        let message = message.translate(&player.locale);
+                        player.send_chat_message(&TextComponent::from_legacy_text(&message));
+                    }
+                }
                 BroadcastMessage::PlayerJoinedInfo(player_join_info) => {
                     let player_info = CPlayerInfoUpdate {
                         players: vec![CPlayerInfoUpdatePlayer {
@@ -883,8 +1389,8 @@ impl Plot {
                 BroadcastMessage::Shutdown => {
                     let mut players: Vec<Player> = self.players.drain(..).collect();
                     for player in players.iter_mut() {
-                        player.save();
-                        player.kick("Server closed".into());
+                        Plot::save_player(&self.message_sender, player);
+                        player.kick(text!("server.closed").translate(&player.locale).into());
                     }
                     self.always_running = false;
                     self.running = false;
@@ -905,6 +1411,76 @@ impl Plot {
                         player.client.send_packet(&player_info);
                     }
                 }
+                BroadcastMessage::KickPlayer(uuid, reason) => {
+                    if let Some(player) = self.players.iter().find(|p| p.uuid == uuid) {
+                        player.kick(reason.translate(&player.locale).into());
+                    }
+                }
+                BroadcastMessage::RunCommand(uuid, command) => {
+                    if let Some(player) = self.players.iter_mut().find(|p| p.uuid == uuid) {
+                        player.command_queue.push(command);
+                    }
+                }
+                BroadcastMessage::Save(saved) => {
+                    self.save_or_notify();
+                    let _ = saved.send(());
+                }
+                BroadcastMessage::GiveItem(uuid, item) => {
+                    if let Some(player) = self.players.iter_mut().find(|p| p.uuid == uuid) {
+                        if !player.give_item(item) {
+                            player.send_error_message(text!("player.inventory_full"));
+                        }
+                    }
+                }
+                BroadcastMessage::Near(query) => {
+                    if query.world != self.world_name {
+                        continue;
+                    }
+                    for (player, distance) in self.players_near(query.uuid, query.pos, query.radius)
+                    {
+                        query.sender.send_system_message(text!(
+                            "near.player",
+                            player = player.username,
+                            distance = distance.round(),
+                            x = self.world.x,
+                            z = self.world.z
+                        ));
+                    }
+                }
+                BroadcastMessage::WhoIs(uuid, sender) => {
+                    if let Some(player) = self.players.iter().find(|p| p.uuid == uuid) {
+                        let lines = [
+                            text!("whois.name", player = player.username),
+                            text!("whois.uuid", uuid = HyphenatedUUID(player.uuid)),
+                            text!(
+                                "whois.gamemode",
+                                gamemode = format!("{:?}", player.gamemode)
+                            ),
+                            text!(
+                                "whois.position",
+                                x = format!("{:.1}", player.pos.x),
+                                y = format!("{:.1}", player.pos.y),
+                                z = format!("{:.1}", player.pos.z)
+                            ),
+                            text!(
+                                "whois.plot",
+                                x = self.world.x,
+                                z = self.world.z,
+                                world = self.world_name
+                            ),
+                        ];
+                        for line in lines {
+                            sender.send_system_message(line);
+                        }
+                    }
+                }
+                BroadcastMessage::StaffNotice(message) => {
+                    for player in &self.players {
+                        if player.has_permission("mchprs.notify.errors") {
+                            player.send_error_message(message.clone());
+                        }
+                    }
+                }
             }
         }
         // Handle messages from the private message channel
@@ -931,8 +1507,8 @@ impl Plot {
             if self.locked_players.contains(&player.entity_id) {
                 continue;
             }
-            let (plot_x, plot_z) = player.pos.plot_pos();
-            if plot_x != self.world.x || plot_z != self.world.z {
+            let (plot_x, plot_z) = player.pos.plot_pos(&self.world.geometry);
+            if plot_x != self.world.x || plot_z != self.world.z || player.world != self.world_name {
                 outside_players.push(player.uuid);
             }
         }
@@ -952,7 +1528,7 @@ impl Plot {
         self.players.retain(|player| {
             let alive = player.client.alive();
             if !alive {
-                player.save();
+                Plot::save_player(message_sender, player);
                 message_sender
                     .send(Message::PlayerLeft(player.uuid))
                     .unwrap();
@@ -973,6 +1549,7 @@ impl Plot {
             }
         }
         // Handle received packets
+        let _span = debug_span!("handle_packets").entered();
         for player_idx in 0..self.players.len() {
             self.handle_packets_for_player(player_idx);
         }
@@ -980,6 +1557,12 @@ impl Plot {
 
     fn update(&mut self) {
         self.handle_messages();
+        #[cfg(feature = "wasm")]
+        if self.extensions.reload_changed() {
+            for player in 0..self.players.len() {
+                self.declare_extension_commands(player);
+            }
+        }
 
         // Only tick if there are players in the plot
         if !self.players.is_empty() {
@@ -1014,6 +1597,7 @@ impl Plot {
 
             self.last_update_time = now;
             if batch_size != 0 {
+                let _span = debug_span!("tick_batch", batch_size).entered();
                 // 50_000 (= 3.33 MHz) here is arbitrary.
                 // We just need a number that's not too high so we actually get around to sending
                 // block updates.
@@ -1031,11 +1615,14 @@ impl Plot {
                         }
                     }
                 }
-                self.last_nspt = Some(self.last_update_time.elapsed() / ticks_completed);
+                let nspt = self.last_update_time.elapsed() / ticks_completed;
+                self.last_nspt = Some(nspt);
+                self.timings.record_tick_duration(nspt, ticks_completed);
             }
 
             if self.auto_redpiler
                 && !self.redpiler.is_active()
+                && self.compiling.is_none()
                 && (self.tps == Tps::Unlimited || self.timings.is_running_behind())
             {
                 self.start_redpiler(Default::default());
@@ -1045,17 +1632,23 @@ impl Plot {
             let time_since_last_world_send = now - self.last_world_send_time;
             if time_since_last_world_send > world_send_rate {
                 self.last_world_send_time = now;
+                let _span = debug_span!("world_send").entered();
                 self.world.flush_block_changes();
             }
         } else {
             self.timings.set_ticking(false);
-            // Unload plot after 600 seconds unless the plot should be always loaded
-            if self.last_player_time.elapsed().as_secs() > 600 && !self.always_running {
+            // Unload plot after some time unless the plot should be always loaded
+            let unload_time =
+                Duration::from_secs(worlds::plot_unload_time(&self.world_name).max(0) as u64);
+            if self.last_player_time.elapsed() > unload_time && !self.always_running {
                 self.running = false;
                 self.timings.stop();
             }
         }
 
+        let map_feed = self.world.map_feed.take();
+        self.world.map_feed = webmap::update_feed(&self.world_name, &self.world, map_feed);
+
         self.update_players();
 
         // Handle commands before removing players just in case they ran a command before leaving
@@ -1063,70 +1656,212 @@ impl Plot {
 
         self.remove_dc_players();
         self.remove_oob_players();
+
+        if self.last_stats_update.elapsed() > Duration::from_secs(1) {
+            self.last_stats_update = Instant::now();
+            self.report_metrics();
+            self.update_stats_sidebars();
+            self.check_mspt_budget();
+        }
     }
 
-    fn create_async_rt() -> Runtime {
-        Runtime::new().unwrap()
+    fn set_tps(&mut self, tps: Tps) {
+        self.sleep_time = sleep_time_for_tps(tps);
+        self.timings.set_tps(tps);
+        self.tps = tps;
+        self.reset_timings();
     }
 
-    fn generate_chunk(layers: i32, x: i32, z: i32) -> Chunk {
-        let mut chunk = Chunk::empty(x, z, PLOT_SECTIONS);
+    /// Reduces the rtps of the plot if it keeps spending more time ticking than the configured
+    /// budget allows, so that one plot can't starve the rest of the server.
+    fn check_mspt_budget(&mut self) {
+        if CONFIG.plot_mspt_budget <= 0 || self.players.is_empty() {
+            self.budget_strikes = 0;
+            return;
+        }
+        let budget = CONFIG.plot_mspt_budget as f64 / 1000.0;
+        let (Some(load), Some(report)) = (
+            self.timings.tick_load(),
+            self.timings.tick_duration_report(),
+        ) else {
+            return;
+        };
 
-        for ry in 0..layers {
-            for rx in 0..16 {
-                for rz in 0..16 {
-                    let block_x = (x << 4) | rx;
-                    let block_z = (z << 4) | rz;
+        // The time spent ticking for every 50ms game tick
+        let mspt = load * 0.05;
+        if mspt <= budget {
+            self.budget_strikes = 0;
+            return;
+        }
+        self.budget_strikes += 1;
+        if self.budget_strikes < MSPT_BUDGET_STRIKES {
+            return;
+        }
+        self.budget_strikes = 0;
 
-                    let block = if block_x % PLOT_BLOCK_WIDTH == 0
-                        || block_z % PLOT_BLOCK_WIDTH == 0
-                        || (block_x + 1) % PLOT_BLOCK_WIDTH == 0
-                        || (block_z + 1) % PLOT_BLOCK_WIDTH == 0
-                    {
-                        Block::StoneBricks {}
-                    } else {
-                        Block::Sandstone {}
-                    };
-                    chunk.set_block(rx as u32, ry as u32, rz as u32, block.get_id());
-                }
+        // The rtps at which ticking takes up exactly the budget
+        let per_tick = report.average.as_secs_f64().max(f64::EPSILON);
+        let mut rtps = ((budget * 20.0 / per_tick) as u32).max(1);
+        if let Tps::Limited(tps) = self.tps {
+            rtps = rtps.min(tps.saturating_sub(1).max(1));
+        }
+        if self.tps == Tps::Limited(rtps) {
+            return;
+        }
+
+        info!(
+            "Plot {}, {} went over its MSPT budget ({:.1}ms), reducing rtps from {} to {}",
+            self.world.x,
+            self.world.z,
+            mspt * 1000.0,
+            self.tps,
+            rtps
+        );
+        self.set_tps(Tps::Limited(rtps));
+        let message = text!(
+            "plot.over_budget",
+            budget = CONFIG.plot_mspt_budget,
+            rtps = rtps
+        );
+        for player in &self.players {
+            let message = message.translate(&player.locale);
+            player.send_chat_message(&TextComponent::from_legacy_text(&message));
+        }
+    }
+
+    fn update_stats_sidebars(&mut self) {
+        if !self.players.iter().any(|p| p.stats_sidebar) {
+            return;
+        }
+
+        let rtps = match self.timings.generate_report() {
+            Some(report) => format!("{:.1}", report.ten_s),
+            None => "-".to_string(),
+        };
+        let mspt = match self.timings.tick_duration_report() {
+            Some(report) => format!("{:.2}", report.average.as_secs_f64() * 1000.0),
+            None => "-".to_string(),
+        };
+        for player in &self.players {
+            if !player.stats_sidebar {
+                continue;
             }
+            let selection = match (player.first_position, player.second_position) {
+                (Some(first), Some(second)) => {
+                    let size = first.max(second) - first.min(second);
+                    format!("§a{}x{}x{}", size.x + 1, size.y + 1, size.z + 1)
+                }
+                _ => "§7none".to_string(),
+            };
+            let lines = vec![
+                format!("§6TPS: §a{}", rtps),
+                format!("§6MSPT: §a{}", mspt),
+                format!("§6RTPS: §a{}", self.tps),
+                format!("§6Plot: §a{}, {}", self.world.x, self.world.z),
+                format!("§6Selection: {}", selection),
+            ];
+            self.scoreboard.set_stats(player, lines);
         }
-        chunk
     }
 
-    fn from_data(
-        plot_data: PlotData,
+    fn report_metrics(&self) {
+        let achieved_rtps = self
+            .timings
+            .generate_report()
+            .map_or(0.0, |report| report.ten_s);
+        let target_rtps = match self.tps {
+            Tps::Limited(tps) => Some(tps),
+            Tps::Unlimited => None,
+        };
+        metrics::update_plot(
+            &self.world_name,
+            self.world.x,
+            self.world.z,
+            PlotMetrics {
+                tick_duration: self.timings.tick_duration_report().map(|r| r.average),
+                target_rtps,
+                achieved_rtps,
+                players: self.players.len(),
+                memory_bytes: metrics::memory_usage_shown().then(|| self.world.memory_usage()),
+            },
+        );
+    }
+
+    /// Loads the chunks of a plot save file into the plot at `x`, `z`
+    pub(crate) fn load_chunks(
+        geometry: &PlotGeometry,
+        chunk_data: Vec<ChunkData>,
         x: i32,
         z: i32,
-        rx: BusReader<BroadcastMessage>,
-        tx: Sender<Message>,
-        priv_rx: Receiver<PrivMessage>,
-        always_running: bool,
-    ) -> Plot {
-        let chunk_x_offset = x << PLOT_SCALE;
-        let chunk_z_offset = z << PLOT_SCALE;
-        let chunks: Vec<Chunk> = plot_data
-            .chunk_data
+    ) -> Vec<Chunk> {
+        let chunk_x_offset = x << geometry.scale;
+        let chunk_z_offset = z << geometry.scale;
+        let chunks: Vec<Chunk> = chunk_data
             .into_iter()
             .enumerate()
             .map(|(i, c)| {
                 c.load(
-                    chunk_x_offset + i as i32 / PLOT_WIDTH,
-                    chunk_z_offset + i as i32 % PLOT_WIDTH,
+                    chunk_x_offset + i as i32 / geometry.width,
+                    chunk_z_offset + i as i32 % geometry.width,
                 )
             })
             .collect();
-        if chunks.len() != NUM_CHUNKS {
+        if chunks.len() != geometry.num_chunks {
             error!("This plot has the wrong number of chunks!");
             let possible_scale = (chunks.len() as f64).sqrt().log2();
-            error!("Note: it most likely came from a server running plot scale {}, this server is running a plot scale of {}", possible_scale, PLOT_SCALE);
+            error!("Note: it most likely came from a server running plot scale {}, this server is running a plot scale of {}", possible_scale, geometry.scale);
         }
+        chunks
+    }
+
+    /// Replaces all the blocks in the plot with the ones in `plot_data`, like when the plot is
+    /// cleared
+    fn replace_world(&mut self, plot_data: PlotData) {
+        self.reset_redpiler();
+        let world = &self.world;
+        let chunks = Plot::load_chunks(&world.geometry, plot_data.chunk_data, world.x, world.z);
+        let entities = Entities::load(&world.geometry, plot_data.entities, world.x, world.z);
+        self.world.chunks = chunks;
+        self.world.to_be_ticked = plot_data.pending_ticks;
+        self.replace_entities(entities);
+        self.record_snapshot();
+
+        let close = CCloseContainer { window_id: 1 }.encode();
+        for player in &mut self.players {
+            if player.open_container_pos.take().is_some() {
+                player.client.send_packet(&close);
+            }
+        }
+        self.update_container_viewers();
+        for player in 0..self.players.len() {
+            self.update_view_pos_for_player(player, true);
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn from_data(
+        world_name: String,
+        plot_data: PlotData,
+        x: i32,
+        z: i32,
+        rx: BusReader<BroadcastMessage>,
+        tx: Sender<Message>,
+        priv_rx: Receiver<PrivMessage>,
+        always_running: bool,
+    ) -> Plot {
+        let geometry = geometry(&world_name);
+        let chunks = Plot::load_chunks(&geometry, plot_data.chunk_data, x, z);
         let world = PlotWorld {
             x,
             z,
+            geometry,
             chunks,
             to_be_ticked: plot_data.pending_ticks,
             packet_senders: Vec::new(),
+            map_feed: None,
+            container_viewers: Default::default(),
+            time_of_day: plot_data.time_of_day.0,
+            entities: Entities::load(&geometry, plot_data.entities, x, z),
         };
         let tps = plot_data.tps;
         let world_send_rate = plot_data.world_send_rate;
@@ -1134,6 +1869,8 @@ impl Plot {
             last_player_time: Instant::now(),
             last_update_time: Instant::now(),
             last_world_send_time: Instant::now(),
+            last_stats_update: Instant::now(),
+            budget_strikes: 0,
             lag_time: Duration::new(0, 0),
             sleep_time: sleep_time_for_tps(tps),
             last_nspt: None,
@@ -1143,20 +1880,30 @@ impl Plot {
             players: Vec::new(),
             locked_players: HashSet::new(),
             running: true,
-            auto_redpiler: CONFIG.auto_redpiler,
+            auto_redpiler: worlds::auto_redpiler(&world_name),
             tps,
             world_send_rate,
             always_running,
             redpiler: Default::default(),
+            compiling: None,
             timings: TimingsMonitor::new(tps),
-            owner: database::get_plot_owner(x, z).map(|s| s.parse::<HyphenatedUUID>().unwrap().0),
-            async_rt: Plot::create_async_rt(),
+            owner: database::get_plot_owner(&world_name, x, z)
+                .map(|s| s.parse::<HyphenatedUUID>().unwrap().0),
+            world_name,
             scoreboard: Default::default(),
+            profiler: None,
+            recording: None,
+            screen_capture: None,
+            #[cfg(feature = "wasm")]
+            extensions: wasm::Extensions::new(),
+            #[cfg(feature = "lua")]
+            script: None,
             world,
         }
     }
 
     fn load(
+        world_name: String,
         x: i32,
         z: i32,
         rx: BusReader<BroadcastMessage>,
@@ -1164,82 +1911,123 @@ impl Plot {
         priv_rx: Receiver<PrivMessage>,
         always_running: bool,
     ) -> Result<Plot, (Error, Sender<Message>)> {
-        let plot_path = format!("./world/plots/p{},{}", x, z);
-        Ok(if Path::new(&plot_path).exists() {
-            match data::load_plot(plot_path) {
-                Ok(data) => Plot::from_data(data, x, z, rx, tx, priv_rx, always_running),
-                Err(err) => {
-                    return Result::Err((
-                        err.context(format!("error loading plot {},{}", x, z)),
-                        tx,
-                    ))
-                }
-            }
+        let plot_path = worlds::plots_dir(&world_name).join(format!("p{},{}", x, z));
+        let data = if plot_path.exists() {
+            data::load_plot(plot_path)
         } else {
-            Plot::from_data(data::empty_plot(), x, z, rx, tx, priv_rx, always_running)
-        })
+            data::empty_plot(&world_name)
+        };
+        match data {
+            Ok(data) => Ok(Plot::from_data(
+                world_name,
+                data,
+                x,
+                z,
+                rx,
+                tx,
+                priv_rx,
+                always_running,
+            )),
+            Err(err) => Err((err.context(format!("error loading plot {},{}", x, z)), tx)),
+        }
     }
 
-    fn save(&mut self) {
+    fn to_data(&mut self) -> PlotData {
         let world = &mut self.world;
         let chunk_data: Vec<ChunkData> = world.chunks.iter_mut().map(ChunkData::new).collect();
-        let data = PlotData {
+        PlotData {
             tps: self.tps,
             world_send_rate: self.world_send_rate,
             chunk_data,
             pending_ticks: world.to_be_ticked.clone(),
-        };
-        data.save_to_file(format!("./world/plots/p{},{}", world.x, world.z))
-            .unwrap();
+            time_of_day: TimeOfDay(world.time_of_day),
+            entities: world.entities.save(&world.geometry, world.x, world.z),
+        }
+    }
+
+    fn save(&mut self) -> anyhow::Result<()> {
+        let data = self.to_data();
+        let plot_path =
+            worlds::plots_dir(&self.world_name).join(format!("p{},{}", self.world.x, self.world.z));
+        data.save_to_file(&plot_path)
+            .with_context(|| format!("failed to save {}", plot_path.display()))?;
 
         self.reset_timings();
+        Ok(())
     }
 
-    fn run(&mut self, initial_player: Option<Player>) {
-        let _guard = self.async_rt.enter();
+    /// Saves the plot, logging and telling staff if it failed instead of stopping the plot,
+    /// which would lose even more
+    fn save_or_notify(&mut self) {
+        if let Err(err) = self.save() {
+            error!(
+                "Plot {},{} failed to save: {:?}",
+                self.world.x, self.world.z, err
+            );
+            let message = text!(
+                "plot.save_error",
+                x = self.world.x,
+                z = self.world.z,
+                error = format!("{:#}", err)
+            );
+            let _ = self.message_sender.send(Message::NotifyStaff(message));
+        }
+    }
 
-        if let Some(player) = initial_player {
-            self.enter_plot(player);
+    /// Saves the player, logging and telling staff if it failed
+    fn save_player(message_sender: &Sender<Message>, player: &Player) {
+        if let Err(err) = player.save() {
+            error!(
+                "Failed to save the player data of {}: {:?}",
+                player.username, err
+            );
+            let message = text!(
+                "server.player_save_error",
+                player = player.username,
+                error = format!("{:#}", err)
+            );
+            let _ = message_sender.send(Message::NotifyStaff(message));
         }
+    }
 
-        while self.running {
+    /// Updates the plot once, returning how long to wait before updating it again, or `None` if
+    /// it stopped running and was saved
+    fn step(&mut self) -> Option<Duration> {
+        let wait = if self.compiling.is_some() {
+            self.update_while_compiling();
+            Duration::from_millis(20)
+        } else if self.sleep_time <= Duration::from_millis(5) && !self.players.is_empty() {
             // Fast path, for super high RTPS
-            if self.sleep_time <= Duration::from_millis(5) && !self.players.is_empty() {
-                self.update();
-                if self.tps != Tps::Unlimited {
-                    thread::yield_now();
-                }
-                continue;
-            }
-
+            self.update();
+            Duration::ZERO
+        } else {
             let before = Instant::now();
             self.update();
-            let delta = Instant::now().duration_since(before);
+            self.sleep_time.saturating_sub(before.elapsed())
+        };
 
-            if delta < self.sleep_time {
-                let sleep_time = self.sleep_time - delta;
-                thread::sleep(sleep_time);
-            } else {
-                thread::yield_now();
-            }
+        if !self.running {
+            self.save_or_notify();
+            return None;
         }
-
-        self.save();
+        Some(wait)
     }
 
     /// This function is used in case of an error. It will try to send the player to spawn if this
     /// isn't already a spawn plot.
-    fn send_player_away(plot_x: i32, plot_z: i32, player: &mut Player) {
+    fn send_player_away(geometry: &PlotGeometry, plot_x: i32, plot_z: i32, player: &mut Player) {
         let (px, pz) = if plot_x == 0 && plot_z == 0 {
             // Can't send players to spawn if spawn crashed!
-            Plot::get_center(1, 0)
+            Plot::get_center(geometry, 1, 0)
         } else {
-            Plot::get_center(0, 0)
+            Plot::get_center(geometry, 0, 0)
         };
         player.teleport(PlayerPos::new(px, 64.0, pz));
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn load_and_run(
+        world_name: String,
         x: i32,
         z: i32,
         rx: BusReader<BroadcastMessage>,
@@ -1248,36 +2036,42 @@ impl Plot {
         always_running: bool,
         initial_player: Option<Player>,
     ) {
-        thread::Builder::new()
-            .name(format!("p{},{}", x, z))
-            .spawn(
-                move || match Plot::load(x, z, rx, tx, priv_rx, always_running) {
-                    Ok(mut plot) => plot.run(initial_player),
-                    Err((err, tx)) => {
-                        if let Some(mut player) = initial_player {
-                            player.send_error_message("There was an error loading that plot.");
-                            Plot::send_player_away(x, z, &mut player);
-                            tx.send(Message::PlayerLeavePlot(player)).unwrap();
-                        }
-                        tx.send(Message::PlotUnload(x, z)).unwrap();
-                        panic!("{err:?}");
+        executor::spawn(move || {
+            let plot = Plot::load(world_name.clone(), x, z, rx, tx, priv_rx, always_running);
+            match plot {
+                Ok(mut plot) => {
+                    if let Some(player) = initial_player {
+                        plot.enter_plot(player);
                     }
-                },
-            )
-            .unwrap();
+                    Some(plot)
+                }
+                Err((err, tx)) => {
+                    error!("Failed to load plot {},{}: {:?}", x, z, err);
+                    if let Some(mut player) = initial_player {
+                        player.send_error_message(text!("plot.load_error"));
+                        Plot::send_player_away(&geometry(&world_name), x, z, &mut player);
+                        let _ = tx.send(Message::PlayerLeavePlot(player));
+                    }
+                    let _ = tx.send(Message::PlotUnload(world_name, x, z));
+                    None
+                }
+            }
+        });
     }
 }
 
 impl Drop for Plot {
     fn drop(&mut self) {
+        metrics::remove_plot(&self.world_name, self.world.x, self.world.z);
+
         if !self.players.is_empty() {
             for player in &mut self.players {
-                player.save(); // just in case
+                Plot::save_player(&self.message_sender, player); // just in case
 
                 let world = &self.world;
-                Plot::send_player_away(world.x, world.z, player);
+                Plot::send_player_away(&world.geometry, world.x, world.z, player);
 
-                player.send_error_message("The plot you were previously in has crashed!");
+                player.send_error_message(text!("plot.crashed"));
             }
 
             while !self.players.is_empty() {
@@ -1288,23 +2082,46 @@ impl Drop for Plot {
                     .unwrap();
             }
         }
-        let world = &self.world;
-        self.message_sender
-            .send(Message::PlotUnload(world.x, world.z))
-            .unwrap();
 
         self.reset_redpiler();
         self.world
             .chunks
             .iter_mut()
             .for_each(|chunk| chunk.compress());
-        self.save();
+        self.save_or_notify();
+
+        match self.stop_recording() {
+            Some(Ok(path)) => info!("Saved the recording of the plot to {}", path.display()),
+            Some(Err(err)) => error!("Failed to save the recording of the plot: {:?}", err),
+            None => {}
+        }
+
+        // The plot must be saved before the server is told about the unload, otherwise the
+        // plot could get loaded again from stale data.
+        let world = &self.world;
+        self.message_sender
+            .send(Message::PlotUnload(
+                self.world_name.clone(),
+                world.x,
+                world.z,
+            ))
+            .unwrap();
+
+        // Players may have been sent here while we were unloading. Send them back to the
+        // server so the plot can be loaded again for them.
+        while let Ok(message) = self.priv_message_receiver.try_recv() {
+            let player = match message {
+                PrivMessage::PlayerEnterPlot(player) => player,
+                PrivMessage::PlayerTeleportOther(player, _) => player,
+            };
+            let _ = self.message_sender.send(Message::PlayerLeavePlot(player));
+        }
     }
 }
 
 #[test]
 fn chunk_save_and_load_test() {
-    let mut chunk = Chunk::empty(1, 1, PLOT_SECTIONS);
+    let mut chunk = Chunk::empty(1, 1, PlotGeometry::default().sections);
     chunk.set_block(13, 63, 12, 332);
     chunk.set_block(13, 62, 12, 331);
     let chunk_data = ChunkData::new(&mut chunk);
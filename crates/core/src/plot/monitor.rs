@@ -1,11 +1,21 @@
 use mchprs_save_data::plot_data::Tps;
 use std::collections::VecDeque;
 use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, Once};
 use std::thread;
-use std::thread::JoinHandle;
-use std::time::Duration;
-use tracing::warn;
+use std::time::{Duration, Instant};
+
+/// How far back tick durations are kept for the tick duration report
+const TICK_DURATION_WINDOW: Duration = Duration::from_secs(10);
+/// Plots running at unlimited rtps update very often, so the number of samples is capped
+const MAX_TICK_DURATION_SAMPLES: usize = 1000;
+/// How often the ticks that passed are sampled
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Monitors that were created since the sampling thread last picked them up. One thread samples
+/// every monitor, so it doesn't take a thread for each loaded plot.
+static NEW_MONITORS: Mutex<Vec<Arc<MonitorData>>> = Mutex::new(Vec::new());
+static START_SAMPLING: Once = Once::new();
 
 #[derive(Default)]
 struct AtomicTps {
@@ -48,6 +58,87 @@ struct MonitorData {
     timings_record: Mutex<VecDeque<u32>>,
 }
 
+/// What the sampling thread knows about a monitor from its last sample
+struct Sampler {
+    data: Arc<MonitorData>,
+    last_tps: u32,
+    last_ticks_count: u64,
+    was_ticking_before: bool,
+    behind_for: u32,
+}
+
+impl Sampler {
+    fn new(data: Arc<MonitorData>) -> Sampler {
+        Sampler {
+            last_tps: data.tps.tps.load(Ordering::Relaxed),
+            last_ticks_count: data.ticks_passed.load(Ordering::Relaxed),
+            was_ticking_before: data.ticking.load(Ordering::Relaxed),
+            behind_for: 0,
+            data,
+        }
+    }
+
+    fn sample(&mut self) {
+        let data = &self.data;
+        let ticks_count = data.ticks_passed.load(Ordering::Relaxed);
+        if ticks_count == 0 {
+            return;
+        }
+        let ticks_passed = (ticks_count - self.last_ticks_count) as u32;
+        self.last_ticks_count = ticks_count;
+
+        let tps = data.tps.tps.load(Ordering::Relaxed);
+        let ticking = data.ticking.load(Ordering::Relaxed);
+        if !(ticking && self.was_ticking_before)
+            || tps != self.last_tps
+            || data.reset_timings.load(Ordering::Relaxed) > 0
+        {
+            data.reset_timings.fetch_sub(1, Ordering::Relaxed);
+            self.was_ticking_before = ticking;
+            self.last_tps = tps;
+            return;
+        }
+
+        // 5% threshold
+        if data.tps.unlimited.load(Ordering::Relaxed) || ticks_passed < (tps / 2) * 95 / 100 {
+            self.behind_for += 1;
+        } else {
+            self.behind_for = 0;
+            data.too_slow.store(false, Ordering::Relaxed);
+        }
+
+        if self.behind_for >= 3 {
+            data.too_slow.store(true, Ordering::Relaxed);
+            // warn!(
+            //     "running behind by {} ticks",
+            //     ((tps / 2) * 95 / 100) - ticks_passed
+            // );
+        }
+
+        // The timings record will only go back 15 minutes.
+        // This means that, with the 500ms interval, the timings record will
+        // have a max size of 1800 entries.
+        let mut timings_record = data.timings_record.lock().unwrap();
+        if timings_record.len() == 1800 {
+            timings_record.pop_back();
+        }
+        timings_record.push_front(ticks_passed);
+    }
+}
+
+struct TickDurationSample {
+    time: Instant,
+    /// The average duration of the ticks in the batch
+    duration: Duration,
+    ticks: u32,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct TickDurationReport {
+    pub average: Duration,
+    pub max: Duration,
+}
+
 #[derive(Debug)]
 pub struct TimingsReport {
     pub ten_s: f32,
@@ -58,7 +149,7 @@ pub struct TimingsReport {
 
 pub struct TimingsMonitor {
     data: Arc<MonitorData>,
-    monitor_thread: Option<JoinHandle<()>>,
+    tick_durations: Mutex<VecDeque<TickDurationSample>>,
 }
 
 impl TimingsMonitor {
@@ -72,20 +163,21 @@ impl TimingsMonitor {
             timings_record: Default::default(),
             tps: AtomicTps::from_tps(tps),
         });
-        let monitor_thread = Some(Self::run_thread(data.clone()));
+        START_SAMPLING.call_once(|| {
+            thread::Builder::new()
+                .name("timings".to_string())
+                .spawn(Self::run_sampling)
+                .unwrap();
+        });
+        NEW_MONITORS.lock().unwrap().push(data.clone());
         TimingsMonitor {
             data,
-            monitor_thread,
+            tick_durations: Default::default(),
         }
     }
 
     pub fn stop(&mut self) {
         self.data.running.store(false, Ordering::Relaxed);
-        if let Some(handle) = self.monitor_thread.take() {
-            if handle.join().is_err() {
-                warn!("Failed to join monitor thread handle");
-            }
-        }
     }
 
     pub fn generate_report(&self) -> Option<TimingsReport> {
@@ -121,6 +213,68 @@ impl TimingsMonitor {
         })
     }
 
+    /// Records how long the ticks of a batch took on average
+    pub fn record_tick_duration(&self, duration: Duration, ticks: u32) {
+        let now = Instant::now();
+        let mut samples = self.tick_durations.lock().unwrap();
+        while samples.len() >= MAX_TICK_DURATION_SAMPLES
+            || samples
+                .front()
+                .is_some_and(|sample| now - sample.time > TICK_DURATION_WINDOW)
+        {
+            samples.pop_front();
+        }
+        samples.push_back(TickDurationSample {
+            time: now,
+            duration,
+            ticks,
+        });
+    }
+
+    /// Returns the average and max tick duration of the last 10 seconds of ticking
+    pub fn tick_duration_report(&self) -> Option<TickDurationReport> {
+        let now = Instant::now();
+        let samples = self.tick_durations.lock().unwrap();
+        let mut total = Duration::ZERO;
+        let mut ticks = 0;
+        let mut max = Duration::ZERO;
+        for sample in samples.iter() {
+            if now - sample.time > TICK_DURATION_WINDOW {
+                continue;
+            }
+            total += sample.duration * sample.ticks;
+            ticks += sample.ticks;
+            max = max.max(sample.duration);
+        }
+        if ticks == 0 {
+            return None;
+        }
+        Some(TickDurationReport {
+            average: total / ticks,
+            max,
+        })
+    }
+
+    /// Returns the fraction of time spent ticking over the last 10 seconds, or `None` if the
+    /// samples don't cover at least a second of ticking
+    pub fn tick_load(&self) -> Option<f64> {
+        let now = Instant::now();
+        let samples = self.tick_durations.lock().unwrap();
+        let oldest = samples
+            .iter()
+            .find(|sample| now - sample.time <= TICK_DURATION_WINDOW)?;
+        let span = now - oldest.time;
+        if span < Duration::from_secs(1) {
+            return None;
+        }
+        let busy: Duration = samples
+            .iter()
+            .filter(|sample| now - sample.time <= TICK_DURATION_WINDOW)
+            .map(|sample| sample.duration * sample.ticks)
+            .sum();
+        Some(busy.as_secs_f64() / span.as_secs_f64())
+    }
+
     pub fn set_tps(&self, new_tps: Tps) {
         self.data.tps.update(new_tps);
         self.data.too_slow.store(false, Ordering::Relaxed);
@@ -144,73 +298,29 @@ impl TimingsMonitor {
 
     pub fn reset_timings(&self) {
         self.data.reset_timings.store(4, Ordering::Relaxed);
+        self.tick_durations.lock().unwrap().clear();
     }
 
-    fn run_thread(data: Arc<MonitorData>) -> JoinHandle<()> {
-        thread::spawn(move || {
-            let mut last_tps = data.tps.tps.load(Ordering::Relaxed);
-            let mut last_ticks_count = data.ticks_passed.load(Ordering::Relaxed);
-            let mut was_ticking_before = data.ticking.load(Ordering::Relaxed);
-
-            let mut behind_for = 0;
-            loop {
-                thread::sleep(Duration::from_millis(500));
-                if !data.running.load(Ordering::Relaxed) {
-                    return;
-                }
-
-                let ticks_count = data.ticks_passed.load(Ordering::Relaxed);
-                if ticks_count == 0 {
-                    continue;
-                }
-                let ticks_passed = (ticks_count - last_ticks_count) as u32;
-                last_ticks_count = ticks_count;
-
-                let tps = data.tps.tps.load(Ordering::Relaxed);
-                let ticking = data.ticking.load(Ordering::Relaxed);
-                if !(ticking && was_ticking_before)
-                    || tps != last_tps
-                    || data.reset_timings.load(Ordering::Relaxed) > 0
-                {
-                    data.reset_timings.fetch_sub(1, Ordering::Relaxed);
-                    was_ticking_before = ticking;
-                    last_tps = tps;
-                    continue;
-                }
-
-                // 5% threshold
-                if data.tps.unlimited.load(Ordering::Relaxed) || ticks_passed < (tps / 2) * 95 / 100
-                {
-                    behind_for += 1;
-                } else {
-                    behind_for = 0;
-                    data.too_slow.store(false, Ordering::Relaxed);
-                }
-
-                if behind_for >= 3 {
-                    data.too_slow.store(true, Ordering::Relaxed);
-                    // warn!(
-                    //     "running behind by {} ticks",
-                    //     ((tps / 2) * 95 / 100) - ticks_passed
-                    // );
-                }
-
-                // The timings record will only go back 15 minutes.
-                // This means that, with the 500ms interval, the timings record will
-                // have a max size of 1800 entries.
-                let mut timings_record = data.timings_record.lock().unwrap();
-                if timings_record.len() == 1800 {
-                    timings_record.pop_back();
-                }
-                timings_record.push_front(ticks_passed);
+    /// Samples every running monitor each interval, until the server stops
+    fn run_sampling() {
+        let mut samplers: Vec<Sampler> = Vec::new();
+        loop {
+            thread::sleep(SAMPLE_INTERVAL);
+            samplers.retain(|sampler| sampler.data.running.load(Ordering::Relaxed));
+            for sampler in &mut samplers {
+                sampler.sample();
             }
-        })
+            // New monitors are only sampled from the next interval on, so their first sample
+            // covers a whole interval
+            let new_monitors = std::mem::take(&mut *NEW_MONITORS.lock().unwrap());
+            samplers.extend(new_monitors.into_iter().map(Sampler::new));
+        }
     }
 }
 
 impl Drop for TimingsMonitor {
     fn drop(&mut self) {
-        // Joining the thread in drop is a bad idea so we just let it detach
+        // The sampling thread stops sampling monitors once they aren't running
         self.data.running.store(false, Ordering::Relaxed);
     }
 }
@@ -0,0 +1,76 @@
+//! The navigation tool, an item set in the config that teleports players to the block they are
+//! looking at when they left click with it, like `/jumpto`, and through the wall in front of them
+//! when they right click, like `/thru`.
+
+use super::{worldedit, Plot};
+use crate::config::CONFIG;
+use mchprs_blocks::items::Item;
+use once_cell::sync::Lazy;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+/// A click sends more than one packet, like a swing and a dig for a left click on a block, so
+/// clicks closer together than this only teleport once
+const COOLDOWN: Duration = Duration::from_millis(250);
+
+static TOOL: Lazy<Option<Item>> = Lazy::new(|| {
+    let name = CONFIG.navigation_tool.trim();
+    if name.is_empty() {
+        return None;
+    }
+    let item = Item::from_registry_name(name.strip_prefix("minecraft:").unwrap_or(name));
+    if item.is_none() {
+        warn!(
+            "The navigation tool {} is not an item the server knows",
+            name
+        );
+    }
+    item
+});
+
+#[derive(Debug, Clone, Copy)]
+pub(super) enum Click {
+    Left,
+    Right,
+}
+
+impl Plot {
+    /// Whether the player has the navigation tool in their main hand
+    pub(super) fn holding_navigation_tool(&self, player: usize) -> bool {
+        let Some(tool) = *TOOL else {
+            return false;
+        };
+        let player = &self.players[player];
+        player.inventory[player.selected_slot as usize + 36]
+            .as_ref()
+            .is_some_and(|item| item.item_type == tool)
+    }
+
+    /// Teleports the player if they clicked with the navigation tool. Returns true if the click
+    /// was used by the tool, in which case it shouldn't do anything else.
+    pub(super) fn use_navigation_tool(&mut self, player: usize, click: Click) -> bool {
+        if !self.holding_navigation_tool(player) {
+            return false;
+        }
+        let permission = match click {
+            Click::Left => "worldedit.navigation.jumpto.tool",
+            Click::Right => "worldedit.navigation.thru.tool",
+        };
+        let player = &mut self.players[player];
+        if !player.has_permission(permission) {
+            return false;
+        }
+        if player
+            .last_navigation
+            .is_some_and(|last| last.elapsed() < COOLDOWN)
+        {
+            return true;
+        }
+        player.last_navigation = Some(Instant::now());
+        match click {
+            Click::Left => worldedit::jump_to(&self.world, player),
+            Click::Right => worldedit::pass_through(&self.world, player),
+        }
+        true
+    }
+}
@@ -0,0 +1,121 @@
+//! Heavy commands that can be given a cooldown in the config. A player can only run one of them
+//! at a time: commands that were sent while one was still running are turned away instead of
+//! being run one after another once it finishes.
+
+use super::Plot;
+use crate::config::CONFIG;
+use crate::lang::Text;
+use std::time::{Duration, Instant};
+
+/// Operations that finish within a tick can't have had other commands sent while they ran
+const LONG_OPERATION: Duration = Duration::from_millis(50);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Operation {
+    Paste,
+    PlotClear,
+    TickWarp,
+}
+
+impl Operation {
+    fn command(self) -> &'static str {
+        match self {
+            Operation::Paste => "//paste",
+            Operation::PlotClear => "/plot clear",
+            Operation::TickWarp => "/radvance",
+        }
+    }
+
+    fn cooldown(self) -> Duration {
+        let Some(cooldowns) = &CONFIG.cooldowns else {
+            return Duration::ZERO;
+        };
+        let secs = match self {
+            Operation::Paste => cooldowns.paste,
+            Operation::PlotClear => cooldowns.plot_clear,
+            Operation::TickWarp => cooldowns.radvance,
+        };
+        Duration::from_secs(secs)
+    }
+}
+
+/// Formats a wait as whole seconds, rounding up so it never says 0, or as minutes if it is a
+/// whole number of them
+pub(crate) fn format_wait(wait: Duration) -> Text {
+    let secs = wait.as_secs() + (wait.subsec_nanos() > 0) as u64;
+    if secs == 60 {
+        text!("time.minute")
+    } else if secs > 60 && secs % 60 == 0 {
+        text!("time.minutes", amount = secs / 60)
+    } else if secs == 1 {
+        text!("time.second")
+    } else {
+        text!("time.seconds", amount = secs)
+    }
+}
+
+impl Plot {
+    /// Checks whether the player can start `operation` and marks it as running if they can.
+    /// This has to be followed by `finish_operation` once the operation is done.
+    pub(super) fn start_operation(&mut self, player: usize, operation: Operation) -> bool {
+        let player = &mut self.players[player];
+        if let Some(running) = player.operation {
+            player.send_error_message(text!(
+                "operation.running",
+                running = running.command(),
+                command = operation.command()
+            ));
+            return false;
+        }
+        if !player.has_permission("mchprs.cooldown.bypass") {
+            if let Some(last_used) = player.operation_cooldowns.get(&operation) {
+                let wait = operation.cooldown().saturating_sub(last_used.elapsed());
+                if !wait.is_zero() {
+                    let wait = format_wait(wait).translate(&player.locale);
+                    player.send_error_message(text!(
+                        "operation.cooldown",
+                        command = operation.command(),
+                        wait = wait
+                    ));
+                    return false;
+                }
+            }
+        }
+        player.operation = Some(operation);
+        player.operation_started = Instant::now();
+        true
+    }
+
+    pub(super) fn finish_operation(&mut self, player: usize) {
+        let player = &mut self.players[player];
+        let Some(operation) = player.operation else {
+            return;
+        };
+        player.operation_cooldowns.insert(operation, Instant::now());
+        if player.operation_started.elapsed() < LONG_OPERATION {
+            player.operation = None;
+        } else {
+            // The commands sent while it was running are only received in the next tick, so
+            // it is released after those have been handled
+            player.operation_finished = true;
+        }
+    }
+
+    /// Lets the player start operations again after the commands they sent while the last one
+    /// was running have been handled
+    pub(super) fn release_operation(&mut self, player: usize) {
+        let player = &mut self.players[player];
+        player.operation_finished = false;
+        player.operation = None;
+    }
+}
+
+#[test]
+fn wait_rounds_up() {
+    let wait = |wait| format_wait(wait).translate("en_us");
+    assert_eq!(wait(Duration::from_millis(200)), "1 second");
+    assert_eq!(wait(Duration::from_millis(4100)), "5 seconds");
+    assert_eq!(wait(Duration::from_secs(30)), "30 seconds");
+    assert_eq!(wait(Duration::from_secs(90)), "90 seconds");
+    assert_eq!(wait(Duration::from_secs(300)), "5 minutes");
+}
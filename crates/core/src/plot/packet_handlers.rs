@@ -1,27 +1,109 @@
+use super::navigation::Click;
+use super::worldedit::ray_trace_block;
 use super::Plot;
 use crate::config::CONFIG;
+use crate::events::{Event, EventResult, PlayerInfo};
+use crate::interaction;
+use crate::lang::MAX_LOCALE_LENGTH;
+use crate::metrics;
 use crate::player::{PacketSender, PlayerPos, SkinParts};
 use crate::server::Message;
 use crate::utils::{self, HyphenatedUUID};
 use mchprs_blocks::block_entities::BlockEntity;
-use mchprs_blocks::items::{Item, ItemStack};
+use mchprs_blocks::blocks::Block;
+use mchprs_blocks::items::Item;
 use mchprs_blocks::BlockPos;
 use mchprs_network::packets::clientbound::*;
 use mchprs_network::packets::serverbound::*;
+use mchprs_network::packets::SlotData;
 use mchprs_world::World;
 use serde_json::json;
 use std::fs;
 use std::path::PathBuf;
+use std::sync::atomic::Ordering;
 use std::time::Instant;
-use tracing::error;
+use tracing::{error, warn};
+
+/// How far away players can pick blocks in creative mode
+const PICK_BLOCK_RANGE: f64 = 5.0;
+
+/// The longest line the vanilla client sends when editing a sign
+const MAX_SIGN_LINE_LENGTH: usize = 384;
+/// The longest chat message or command the vanilla client sends
+const MAX_CHAT_LENGTH: usize = 256;
+/// The longest command the vanilla client asks for suggestions for
+const MAX_SUGGESTIONS_LENGTH: usize = 32500;
+/// The slots of the player's own inventory, including the armor and offhand slots
+const INVENTORY_SLOTS: i16 = 46;
+/// The inventory slot of the offhand
+const OFFHAND_SLOT: u32 = 45;
+/// The farthest a player can move in one packet. Flying at the highest `/speed` covers less than
+/// 6 blocks in a tick, and clients send their position every tick.
+const MAX_MOVE_DISTANCE: f64 = 100.0;
 
 impl Plot {
     pub(super) fn handle_packets_for_player(&mut self, player: usize) {
-        let packets = self.players[player].client.receive_packets();
-        for packet in packets {
+        // Packets that arrive after a redpiler compile was started wait for it in the queue
+        while self.compiling.is_none() {
+            // The rest of the packets can't be trusted after an invalid one
+            if !self.players[player].client.alive() {
+                break;
+            }
+            let Some(packet) = self.players[player].client.receive_packet() else {
+                break;
+            };
+            metrics::PACKETS_RECEIVED.fetch_add(1, Ordering::Relaxed);
             packet.handle(self, player);
         }
     }
+
+    /// Disconnects a player whose client sent something that no vanilla client would, like a
+    /// slot that doesn't exist. Trusting those would panic when they are used as an index.
+    fn reject_packet(&mut self, player: usize, problem: &str) {
+        let player = &mut self.players[player];
+        warn!(
+            "Disconnecting {} for sending an invalid packet: {}",
+            player.username, problem
+        );
+        player.kick(
+            text!("player.invalid_packet")
+                .translate(&player.locale)
+                .into(),
+        );
+        player.client.close_connection();
+    }
+
+    /// Checks a move the client sent before it is applied. Movement from before the client
+    /// confirmed a teleport is ignored, and moves that are impossibly far or that would take a
+    /// player locked to the plot out of it are undone by teleporting the player back.
+    fn accept_move(&mut self, player: usize, new: PlayerPos) -> bool {
+        let locked = self
+            .locked_players
+            .contains(&self.players[player].entity_id);
+        let (plot_x, plot_z) = (self.world.x, self.world.z);
+        let player = &mut self.players[player];
+        if player.is_awaiting_teleport() {
+            return false;
+        }
+        let old = player.pos;
+        let finite = new.x.is_finite() && new.y.is_finite() && new.z.is_finite();
+        if !finite || old.distance(new) > MAX_MOVE_DISTANCE {
+            player.teleport(old);
+            return false;
+        }
+        if locked && new.plot_pos(&self.world.geometry) != (plot_x, plot_z) {
+            player.teleport(old);
+            return false;
+        }
+        true
+    }
+}
+
+/// Whether the client could have put this item in a slot, which in creative mode is any item,
+/// but never more of it than fits in a stack
+fn is_valid_item(slot_data: &SlotData) -> bool {
+    let max_count = Item::from_id(slot_data.item_id as u32).max_stack_size();
+    slot_data.item_count > 0 && slot_data.item_count as u32 <= max_count
 }
 
 impl ServerBoundPacketHandler for Plot {
@@ -30,6 +112,10 @@ impl ServerBoundPacketHandler for Plot {
         packet: SCommandSuggestionsRequest,
         player_idx: usize,
     ) {
+        if packet.text.chars().count() > MAX_SUGGESTIONS_LENGTH {
+            self.reject_packet(player_idx, "the command to suggest for is too long");
+            return;
+        }
         if !packet.text.starts_with("//load ") {
             return;
         }
@@ -77,56 +163,118 @@ impl ServerBoundPacketHandler for Plot {
         self.players[player_idx].send_packet(&res.encode());
     }
 
+    fn handle_confirm_teleportation(&mut self, packet: SConfirmTeleportation, player: usize) {
+        self.players[player].confirm_teleport(packet.teleport_id);
+    }
+
+    fn handle_interact(&mut self, interact: SInteract, player: usize) {
+        // Clients send `InteractAt` before `Interact` for the same click, and try again with the
+        // off hand when nothing happened with the main hand
+        let attack = match interact.ty {
+            SInteractType::Interact { hand: 0 } => false,
+            SInteractType::Attack => true,
+            _ => return,
+        };
+        self.interact_with_entity(player, interact.entity_id as u32, attack);
+    }
+
     fn handle_keep_alive(&mut self, _keep_alive: SKeepAlive, player_idx: usize) {
         self.players[player_idx].last_keep_alive_received = Instant::now();
     }
 
+    /// Shows the other players the item in the player's main hand
+    fn send_held_item_to_others(&self, player: usize) {
+        let slot = self.players[player].selected_slot as usize + 36;
+        let entity_equipment = CSetEquipment {
+            entity_id: self.players[player].entity_id as i32,
+            equipment: vec![CSetEquipmentEquipment {
+                slot: 0, // Main hand
+                item: self.players[player].inventory[slot]
+                    .as_ref()
+                    .map(utils::encode_slot_data),
+            }],
+        }
+        .encode();
+        for other_player in 0..self.players.len() {
+            if player == other_player {
+                continue;
+            };
+            self.players[other_player]
+                .client
+                .send_packet(&entity_equipment);
+        }
+    }
+
+    /// The creative client handles pick block by itself and only tells the server which item
+    /// ended up in the hotbar. If that item is the plain item of the block the player is looking
+    /// at and it was set into the slot the client picks into, the server picks the block again
+    /// so that its state is kept. Returns true if it did.
+    fn handle_pick_block(&mut self, player: usize, slot: i16, slot_data: &SlotData) -> bool {
+        if !(36..45).contains(&slot) || slot_data.nbt.is_some() || slot_data.item_count != 1 {
+            return false;
+        }
+        let p = &self.players[player];
+        let Some(pos) = ray_trace_block(
+            &self.world,
+            p.pos,
+            p.pitch as f64,
+            p.yaw as f64,
+            PICK_BLOCK_RANGE,
+        ) else {
+            return false;
+        };
+        let Some(item) = interaction::pick_block_item(self.world.get_block(pos)) else {
+            return false;
+        };
+        if item.nbt.is_none() || item.item_type.get_id() != slot_data.item_id as u32 {
+            return false;
+        }
+        if !self.players[player].is_pick_slot(slot as usize, item.item_type) {
+            return false;
+        }
+
+        self.players[player].pick_item(item);
+        self.send_held_item_to_others(player);
+        true
+    }
+
     fn handle_set_creative_mode_slot(
         &mut self,
         creative_inventory_action: SSetCreativeModeSlot,
         player: usize,
     ) {
+        let slot = creative_inventory_action.slot;
+        // Items dropped out of the creative inventory are sent for slot -1
+        if slot == -1 {
+            return;
+        }
+        if !(0..INVENTORY_SLOTS).contains(&slot) {
+            self.reject_packet(player, &format!("there is no inventory slot {}", slot));
+            return;
+        }
         if let Some(slot_data) = creative_inventory_action.clicked_item {
-            if creative_inventory_action.slot < 0 || creative_inventory_action.slot >= 46 {
+            if !is_valid_item(&slot_data) {
+                self.reject_packet(player, &format!("invalid item {:?}", slot_data));
                 return;
             }
-            let item = ItemStack {
-                count: slot_data.item_count as u8,
-                item_type: Item::from_id(slot_data.item_id as u32),
-                nbt: slot_data.nbt.map(nbt::Blob::with_content),
-            };
-            self.players[player].inventory[creative_inventory_action.slot as usize] = Some(item);
-            if creative_inventory_action.slot as u32 == self.players[player].selected_slot + 36 {
-                let entity_equipment = CSetEquipment {
-                    entity_id: self.players[player].entity_id as i32,
-                    equipment: vec![CSetEquipmentEquipment {
-                        slot: 0, // Main hand
-                        item: self.players[player].inventory
-                            [creative_inventory_action.slot as usize]
-                            .as_ref()
-                            .map(utils::encode_slot_data),
-                    }],
-                }
-                .encode();
-                for other_player in 0..self.players.len() {
-                    if player == other_player {
-                        continue;
-                    };
-                    self.players[other_player]
-                        .client
-                        .send_packet(&entity_equipment);
-                }
+            if self.handle_pick_block(player, slot, &slot_data) {
+                return;
+            }
+            let item = utils::decode_slot_data(slot_data);
+            self.players[player].inventory[slot as usize] = Some(item);
+            if slot as u32 == self.players[player].selected_slot + 36 {
+                self.send_held_item_to_others(player);
             }
         } else {
-            self.players[player].inventory[creative_inventory_action.slot as usize] = None;
+            self.players[player].inventory[slot as usize] = None;
         }
 
         // We wanna retrieve the item slot from the inventory
         // rather than re-using the value from the client,
         // to avoid getting out of sync if the server validates
         // or sanitizes data or something
-        let item = self.players[player].inventory[creative_inventory_action.slot as usize].clone();
-        self.players[player].set_inventory_slot(creative_inventory_action.slot as u32, item);
+        let item = self.players[player].inventory[slot as usize].clone();
+        self.players[player].set_inventory_slot(slot as u32, item);
     }
 
     fn handle_player_abilities(&mut self, player_abilities: SPlayerAbilities, player: usize) {
@@ -134,6 +282,9 @@ impl ServerBoundPacketHandler for Plot {
     }
 
     fn handle_swing_arm(&mut self, animation: SSwingArm, player: usize) {
+        if animation.hand == 0 {
+            self.use_navigation_tool(player, Click::Left);
+        }
         let animation_id = match animation.hand {
             0 => 0,
             1 => 3,
@@ -155,6 +306,10 @@ impl ServerBoundPacketHandler for Plot {
     }
 
     fn handle_use_item_on(&mut self, use_item_on: SUseItemOn, player: usize) {
+        if !(0..6).contains(&use_item_on.face) {
+            self.reject_packet(player, &format!("there is no face {}", use_item_on.face));
+            return;
+        }
         self.handle_use_item_impl(&use_item_on, player);
 
         let acknowledge_block_change = CAcknowledgeBlockChange {
@@ -164,7 +319,36 @@ impl ServerBoundPacketHandler for Plot {
         self.players[player].send_packet(&acknowledge_block_change);
     }
 
+    fn handle_use_item(&mut self, use_item: SUseItem, player: usize) {
+        if use_item.hand == 0 {
+            self.use_navigation_tool(player, Click::Right);
+        }
+        let slot = match use_item.hand {
+            0 => self.players[player].selected_slot + 36,
+            _ => OFFHAND_SLOT,
+        };
+        if let Some(item) = &self.players[player].inventory[slot as usize] {
+            if item.item_type == (Item::WrittenBook {}) {
+                let open_book = COpenBook {
+                    hand: use_item.hand,
+                }
+                .encode();
+                self.players[player].send_packet(&open_book);
+            }
+        }
+
+        let acknowledge_block_change = CAcknowledgeBlockChange {
+            sequence_id: use_item.sequence,
+        }
+        .encode();
+        self.players[player].send_packet(&acknowledge_block_change);
+    }
+
     fn handle_chat_command(&mut self, chat_command: SChatCommand, player: usize) {
+        if chat_command.command.chars().count() > MAX_CHAT_LENGTH {
+            self.reject_packet(player, "the command is too long");
+            return;
+        }
         self.players[player]
             .command_queue
             .push(chat_command.command);
@@ -172,13 +356,33 @@ impl ServerBoundPacketHandler for Plot {
 
     fn handle_chat_message(&mut self, chat_message: SChatMessage, player: usize) {
         let message = chat_message.message;
+        if message.chars().count() > MAX_CHAT_LENGTH {
+            self.reject_packet(player, "the chat message is too long");
+            return;
+        }
+        #[cfg(feature = "wasm")]
+        if !self.extensions.on_chat(&self.players[player], &message) {
+            return;
+        }
+        let event = Event::Chat {
+            player: PlayerInfo::of(&self.players[player]),
+            message: message.clone(),
+        };
+        if self.publish_event(event) == EventResult::Cancel {
+            return;
+        }
         let player = &self.players[player];
         let broadcast_message = Message::ChatInfo(player.uuid, player.username.clone(), message);
         self.message_sender.send(broadcast_message).unwrap();
     }
 
     fn handle_client_information(&mut self, client_settings: SClientInformation, player: usize) {
+        if client_settings.locale.len() > MAX_LOCALE_LENGTH {
+            self.reject_packet(player, "the locale is too long");
+            return;
+        }
         let player = &mut self.players[player];
+        player.locale = client_settings.locale.to_lowercase();
         player.skin_parts =
             SkinParts::from_bits_truncate(client_settings.displayed_skin_parts as u32);
         let metadata_entry = CSetEntityMetadataEntry {
@@ -202,9 +406,36 @@ impl ServerBoundPacketHandler for Plot {
         }
     }
 
+    fn handle_edit_book(&mut self, packet: SEditBook, player: usize) {
+        let slot = match packet.slot {
+            0..=8 => packet.slot as u32 + 36,
+            40 => OFFHAND_SLOT,
+            _ => {
+                self.reject_packet(player, &format!("there is no hotbar slot {}", packet.slot));
+                return;
+            }
+        };
+        let Some(mut book) = self.players[player].inventory[slot as usize].clone() else {
+            return;
+        };
+        if book.item_type != (Item::WritableBook {}) {
+            return;
+        }
+        let author = self.players[player].username.clone();
+        let signature = packet.title.map(|title| (title, author.as_str()));
+        utils::write_book(&mut book, packet.pages, signature);
+        self.players[player].set_inventory_slot(slot, Some(book));
+        if slot == self.players[player].selected_slot + 36 {
+            self.send_held_item_to_others(player);
+        }
+    }
+
     fn handle_set_player_position(&mut self, player_position: SSetPlayerPosition, player: usize) {
         let old = self.players[player].pos;
         let new = PlayerPos::new(player_position.x, player_position.y, player_position.z);
+        if !self.accept_move(player, new) {
+            return;
+        }
         self.players[player].pos = new;
         self.players[player].on_ground = player_position.on_ground;
         let packet = if (new.x - old.x).abs() > 8.0
@@ -254,6 +485,9 @@ impl ServerBoundPacketHandler for Plot {
             player_position_and_rotation.y,
             player_position_and_rotation.z,
         );
+        if !self.accept_move(player, new) {
+            return;
+        }
         self.players[player].pos = new;
         self.players[player].yaw = player_position_and_rotation.yaw;
         self.players[player].pitch = player_position_and_rotation.pitch;
@@ -305,6 +539,9 @@ impl ServerBoundPacketHandler for Plot {
     }
 
     fn handle_player_rotation(&mut self, player_rotation: SPlayerRotation, player: usize) {
+        if self.players[player].is_awaiting_teleport() {
+            return;
+        }
         self.players[player].yaw = player_rotation.yaw;
         self.players[player].pitch = player_rotation.pitch;
         self.players[player].on_ground = player_rotation.on_ground;
@@ -407,29 +644,42 @@ impl ServerBoundPacketHandler for Plot {
     }
 
     fn handle_set_held_item(&mut self, held_item_change: SSetHeldItem, player: usize) {
-        let entity_equipment = CSetEquipment {
-            entity_id: self.players[player].entity_id as i32,
-            equipment: vec![CSetEquipmentEquipment {
-                slot: 0, // Main hand
-                item: self.players[player].inventory[held_item_change.slot as usize + 36]
-                    .as_ref()
-                    .map(utils::encode_slot_data),
-            }],
-        }
-        .encode();
-        for other_player in 0..self.players.len() {
-            if player == other_player {
-                continue;
-            };
-            self.players[other_player]
-                .client
-                .send_packet(&entity_equipment);
+        if !(0..9).contains(&held_item_change.slot) {
+            let problem = format!("there is no hotbar slot {}", held_item_change.slot);
+            self.reject_packet(player, &problem);
+            return;
         }
         self.players[player].selected_slot = held_item_change.slot as u32;
+        self.send_held_item_to_others(player);
     }
 
-    fn handle_update_sign(&mut self, packet: SUpdateSign, _player: usize) {
+    fn handle_update_sign(&mut self, packet: SUpdateSign, player: usize) {
+        if packet
+            .lines
+            .iter()
+            .any(|line| line.chars().count() > MAX_SIGN_LINE_LENGTH)
+        {
+            self.reject_packet(player, "a sign line is too long");
+            return;
+        }
         let pos = BlockPos::new(packet.x, packet.y, packet.z);
+        if !Plot::in_plot_bounds(
+            &self.world.geometry,
+            self.world.x,
+            self.world.z,
+            pos.x,
+            pos.z,
+        ) || !matches!(
+            self.world.get_block(pos),
+            Block::Sign { .. } | Block::WallSign { .. }
+        ) {
+            return;
+        }
+        if !self.can_interact(player) {
+            self.players[player].send_no_permission_message();
+            return;
+        }
+
         let mut rows = packet
             .lines
             .iter()
@@ -452,4 +702,183 @@ impl ServerBoundPacketHandler for Plot {
         self.world
             .set_block_entity(pos, BlockEntity::Sign(Box::new(block_entity)));
     }
+
+    fn handle_click_container(&mut self, packet: SClickContainer, player: usize) {
+        if let Some((_, Some(item))) = packet
+            .changed_slots
+            .iter()
+            .find(|(_, item)| item.as_ref().is_some_and(|item| !is_valid_item(item)))
+        {
+            self.reject_packet(player, &format!("invalid item {:?}", item));
+            return;
+        }
+
+        // Any item is allowed in the slots, as every player is in creative mode
+        if packet.window_id == 0 {
+            if let Some((slot, _)) = packet
+                .changed_slots
+                .iter()
+                .find(|(slot, _)| !(0..INVENTORY_SLOTS).contains(slot))
+            {
+                self.reject_packet(player, &format!("there is no inventory slot {}", slot));
+                return;
+            }
+            for (slot, item) in packet.changed_slots {
+                self.players[player].inventory[slot as usize] = item.map(utils::decode_slot_data);
+            }
+            return;
+        }
+
+        let Some(pos) = self.players[player].open_container_pos else {
+            return;
+        };
+        let block = self.world.get_block(pos);
+        if !matches!(
+            block,
+            Block::Barrel { .. }
+                | Block::Furnace { .. }
+                | Block::Hopper { .. }
+                | Block::TrappedChest { .. }
+        ) {
+            return;
+        }
+        let (mut inventory, ty, old_override) = match self.world.get_block_entity(pos) {
+            Some(BlockEntity::Container {
+                inventory,
+                ty,
+                comparator_override,
+            }) => (inventory.clone(), *ty, *comparator_override),
+            _ => (Vec::new(), interaction::container_type(block), 0),
+        };
+
+        // The slots of the container come first, followed by the player's inventory and hotbar
+        let num_slots = ty.num_slots() as i16;
+        if let Some((slot, _)) = packet
+            .changed_slots
+            .iter()
+            .find(|(slot, _)| !(0..num_slots + 36).contains(slot))
+        {
+            self.reject_packet(player, &format!("there is no container slot {}", slot));
+            return;
+        }
+        let mut container_changed = false;
+        for (slot, item) in packet.changed_slots {
+            let item = item.map(utils::decode_slot_data);
+            if (0..num_slots).contains(&slot) {
+                container_changed = true;
+                inventory.retain(|entry| entry.slot as i16 != slot);
+                if let Some(item) = &item {
+                    inventory.push(utils::stack_to_inventory_entry(slot as i8, item));
+                }
+            } else {
+                self.players[player].inventory[(slot - num_slots + 9) as usize] = item;
+            }
+        }
+        if !container_changed {
+            return;
+        }
+
+        let block_entity = BlockEntity::container(inventory, ty);
+        let override_changed = matches!(
+            block_entity,
+            BlockEntity::Container { comparator_override, .. } if comparator_override != old_override
+        );
+        self.world.set_block_entity(pos, block_entity);
+        if override_changed {
+            self.reset_redpiler();
+            mchprs_redstone::update_surrounding_blocks(&mut self.world, pos);
+            self.record_change(pos);
+        }
+    }
+
+    fn handle_click_container_button(&mut self, packet: SClickContainerButton, player: usize) {
+        let Some(pos) = self.players[player].open_container_pos else {
+            return;
+        };
+        let Block::Lectern {
+            facing, powered, ..
+        } = self.world.get_block(pos)
+        else {
+            return;
+        };
+        let Some(BlockEntity::Lectern {
+            comparator_override: old_override,
+            book,
+            page,
+            pages,
+        }) = self.world.get_block_entity(pos).cloned()
+        else {
+            return;
+        };
+
+        let new_page = match packet.button_id {
+            1 => page - 1,
+            2 => page + 1,
+            // Take the book
+            3 => {
+                if !self.players[player].give_item(utils::inventory_entry_to_stack(&book)) {
+                    self.players[player].send_error_message(text!("player.inventory_full"));
+                    return;
+                }
+                self.world.delete_block_entity(pos);
+                self.world.set_block(
+                    pos,
+                    Block::Lectern {
+                        facing,
+                        has_book: false,
+                        powered,
+                    },
+                );
+                let close = CCloseContainer { window_id: 1 }.encode();
+                for player in &mut self.players {
+                    if player.open_container_pos == Some(pos) {
+                        player.open_container_pos = None;
+                        player.client.send_packet(&close);
+                    }
+                }
+                self.update_container_viewers();
+                self.reset_redpiler();
+                mchprs_redstone::update_surrounding_blocks(&mut self.world, pos);
+                self.record_change(pos);
+                self.world.flush_block_changes();
+                return;
+            }
+            button @ 100.. => button as i32 - 100,
+            _ => return,
+        };
+        if new_page == page || !(0..pages).contains(&new_page) {
+            return;
+        }
+
+        let block_entity = BlockEntity::lectern(book, new_page);
+        let override_changed = matches!(
+            block_entity,
+            BlockEntity::Lectern { comparator_override, .. } if comparator_override != old_override
+        );
+        self.world.set_block_entity(pos, block_entity);
+        let set_page = CSetContainerProperty {
+            window_id: 1,
+            property: 0,
+            value: new_page as i16,
+        }
+        .encode();
+        for player in &self.players {
+            if player.open_container_pos == Some(pos) {
+                player.client.send_packet(&set_page);
+            }
+        }
+        if override_changed {
+            self.reset_redpiler();
+            mchprs_redstone::update_surrounding_blocks(&mut self.world, pos);
+            self.record_change(pos);
+        }
+    }
+
+    fn handle_close_container(&mut self, packet: SCloseContainer, player: usize) {
+        if packet.window_id != 0 {
+            self.players[player].open_container_pos = None;
+            self.update_container_viewers();
+            self.world.flush_block_changes();
+        }
+    }
 }
@@ -0,0 +1,136 @@
+//! Measures which components take up tick time in a plot.
+//!
+//! Every scheduled tick is timed and attributed to the block that was ticked, including the
+//! block updates it causes. This means wire is counted as part of whatever powered it.
+//! Redpiler ticks aren't seen by the profiler since they don't run through the world.
+
+use crate::lang::Text;
+use mchprs_blocks::blocks::Block;
+use mchprs_blocks::BlockPos;
+use rustc_hash::FxHashMap;
+use std::time::{Duration, Instant};
+
+/// How many positions are shown in the report
+const REPORT_POSITIONS: usize = 5;
+
+#[derive(Default, Clone, Copy)]
+struct Timings {
+    duration: Duration,
+    count: u64,
+}
+
+impl Timings {
+    fn add(&mut self, duration: Duration) {
+        self.duration += duration;
+        self.count += 1;
+    }
+}
+
+pub struct Profiler {
+    started: Instant,
+    stopped: Option<Instant>,
+    ticks: u64,
+    total: Timings,
+    components: FxHashMap<&'static str, Timings>,
+    positions: FxHashMap<BlockPos, (&'static str, Timings)>,
+}
+
+impl Profiler {
+    pub fn start() -> Profiler {
+        Profiler {
+            started: Instant::now(),
+            stopped: None,
+            ticks: 0,
+            total: Default::default(),
+            components: Default::default(),
+            positions: Default::default(),
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.stopped.is_none()
+    }
+
+    pub fn stop(&mut self) {
+        if self.stopped.is_none() {
+            self.stopped = Some(Instant::now());
+        }
+    }
+
+    /// Called once every redstone tick while the profiler is running
+    pub fn tick(&mut self) {
+        self.ticks += 1;
+    }
+
+    pub fn record(&mut self, block: Block, pos: BlockPos, duration: Duration) {
+        let name = block.get_name();
+        self.total.add(duration);
+        self.components.entry(name).or_default().add(duration);
+        let (pos_name, timings) = self
+            .positions
+            .entry(pos)
+            .or_insert((name, Default::default()));
+        *pos_name = name;
+        timings.add(duration);
+    }
+
+    fn percent(&self, duration: Duration) -> f64 {
+        if self.total.duration.is_zero() {
+            return 0.0;
+        }
+        duration.as_secs_f64() / self.total.duration.as_secs_f64() * 100.0
+    }
+
+    /// Returns the report as lines of legacy formatted text
+    pub fn report(&self) -> Vec<Text> {
+        let elapsed = self.stopped.unwrap_or_else(Instant::now) - self.started;
+        let elapsed = format!("{:.1}", elapsed.as_secs_f64());
+        let duration = format!("{:.2}", self.total.duration.as_secs_f64() * 1000.0);
+        let mut lines = vec![if self.is_running() {
+            text!(
+                "profile.report.running",
+                elapsed = elapsed,
+                ticks = self.ticks,
+                block_ticks = self.total.count,
+                duration = duration
+            )
+        } else {
+            text!(
+                "profile.report.stopped",
+                elapsed = elapsed,
+                ticks = self.ticks,
+                block_ticks = self.total.count,
+                duration = duration
+            )
+        }];
+        if self.total.count == 0 {
+            lines.push(text!("profile.report.no_ticks"));
+            return lines;
+        }
+
+        let mut components: Vec<_> = self.components.iter().collect();
+        components.sort_by(|a, b| b.1.duration.cmp(&a.1.duration));
+        let components = components
+            .iter()
+            .map(|(name, timings)| format!("&a{} {:.1}%", name, self.percent(timings.duration)))
+            .collect::<Vec<_>>()
+            .join("&6, ");
+        lines.push(text!("profile.report.components", components = components));
+
+        let mut positions: Vec<_> = self.positions.iter().collect();
+        positions.sort_by(|a, b| b.1 .1.duration.cmp(&a.1 .1.duration));
+        lines.push(text!("profile.report.positions"));
+        for (pos, (name, timings)) in positions.into_iter().take(REPORT_POSITIONS) {
+            lines.push(text!(
+                "profile.report.position",
+                x = pos.x,
+                y = pos.y,
+                z = pos.z,
+                block = name,
+                percent = format!("{:.1}", self.percent(timings.duration)),
+                ticks = timings.count
+            ));
+        }
+        lines
+    }
+}
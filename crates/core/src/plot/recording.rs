@@ -0,0 +1,271 @@
+//! Records everything that changes a plot other than redstone itself, so it can be replayed tick
+//! for tick with `mchprs replay`.
+//!
+//! Redstone has no randomness, so the plot at the start of a recording and the inputs between
+//! each redstone tick are all that is needed to reproduce it. Commands with random results, like
+//! worldedit patterns with weights, are recorded by the blocks they left behind instead of by
+//! how they were run.
+
+use super::Plot;
+use crate::bench::HeadlessPlot;
+use crate::interaction;
+use anyhow::{bail, Result};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use chrono::Local;
+use mchprs_blocks::block_entities::BlockEntity;
+use mchprs_blocks::blocks::Block;
+use mchprs_blocks::BlockPos;
+use mchprs_save_data::plot_data::PlotData;
+use mchprs_world::World;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+const RECORDINGS_DIR: &str = "./recordings";
+const RECORDING_MAGIC: &[u8; 8] = b"MCHPRSRC";
+const VERSION: u32 = 0;
+
+/// Something that changed the plot between redstone ticks
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Input {
+    /// A block used with [`mchprs_redstone::on_use`], like a lever being flipped
+    Use {
+        pos: BlockPos,
+    },
+    /// A block placed by a player, with the block entity it was placed with
+    Place {
+        pos: BlockPos,
+        block: u32,
+        block_entity: Option<BlockEntity>,
+    },
+    Break {
+        pos: BlockPos,
+    },
+    /// A block or its block entity changing outside of redstone, like a cauldron being filled or
+    /// a container's contents being changed, after which the blocks around it are updated
+    Change {
+        pos: BlockPos,
+        block: u32,
+        block_entity: Option<BlockEntity>,
+    },
+    /// A block set without updating anything around it, like by a plugin or script
+    SetBlock {
+        pos: BlockPos,
+        block: u32,
+    },
+    PressurePlate {
+        pos: BlockPos,
+        powered: bool,
+    },
+    /// How many players have each container open, which is what powers trapped chests
+    ContainerViewers(Vec<(BlockPos, u8)>),
+    /// The whole plot after a change that isn't any of the other inputs, like a worldedit
+    /// command or the plot being cleared
+    Snapshot(PlotData),
+}
+
+impl fmt::Display for Input {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Input::Use { pos } => write!(f, "used {}", pos),
+            Input::Place { pos, block, .. } => {
+                write!(f, "placed {:?} at {}", Block::from_id(*block), pos)
+            }
+            Input::Break { pos } => write!(f, "broke {}", pos),
+            Input::Change { pos, block, .. } => {
+                write!(f, "changed {} to {:?}", pos, Block::from_id(*block))
+            }
+            Input::SetBlock { pos, block } => {
+                write!(f, "set {} to {:?}", pos, Block::from_id(*block))
+            }
+            Input::PressurePlate { pos, powered } => {
+                write!(
+                    f,
+                    "set the pressure plate at {} to powered: {}",
+                    pos, powered
+                )
+            }
+            Input::ContainerViewers(viewers) => {
+                write!(f, "{} containers are open", viewers.len())
+            }
+            Input::Snapshot(_) => write!(f, "the whole plot changed"),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Recording {
+    pub plot_x: i32,
+    pub plot_z: i32,
+    /// The plot when the recording was started
+    pub start: PlotData,
+    /// Every input, with how many redstone ticks were recorded before it happened
+    pub inputs: Vec<(u64, Input)>,
+    /// How many redstone ticks were recorded in total
+    pub ticks: u64,
+}
+
+impl Recording {
+    pub fn load_from_file(path: impl AsRef<Path>) -> Result<Recording> {
+        let mut file = File::open(path)?;
+        let mut magic = [0; 8];
+        file.read_exact(&mut magic)?;
+        if &magic != RECORDING_MAGIC {
+            bail!("the file is not a recording");
+        }
+        let version = file.read_u32::<LittleEndian>()?;
+        if version != VERSION {
+            bail!(
+                "the recording has version {}, but only version {} can be replayed",
+                version,
+                VERSION
+            );
+        }
+        let data = zstd::decode_all(file)?;
+        Ok(bincode::deserialize(&data)?)
+    }
+
+    pub fn save_to_file(&self, path: impl AsRef<Path>) -> Result<()> {
+        let mut file = File::create(path)?;
+        file.write_all(RECORDING_MAGIC)?;
+        file.write_u32::<LittleEndian>(VERSION)?;
+        let data = bincode::serialize(self)?;
+        file.write_all(&zstd::encode_all(
+            data.as_slice(),
+            zstd::DEFAULT_COMPRESSION_LEVEL,
+        )?)?;
+        file.sync_data()?;
+        Ok(())
+    }
+}
+
+impl Plot {
+    pub(super) fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    /// Starts recording the plot. Redpiler is reset first, since the pending ticks it holds have
+    /// to be in the snapshot of the plot.
+    pub(super) fn start_recording(&mut self) {
+        self.reset_redpiler();
+        let start = self.to_data();
+        let mut recording = Recording {
+            plot_x: self.world.x,
+            plot_z: self.world.z,
+            start,
+            inputs: Vec::new(),
+            ticks: 0,
+        };
+        if !self.world.container_viewers.is_empty() {
+            let viewers = self.world.container_viewers.clone().into_iter().collect();
+            recording.inputs.push((0, Input::ContainerViewers(viewers)));
+        }
+        self.recording = Some(recording);
+    }
+
+    /// Stops recording and saves the recording, returning where it was saved
+    pub(super) fn stop_recording(&mut self) -> Option<Result<PathBuf>> {
+        let recording = self.recording.take()?;
+        let name = Local::now().format("%Y-%m-%d-%H%M%S");
+        let path = Path::new(RECORDINGS_DIR).join(format!(
+            "{}-p{},{}-{}.rec",
+            self.world_name, recording.plot_x, recording.plot_z, name
+        ));
+        let result = fs::create_dir_all(RECORDINGS_DIR)
+            .map_err(Into::into)
+            .and_then(|_| recording.save_to_file(&path));
+        Some(result.map(|_| path))
+    }
+
+    pub(super) fn record_ticks(&mut self, ticks: u64) {
+        if let Some(recording) = &mut self.recording {
+            recording.ticks += ticks;
+        }
+    }
+
+    pub(crate) fn record(&mut self, input: Input) {
+        if let Some(recording) = &mut self.recording {
+            recording.inputs.push((recording.ticks, input));
+        }
+    }
+
+    /// Records the block at `pos` and its block entity as they are now
+    pub(super) fn record_change(&mut self, pos: BlockPos) {
+        if self.is_recording() {
+            self.record(Input::Change {
+                pos,
+                block: self.world.get_block_raw(pos),
+                block_entity: self.world.get_block_entity(pos).cloned(),
+            });
+        }
+    }
+
+    /// Records what using the block at `pos` did, given the block that was there before
+    pub(super) fn record_use(&mut self, pos: BlockPos, old_block: Block) {
+        if !self.is_recording() {
+            return;
+        }
+        if mchprs_redstone::is_usable(old_block) {
+            self.record(Input::Use { pos });
+        } else if self.world.get_block(pos) != old_block {
+            self.record_change(pos);
+        }
+    }
+
+    /// Records the whole plot as it is now. Redpiler is reset first so the blocks and pending
+    /// ticks in the world are up to date.
+    pub(super) fn record_snapshot(&mut self) {
+        if self.is_recording() {
+            self.reset_redpiler();
+            let snapshot = self.to_data();
+            self.record(Input::Snapshot(snapshot));
+        }
+    }
+}
+
+/// Changes the plot in the same way as the input changed the recorded plot. Inputs that redpiler
+/// handles itself, like levers being flipped, should be given to redpiler instead while it is
+/// active.
+pub fn apply_input(plot: &mut HeadlessPlot, input: &Input) {
+    let world = plot.world_mut();
+    match input {
+        &Input::Use { pos } => {
+            mchprs_redstone::on_use(world.get_block(pos), world, pos);
+        }
+        Input::Place {
+            pos,
+            block,
+            block_entity,
+        } => {
+            if let Some(block_entity) = block_entity {
+                world.set_block_entity(*pos, block_entity.clone());
+            }
+            interaction::place_in_world(Block::from_id(*block), world, *pos, &None);
+        }
+        &Input::Break { pos } => interaction::destroy(world.get_block(pos), world, pos),
+        Input::Change {
+            pos,
+            block,
+            block_entity,
+        } => {
+            match block_entity {
+                Some(block_entity) => world.set_block_entity(*pos, block_entity.clone()),
+                None => world.delete_block_entity(*pos),
+            }
+            world.set_block_raw(*pos, *block);
+            mchprs_redstone::update_surrounding_blocks(world, *pos);
+        }
+        &Input::SetBlock { pos, block } => {
+            world.set_block_raw(pos, block);
+        }
+        &Input::PressurePlate { pos, powered } => {
+            interaction::set_pressure_plate(world, pos, powered)
+        }
+        Input::ContainerViewers(viewers) => {
+            plot.set_container_viewers(viewers.iter().copied().collect())
+        }
+        Input::Snapshot(data) => plot.load_data(data.clone()),
+    }
+}
@@ -5,6 +5,10 @@ use mchprs_network::packets::clientbound::{
 };
 use mchprs_redpiler::CompilerOptions;
 use mchprs_text::{ColorCode, TextComponentBuilder};
+use rustc_hash::FxHashMap;
+
+/// The objective for the per-player stats sidebar
+const STATS_OBJECTIVE: &str = "server_stats";
 
 #[derive(PartialEq, Eq, Default, Clone, Copy)]
 pub enum RedpilerState {
@@ -26,12 +30,15 @@ impl RedpilerState {
 
 pub struct Scoreboard {
     current_state: Vec<String>,
+    /// The current stats sidebar lines of every player that has it enabled
+    stats_state: FxHashMap<u128, Vec<String>>,
 }
 
 impl Default for Scoreboard {
     fn default() -> Scoreboard {
         let mut sb = Scoreboard {
             current_state: vec![],
+            stats_state: FxHashMap::default(),
         };
         sb.set_redpiler_state(&[], RedpilerState::Stopped);
         sb
@@ -84,7 +91,7 @@ impl Scoreboard {
         players.iter().for_each(|p| p.send_packet(&update_packet));
     }
 
-    pub fn add_player(&self, player: &Player) {
+    pub fn add_player(&mut self, player: &Player) {
         player.send_packet(
             &CUpdateObjectives {
                 objective_name: "redpiler_status".into(),
@@ -107,12 +114,98 @@ impl Scoreboard {
         for i in 0..self.current_state.len() {
             player.send_packet(&self.make_update_packet(i).encode());
         }
+        if player.stats_sidebar {
+            self.show_stats(player);
+        }
     }
 
     pub fn remove_player(&mut self, player: &Player) {
         for i in 0..self.current_state.len() {
             player.send_packet(&self.make_removal_packet(i).encode());
         }
+        // The objective is removed so it can be created again by the next plot
+        if self.stats_state.remove(&player.uuid).is_some() {
+            player.send_packet(&Self::stats_objective_packet(1).encode());
+        }
+    }
+
+    fn stats_objective_packet(mode: u8) -> CUpdateObjectives {
+        CUpdateObjectives {
+            objective_name: STATS_OBJECTIVE.into(),
+            mode,
+            objective_value: TextComponentBuilder::new("Stats".into())
+                .color_code(ColorCode::Gold)
+                .finish(),
+            ty: 0,
+            number_format: Some(ObjectiveNumberFormat::Blank),
+        }
+    }
+
+    /// Shows the stats sidebar to a player in place of the redpiler status
+    pub fn show_stats(&mut self, player: &Player) {
+        if self.stats_state.contains_key(&player.uuid) {
+            return;
+        }
+        player.send_packet(&Self::stats_objective_packet(0).encode());
+        player.send_packet(
+            &CDisplayObjective {
+                position: 1,
+                score_name: STATS_OBJECTIVE.into(),
+            }
+            .encode(),
+        );
+        self.stats_state.insert(player.uuid, Vec::new());
+    }
+
+    /// Removes the stats sidebar of a player and shows the redpiler status again
+    pub fn hide_stats(&mut self, player: &Player) {
+        if self.stats_state.remove(&player.uuid).is_none() {
+            return;
+        }
+        player.send_packet(&Self::stats_objective_packet(1).encode());
+        player.send_packet(
+            &CDisplayObjective {
+                position: 1,
+                score_name: "redpiler_status".into(),
+            }
+            .encode(),
+        );
+    }
+
+    /// Updates the lines of a player's stats sidebar, only sending the lines that changed
+    pub fn set_stats(&mut self, player: &Player, lines: Vec<String>) {
+        let Some(current) = self.stats_state.get_mut(&player.uuid) else {
+            return;
+        };
+        let len = lines.len();
+        // Scores are ordered by their value, so every line has to be resent if the length changes
+        let resend_all = current.len() != len;
+        for old in current.iter() {
+            if !lines.contains(old) {
+                player.send_packet(
+                    &CResetScore {
+                        entity_name: old.clone(),
+                        objective_name: Some(STATS_OBJECTIVE.to_string()),
+                    }
+                    .encode(),
+                );
+            }
+        }
+        for (i, line) in lines.iter().enumerate() {
+            if resend_all || current.get(i) != Some(line) {
+                player.send_packet(
+                    &CUpdateScore {
+                        entity_name: line.clone(),
+                        objective_name: STATS_OBJECTIVE.to_string(),
+                        value: (len - i) as i32,
+                        display_name: None,
+                        number_format: None,
+                    }
+                    .encode(),
+                );
+            }
+        }
+        *current = lines;
     }
 
     pub fn set_redpiler_state(&mut self, players: &[Player], state: RedpilerState) {
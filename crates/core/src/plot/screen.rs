@@ -0,0 +1,309 @@
+//! Captures a redstone display, like a game of life or a video player, by sampling a grid of
+//! redstone lamps every redstone tick. The capture is saved as an animated GIF, or as a PNG of
+//! every frame, so it can be shared without recording the game.
+
+use super::{Plot, ASYNC_RT};
+use crate::player::{MessageSender, PacketSender};
+use anyhow::Result;
+use chrono::Local;
+use mchprs_blocks::blocks::Block;
+use mchprs_blocks::BlockPos;
+use mchprs_save_data::plot_data::Tps;
+use mchprs_world::World;
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use tracing::error;
+
+const SCREENS_DIR: &str = "./screens";
+/// The most lamps a screen can have along each side
+pub const MAX_SCREEN_SIZE: i32 = 256;
+pub const MAX_CAPTURE_TICKS: u64 = 1200;
+pub const DEFAULT_CAPTURE_TICKS: u64 = 100;
+/// Small screens are scaled up to about this many pixels along their longest side
+const TARGET_IMAGE_SIZE: usize = 256;
+
+/// Columns without a lamp, unlit lamps and lit lamps
+const PALETTE: [u8; 9] = [0, 0, 0, 0x4a, 0x30, 0x1e, 0xff, 0xd7, 0x7a];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScreenFormat {
+    Gif,
+    Png,
+}
+
+impl ScreenFormat {
+    pub fn parse(format: &str) -> Option<ScreenFormat> {
+        match format {
+            "gif" => Some(ScreenFormat::Gif),
+            "png" => Some(ScreenFormat::Png),
+            _ => None,
+        }
+    }
+}
+
+/// Whether each lamp of the screen was lit, packed into bits
+type Frame = Vec<u64>;
+
+/// The lamps of a screen and the frames captured from them
+struct Screen {
+    width: usize,
+    height: usize,
+    /// The lamp shown by each pixel, row by row from the north, or `None` for the columns without
+    /// a lamp
+    lamps: Vec<Option<BlockPos>>,
+    /// Every frame that was different from the one before, with how many redstone ticks it was
+    /// shown for
+    frames: Vec<(Frame, u32)>,
+}
+
+impl Screen {
+    fn new(world: &impl World, x: (i32, i32), z: (i32, i32), height: i32) -> Screen {
+        let mut lamps = Vec::new();
+        for z in z.0..=z.1 {
+            for x in x.0..=x.1 {
+                // Only the top of the column can be seen from above
+                let lamp = (0..height)
+                    .rev()
+                    .map(|y| BlockPos::new(x, y, z))
+                    .find(|&pos| matches!(world.get_block(pos), Block::RedstoneLamp { .. }));
+                lamps.push(lamp);
+            }
+        }
+        Screen {
+            width: (x.1 - x.0 + 1) as usize,
+            height: (z.1 - z.0 + 1) as usize,
+            lamps,
+            frames: Vec::new(),
+        }
+    }
+
+    fn capture_frame(&mut self, world: &impl World) {
+        let mut frame = vec![0; self.lamps.len().div_ceil(64)];
+        for (i, pos) in self.lamps.iter().enumerate() {
+            if let Some(pos) = *pos {
+                if matches!(world.get_block(pos), Block::RedstoneLamp { lit: true }) {
+                    frame[i / 64] |= 1 << (i % 64);
+                }
+            }
+        }
+        match self.frames.last_mut() {
+            Some((last, ticks)) if *last == frame => *ticks += 1,
+            _ => self.frames.push((frame, 1)),
+        }
+    }
+
+    /// Turns a frame into the palette index of every pixel, scaled up so small screens can be
+    /// seen
+    fn render(&self, frame: &Frame) -> Vec<u8> {
+        let scale = self.scale();
+        let mut pixels = Vec::with_capacity(self.width * self.height * scale * scale);
+        let mut row = Vec::with_capacity(self.width * scale);
+        for z in 0..self.height {
+            row.clear();
+            for x in 0..self.width {
+                let i = z * self.width + x;
+                let index = match self.lamps[i] {
+                    None => 0,
+                    Some(_) if frame[i / 64] & (1 << (i % 64)) != 0 => 2,
+                    Some(_) => 1,
+                };
+                row.extend(std::iter::repeat(index).take(scale));
+            }
+            for _ in 0..scale {
+                pixels.extend_from_slice(&row);
+            }
+        }
+        pixels
+    }
+
+    fn scale(&self) -> usize {
+        (TARGET_IMAGE_SIZE / self.width.max(self.height)).clamp(1, 16)
+    }
+
+    fn has_lamps(&self) -> bool {
+        self.lamps.iter().any(Option::is_some)
+    }
+}
+
+pub struct ScreenCapture {
+    screen: Screen,
+    ticks_left: u64,
+    format: ScreenFormat,
+    /// The rtps the frames are played back at
+    rtps: u32,
+    world: String,
+    plot_x: i32,
+    plot_z: i32,
+    /// The player that started the capture, who is told where it was saved
+    sender: MessageSender,
+}
+
+impl ScreenCapture {
+    fn save(&self) -> Result<PathBuf> {
+        let name = Local::now().format("%Y-%m-%d-%H%M%S");
+        let path = Path::new(SCREENS_DIR).join(format!(
+            "{}-p{},{}-{}",
+            self.world, self.plot_x, self.plot_z, name
+        ));
+        let screen = &self.screen;
+        let scale = screen.scale();
+        let (width, height) = (screen.width * scale, screen.height * scale);
+        match self.format {
+            ScreenFormat::Gif => {
+                fs::create_dir_all(SCREENS_DIR)?;
+                let path = path.with_extension("gif");
+                let file = BufWriter::new(File::create(&path)?);
+                let mut encoder = gif::Encoder::new(file, width as u16, height as u16, &PALETTE)?;
+                encoder.set_repeat(gif::Repeat::Infinite)?;
+                for (frame, ticks) in &screen.frames {
+                    let mut gif_frame = gif::Frame::from_indexed_pixels(
+                        width as u16,
+                        height as u16,
+                        screen.render(frame),
+                        None,
+                    );
+                    // GIF delays are in hundredths of a second, and most viewers play anything
+                    // shorter than 2 of them much slower
+                    gif_frame.delay =
+                        (*ticks as u64 * 100 / self.rtps as u64).clamp(2, u16::MAX as u64) as u16;
+                    encoder.write_frame(&gif_frame)?;
+                }
+                Ok(path)
+            }
+            ScreenFormat::Png => {
+                fs::create_dir_all(&path)?;
+                let mut tick = 0;
+                for (frame, ticks) in &screen.frames {
+                    let file = File::create(path.join(format!("tick_{:05}.png", tick)))?;
+                    let mut encoder =
+                        png::Encoder::new(BufWriter::new(file), width as u32, height as u32);
+                    encoder.set_color(png::ColorType::Indexed);
+                    encoder.set_depth(png::BitDepth::Eight);
+                    encoder.set_palette(&PALETTE[..]);
+                    let mut writer = encoder.write_header()?;
+                    writer.write_image_data(&screen.render(frame))?;
+                    tick += ticks;
+                }
+                Ok(path)
+            }
+        }
+    }
+}
+
+impl Plot {
+    pub(super) fn is_capturing_screen(&self) -> bool {
+        self.screen_capture.is_some()
+    }
+
+    /// Starts capturing the lamps between the two corners for `ticks` redstone ticks. Returns
+    /// false if there are no lamps there.
+    pub(super) fn start_screen_capture(
+        &mut self,
+        player: usize,
+        (x1, z1): (i32, i32),
+        (x2, z2): (i32, i32),
+        ticks: u64,
+        format: ScreenFormat,
+    ) -> bool {
+        let (x, z) = ((x1.min(x2), x1.max(x2)), (z1.min(z2), z1.max(z2)));
+        let screen = Screen::new(&self.world, x, z, self.world.geometry.block_height);
+        if !screen.has_lamps() {
+            return false;
+        }
+        let rtps = match self.tps {
+            Tps::Limited(rtps) if rtps != 0 => rtps,
+            // Played back at the normal speed of redstone
+            _ => 10,
+        };
+        self.screen_capture = Some(ScreenCapture {
+            screen,
+            ticks_left: ticks,
+            format,
+            rtps,
+            world: self.world_name.clone(),
+            plot_x: self.world.x,
+            plot_z: self.world.z,
+            sender: MessageSender::new(&self.players[player]),
+        });
+        true
+    }
+
+    /// Captures a frame of the screen after a redstone tick, saving the capture once it has all
+    /// of its frames
+    pub(super) fn capture_screen(&mut self) {
+        let Some(capture) = &mut self.screen_capture else {
+            return;
+        };
+        if self.redpiler.is_active() {
+            self.redpiler.flush(&mut self.world);
+        }
+        capture.screen.capture_frame(&self.world);
+        capture.ticks_left -= 1;
+        if capture.ticks_left == 0 {
+            self.stop_screen_capture();
+        }
+    }
+
+    /// Stops capturing and saves what was captured so far. Encoding the frames can take a while,
+    /// so it is done off the plot's thread.
+    pub(super) fn stop_screen_capture(&mut self) -> bool {
+        let Some(capture) = self.screen_capture.take() else {
+            return false;
+        };
+        if capture.screen.frames.is_empty() {
+            capture.sender.send_error_message(text!("screen.no_frames"));
+            return true;
+        }
+        ASYNC_RT.spawn_blocking(move || match capture.save() {
+            Ok(path) => capture
+                .sender
+                .send_system_message(text!("screen.saved", path = path.display())),
+            Err(err) => {
+                error!("Failed to save screen capture: {:?}", err);
+                capture.sender.send_error_message(text!("screen.error"));
+            }
+        });
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bench::HeadlessPlot;
+
+    #[test]
+    fn identical_frames_are_merged() {
+        let mut plot = HeadlessPlot::empty();
+        let world = plot.world_mut();
+        world.set_block(BlockPos::new(0, 3, 0), Block::RedstoneLamp { lit: false });
+        world.set_block(BlockPos::new(1, 5, 0), Block::RedstoneLamp { lit: true });
+        world.set_block(BlockPos::new(1, 2, 0), Block::RedstoneLamp { lit: false });
+        let mut screen = Screen::new(plot.world(), (0, 2), (0, 0), 16);
+        assert_eq!(
+            screen.lamps,
+            [
+                Some(BlockPos::new(0, 3, 0)),
+                Some(BlockPos::new(1, 5, 0)),
+                None
+            ]
+        );
+
+        screen.capture_frame(plot.world());
+        screen.capture_frame(plot.world());
+        plot.world_mut()
+            .set_block(BlockPos::new(0, 3, 0), Block::RedstoneLamp { lit: true });
+        screen.capture_frame(plot.world());
+        assert_eq!(screen.frames, [(vec![0b10], 2), (vec![0b11], 1)]);
+
+        let scale = screen.scale();
+        let pixels = screen.render(&screen.frames[0].0);
+        assert_eq!(pixels.len(), 3 * scale * scale);
+        let row: Vec<u8> = [1, 2, 0]
+            .into_iter()
+            .flat_map(|index| std::iter::repeat(index).take(scale))
+            .collect();
+        assert_eq!(pixels[..row.len()], row);
+    }
+}
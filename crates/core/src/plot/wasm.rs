@@ -0,0 +1,454 @@
+//! Sandboxed server extensions compiled to WebAssembly, loaded from `./extensions`.
+//!
+//! Every plot runs its own instance of each extension, and extensions are reloaded when their
+//! file changes. An extension runs with limited memory and fuel, so a broken one can't take
+//! down the plot it's running in.
+//!
+//! Extensions must export `memory` and `alloc(len: i32) -> i32`, which the server uses to
+//! pass strings to the extension. Ownership of these allocations passes to the extension.
+//! The following exports are optional:
+//!
+//! - `init()`: Called once after the extension is loaded. This is the only place commands can
+//!   be registered.
+//! - `on_command(name_ptr, name_len, args_ptr, args_len, username_ptr, username_len, uuid_ptr,
+//!   uuid_len)`: Called when a player runs one of the registered commands. The arguments are
+//!   passed as a single string, followed by the name and hyphenated uuid of the player.
+//! - `on_chat(username_ptr, username_len, message_ptr, message_len) -> i32`: Called when a
+//!   player in the plot sends a chat message. Returning 0 cancels the message.
+//!
+//! The server provides these functions in the `mchprs` import module:
+//!
+//! - `log(ptr, len)`: Writes a message to the server log.
+//! - `register_command(ptr, len)`: Registers a command, without the leading slash. Commands
+//!   the server already has are refused.
+//! - `send_message(ptr, len)`: Sends a message with `&` color codes to the player the current
+//!   event is about.
+//! - `get_block(x, y, z) -> i32`: Returns the block state id at a position, or -1 if the
+//!   position is outside of the plot. Blocks can only be accessed from `on_command`.
+//! - `can_build() -> i32`: Returns 1 if the player running the command may build in the plot.
+//! - `set_block(x, y, z, id) -> i32`: Sets the block state id at a position. Returns 1 if the
+//!   block was changed. Blocks are never changed for players that can't build in the plot.
+
+use super::{commands, Plot, PlotWorld};
+use crate::player::{PacketSender, Player};
+use crate::utils::HyphenatedUUID;
+use anyhow::{Context, Result};
+use mchprs_blocks::blocks::Block;
+use mchprs_blocks::BlockPos;
+use mchprs_text::TextComponent;
+use mchprs_world::World;
+use once_cell::sync::Lazy;
+use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+use std::{fs, mem};
+use tracing::{debug, error, info, warn};
+use wasmtime::{
+    Caller, Config, Engine, Instance, Linker, Module, Store, StoreLimits, StoreLimitsBuilder,
+};
+
+const EXTENSIONS_DIR: &str = "./extensions";
+/// How often the extensions folder is checked for changes
+const SCAN_INTERVAL: Duration = Duration::from_secs(2);
+/// Roughly how many instructions an extension may run every time it is called
+const FUEL_PER_CALL: u64 = 100_000_000;
+const MAX_MEMORY: usize = 64 * 1024 * 1024;
+
+static ENGINE: Lazy<Engine> = Lazy::new(|| {
+    let mut config = Config::new();
+    config.consume_fuel(true);
+    Engine::new(&config).expect("failed to create wasm engine")
+});
+
+/// The names of every command an extension registered. The declared commands need static
+/// names, and extensions register the same commands again whenever they are reloaded.
+static COMMAND_NAMES: Lazy<Mutex<HashSet<&'static str>>> = Lazy::new(Default::default);
+
+fn intern_command_name(name: String) -> &'static str {
+    let mut names = COMMAND_NAMES.lock().unwrap();
+    match names.get(name.as_str()) {
+        Some(name) => name,
+        None => {
+            let name = Box::leak(name.into_boxed_str());
+            names.insert(name);
+            name
+        }
+    }
+}
+
+struct HostState {
+    commands: Vec<&'static str>,
+    /// The world of the plot. This is only moved in while running a command.
+    world: Option<PlotWorld>,
+    /// Whether the player running the current command may build in the plot
+    can_build: bool,
+    /// Messages for the player the current event is about
+    messages: Vec<String>,
+    limits: StoreLimits,
+}
+
+fn read_str(caller: &mut Caller<'_, HostState>, ptr: i32, len: i32) -> Option<String> {
+    let memory = caller.get_export("memory")?.into_memory()?;
+    let data = memory
+        .data(&caller)
+        .get(ptr as u32 as usize..)?
+        .get(..len as u32 as usize)?;
+    Some(String::from_utf8_lossy(data).into_owned())
+}
+
+fn in_bounds(world: &PlotWorld, pos: BlockPos) -> bool {
+    Plot::in_plot_bounds(&world.geometry, world.x, world.z, pos.x, pos.z)
+        && (0..world.geometry.block_height).contains(&pos.y)
+}
+
+fn create_linker() -> Result<Linker<HostState>> {
+    let mut linker = Linker::new(&ENGINE);
+    linker.func_wrap(
+        "mchprs",
+        "log",
+        |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| {
+            if let Some(message) = read_str(&mut caller, ptr, len) {
+                info!("[extension] {}", message);
+            }
+        },
+    )?;
+    linker.func_wrap(
+        "mchprs",
+        "register_command",
+        |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| {
+            let Some(name) = read_str(&mut caller, ptr, len) else {
+                return;
+            };
+            if commands::is_builtin_command(&name) {
+                warn!(
+                    "An extension can't register /{}, which already exists",
+                    name
+                );
+                return;
+            }
+            caller.data_mut().commands.push(intern_command_name(name));
+        },
+    )?;
+    linker.func_wrap(
+        "mchprs",
+        "send_message",
+        |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| {
+            if let Some(message) = read_str(&mut caller, ptr, len) {
+                caller.data_mut().messages.push(message);
+            }
+        },
+    )?;
+    linker.func_wrap(
+        "mchprs",
+        "get_block",
+        |caller: Caller<'_, HostState>, x: i32, y: i32, z: i32| -> i32 {
+            let pos = BlockPos::new(x, y, z);
+            match &caller.data().world {
+                Some(world) if in_bounds(world, pos) => world.get_block_raw(pos) as i32,
+                _ => -1,
+            }
+        },
+    )?;
+    linker.func_wrap(
+        "mchprs",
+        "can_build",
+        |caller: Caller<'_, HostState>| -> i32 { caller.data().can_build as i32 },
+    )?;
+    linker.func_wrap(
+        "mchprs",
+        "set_block",
+        |mut caller: Caller<'_, HostState>, x: i32, y: i32, z: i32, id: i32| -> i32 {
+            let pos = BlockPos::new(x, y, z);
+            let id = id as u32;
+            // Make sure the id is a valid block state
+            if Block::from_id(id).get_id() != id || !caller.data().can_build {
+                return 0;
+            }
+            match &mut caller.data_mut().world {
+                Some(world) if in_bounds(world, pos) => world.set_block_raw(pos, id) as i32,
+                _ => 0,
+            }
+        },
+    )?;
+    Ok(linker)
+}
+
+struct Extension {
+    name: String,
+    path: PathBuf,
+    modified: SystemTime,
+    store: Store<HostState>,
+    instance: Instance,
+}
+
+impl Extension {
+    fn load(linker: &Linker<HostState>, path: &Path, modified: SystemTime) -> Result<Extension> {
+        let module = Module::from_file(&ENGINE, path)?;
+        let state = HostState {
+            commands: Vec::new(),
+            world: None,
+            can_build: false,
+            messages: Vec::new(),
+            limits: StoreLimitsBuilder::new().memory_size(MAX_MEMORY).build(),
+        };
+        let mut store = Store::new(&ENGINE, state);
+        store.limiter(|state| &mut state.limits);
+        store.set_fuel(FUEL_PER_CALL)?;
+        let instance = linker.instantiate(&mut store, &module)?;
+        if let Ok(init) = instance.get_typed_func::<(), ()>(&mut store, "init") {
+            init.call(&mut store, ())?;
+        }
+
+        let name = path
+            .file_stem()
+            .map_or_else(String::new, |stem| stem.to_string_lossy().into_owned());
+        Ok(Extension {
+            name,
+            path: path.to_path_buf(),
+            modified,
+            store,
+            instance,
+        })
+    }
+
+    fn write_str(&mut self, s: &str) -> Result<(i32, i32)> {
+        let alloc = self
+            .instance
+            .get_typed_func::<i32, i32>(&mut self.store, "alloc")?;
+        let ptr = alloc.call(&mut self.store, s.len() as i32)?;
+        let memory = self
+            .instance
+            .get_memory(&mut self.store, "memory")
+            .context("extension does not export its memory")?;
+        memory.write(&mut self.store, ptr as u32 as usize, s.as_bytes())?;
+        Ok((ptr, s.len() as i32))
+    }
+
+    fn call_on_command(&mut self, player: &Player, command: &str, args: &str) -> Result<()> {
+        self.store.set_fuel(FUEL_PER_CALL)?;
+        let Ok(on_command) = self
+            .instance
+            .get_typed_func::<(i32, i32, i32, i32, i32, i32, i32, i32), ()>(
+                &mut self.store,
+                "on_command",
+            )
+        else {
+            return Ok(());
+        };
+        let (name_ptr, name_len) = self.write_str(command)?;
+        let (args_ptr, args_len) = self.write_str(args)?;
+        let (username_ptr, username_len) = self.write_str(&player.username)?;
+        let (uuid_ptr, uuid_len) = self.write_str(&HyphenatedUUID(player.uuid).to_string())?;
+        on_command.call(
+            &mut self.store,
+            (
+                name_ptr,
+                name_len,
+                args_ptr,
+                args_len,
+                username_ptr,
+                username_len,
+                uuid_ptr,
+                uuid_len,
+            ),
+        )
+    }
+
+    fn call_on_chat(&mut self, username: &str, message: &str) -> Result<bool> {
+        self.store.set_fuel(FUEL_PER_CALL)?;
+        let Ok(on_chat) = self
+            .instance
+            .get_typed_func::<(i32, i32, i32, i32), i32>(&mut self.store, "on_chat")
+        else {
+            return Ok(true);
+        };
+        let (username_ptr, username_len) = self.write_str(username)?;
+        let (message_ptr, message_len) = self.write_str(message)?;
+        let allowed = on_chat.call(
+            &mut self.store,
+            (username_ptr, username_len, message_ptr, message_len),
+        )?;
+        Ok(allowed != 0)
+    }
+
+    fn send_messages(&mut self, player: &Player) {
+        for message in self.store.data_mut().messages.drain(..) {
+            player.send_chat_message(&TextComponent::from_legacy_text(&message));
+        }
+    }
+}
+
+/// The extensions running in a plot
+pub struct Extensions {
+    linker: Option<Linker<HostState>>,
+    extensions: Vec<Extension>,
+    /// Extensions that failed to load, so they aren't retried until they change
+    failed: Vec<(PathBuf, SystemTime)>,
+    last_scan: Option<Instant>,
+}
+
+impl Extensions {
+    pub fn new() -> Extensions {
+        let linker = create_linker()
+            .map_err(|err| error!("Failed to set up wasm extensions: {}", err))
+            .ok();
+        Extensions {
+            linker,
+            extensions: Vec::new(),
+            failed: Vec::new(),
+            last_scan: None,
+        }
+    }
+
+    /// Loads new extensions and reloads the ones that changed since they were loaded. Returns
+    /// true if the commands of the extensions changed.
+    pub fn reload_changed(&mut self) -> bool {
+        if self
+            .last_scan
+            .is_some_and(|time| time.elapsed() < SCAN_INTERVAL)
+        {
+            return false;
+        }
+        self.last_scan = Some(Instant::now());
+        let Some(linker) = &self.linker else {
+            return false;
+        };
+        let old_commands = self.commands();
+
+        let mut found = Vec::new();
+        for entry in fs::read_dir(EXTENSIONS_DIR).into_iter().flatten().flatten() {
+            let path = entry.path();
+            if path.extension() != Some(OsStr::new("wasm")) {
+                continue;
+            }
+            let Ok(modified) = entry.metadata().and_then(|meta| meta.modified()) else {
+                continue;
+            };
+            found.push(path.clone());
+
+            let existing = self.extensions.iter().position(|ext| ext.path == path);
+            if existing.is_some_and(|idx| self.extensions[idx].modified == modified)
+                || self.failed.contains(&(path.clone(), modified))
+            {
+                continue;
+            }
+            self.failed.retain(|(failed_path, _)| *failed_path != path);
+
+            match Extension::load(linker, &path, modified) {
+                Ok(extension) => {
+                    debug!("Loaded extension {}", extension.name);
+                    match existing {
+                        Some(idx) => self.extensions[idx] = extension,
+                        None => self.extensions.push(extension),
+                    }
+                }
+                Err(err) => {
+                    error!("Failed to load extension {}: {:#}", path.display(), err);
+                    if let Some(idx) = existing {
+                        self.extensions.remove(idx);
+                    }
+                    self.failed.push((path, modified));
+                }
+            }
+        }
+        self.extensions.retain(|ext| found.contains(&ext.path));
+        self.commands() != old_commands
+    }
+
+    /// The commands registered by every extension, which plots add to the declared commands
+    pub fn commands(&self) -> Vec<&'static str> {
+        let mut commands: Vec<_> = self
+            .extensions
+            .iter()
+            .flat_map(|ext| ext.store.data().commands.iter().copied())
+            .collect();
+        commands.sort_unstable();
+        commands.dedup();
+        commands
+    }
+
+    pub fn has_command(&self, command: &str) -> bool {
+        self.extensions
+            .iter()
+            .any(|ext| ext.store.data().commands.contains(&command))
+    }
+
+    /// Runs an extension command. Returns `false` if no extension registered the command.
+    /// `can_build` is whether the player may change blocks in the plot.
+    pub fn run_command(
+        &mut self,
+        world: &mut PlotWorld,
+        player: &Player,
+        can_build: bool,
+        command: &str,
+        args: &[&str],
+    ) -> bool {
+        let Some(extension) = self
+            .extensions
+            .iter_mut()
+            .find(|ext| ext.store.data().commands.contains(&command))
+        else {
+            return false;
+        };
+
+        // The world is moved into the extension's store for the duration of the call
+        let placeholder = PlotWorld {
+            x: world.x,
+            z: world.z,
+            geometry: world.geometry,
+            chunks: Vec::new(),
+            to_be_ticked: Vec::new(),
+            packet_senders: Vec::new(),
+            map_feed: None,
+            container_viewers: Default::default(),
+            time_of_day: world.time_of_day,
+            entities: Default::default(),
+        };
+        let state = extension.store.data_mut();
+        state.world = Some(mem::replace(world, placeholder));
+        state.can_build = can_build;
+        let result = extension.call_on_command(player, command, &args.join(" "));
+        *world = extension.store.data_mut().world.take().unwrap();
+
+        extension.send_messages(player);
+        if let Err(err) = result {
+            warn!(
+                "Extension {} failed to run /{}: {:#}",
+                extension.name, command, err
+            );
+            player.send_error_message(text!("extension.command_failed", error = err));
+        }
+        true
+    }
+
+    /// Returns `false` if an extension cancelled the chat message
+    pub fn on_chat(&mut self, player: &Player, message: &str) -> bool {
+        let mut allowed = true;
+        for extension in &mut self.extensions {
+            match extension.call_on_chat(&player.username, message) {
+                Ok(ext_allowed) => allowed &= ext_allowed,
+                Err(err) => warn!(
+                    "Extension {} failed to handle chat: {:#}",
+                    extension.name, err
+                ),
+            }
+            extension.send_messages(player);
+        }
+        allowed
+    }
+}
+
+impl Plot {
+    /// Sends the player the declared commands with the commands of the plot's extensions, if
+    /// they aren't the ones the player's client already knows about
+    pub(super) fn declare_extension_commands(&mut self, player: usize) {
+        let extension_commands = self.extensions.commands();
+        let player = &mut self.players[player];
+        if player.extension_commands != extension_commands {
+            player.send_packet(&commands::declare_commands(&extension_commands));
+            player.extension_commands = extension_commands;
+        }
+    }
+}
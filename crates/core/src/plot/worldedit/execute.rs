@@ -1,10 +1,9 @@
 use super::*;
 use crate::config::CONFIG;
 use crate::player::PacketSender;
-use crate::plot::PLOT_BLOCK_HEIGHT;
+use crate::progress::ProgressBar;
 use crate::utils::{self, HyphenatedUUID};
-use mchprs_blocks::block_entities::InventoryEntry;
-use mchprs_blocks::blocks::{Block, FlipDirection, RotateAmt};
+use mchprs_blocks::blocks::{Block, RotateAmt};
 use mchprs_blocks::items::{Item, ItemStack};
 use mchprs_blocks::{BlockFace, BlockFacing, BlockPos};
 use mchprs_network::packets::clientbound::*;
@@ -47,7 +46,9 @@ pub(super) fn execute_set(ctx: CommandExecuteContext<'_>) {
         ctx.player.first_position.unwrap(),
         ctx.player.second_position.unwrap(),
     );
+    let mut progress = ProgressBar::new(ctx.player, text!("progress.set"));
     for x in operation.x_range() {
+        progress.set_progress(operation.progress(x));
         for y in operation.y_range() {
             for z in operation.z_range() {
                 let block_pos = BlockPos::new(x, y, z);
@@ -62,10 +63,10 @@ pub(super) fn execute_set(ctx: CommandExecuteContext<'_>) {
 
     let blocks_updated = operation.blocks_updated();
 
-    ctx.player.send_worldedit_message(&format!(
-        "Operation completed: {} block(s) affected ({:?})",
-        blocks_updated,
-        start_time.elapsed()
+    ctx.player.send_worldedit_message(text!(
+        "worldedit.operation_completed",
+        count = blocks_updated,
+        time = format!("{:?}", start_time.elapsed())
     ));
 }
 
@@ -82,7 +83,9 @@ pub(super) fn execute_replace(ctx: CommandExecuteContext<'_>) {
         ctx.player.first_position.unwrap(),
         ctx.player.second_position.unwrap(),
     );
+    let mut progress = ProgressBar::new(ctx.player, text!("progress.replace"));
     for x in operation.x_range() {
+        progress.set_progress(operation.progress(x));
         for y in operation.y_range() {
             for z in operation.z_range() {
                 let block_pos = BlockPos::new(x, y, z);
@@ -100,10 +103,10 @@ pub(super) fn execute_replace(ctx: CommandExecuteContext<'_>) {
 
     let blocks_updated = operation.blocks_updated();
 
-    ctx.player.send_worldedit_message(&format!(
-        "Operation completed: {} block(s) affected ({:?})",
-        blocks_updated,
-        start_time.elapsed()
+    ctx.player.send_worldedit_message(text!(
+        "worldedit.operation_completed",
+        count = blocks_updated,
+        time = format!("{:?}", start_time.elapsed())
     ));
 }
 
@@ -125,10 +128,54 @@ pub(super) fn execute_count(ctx: CommandExecuteContext<'_>) {
         }
     }
 
-    ctx.player.send_worldedit_message(&format!(
-        "Counted {} block(s) ({:?})",
-        blocks_counted,
-        start_time.elapsed()
+    ctx.player.send_worldedit_message(text!(
+        "worldedit.count.done",
+        count = blocks_counted,
+        time = format!("{:?}", start_time.elapsed())
+    ));
+}
+
+pub(super) fn execute_blockcount(ctx: CommandExecuteContext<'_>) {
+    let start_time = Instant::now();
+
+    let count = ComponentCount::of(
+        ctx.plot,
+        ctx.player.first_position.unwrap(),
+        ctx.player.second_position.unwrap(),
+    );
+    let [one, two, three, four] = count.repeaters;
+    let player = ctx.player;
+    player.send_worldedit_message(text!(
+        "worldedit.blockcount.header",
+        count = count.total(),
+        time = format!("{:?}", start_time.elapsed())
+    ));
+    player.send_worldedit_message(text!(
+        "worldedit.blockcount.repeaters",
+        count = one + two + three + four,
+        one = one,
+        two = two,
+        three = three,
+        four = four
+    ));
+    player.send_worldedit_message(text!(
+        "worldedit.blockcount.comparators",
+        count = count.comparators(),
+        compare = count.compare_comparators,
+        subtract = count.subtract_comparators
+    ));
+    player.send_worldedit_message(text!("worldedit.blockcount.torches", count = count.torches));
+    player.send_worldedit_message(text!("worldedit.blockcount.wires", count = count.wires));
+    player.send_worldedit_message(text!(
+        "worldedit.blockcount.observers",
+        count = count.observers
+    ));
+    player.send_worldedit_message(text!("worldedit.blockcount.lamps", count = count.lamps));
+    player.send_worldedit_message(text!("worldedit.blockcount.sources", count = count.sources));
+    player.send_worldedit_message(text!("worldedit.blockcount.other", count = count.other));
+    player.send_worldedit_message(text!(
+        "worldedit.blockcount.cost",
+        updates = count.updates_per_tick()
     ));
 }
 
@@ -144,9 +191,9 @@ pub(super) fn execute_copy(ctx: CommandExecuteContext<'_>) {
     );
     ctx.player.worldedit_clipboard = Some(clipboard);
 
-    ctx.player.send_worldedit_message(&format!(
-        "Your selection was copied. ({:?})",
-        start_time.elapsed()
+    ctx.player.send_worldedit_message(text!(
+        "worldedit.copy.done",
+        time = format!("{:?}", start_time.elapsed())
     ));
 }
 
@@ -163,9 +210,9 @@ pub(super) fn execute_cut(ctx: CommandExecuteContext<'_>) {
     ctx.player.worldedit_clipboard = Some(clipboard);
     clear_area(ctx.plot, first_pos, second_pos);
 
-    ctx.player.send_worldedit_message(&format!(
-        "Your selection was cut. ({:?})",
-        start_time.elapsed()
+    ctx.player.send_worldedit_message(text!(
+        "worldedit.cut.done",
+        time = format!("{:?}", start_time.elapsed())
     ));
 }
 
@@ -203,6 +250,7 @@ pub(super) fn execute_move(mut ctx: CommandExecuteContext<'_>) {
         &clipboard,
         direction.offset_pos(zero_pos, move_amt as i32),
         ctx.has_flag('a'),
+        None,
     );
 
     if ctx.has_flag('s') {
@@ -213,9 +261,9 @@ pub(super) fn execute_move(mut ctx: CommandExecuteContext<'_>) {
         player.worldedit_set_second_position(second_pos);
     }
 
-    ctx.player.send_worldedit_message(&format!(
-        "Your selection was moved. ({:?})",
-        start_time.elapsed()
+    ctx.player.send_worldedit_message(text!(
+        "worldedit.move.done",
+        time = format!("{:?}", start_time.elapsed())
     ));
 }
 
@@ -236,28 +284,31 @@ pub(super) fn execute_paste(ctx: CommandExecuteContext<'_>) {
             offset_z + cb.size_z as i32,
         );
         capture_undo(ctx.plot, ctx.player, first_pos, second_pos);
-        paste_clipboard(ctx.plot, cb, pos, ctx.has_flag('a'));
+        let mut progress = ProgressBar::new(ctx.player, text!("progress.paste"));
+        paste_clipboard(ctx.plot, cb, pos, ctx.has_flag('a'), Some(&mut progress));
         if ctx.has_flag('u') {
             update(ctx.plot, first_pos, second_pos);
         }
-        ctx.player.send_worldedit_message(&format!(
-            "Your clipboard was pasted. ({:?})",
-            start_time.elapsed()
+        ctx.player.send_worldedit_message(text!(
+            "worldedit.paste.done",
+            time = format!("{:?}", start_time.elapsed())
         ));
     } else {
-        ctx.player.send_system_message("Your clipboard is empty!");
+        ctx.player
+            .send_system_message(text!("worldedit.clipboard_empty_short"));
     }
 }
 
 static SCHEMATI_VALIDATE_REGEX: Lazy<Regex> =
-    Lazy::new(|| Regex::new(r"[a-zA-Z0-9_.]+\.schem(atic)?").unwrap());
+    Lazy::new(|| Regex::new(r"[a-zA-Z0-9_.]+\.(schem(atic)?|litematic)").unwrap());
 
 pub(super) fn execute_load(ctx: CommandExecuteContext<'_>) {
     let start_time = Instant::now();
 
     let mut file_name = ctx.arguments[0].unwrap_string().clone();
     if !SCHEMATI_VALIDATE_REGEX.is_match(&file_name) {
-        ctx.player.send_error_message("Filename is invalid");
+        ctx.player
+            .send_error_message(text!("worldedit.invalid_filename"));
         return;
     }
 
@@ -270,24 +321,22 @@ pub(super) fn execute_load(ctx: CommandExecuteContext<'_>) {
     match clipboard {
         Ok(cb) => {
             ctx.player.worldedit_clipboard = Some(cb);
-            ctx.player.send_worldedit_message(&format!(
-                "The schematic was loaded to your clipboard. Do //paste to birth it into the world. ({:?})",
-                start_time.elapsed()
+            ctx.player.send_worldedit_message(text!(
+                "worldedit.load.done",
+                time = format!("{:?}", start_time.elapsed())
             ));
         }
         Err(e) => {
             if let Some(e) = e.downcast_ref::<std::io::Error>() {
                 if e.kind() == std::io::ErrorKind::NotFound {
-                    let msg = "The specified schematic file could not be found.";
-                    ctx.player.send_error_message(msg);
+                    ctx.player
+                        .send_error_message(text!("worldedit.load.not_found"));
                     return;
                 }
             }
             error!("There was an error loading a schematic:");
             error!("{}", e);
-            ctx.player.send_error_message(
-                "There was an error loading the schematic. Check console for more details.",
-            );
+            ctx.player.send_error_message(text!("worldedit.load.error"));
         }
     }
 }
@@ -297,7 +346,8 @@ pub(super) fn execute_save(ctx: CommandExecuteContext<'_>) {
 
     let mut file_name = ctx.arguments[0].unwrap_string().clone();
     if !SCHEMATI_VALIDATE_REGEX.is_match(&file_name) {
-        ctx.player.send_error_message("Filename is invalid");
+        ctx.player
+            .send_error_message(text!("worldedit.invalid_filename"));
         return;
     }
 
@@ -309,16 +359,15 @@ pub(super) fn execute_save(ctx: CommandExecuteContext<'_>) {
     let clipboard = ctx.player.worldedit_clipboard.as_ref().unwrap();
     match save_schematic(&file_name, clipboard) {
         Ok(_) => {
-            ctx.player.send_worldedit_message(&format!(
-                "The schematic was saved sucessfuly. ({:?})",
-                start_time.elapsed()
+            ctx.player.send_worldedit_message(text!(
+                "worldedit.save.done",
+                time = format!("{:?}", start_time.elapsed())
             ));
         }
         Err(err) => {
             error!("There was an error saving a schematic: ");
             error!("{:?}", err);
-            ctx.player
-                .send_error_message("There was an error saving the schematic.");
+            ctx.player.send_error_message(text!("worldedit.save.error"));
         }
     }
 }
@@ -330,24 +379,16 @@ pub(super) fn execute_stack(ctx: CommandExecuteContext<'_>) {
     let direction = ctx.arguments[1].unwrap_direction();
     let pos1 = ctx.player.first_position.unwrap();
     let pos2 = ctx.player.second_position.unwrap();
-    let clipboard = create_clipboard(ctx.plot, pos1, pos1, pos2);
-    let stack_offset = match direction {
-        BlockFacing::North | BlockFacing::South => clipboard.size_z,
-        BlockFacing::East | BlockFacing::West => clipboard.size_x,
-        BlockFacing::Up | BlockFacing::Down => clipboard.size_y,
-    };
-    let mut undo_cbs = Vec::new();
-    for i in 1..stack_amt + 1 {
-        let offset = (i * stack_offset) as i32;
-        let block_pos = direction.offset_pos(pos1, offset);
-        undo_cbs.push(create_clipboard(
-            ctx.plot,
-            pos1,
-            block_pos,
-            direction.offset_pos(pos2, offset),
-        ));
-        paste_clipboard(ctx.plot, &clipboard, block_pos, ctx.has_flag('a'));
-    }
+    let mut progress = ProgressBar::new(ctx.player, text!("progress.stack"));
+    let undo_cbs = stack(
+        ctx.plot,
+        pos1,
+        pos2,
+        stack_amt,
+        direction,
+        ctx.has_flag('a'),
+        Some(&mut progress),
+    );
     let undo = WorldEditUndo {
         clipboards: undo_cbs,
         pos: pos1,
@@ -356,85 +397,41 @@ pub(super) fn execute_stack(ctx: CommandExecuteContext<'_>) {
     };
     ctx.player.worldedit_undo.push(undo);
 
-    ctx.player.send_worldedit_message(&format!(
-        "Your selection was stacked. ({:?})",
-        start_time.elapsed()
+    ctx.player.send_worldedit_message(text!(
+        "worldedit.stack.done",
+        time = format!("{:?}", start_time.elapsed())
     ));
 }
 
 pub(super) fn execute_undo(ctx: CommandExecuteContext<'_>) {
     if ctx.player.worldedit_undo.is_empty() {
         ctx.player
-            .send_error_message("There is nothing left to undo.");
+            .send_error_message(text!("worldedit.undo.nothing_left"));
         return;
     }
     let undo = ctx.player.worldedit_undo.pop().unwrap();
     if undo.plot_x != ctx.plot.x || undo.plot_z != ctx.plot.z {
         ctx.player
-            .send_error_message("Cannot undo outside of your current plot.");
+            .send_error_message(text!("worldedit.undo.other_plot"));
         return;
     }
-    let redo = WorldEditUndo {
-        clipboards: undo
-            .clipboards
-            .iter()
-            .map(|clipboard| {
-                let first_pos = BlockPos {
-                    x: undo.pos.x - clipboard.offset_x,
-                    y: undo.pos.y - clipboard.offset_y,
-                    z: undo.pos.z - clipboard.offset_z,
-                };
-                let second_pos = BlockPos {
-                    x: first_pos.x + clipboard.size_x as i32 - 1,
-                    y: first_pos.y + clipboard.size_y as i32 - 1,
-                    z: first_pos.z + clipboard.size_z as i32 - 1,
-                };
-                create_clipboard(ctx.plot, undo.pos, first_pos, second_pos)
-            })
-            .collect(),
-        ..undo
-    };
-    for clipboard in &undo.clipboards {
-        paste_clipboard(ctx.plot, clipboard, undo.pos, false);
-    }
+    let redo = restore(ctx.plot, undo);
     ctx.player.worldedit_redo.push(redo);
 }
 
 pub(super) fn execute_redo(ctx: CommandExecuteContext<'_>) {
     if ctx.player.worldedit_redo.is_empty() {
         ctx.player
-            .send_error_message("There is nothing left to redo.");
+            .send_error_message(text!("worldedit.redo.nothing_left"));
         return;
     }
     let redo = ctx.player.worldedit_redo.pop().unwrap();
     if redo.plot_x != ctx.plot.x || redo.plot_z != ctx.plot.z {
         ctx.player
-            .send_error_message("Cannot redo outside of your current plot.");
+            .send_error_message(text!("worldedit.redo.other_plot"));
         return;
     }
-    let undo = WorldEditUndo {
-        clipboards: redo
-            .clipboards
-            .iter()
-            .map(|clipboard| {
-                let first_pos = BlockPos {
-                    x: redo.pos.x - clipboard.offset_x,
-                    y: redo.pos.y - clipboard.offset_y,
-                    z: redo.pos.z - clipboard.offset_z,
-                };
-                let second_pos = BlockPos {
-                    x: first_pos.x + clipboard.size_x as i32 - 1,
-                    y: first_pos.y + clipboard.size_y as i32 - 1,
-                    z: first_pos.z + clipboard.size_z as i32 - 1,
-                };
-                create_clipboard(ctx.plot, redo.pos, first_pos, second_pos)
-            })
-            .collect(),
-        ..redo
-    };
-    for clipboard in &redo.clipboards {
-        paste_clipboard(ctx.plot, clipboard, redo.pos, false);
-    }
+    let undo = restore(ctx.plot, redo);
     ctx.player.worldedit_undo.push(undo);
 }
 
@@ -442,7 +439,7 @@ pub(super) fn execute_sel(ctx: CommandExecuteContext<'_>) {
     let player = ctx.player;
     player.first_position = None;
     player.second_position = None;
-    player.send_worldedit_message("Selection cleared.");
+    player.send_worldedit_message(text!("worldedit.selection_cleared"));
     player.worldedit_send_cui("s|cuboid");
 }
 
@@ -466,7 +463,7 @@ pub(super) fn execute_hpos1(mut ctx: CommandExecuteContext<'_>) {
     let player = ctx.player;
     match result {
         Some(pos) => player.worldedit_set_first_position(pos),
-        None => player.send_error_message("No block in sight!"),
+        None => player.send_error_message(text!("worldedit.no_block_in_sight")),
     }
 }
 
@@ -480,7 +477,7 @@ pub(super) fn execute_hpos2(mut ctx: CommandExecuteContext<'_>) {
     let player = &mut ctx.player;
     match result {
         Some(pos) => player.worldedit_set_second_position(pos),
-        None => player.send_error_message("No block in sight!"),
+        None => player.send_error_message(text!("worldedit.no_block_in_sight")),
     }
 }
 
@@ -495,7 +492,7 @@ pub(super) fn execute_expand(ctx: CommandExecuteContext<'_>) {
         false,
     );
 
-    player.send_worldedit_message(&format!("Region expanded {} block(s).", amount));
+    player.send_worldedit_message(text!("worldedit.expand.done", amount = amount));
 }
 
 pub(super) fn execute_contract(ctx: CommandExecuteContext<'_>) {
@@ -509,7 +506,7 @@ pub(super) fn execute_contract(ctx: CommandExecuteContext<'_>) {
         true,
     );
 
-    player.send_worldedit_message(&format!("Region contracted {} block(s).", amount));
+    player.send_worldedit_message(text!("worldedit.contract.done", amount = amount));
 }
 
 pub(super) fn execute_shift(ctx: CommandExecuteContext<'_>) {
@@ -541,7 +538,7 @@ pub(super) fn execute_shift(ctx: CommandExecuteContext<'_>) {
         BlockFacing::North => move_both_points(0, 0, -(amount as i32)),
     }
 
-    player.send_worldedit_message(&format!("Region shifted {} block(s).", amount));
+    player.send_worldedit_message(text!("worldedit.shift.done", amount = amount));
 }
 
 pub(super) fn execute_flip(ctx: CommandExecuteContext<'_>) {
@@ -549,79 +546,12 @@ pub(super) fn execute_flip(ctx: CommandExecuteContext<'_>) {
 
     let direction = ctx.arguments[0].unwrap_direction();
     let clipboard = ctx.player.worldedit_clipboard.as_ref().unwrap();
-    let size_x = clipboard.size_x;
-    let size_y = clipboard.size_y;
-    let size_z = clipboard.size_z;
-    let volume = size_x * size_y * size_z;
-
-    let flip_pos = |mut pos: BlockPos| {
-        match direction {
-            BlockFacing::East | BlockFacing::West => pos.x = size_x as i32 - 1 - pos.x,
-            BlockFacing::North | BlockFacing::South => pos.z = size_z as i32 - 1 - pos.z,
-            BlockFacing::Up | BlockFacing::Down => pos.y = size_y as i32 - 1 - pos.y,
-        }
-        pos
-    };
-
-    let mut newcpdata = PalettedBitBuffer::new((volume) as usize, 9);
-
-    let mut c_x = 0;
-    let mut c_y = 0;
-    let mut c_z = 0;
-    for i in 0..volume {
-        let BlockPos {
-            x: n_x,
-            y: n_y,
-            z: n_z,
-        } = flip_pos(BlockPos::new(c_x, c_y, c_z));
-        let n_i = (n_y as u32 * size_x * size_z) + (n_z as u32 * size_x) + n_x as u32;
-
-        let mut block = Block::from_id(clipboard.data.get_entry(i as usize));
-        match direction {
-            BlockFacing::East | BlockFacing::West => block.flip(FlipDirection::FlipX),
-            BlockFacing::North | BlockFacing::South => block.flip(FlipDirection::FlipZ),
-            _ => {}
-        }
-        newcpdata.set_entry(n_i as usize, block.get_id());
-
-        // Ok now lets increment the coordinates for the next block
-        c_x += 1;
-
-        if c_x as u32 == size_x {
-            c_x = 0;
-            c_z += 1;
-
-            if c_z as u32 == size_z {
-                c_z = 0;
-                c_y += 1;
-            }
-        }
-    }
-
-    let offset = flip_pos(BlockPos::new(
-        clipboard.offset_x,
-        clipboard.offset_y,
-        clipboard.offset_z,
-    ));
-    let cb = WorldEditClipboard {
-        offset_x: offset.x,
-        offset_y: offset.y,
-        offset_z: offset.z,
-        size_x,
-        size_y,
-        size_z,
-        data: newcpdata,
-        block_entities: clipboard
-            .block_entities
-            .iter()
-            .map(|(pos, e)| (flip_pos(*pos), e.clone()))
-            .collect(),
-    };
+    let cb = flip_clipboard(clipboard, direction);
 
     ctx.player.worldedit_clipboard = Some(cb);
-    ctx.player.send_worldedit_message(&format!(
-        "The clipboard copy has been flipped. ({:?})",
-        start_time.elapsed()
+    ctx.player.send_worldedit_message(text!(
+        "worldedit.flip.done",
+        time = format!("{:?}", start_time.elapsed())
     ));
 }
 
@@ -631,7 +561,7 @@ pub(super) fn execute_rotate(ctx: CommandExecuteContext<'_>) {
     let rotate_amt = match rotate_amt % 360 {
         0 => {
             ctx.player
-                .send_worldedit_message("Successfully rotated by 0! That took a lot of work.");
+                .send_worldedit_message(text!("worldedit.rotate.zero"));
             return;
         }
         90 => RotateAmt::Rotate90,
@@ -639,95 +569,18 @@ pub(super) fn execute_rotate(ctx: CommandExecuteContext<'_>) {
         270 => RotateAmt::Rotate270,
         _ => {
             ctx.player
-                .send_error_message("Rotate amount must be a multiple of 90.");
+                .send_error_message(text!("worldedit.rotate.invalid"));
             return;
         }
     };
 
     let clipboard = ctx.player.worldedit_clipboard.as_ref().unwrap();
-    let size_x = clipboard.size_x;
-    let size_y = clipboard.size_y;
-    let size_z = clipboard.size_z;
-    let volume = size_x * size_y * size_z;
-
-    let (n_size_x, n_size_z) = match rotate_amt {
-        RotateAmt::Rotate90 | RotateAmt::Rotate270 => (size_z, size_x),
-        _ => (size_x, size_z),
-    };
-
-    let rotate_pos = |pos: BlockPos| match rotate_amt {
-        RotateAmt::Rotate90 => BlockPos {
-            x: n_size_x as i32 - 1 - pos.z,
-            y: pos.y,
-            z: pos.x,
-        },
-        RotateAmt::Rotate180 => BlockPos {
-            x: n_size_x as i32 - 1 - pos.x,
-            y: pos.y,
-            z: n_size_z as i32 - 1 - pos.z,
-        },
-        RotateAmt::Rotate270 => BlockPos {
-            x: pos.z,
-            y: pos.y,
-            z: n_size_z as i32 - 1 - pos.x,
-        },
-    };
-
-    let mut newcpdata = PalettedBitBuffer::new((volume) as usize, 9);
-
-    let mut c_x = 0;
-    let mut c_y = 0;
-    let mut c_z = 0;
-    for i in 0..volume {
-        let BlockPos {
-            x: n_x,
-            y: n_y,
-            z: n_z,
-        } = rotate_pos(BlockPos::new(c_x, c_y, c_z));
-        let n_i = (n_y as u32 * n_size_x * n_size_z) + (n_z as u32 * n_size_x) + n_x as u32;
-
-        let mut block = Block::from_id(clipboard.data.get_entry(i as usize));
-        block.rotate(rotate_amt);
-        newcpdata.set_entry(n_i as usize, block.get_id());
-
-        // Ok now lets increment the coordinates for the next block
-        c_x += 1;
-
-        if c_x as u32 == size_x {
-            c_x = 0;
-            c_z += 1;
-
-            if c_z as u32 == size_z {
-                c_z = 0;
-                c_y += 1;
-            }
-        }
-    }
-
-    let offset = rotate_pos(BlockPos::new(
-        clipboard.offset_x,
-        clipboard.offset_y,
-        clipboard.offset_z,
-    ));
-    let cb = WorldEditClipboard {
-        offset_x: offset.x,
-        offset_y: offset.y,
-        offset_z: offset.z,
-        size_x: n_size_x,
-        size_y,
-        size_z: n_size_z,
-        data: newcpdata,
-        block_entities: clipboard
-            .block_entities
-            .iter()
-            .map(|(pos, e)| (rotate_pos(*pos), e.clone()))
-            .collect(),
-    };
+    let cb = rotate_clipboard(clipboard, rotate_amt);
 
     ctx.player.worldedit_clipboard = Some(cb);
-    ctx.player.send_worldedit_message(&format!(
-        "The clipboard copy has been rotated. ({:?})",
-        start_time.elapsed()
+    ctx.player.send_worldedit_message(text!(
+        "worldedit.rotate.done",
+        time = format!("{:?}", start_time.elapsed())
     ));
 }
 
@@ -747,7 +600,7 @@ pub(super) fn execute_help(mut ctx: CommandExecuteContext<'_>) {
     let (command_name, command) = match maybe_command {
         Some(command) => command,
         None => {
-            player.send_error_message(&format!("Unknown command: {}", command_name));
+            player.send_error_message(text!("worldedit.help.unknown", command = command_name));
             return;
         }
     };
@@ -873,6 +726,14 @@ pub(super) fn execute_up(ctx: CommandExecuteContext<'_>) {
     player.teleport(pos);
 }
 
+pub(super) fn execute_jumpto(ctx: CommandExecuteContext<'_>) {
+    jump_to(ctx.plot, ctx.player);
+}
+
+pub(super) fn execute_thru(ctx: CommandExecuteContext<'_>) {
+    pass_through(ctx.plot, ctx.player);
+}
+
 pub(super) fn execute_ascend(ctx: CommandExecuteContext<'_>) {
     let initial_levels = ctx.arguments[0].unwrap_uint();
     let mut levels = initial_levels;
@@ -881,7 +742,7 @@ pub(super) fn execute_ascend(ctx: CommandExecuteContext<'_>) {
     let player_pos = player.pos.block_pos();
     let mut player_y = player_pos.y;
 
-    for (y, _) in (player_y..=PLOT_BLOCK_HEIGHT).enumerate() {
+    for (y, _) in (player_y..=ctx.plot.geometry.block_height).enumerate() {
         if levels == 0 {
             break;
         }
@@ -900,12 +761,15 @@ pub(super) fn execute_ascend(ctx: CommandExecuteContext<'_>) {
     }
 
     if player_y == player_pos.y {
-        player.send_error_message("No free spot above you found.");
+        player.send_error_message(text!("worldedit.ascend.no_spot"));
     } else {
         let mut pos = player.pos;
         pos.y = player_y as f64;
         player.teleport(pos);
-        player.send_worldedit_message(&format!("Ascended {} levels.", initial_levels - levels));
+        player.send_worldedit_message(text!(
+            "worldedit.up.ascended",
+            levels = initial_levels - levels
+        ));
     }
 }
 
@@ -936,12 +800,15 @@ pub(super) fn execute_descend(ctx: CommandExecuteContext<'_>) {
     }
 
     if player_y == player_pos.y {
-        player.send_error_message("No free spot below you found.");
+        player.send_error_message(text!("worldedit.descend.no_spot"));
     } else {
         let mut pos = player.pos;
         pos.y = player_y as f64;
         player.teleport(pos);
-        player.send_worldedit_message(&format!("Descended {} levels.", initial_levels - levels));
+        player.send_worldedit_message(text!(
+            "worldedit.down.descended",
+            levels = initial_levels - levels
+        ));
     }
 }
 
@@ -965,7 +832,7 @@ pub(super) fn execute_rstack(ctx: CommandExecuteContext<'_>) {
             block_pos,
             pos2 + direction * offset,
         ));
-        paste_clipboard(ctx.plot, &clipboard, block_pos, !ctx.has_flag('a'));
+        paste_clipboard(ctx.plot, &clipboard, block_pos, !ctx.has_flag('a'), None);
     }
     undo_cbs.reverse();
     let undo = WorldEditUndo {
@@ -986,9 +853,9 @@ pub(super) fn execute_rstack(ctx: CommandExecuteContext<'_>) {
     let player = ctx.player;
     player.worldedit_undo.push(undo);
 
-    player.send_worldedit_message(&format!(
-        "Your selection was stacked successfully. ({:?})",
-        start_time.elapsed()
+    player.send_worldedit_message(text!(
+        "worldedit.rstack.done",
+        time = format!("{:?}", start_time.elapsed())
     ));
 }
 
@@ -1003,15 +870,15 @@ pub(super) fn execute_update(ctx: CommandExecuteContext<'_>) {
         (first_pos, second_pos)
     } else {
         ctx.player
-            .send_error_message("Your selection is incomplete.");
+            .send_error_message(text!("worldedit.incomplete_selection"));
         return;
     };
 
     update(ctx.plot, first_pos, second_pos);
 
-    ctx.player.send_worldedit_message(&format!(
-        "Your selection was updated sucessfully. ({:?})",
-        start_time.elapsed()
+    ctx.player.send_worldedit_message(text!(
+        "worldedit.update.done",
+        time = format!("{:?}", start_time.elapsed())
     ));
 }
 
@@ -1025,8 +892,10 @@ pub(super) fn execute_replace_container(ctx: CommandExecuteContext<'_>) {
         ContainerType::Furnace => Block::Furnace {},
         ContainerType::Barrel => Block::Barrel {},
         ContainerType::Hopper => Block::Hopper {},
+        ContainerType::TrappedChest => Block::TrappedChest {
+            facing: Default::default(),
+        },
     };
-    let slots = to.num_slots() as u32;
 
     let operation = worldedit_start_operation(ctx.player);
     for x in operation.x_range() {
@@ -1035,10 +904,7 @@ pub(super) fn execute_replace_container(ctx: CommandExecuteContext<'_>) {
                 let pos = BlockPos::new(x, y, z);
                 let block = ctx.plot.get_block(pos);
 
-                if !matches!(
-                    block,
-                    Block::Furnace {} | Block::Barrel {} | Block::Hopper {}
-                ) {
+                if !is_container(block) {
                     continue;
                 }
                 let block_entity = ctx.plot.get_block_entity(pos);
@@ -1051,29 +917,7 @@ pub(super) fn execute_replace_container(ctx: CommandExecuteContext<'_>) {
                     if *ty != from {
                         continue;
                     }
-                    let ss = *comparator_override;
-
-                    let items_needed = match ss {
-                        0 => 0,
-                        15 => slots * 64,
-                        _ => ((32 * slots * ss as u32) as f32 / 7.0 - 1.0).ceil() as u32,
-                    } as usize;
-                    let mut inventory = Vec::new();
-                    for (slot, items_added) in (0..items_needed).step_by(64).enumerate() {
-                        let count = (items_needed - items_added).min(64);
-                        inventory.push(InventoryEntry {
-                            id: Item::Redstone {}.get_id(),
-                            slot: slot as i8,
-                            count: count as i8,
-                            nbt: None,
-                        });
-                    }
-
-                    let new_entity = BlockEntity::Container {
-                        comparator_override: ss,
-                        inventory,
-                        ty: to,
-                    };
+                    let new_entity = BlockEntity::container_with_signal(*comparator_override, to);
                     ctx.plot.set_block_entity(pos, new_entity);
                     ctx.plot.set_block(pos, new_block);
                 }
@@ -1081,9 +925,104 @@ pub(super) fn execute_replace_container(ctx: CommandExecuteContext<'_>) {
         }
     }
 
-    ctx.player.send_worldedit_message(&format!(
-        "Your selection was replaced sucessfully. ({:?})",
-        start_time.elapsed()
+    ctx.player.send_worldedit_message(text!(
+        "worldedit.replace_container.done",
+        time = format!("{:?}", start_time.elapsed())
+    ));
+}
+
+/// Returns the signal strength given to `/container` or `//container`, or `None` after telling
+/// the player what is wrong with it
+fn container_signal_strength(ctx: &CommandExecuteContext<'_>) -> Option<u8> {
+    if ctx.arguments[0].unwrap_string() != "ss" {
+        ctx.player
+            .send_error_message(text!("worldedit.container.usage"));
+        return None;
+    }
+    let ss = ctx.arguments[1].unwrap_uint();
+    if ss > 15 {
+        ctx.player
+            .send_error_message(text!("worldedit.container.invalid_ss"));
+        return None;
+    }
+    Some(ss as u8)
+}
+
+fn is_container(block: Block) -> bool {
+    matches!(
+        block,
+        Block::Furnace {} | Block::Barrel {} | Block::Hopper {} | Block::TrappedChest { .. }
+    )
+}
+
+/// Fills the container at `pos` so comparators read `ss` from it. Returns false if the block
+/// isn't a container.
+fn set_container_signal(world: &mut PlotWorld, pos: BlockPos, ss: u8) -> bool {
+    let block = world.get_block(pos);
+    if !is_container(block) {
+        return false;
+    }
+    let ty = crate::interaction::container_type(block);
+    world.set_block_entity(pos, BlockEntity::container_with_signal(ss, ty));
+    mchprs_redstone::update_surrounding_blocks(world, pos);
+    true
+}
+
+pub(super) fn execute_container(ctx: CommandExecuteContext<'_>) {
+    let Some(ss) = container_signal_strength(&ctx) else {
+        return;
+    };
+    let player = ctx.player;
+    let pos = ray_trace_block(
+        ctx.plot,
+        player.pos,
+        player.pitch as f64,
+        player.yaw as f64,
+        10.0,
+    );
+    let Some(pos) = pos else {
+        player.send_error_message(text!("worldedit.no_block_in_sight"));
+        return;
+    };
+    if !is_container(ctx.plot.get_block(pos)) {
+        player.send_error_message(text!("worldedit.container.not_container"));
+        return;
+    }
+
+    capture_undo(ctx.plot, player, pos, pos);
+    set_container_signal(ctx.plot, pos, ss);
+    player.send_worldedit_message(text!("worldedit.container.set", ss = ss));
+}
+
+pub(super) fn execute_container_selection(ctx: CommandExecuteContext<'_>) {
+    let start_time = Instant::now();
+    let Some(ss) = container_signal_strength(&ctx) else {
+        return;
+    };
+
+    let operation = worldedit_start_operation(ctx.player);
+    capture_undo(
+        ctx.plot,
+        ctx.player,
+        ctx.player.first_position.unwrap(),
+        ctx.player.second_position.unwrap(),
+    );
+    let mut count = 0;
+    for x in operation.x_range() {
+        for y in operation.y_range() {
+            for z in operation.z_range() {
+                if set_container_signal(ctx.plot, BlockPos::new(x, y, z), ss) {
+                    count += 1;
+                }
+            }
+        }
+    }
+
+    ctx.player.send_worldedit_message(text!(
+        "worldedit.container.done",
+        count = count,
+        ss = ss,
+        time = format!("{:?}", start_time.elapsed())
     ));
 }
 
@@ -3,13 +3,16 @@
 mod execute;
 mod schematic;
 
+pub(crate) use schematic::{load_schematic, load_schematic_file, parse_block, save_schematic_file};
+
 use super::commands::CommandFlags;
-use super::{Plot, PlotWorld};
+use super::{Operation, Plot, PlotWorld};
 use crate::player::{PacketSender, Player, PlayerPos};
+use crate::progress::ProgressBar;
 use execute::*;
 use mchprs_blocks::block_entities::{BlockEntity, ContainerType};
-use mchprs_blocks::blocks::Block;
-use mchprs_blocks::{BlockFacing, BlockPos};
+use mchprs_blocks::blocks::{Block, ComparatorMode, FlipDirection, RotateAmt};
+use mchprs_blocks::{BlockFace, BlockFacing, BlockPos};
 use mchprs_network::packets::clientbound::{CCommandsNode, CDeclareCommandsNodeParser};
 use mchprs_utils::map;
 use mchprs_world::storage::PalettedBitBuffer;
@@ -20,8 +23,15 @@ use regex::Regex;
 use rustc_hash::FxHashMap;
 use std::collections::HashMap;
 use std::fmt;
+use std::iter;
 use std::ops::RangeInclusive;
 use std::str::FromStr;
+use tracing::debug_span;
+
+/// Whether [`execute_command`] handles a command, either by its name or by an alias
+pub fn is_command(name: &str) -> bool {
+    name == "/" || COMMANDS.contains_key(name) || ALIASES.contains_key(name)
+}
 
 // Attempts to execute a worldedit command. Returns true of the command was handled.
 // The command is not handled if it is not found in the worldedit commands and alias lists.
@@ -31,14 +41,22 @@ pub fn execute_command(
     command: &str,
     args: &mut Vec<&str>,
 ) -> bool {
+    let _span = debug_span!("worldedit", command).entered();
+    if command == "/" && args.is_empty() {
+        return repeat_last_command(plot, player_idx);
+    }
+    let command_line = iter::once(command)
+        .chain(args.iter().copied())
+        .collect::<Vec<_>>()
+        .join(" ");
     let player = &mut plot.players[player_idx];
-    let command = if let Some(command) = COMMANDS.get(command) {
-        command
+    let (name, command) = if let Some((name, command)) = COMMANDS.get_key_value(command) {
+        (*name, command)
     } else if let Some(command) = ALIASES.get(command) {
         let mut alias: Vec<&str> = command.split(' ').collect();
-        let command = alias.remove(0);
+        let name = alias.remove(0);
         args.append(&mut alias);
-        &COMMANDS[command]
+        (name, &COMMANDS[name])
     } else {
         return false;
     };
@@ -67,23 +85,35 @@ pub fn execute_command(
         let plot_x = plot.world.x;
         let plot_z = plot.world.z;
         if player.first_position.is_none() || player.second_position.is_none() {
-            player.send_error_message("Make a region selection first.");
+            player.send_error_message(text!("worldedit.no_selection"));
             return true;
         }
         let first_pos = player.first_position.unwrap();
         let second_pos = player.second_position.unwrap();
-        if !Plot::in_plot_bounds(plot_x, plot_z, first_pos.x, first_pos.z) {
-            player.send_system_message("First position is outside plot bounds!");
+        if !Plot::in_plot_bounds(
+            &plot.world.geometry,
+            plot_x,
+            plot_z,
+            first_pos.x,
+            first_pos.z,
+        ) {
+            player.send_system_message(text!("worldedit.first_position_outside"));
             return true;
         }
-        if !Plot::in_plot_bounds(plot_x, plot_z, second_pos.x, second_pos.z) {
-            player.send_system_message("Second position is outside plot bounds!");
+        if !Plot::in_plot_bounds(
+            &plot.world.geometry,
+            plot_x,
+            plot_z,
+            second_pos.x,
+            second_pos.z,
+        ) {
+            player.send_system_message(text!("worldedit.second_position_outside"));
             return true;
         }
     }
 
     if command.requires_clipboard && player.worldedit_clipboard.is_none() {
-        player.send_error_message("Your clipboard is empty. Use //copy first.");
+        player.send_error_message(text!("worldedit.clipboard_empty"));
         return true;
     }
 
@@ -101,20 +131,21 @@ pub fn execute_command(
             let flags = arg.chars();
             for flag in flags.skip(1) {
                 if with_argument {
-                    player.send_error_message("Flag with argument must be last in grouping");
+                    player.send_error_message(text!("worldedit.flag_argument_not_last"));
                     return true;
                 }
                 let flag_desc = if let Some(desc) = flag_descs.iter().find(|d| d.letter == flag) {
                     desc
                 } else {
-                    player.send_error_message(&format!("Unknown flag: {}", flag));
+                    player.send_error_message(text!("worldedit.unknown_flag", flag = flag));
                     return true;
                 };
                 if let Some(argument_type) = flag_desc.argument_type {
                     if (i + 1) >= total_arg_count {
-                        player.send_error_message(&format!(
-                            "Missing {:?} argument for {} flag",
-                            argument_type, flag
+                        player.send_error_message(text!(
+                            "worldedit.missing_flag_argument",
+                            argument = format!("{:?}", argument_type),
+                            flag = flag
                         ));
                         return true;
                     }
@@ -131,7 +162,7 @@ pub fn execute_command(
     let arg_descs = command.arguments;
 
     if args.len() > arg_descs.len() {
-        player.send_error_message("Too many arguments.");
+        player.send_error_message(text!("command.too_many_arguments"));
         return true;
     }
 
@@ -146,8 +177,17 @@ pub fn execute_command(
             }
         }
     }
+    if let Some(operation) = command.operation {
+        if !plot.start_operation(player_idx, operation) {
+            return true;
+        }
+    }
     if command.mutates_world {
         plot.reset_redpiler();
+        // Undoing isn't repeated, so `//` still runs the last edit after looking at an undo
+        if !matches!(name, "/undo" | "/redo") {
+            plot.players[player_idx].last_worldedit_command = Some(command_line);
+        }
     }
     let ctx = CommandExecuteContext {
         plot: &mut plot.world,
@@ -156,9 +196,28 @@ pub fn execute_command(
         flags: ctx_flags,
     };
     (command.execute_fn)(ctx);
+    if command.mutates_world {
+        plot.record_snapshot();
+    }
+    if command.operation.is_some() {
+        plot.finish_operation(player_idx);
+    }
     true
 }
 
+/// Runs the last command that changed the world again, with the current selection and clipboard
+fn repeat_last_command(plot: &mut Plot, player_idx: usize) -> bool {
+    let player = &mut plot.players[player_idx];
+    let Some(command_line) = player.last_worldedit_command.clone() else {
+        player.send_error_message(text!("worldedit.repeat.none"));
+        return true;
+    };
+    player.send_system_message(text!("worldedit.repeat.repeating", command = command_line));
+    let mut args: Vec<&str> = command_line.split(' ').collect();
+    let command = args.remove(0);
+    execute_command(plot, player_idx, command, &mut args)
+}
+
 #[derive(Debug)]
 struct ArgumentParseError {
     arg_type: ArgumentType,
@@ -257,7 +316,7 @@ impl Argument {
     fn unwrap_container_type(&self) -> ContainerType {
         match self {
             Argument::ContainerType(val) => *val,
-            _ => panic!("Container type must be one of [barrel, furnace, hopper]"),
+            _ => panic!("Container type must be one of [barrel, furnace, hopper, trapped_chest]"),
         }
     }
 
@@ -428,6 +487,8 @@ struct WorldeditCommand {
     permission_node: &'static str,
     mutates_world: bool,
     normal_completions: bool,
+    /// Heavy commands can be given a cooldown, and only one can run at a time
+    operation: Option<Operation>,
 }
 
 impl Default for WorldeditCommand {
@@ -442,6 +503,7 @@ impl Default for WorldeditCommand {
             permission_node: "",
             mutates_world: true,
             normal_completions: true,
+            operation: None,
         }
     }
 }
@@ -477,6 +539,20 @@ static COMMANDS: Lazy<HashMap<&'static str, WorldeditCommand>> = Lazy::new(|| {
             mutates_world: false,
             ..Default::default()
         },
+        "jumpto" => WorldeditCommand {
+            execute_fn: execute_jumpto,
+            description: "Teleport to the block you are looking at",
+            permission_node: "worldedit.navigation.jumpto.command",
+            mutates_world: false,
+            ..Default::default()
+        },
+        "thru" => WorldeditCommand {
+            execute_fn: execute_thru,
+            description: "Pass through the wall you are facing",
+            permission_node: "worldedit.navigation.thru.command",
+            mutates_world: false,
+            ..Default::default()
+        },
         "/pos1" => WorldeditCommand {
             execute_fn: execute_pos1,
             description: "Set position 1",
@@ -556,6 +632,7 @@ static COMMANDS: Lazy<HashMap<&'static str, WorldeditCommand>> = Lazy::new(|| {
                 flag!('u', None, "Also update all affected blocks"),
             ],
             permission_node: "worldedit.clipboard.paste",
+            operation: Some(Operation::Paste),
             ..Default::default()
         },
         "/undo" => WorldeditCommand {
@@ -714,6 +791,35 @@ static COMMANDS: Lazy<HashMap<&'static str, WorldeditCommand>> = Lazy::new(|| {
             ],
             ..Default::default()
         },
+        "blockcount" => WorldeditCommand {
+            requires_positions: true,
+            execute_fn: execute_blockcount,
+            description: "Counts the redstone components in the selection and estimates how much they cost to tick",
+            permission_node: "mchprs.we.blockcount",
+            mutates_world: false,
+            ..Default::default()
+        },
+        "container" => WorldeditCommand {
+            arguments: &[
+                argument!("property", String, "The property to set, ss for the signal strength"),
+                argument!("value", UnsignedInteger, "The signal strength, from 0 to 15"),
+            ],
+            execute_fn: execute_container,
+            description: "Fills the container you are looking at to give a signal strength",
+            permission_node: "mchprs.we.container",
+            ..Default::default()
+        },
+        "/container" => WorldeditCommand {
+            arguments: &[
+                argument!("property", String, "The property to set, ss for the signal strength"),
+                argument!("value", UnsignedInteger, "The signal strength, from 0 to 15"),
+            ],
+            requires_positions: true,
+            execute_fn: execute_container_selection,
+            description: "Fills every container in the selection to give a signal strength",
+            permission_node: "mchprs.we.container",
+            ..Default::default()
+        },
         "/help" => WorldeditCommand {
             arguments: &[
                 argument!("command", String, "Command to retrieve help for"),
@@ -750,6 +856,7 @@ static ALIASES: Lazy<HashMap<&'static str, &'static str>> = Lazy::new(|| {
         "u" => "up",
         "desc" => "descend",
         "asc" => "ascend",
+        "j" => "jumpto",
         "/1" => "/pos1",
         "/2" => "/pos2",
         "/c" => "/copy",
@@ -928,6 +1035,13 @@ impl WorldEditOperation {
         self.blocks_updated
     }
 
+    /// How far along the operation is when it reaches the slice at `x`
+    fn progress(&self, x: i32) -> f32 {
+        let start = *self.x_range.start();
+        let len = self.x_range.end() - start + 1;
+        (x - start) as f32 / len as f32
+    }
+
     fn x_range(&self) -> RangeInclusive<i32> {
         self.x_range.clone()
     }
@@ -977,14 +1091,73 @@ pub fn ray_trace_block(
     None
 }
 
+/// How far `/thru` looks for a free spot on the other side of a wall
+const THRU_DISTANCE: i32 = 64;
+
+fn is_free(world: &PlotWorld, pos: BlockPos) -> bool {
+    let height = world.geometry.block_height;
+    (0..height - 1).contains(&pos.y)
+        && matches!(world.get_block(pos), Block::Air {})
+        && matches!(world.get_block(pos.offset(BlockFace::Top)), Block::Air {})
+}
+
+fn teleport_to_block(player: &mut Player, pos: BlockPos) {
+    let pos = PlayerPos::new(pos.x as f64 + 0.5, pos.y as f64, pos.z as f64 + 0.5);
+    player.teleport(pos);
+}
+
+/// Teleports the player on top of the block they are looking at
+pub fn jump_to(world: &PlotWorld, player: &mut Player) {
+    let target = ray_trace_block(
+        world,
+        player.pos,
+        player.pitch as f64,
+        player.yaw as f64,
+        300.0,
+    );
+    let Some(target) = target else {
+        player.send_error_message(text!("worldedit.no_block_in_sight"));
+        return;
+    };
+    let height = world.geometry.block_height;
+    let spot = (target.y + 1..height)
+        .map(|y| BlockPos::new(target.x, y, target.z))
+        .find(|&pos| is_free(world, pos));
+    match spot {
+        Some(pos) => {
+            teleport_to_block(player, pos);
+            player.send_worldedit_message(text!("worldedit.jumpto.done"));
+        }
+        None => player.send_error_message(text!("worldedit.jumpto.no_spot")),
+    }
+}
+
+/// Moves the player through the wall in front of them, to the first free spot behind it
+pub fn pass_through(world: &PlotWorld, player: &mut Player) {
+    let facing = player.get_facing();
+    let start = player.pos.block_pos();
+    let mut passed_wall = false;
+    for distance in 1..=THRU_DISTANCE {
+        let pos = facing.offset_pos(start, distance);
+        if !is_free(world, pos) {
+            passed_wall = true;
+        } else if passed_wall {
+            teleport_to_block(player, pos);
+            player.send_worldedit_message(text!("worldedit.thru.done"));
+            return;
+        }
+    }
+    player.send_error_message(text!("worldedit.thru.no_spot"));
+}
+
 fn worldedit_start_operation(player: &mut Player) -> WorldEditOperation {
     let first_pos = player.first_position.unwrap();
     let second_pos = player.second_position.unwrap();
     WorldEditOperation::new(first_pos, second_pos)
 }
 
-fn create_clipboard(
-    plot: &mut PlotWorld,
+pub(crate) fn create_clipboard(
+    world: &impl World,
     origin: BlockPos,
     first_pos: BlockPos,
     second_pos: BlockPos,
@@ -1010,10 +1183,10 @@ fn create_clipboard(
         for z in start_pos.z..=end_pos.z {
             for x in start_pos.x..=end_pos.x {
                 let pos = BlockPos::new(x, y, z);
-                let id = plot.get_block_raw(pos);
-                let block = plot.get_block(BlockPos::new(x, y, z));
+                let id = world.get_block_raw(pos);
+                let block = world.get_block(BlockPos::new(x, y, z));
                 if block.has_block_entity() {
-                    if let Some(block_entity) = plot.get_block_entity(pos) {
+                    if let Some(block_entity) = world.get_block_entity(pos) {
                         cb.block_entities
                             .insert(pos - start_pos, block_entity.clone());
                     }
@@ -1026,6 +1199,243 @@ fn create_clipboard(
     cb
 }
 
+/// How many of each redstone component are in a selection, for `/blockcount`
+#[derive(Debug, Default, PartialEq, Eq)]
+struct ComponentCount {
+    /// By delay, from 1 to 4 ticks
+    repeaters: [u32; 4],
+    compare_comparators: u32,
+    subtract_comparators: u32,
+    torches: u32,
+    wires: u32,
+    observers: u32,
+    lamps: u32,
+    /// Levers, buttons, pressure plates and redstone blocks
+    sources: u32,
+    /// Note blocks and iron trapdoors
+    other: u32,
+}
+
+impl ComponentCount {
+    fn of(world: &impl World, first_pos: BlockPos, second_pos: BlockPos) -> ComponentCount {
+        let start = first_pos.min(second_pos);
+        let end = first_pos.max(second_pos);
+        let mut count = ComponentCount::default();
+        for y in start.y..=end.y {
+            for z in start.z..=end.z {
+                for x in start.x..=end.x {
+                    match world.get_block(BlockPos::new(x, y, z)) {
+                        Block::RedstoneRepeater { repeater } => {
+                            count.repeaters[repeater.delay.clamp(1, 4) as usize - 1] += 1
+                        }
+                        Block::RedstoneComparator { comparator } => match comparator.mode {
+                            ComparatorMode::Compare => count.compare_comparators += 1,
+                            ComparatorMode::Subtract => count.subtract_comparators += 1,
+                        },
+                        Block::RedstoneTorch { .. } | Block::RedstoneWallTorch { .. } => {
+                            count.torches += 1
+                        }
+                        Block::RedstoneWire { .. } => count.wires += 1,
+                        Block::Observer { .. } => count.observers += 1,
+                        Block::RedstoneLamp { .. } => count.lamps += 1,
+                        Block::Lever { .. }
+                        | Block::StoneButton { .. }
+                        | Block::StonePressurePlate { .. }
+                        | Block::RedstoneBlock {} => count.sources += 1,
+                        Block::NoteBlock { .. } | Block::IronTrapdoor { .. } => count.other += 1,
+                        _ => {}
+                    }
+                }
+            }
+        }
+        count
+    }
+
+    fn comparators(&self) -> u32 {
+        self.compare_comparators + self.subtract_comparators
+    }
+
+    fn total(&self) -> u32 {
+        self.repeaters.iter().sum::<u32>()
+            + self.comparators()
+            + self.torches
+            + self.wires
+            + self.observers
+            + self.lamps
+            + self.sources
+            + self.other
+    }
+
+    /// Roughly how many block updates the base redstone implementation does in a tick where
+    /// every component changes. Diodes update the block they face and its neighbors, torches
+    /// their neighbors and the neighbors of the block above them, and wire updates everything up
+    /// to 2 blocks away.
+    fn updates_per_tick(&self) -> u32 {
+        (self.repeaters.iter().sum::<u32>() + self.comparators() + self.observers) * 7
+            + self.torches * 12
+            + self.wires * 42
+            + (self.lamps + self.sources + self.other) * 6
+    }
+}
+
+pub(crate) fn flip_clipboard(
+    clipboard: &WorldEditClipboard,
+    direction: BlockFacing,
+) -> WorldEditClipboard {
+    let size_x = clipboard.size_x;
+    let size_y = clipboard.size_y;
+    let size_z = clipboard.size_z;
+    let volume = size_x * size_y * size_z;
+
+    let flip_pos = |mut pos: BlockPos| {
+        match direction {
+            BlockFacing::East | BlockFacing::West => pos.x = size_x as i32 - 1 - pos.x,
+            BlockFacing::North | BlockFacing::South => pos.z = size_z as i32 - 1 - pos.z,
+            BlockFacing::Up | BlockFacing::Down => pos.y = size_y as i32 - 1 - pos.y,
+        }
+        pos
+    };
+
+    let mut newcpdata = PalettedBitBuffer::new((volume) as usize, 9);
+
+    let mut c_x = 0;
+    let mut c_y = 0;
+    let mut c_z = 0;
+    for i in 0..volume {
+        let BlockPos {
+            x: n_x,
+            y: n_y,
+            z: n_z,
+        } = flip_pos(BlockPos::new(c_x, c_y, c_z));
+        let n_i = (n_y as u32 * size_x * size_z) + (n_z as u32 * size_x) + n_x as u32;
+
+        let mut block = Block::from_id(clipboard.data.get_entry(i as usize));
+        match direction {
+            BlockFacing::East | BlockFacing::West => block.flip(FlipDirection::FlipX),
+            BlockFacing::North | BlockFacing::South => block.flip(FlipDirection::FlipZ),
+            _ => {}
+        }
+        newcpdata.set_entry(n_i as usize, block.get_id());
+
+        // Ok now lets increment the coordinates for the next block
+        c_x += 1;
+
+        if c_x as u32 == size_x {
+            c_x = 0;
+            c_z += 1;
+
+            if c_z as u32 == size_z {
+                c_z = 0;
+                c_y += 1;
+            }
+        }
+    }
+
+    let offset = flip_pos(BlockPos::new(
+        clipboard.offset_x,
+        clipboard.offset_y,
+        clipboard.offset_z,
+    ));
+    WorldEditClipboard {
+        offset_x: offset.x,
+        offset_y: offset.y,
+        offset_z: offset.z,
+        size_x,
+        size_y,
+        size_z,
+        data: newcpdata,
+        block_entities: clipboard
+            .block_entities
+            .iter()
+            .map(|(pos, e)| (flip_pos(*pos), e.clone()))
+            .collect(),
+    }
+}
+
+pub(crate) fn rotate_clipboard(
+    clipboard: &WorldEditClipboard,
+    rotate_amt: RotateAmt,
+) -> WorldEditClipboard {
+    let size_x = clipboard.size_x;
+    let size_y = clipboard.size_y;
+    let size_z = clipboard.size_z;
+    let volume = size_x * size_y * size_z;
+
+    let (n_size_x, n_size_z) = match rotate_amt {
+        RotateAmt::Rotate90 | RotateAmt::Rotate270 => (size_z, size_x),
+        _ => (size_x, size_z),
+    };
+
+    let rotate_pos = |pos: BlockPos| match rotate_amt {
+        RotateAmt::Rotate90 => BlockPos {
+            x: n_size_x as i32 - 1 - pos.z,
+            y: pos.y,
+            z: pos.x,
+        },
+        RotateAmt::Rotate180 => BlockPos {
+            x: n_size_x as i32 - 1 - pos.x,
+            y: pos.y,
+            z: n_size_z as i32 - 1 - pos.z,
+        },
+        RotateAmt::Rotate270 => BlockPos {
+            x: pos.z,
+            y: pos.y,
+            z: n_size_z as i32 - 1 - pos.x,
+        },
+    };
+
+    let mut newcpdata = PalettedBitBuffer::new((volume) as usize, 9);
+
+    let mut c_x = 0;
+    let mut c_y = 0;
+    let mut c_z = 0;
+    for i in 0..volume {
+        let BlockPos {
+            x: n_x,
+            y: n_y,
+            z: n_z,
+        } = rotate_pos(BlockPos::new(c_x, c_y, c_z));
+        let n_i = (n_y as u32 * n_size_x * n_size_z) + (n_z as u32 * n_size_x) + n_x as u32;
+
+        let mut block = Block::from_id(clipboard.data.get_entry(i as usize));
+        block.rotate(rotate_amt);
+        newcpdata.set_entry(n_i as usize, block.get_id());
+
+        // Ok now lets increment the coordinates for the next block
+        c_x += 1;
+
+        if c_x as u32 == size_x {
+            c_x = 0;
+            c_z += 1;
+
+            if c_z as u32 == size_z {
+                c_z = 0;
+                c_y += 1;
+            }
+        }
+    }
+
+    let offset = rotate_pos(BlockPos::new(
+        clipboard.offset_x,
+        clipboard.offset_y,
+        clipboard.offset_z,
+    ));
+    WorldEditClipboard {
+        offset_x: offset.x,
+        offset_y: offset.y,
+        offset_z: offset.z,
+        size_x: n_size_x,
+        size_y,
+        size_z: n_size_z,
+        data: newcpdata,
+        block_entities: clipboard
+            .block_entities
+            .iter()
+            .map(|(pos, e)| (rotate_pos(*pos), e.clone()))
+            .collect(),
+    }
+}
+
 fn clear_area(plot: &mut PlotWorld, first_pos: BlockPos, second_pos: BlockPos) {
     let start_pos = first_pos.min(second_pos);
     let end_pos = first_pos.max(second_pos);
@@ -1049,7 +1459,13 @@ fn clear_area(plot: &mut PlotWorld, first_pos: BlockPos, second_pos: BlockPos) {
     }
 }
 
-fn paste_clipboard(plot: &mut PlotWorld, cb: &WorldEditClipboard, pos: BlockPos, ignore_air: bool) {
+pub(crate) fn paste_clipboard(
+    world: &mut impl World,
+    cb: &WorldEditClipboard,
+    pos: BlockPos,
+    ignore_air: bool,
+    mut progress: Option<&mut ProgressBar>,
+) {
     let offset_x = pos.x - cb.offset_x;
     let offset_y = pos.y - cb.offset_y;
     let offset_z = pos.z - cb.offset_z;
@@ -1062,6 +1478,9 @@ fn paste_clipboard(plot: &mut PlotWorld, cb: &WorldEditClipboard, pos: BlockPos,
     let entries = cb.data.entries();
     // I have no clue if these clones are going to cost anything noticeable.
     'top_loop: for y in y_range {
+        if let Some(progress) = &mut progress {
+            progress.set_progress((y - offset_y) as f32 / cb.size_y as f32);
+        }
         for z in z_range.clone() {
             for x in x_range.clone() {
                 if i >= entries {
@@ -1072,13 +1491,13 @@ fn paste_clipboard(plot: &mut PlotWorld, cb: &WorldEditClipboard, pos: BlockPos,
                 if ignore_air && entry == 0 {
                     continue;
                 }
-                plot.set_block_raw(BlockPos::new(x, y, z), entry);
+                world.set_block_raw(BlockPos::new(x, y, z), entry);
             }
         }
     }
 
     // Send block changes before we send block entity data, otherwise it'll be ignored
-    plot.flush_block_changes();
+    world.flush_block_changes();
 
     for (pos, block_entity) in &cb.block_entities {
         let new_pos = BlockPos {
@@ -1086,8 +1505,72 @@ fn paste_clipboard(plot: &mut PlotWorld, cb: &WorldEditClipboard, pos: BlockPos,
             y: pos.y + offset_y,
             z: pos.z + offset_z,
         };
-        plot.set_block_entity(new_pos, block_entity.clone());
+        world.set_block_entity(new_pos, block_entity.clone());
+    }
+}
+
+/// Pastes the selection between `pos1` and `pos2` `amount` more times in a row in `direction`,
+/// returning the clipboards that will undo each copy
+fn stack(
+    world: &mut impl World,
+    pos1: BlockPos,
+    pos2: BlockPos,
+    amount: u32,
+    direction: BlockFacing,
+    ignore_air: bool,
+    mut progress: Option<&mut ProgressBar>,
+) -> Vec<WorldEditClipboard> {
+    let clipboard = create_clipboard(world, pos1, pos1, pos2);
+    let stack_offset = match direction {
+        BlockFacing::North | BlockFacing::South => clipboard.size_z,
+        BlockFacing::East | BlockFacing::West => clipboard.size_x,
+        BlockFacing::Up | BlockFacing::Down => clipboard.size_y,
+    };
+    let mut undo_cbs = Vec::new();
+    for i in 1..amount + 1 {
+        if let Some(progress) = &mut progress {
+            progress.set_progress((i - 1) as f32 / amount as f32);
+        }
+        let offset = (i * stack_offset) as i32;
+        let block_pos = direction.offset_pos(pos1, offset);
+        undo_cbs.push(create_clipboard(
+            world,
+            pos1,
+            block_pos,
+            direction.offset_pos(pos2, offset),
+        ));
+        paste_clipboard(world, &clipboard, block_pos, ignore_air, None);
+    }
+    undo_cbs
+}
+
+/// Pastes the clipboards of an undo or redo back, returning the opposite that pastes back what
+/// they replaced
+fn restore(world: &mut impl World, undo: WorldEditUndo) -> WorldEditUndo {
+    let redo = WorldEditUndo {
+        clipboards: undo
+            .clipboards
+            .iter()
+            .map(|clipboard| {
+                let first_pos = BlockPos {
+                    x: undo.pos.x - clipboard.offset_x,
+                    y: undo.pos.y - clipboard.offset_y,
+                    z: undo.pos.z - clipboard.offset_z,
+                };
+                let second_pos = BlockPos {
+                    x: first_pos.x + clipboard.size_x as i32 - 1,
+                    y: first_pos.y + clipboard.size_y as i32 - 1,
+                    z: first_pos.z + clipboard.size_z as i32 - 1,
+                };
+                create_clipboard(world, undo.pos, first_pos, second_pos)
+            })
+            .collect(),
+        ..undo
+    };
+    for clipboard in &undo.clipboards {
+        paste_clipboard(world, clipboard, undo.pos, false, None);
     }
+    redo
 }
 
 fn capture_undo(
@@ -1154,10 +1637,10 @@ fn expand_selection(player: &mut Player, amount: BlockPos, contract: bool) {
     }
 }
 
-fn update(plot: &mut PlotWorld, first_pos: BlockPos, second_pos: BlockPos) {
-    for_each_block_mut_optimized(plot, first_pos, second_pos, |plot, pos| {
-        let block = plot.get_block(pos);
-        mchprs_redstone::update(block, plot, pos);
+fn update(world: &mut impl World, first_pos: BlockPos, second_pos: BlockPos) {
+    for_each_block_mut_optimized(world, first_pos, second_pos, |world, pos| {
+        let block = world.get_block(pos);
+        mchprs_redstone::update(block, world, pos);
     });
 }
 
@@ -1219,3 +1702,358 @@ pub fn add_command_completions(nodes: &mut Vec<CCommandsNode>) {
         });
     }
 }
+
+#[test]
+fn clipboard_capture() {
+    use mchprs_blocks::blocks::{ComparatorMode, RedstoneComparator};
+    use mchprs_blocks::BlockDirection;
+    use mchprs_world::memory::MemoryWorld;
+
+    let mut world = MemoryWorld::new(1, 1, 1);
+    let comparator = RedstoneComparator::new(BlockDirection::North, ComparatorMode::Compare, false);
+    world.set_block(BlockPos::new(3, 2, 1), Block::Stone {});
+    world.set_block(
+        BlockPos::new(4, 2, 1),
+        Block::RedstoneComparator { comparator },
+    );
+    world.set_block_entity(
+        BlockPos::new(4, 2, 1),
+        BlockEntity::Comparator { output_strength: 7 },
+    );
+
+    let origin = BlockPos::new(3, 0, 3);
+    let cb = create_clipboard(
+        &world,
+        origin,
+        BlockPos::new(4, 2, 1),
+        BlockPos::new(3, 3, 2),
+    );
+    assert_eq!((cb.size_x, cb.size_y, cb.size_z), (2, 2, 2));
+    assert_eq!((cb.offset_x, cb.offset_y, cb.offset_z), (0, -2, 2));
+    // Blocks are stored x first, then z, then y
+    assert_eq!(cb.data.get_entry(0), (Block::Stone {}).get_id());
+    assert_eq!(
+        cb.data.get_entry(1),
+        (Block::RedstoneComparator { comparator }).get_id()
+    );
+    assert_eq!(cb.data.get_entry(2), 0);
+    assert!(matches!(
+        cb.block_entities.get(&BlockPos::new(1, 0, 0)),
+        Some(BlockEntity::Comparator { output_strength: 7 })
+    ));
+}
+
+#[test]
+fn component_count() {
+    use mchprs_blocks::blocks::{RedstoneComparator, RedstoneRepeater};
+    use mchprs_blocks::BlockDirection;
+    use mchprs_world::memory::MemoryWorld;
+
+    let mut world = MemoryWorld::new(1, 1, 1);
+    for (x, delay) in [(0, 1), (1, 1), (2, 4)] {
+        let repeater = RedstoneRepeater {
+            delay,
+            ..Default::default()
+        };
+        world.set_block(BlockPos::new(x, 0, 0), Block::RedstoneRepeater { repeater });
+    }
+    let comparator =
+        RedstoneComparator::new(BlockDirection::North, ComparatorMode::Subtract, false);
+    world.set_block(
+        BlockPos::new(3, 0, 0),
+        Block::RedstoneComparator { comparator },
+    );
+    world.set_block(BlockPos::new(4, 0, 0), Block::RedstoneTorch { lit: true });
+    world.set_block(BlockPos::new(5, 0, 0), Block::Stone {});
+    // Outside of the selection
+    world.set_block(BlockPos::new(0, 1, 0), Block::RedstoneTorch { lit: true });
+
+    let count = ComponentCount::of(&world, BlockPos::new(5, 0, 0), BlockPos::new(0, 0, 0));
+    assert_eq!(
+        count,
+        ComponentCount {
+            repeaters: [2, 0, 0, 1],
+            subtract_comparators: 1,
+            torches: 1,
+            ..Default::default()
+        }
+    );
+    assert_eq!(count.total(), 5);
+    assert_eq!(count.updates_per_tick(), 4 * 7 + 12);
+}
+
+#[cfg(test)]
+mod proptests {
+    use super::*;
+    use mchprs_blocks::blocks::{ComparatorMode, Lever, LeverFace};
+    use mchprs_blocks::blocks::{RedstoneComparator, RedstoneRepeater};
+    use mchprs_blocks::BlockDirection;
+    use mchprs_world::memory::MemoryWorld;
+    use proptest::prelude::*;
+
+    const WORLD_MIN: BlockPos = BlockPos::new(-32, 0, -32);
+    const WORLD_MAX: BlockPos = BlockPos::new(31, 31, 31);
+
+    /// Goes from -32 to 31 along x and z, so selections cross chunk borders at negative
+    /// coordinates and at 0
+    fn empty_world() -> MemoryWorld {
+        MemoryWorld::with_origin(-2, -2, 4, 4, 2)
+    }
+
+    /// Air, full blocks and blocks that face each direction, so rotating and flipping changes
+    /// them
+    fn palette() -> Vec<u32> {
+        let mut blocks = vec![Block::Air {}, Block::Stone {}, Block::Glass {}];
+        for facing in [
+            BlockDirection::North,
+            BlockDirection::East,
+            BlockDirection::South,
+            BlockDirection::West,
+        ] {
+            blocks.push(Block::RedstoneRepeater {
+                repeater: RedstoneRepeater {
+                    facing,
+                    ..Default::default()
+                },
+            });
+            blocks.push(Block::RedstoneComparator {
+                comparator: RedstoneComparator::new(facing, ComparatorMode::Subtract, false),
+            });
+            blocks.push(Block::Lever {
+                lever: Lever::new(LeverFace::Wall, facing, false),
+            });
+        }
+        blocks.iter().map(|block| block.get_id()).collect()
+    }
+
+    fn blocks() -> impl Strategy<Value = Vec<u32>> {
+        prop::collection::vec(prop::sample::select(palette()), 64)
+    }
+
+    /// Two corners up to 4 blocks apart along each axis, with room for 3 more copies of the
+    /// selection in every direction
+    fn selection() -> impl Strategy<Value = (BlockPos, BlockPos)> {
+        (
+            -16..=0i32,
+            15..=16i32,
+            -16..=0i32,
+            -3..=3i32,
+            -3..=3i32,
+            -3..=3i32,
+        )
+            .prop_map(|(x, y, z, dx, dy, dz)| {
+                (
+                    BlockPos::new(x, y, z),
+                    BlockPos::new(x + dx, y + dy, z + dz),
+                )
+            })
+    }
+
+    fn position() -> impl Strategy<Value = BlockPos> {
+        (-32..32i32, 0..32i32, -32..32i32).prop_map(|(x, y, z)| BlockPos::new(x, y, z))
+    }
+
+    fn direction() -> impl Strategy<Value = BlockFacing> {
+        prop::sample::select(vec![
+            BlockFacing::North,
+            BlockFacing::South,
+            BlockFacing::East,
+            BlockFacing::West,
+            BlockFacing::Up,
+            BlockFacing::Down,
+        ])
+    }
+
+    /// Returns the blocks between the corners, in the same order as a clipboard has them
+    fn contents(world: &impl World, first: BlockPos, second: BlockPos) -> Vec<u32> {
+        let (start, end) = (first.min(second), first.max(second));
+        let mut blocks = Vec::new();
+        for y in start.y..=end.y {
+            for z in start.z..=end.z {
+                for x in start.x..=end.x {
+                    blocks.push(world.get_block_raw(BlockPos::new(x, y, z)));
+                }
+            }
+        }
+        blocks
+    }
+
+    fn filled((first, second): (BlockPos, BlockPos), blocks: &[u32]) -> MemoryWorld {
+        let mut world = empty_world();
+        let (start, end) = (first.min(second), first.max(second));
+        let mut i = 0;
+        for y in start.y..=end.y {
+            for z in start.z..=end.z {
+                for x in start.x..=end.x {
+                    world.set_block_raw(BlockPos::new(x, y, z), blocks[i % blocks.len()]);
+                    i += 1;
+                }
+            }
+        }
+        world
+    }
+
+    proptest! {
+        #[test]
+        fn rotating_four_times_changes_nothing(
+            selection in selection(),
+            blocks in blocks(),
+            origin in position(),
+        ) {
+            let (first, second) = selection;
+            let world = filled(selection, &blocks);
+            let cb = create_clipboard(&world, origin, first, second);
+
+            let once = rotate_clipboard(&cb, RotateAmt::Rotate90);
+            prop_assert_eq!((once.size_x, once.size_z), (cb.size_z, cb.size_x));
+            let mut rotated = once;
+            for _ in 0..3 {
+                rotated = rotate_clipboard(&rotated, RotateAmt::Rotate90);
+            }
+            prop_assert_eq!(
+                (rotated.offset_x, rotated.offset_y, rotated.offset_z),
+                (cb.offset_x, cb.offset_y, cb.offset_z)
+            );
+
+            let mut pasted = empty_world();
+            paste_clipboard(&mut pasted, &rotated, origin, false, None);
+            prop_assert_eq!(
+                contents(&pasted, WORLD_MIN, WORLD_MAX),
+                contents(&world, WORLD_MIN, WORLD_MAX)
+            );
+        }
+
+        #[test]
+        fn rotating_by_180_is_flipping_twice(
+            selection in selection(),
+            blocks in blocks(),
+            origin in position(),
+        ) {
+            let (first, second) = selection;
+            let world = filled(selection, &blocks);
+            let cb = create_clipboard(&world, origin, first, second);
+
+            let rotated = rotate_clipboard(&cb, RotateAmt::Rotate180);
+            let flipped = flip_clipboard(
+                &flip_clipboard(&cb, BlockFacing::East),
+                BlockFacing::North,
+            );
+            let mut rotated_world = empty_world();
+            paste_clipboard(&mut rotated_world, &rotated, origin, false, None);
+            let mut flipped_world = empty_world();
+            paste_clipboard(&mut flipped_world, &flipped, origin, false, None);
+            prop_assert_eq!(
+                contents(&rotated_world, WORLD_MIN, WORLD_MAX),
+                contents(&flipped_world, WORLD_MIN, WORLD_MAX)
+            );
+        }
+
+        #[test]
+        fn flipping_twice_changes_nothing(
+            selection in selection(),
+            blocks in blocks(),
+            origin in position(),
+            direction in direction(),
+        ) {
+            let (first, second) = selection;
+            let world = filled(selection, &blocks);
+            let cb = create_clipboard(&world, origin, first, second);
+            let flipped = flip_clipboard(&flip_clipboard(&cb, direction), direction);
+
+            let mut pasted = empty_world();
+            paste_clipboard(&mut pasted, &flipped, origin, false, None);
+            prop_assert_eq!(
+                contents(&pasted, WORLD_MIN, WORLD_MAX),
+                contents(&world, WORLD_MIN, WORLD_MAX)
+            );
+        }
+
+        #[test]
+        fn undoing_a_paste_restores_the_region(
+            selection in selection(),
+            blocks in blocks(),
+            copied in selection(),
+            copied_blocks in blocks(),
+            origin in position(),
+            pos in position(),
+        ) {
+            let mut world = filled(selection, &blocks);
+            let before = contents(&world, WORLD_MIN, WORLD_MAX);
+            let cb = create_clipboard(&filled(copied, &copied_blocks), origin, copied.0, copied.1);
+
+            // The same region execute_paste saves for undoing
+            let first_pos = BlockPos::new(
+                pos.x - cb.offset_x,
+                pos.y - cb.offset_y,
+                pos.z - cb.offset_z,
+            );
+            let second_pos = BlockPos::new(
+                first_pos.x + cb.size_x as i32,
+                first_pos.y + cb.size_y as i32,
+                first_pos.z + cb.size_z as i32,
+            );
+            let undo = WorldEditUndo {
+                clipboards: vec![create_clipboard(&world, first_pos, first_pos, second_pos)],
+                pos: first_pos,
+                plot_x: 0,
+                plot_z: 0,
+            };
+            paste_clipboard(&mut world, &cb, pos, false, None);
+            let after = contents(&world, WORLD_MIN, WORLD_MAX);
+
+            let redo = restore(&mut world, undo);
+            prop_assert_eq!(contents(&world, WORLD_MIN, WORLD_MAX), before);
+            restore(&mut world, redo);
+            prop_assert_eq!(contents(&world, WORLD_MIN, WORLD_MAX), after);
+        }
+
+        #[test]
+        fn stacking_repeats_the_selection(
+            selection in selection(),
+            blocks in blocks(),
+            amount in 1..=3u32,
+            direction in direction(),
+        ) {
+            let (first, second) = selection;
+            let mut world = filled(selection, &blocks);
+            let before = contents(&world, WORLD_MIN, WORLD_MAX);
+            let original = contents(&world, first, second);
+
+            let undo_cbs = stack(&mut world, first, second, amount, direction, false, None);
+            let volume: u32 = undo_cbs
+                .iter()
+                .map(|cb| cb.size_x * cb.size_y * cb.size_z)
+                .sum();
+            prop_assert_eq!(volume as usize, amount as usize * original.len());
+
+            let size = match direction {
+                BlockFacing::North | BlockFacing::South => (first.z - second.z).abs() + 1,
+                BlockFacing::East | BlockFacing::West => (first.x - second.x).abs() + 1,
+                BlockFacing::Up | BlockFacing::Down => (first.y - second.y).abs() + 1,
+            };
+            for i in 1..=amount as i32 {
+                let copy = contents(
+                    &world,
+                    direction.offset_pos(first, i * size),
+                    direction.offset_pos(second, i * size),
+                );
+                prop_assert_eq!(&copy, &original);
+            }
+            // Nothing besides the copies was changed
+            let non_air = |blocks: &[u32]| blocks.iter().filter(|&&id| id != 0).count();
+            prop_assert_eq!(
+                non_air(&contents(&world, WORLD_MIN, WORLD_MAX)),
+                (amount as usize + 1) * non_air(&original)
+            );
+
+            let undo = WorldEditUndo {
+                clipboards: undo_cbs,
+                pos: first,
+                plot_x: 0,
+                plot_z: 0,
+            };
+            restore(&mut world, undo);
+            prop_assert_eq!(contents(&world, WORLD_MIN, WORLD_MAX), before);
+        }
+    }
+}
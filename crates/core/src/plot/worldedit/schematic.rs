@@ -1,5 +1,8 @@
 //! This implements Sponge Schematic Specification ver. 2
 //! https://github.com/SpongePowered/Schematic-Specification/blob/master/versions/schematic-2.md
+//! Files ending in `.litematic` are handled by the [`litematic`] module instead.
+
+mod litematic;
 
 use super::WorldEditClipboard;
 use crate::server::MC_DATA_VERSION;
@@ -14,7 +17,7 @@ use regex::Regex;
 use rustc_hash::FxHashMap;
 use serde::Serialize;
 use std::fs::{self, File};
-use std::path::PathBuf;
+use std::path::Path;
 
 macro_rules! nbt_as {
     // I'm not sure if path is the right type here.
@@ -27,7 +30,7 @@ macro_rules! nbt_as {
     };
 }
 
-fn parse_block(str: &str) -> Option<Block> {
+pub(crate) fn parse_block(str: &str) -> Option<Block> {
     static RE: Lazy<Regex> =
         Lazy::new(|| Regex::new(r"(?:minecraft:)?([a-z_]+)(?:\[([a-z=,0-9]+)\])?").unwrap());
     let captures = RE.captures(str)?;
@@ -43,10 +46,26 @@ fn parse_block(str: &str) -> Option<Block> {
     Some(block)
 }
 
+fn is_litematic(path: impl AsRef<Path>) -> bool {
+    path.as_ref()
+        .extension()
+        .is_some_and(|ext| ext == "litematic")
+}
+
 pub fn load_schematic(file_name: &str) -> Result<WorldEditClipboard> {
-    let mut file = File::open("./schems/".to_owned() + file_name)?;
+    load_schematic_file(Path::new("./schems").join(file_name))
+}
+
+/// Loads a schematic from any path instead of the schems folder
+pub fn load_schematic_file(path: impl AsRef<Path>) -> Result<WorldEditClipboard> {
+    let path = path.as_ref();
+    let mut file = File::open(path)?;
     let nbt = nbt::Blob::from_gzip_reader(&mut file)?;
 
+    if is_litematic(path) {
+        return litematic::load_litematic(&nbt);
+    }
+
     let root = if nbt.content.contains_key("Schematic") {
         nbt_as!(&nbt["Schematic"], nbt::Value::Compound)
     } else {
@@ -208,11 +227,25 @@ struct Schematic {
 }
 
 pub fn save_schematic(file_name: &str, clipboard: &WorldEditClipboard) -> Result<()> {
-    let mut path = PathBuf::from("./schems");
-    path.push(file_name);
-    fs::create_dir_all(path.parent().unwrap())?;
+    save_schematic_file(Path::new("./schems").join(file_name), clipboard)
+}
+
+/// Saves a schematic to any path instead of the schems folder
+pub fn save_schematic_file(path: impl AsRef<Path>, clipboard: &WorldEditClipboard) -> Result<()> {
+    let path = path.as_ref();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut file = File::create(path)?;
+
+    if is_litematic(path) {
+        let name = path.file_stem().unwrap().to_string_lossy();
+        let blob = litematic::save_litematic(&name, clipboard)?;
+        blob.to_gzip_writer(&mut file)?;
+        return Ok(());
+    }
 
-    let mut file = File::create("./schems/".to_owned() + file_name)?;
     let size_x = clipboard.size_x;
     let size_y = clipboard.size_y;
     let size_z = clipboard.size_z;
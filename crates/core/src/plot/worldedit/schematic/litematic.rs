@@ -0,0 +1,457 @@
+//! This implements the Litematica schematic format (`.litematic`)
+//! https://github.com/maruohon/litematica/blob/pre-rewrite/fabric/1.20.x/src/main/java/fi/dy/masa/litematica/schematic/LitematicaSchematic.java
+
+use crate::plot::worldedit::WorldEditClipboard;
+use crate::server::MC_DATA_VERSION;
+use anyhow::{bail, Result};
+use mchprs_blocks::block_entities::BlockEntity;
+use mchprs_blocks::blocks::Block;
+use mchprs_blocks::BlockPos;
+use mchprs_utils::map;
+use mchprs_world::storage::PalettedBitBuffer;
+use nbt::Value;
+use rustc_hash::FxHashMap;
+use std::collections::HashMap;
+use std::time::SystemTime;
+
+const LITEMATIC_VERSION: i32 = 6;
+const LITEMATIC_SUB_VERSION: i32 = 1;
+
+macro_rules! nbt_as {
+    ($e:expr, $p:path) => {
+        match $e {
+            $p(val) => val,
+            _ => bail!(concat!("Could not parse nbt value as ", stringify!($p))),
+        }
+    };
+}
+
+fn read_vec(nbt: &Value) -> Result<BlockPos> {
+    let compound = nbt_as!(nbt, Value::Compound);
+    Ok(BlockPos::new(
+        *nbt_as!(&compound["x"], Value::Int),
+        *nbt_as!(&compound["y"], Value::Int),
+        *nbt_as!(&compound["z"], Value::Int),
+    ))
+}
+
+fn write_vec(x: i32, y: i32, z: i32) -> Value {
+    Value::Compound(map! {
+        "x" => Value::Int(x),
+        "y" => Value::Int(y),
+        "z" => Value::Int(z)
+    })
+}
+
+/// Litematica stores block states in a bit array where entries can span across two longs,
+/// unlike the vanilla chunk format.
+fn bits_for_palette(palette_len: usize) -> u32 {
+    (usize::BITS - (palette_len.max(1) - 1).leading_zeros()).max(2)
+}
+
+fn get_packed(data: &[i64], bits: u32, index: usize) -> u32 {
+    let start = index * bits as usize;
+    let long_idx = start / 64;
+    let offset = (start % 64) as u32;
+    let mask = (1u64 << bits) - 1;
+    let mut val = data[long_idx] as u64 >> offset;
+    if offset + bits > 64 {
+        val |= (data[long_idx + 1] as u64) << (64 - offset);
+    }
+    (val & mask) as u32
+}
+
+fn set_packed(data: &mut [i64], bits: u32, index: usize, val: u32) {
+    let start = index * bits as usize;
+    let long_idx = start / 64;
+    let offset = (start % 64) as u32;
+    let val = val as u64;
+    data[long_idx] |= (val << offset) as i64;
+    if offset + bits > 64 {
+        data[long_idx + 1] |= (val >> (64 - offset)) as i64;
+    }
+}
+
+fn parse_palette_entry(entry: &Value) -> Result<u32> {
+    let entry = nbt_as!(entry, Value::Compound);
+    let name = nbt_as!(&entry["Name"], Value::String);
+    let Some(mut block) = Block::from_name(name.trim_start_matches("minecraft:")) else {
+        return Ok(Block::Air {}.get_id());
+    };
+    if let Some(Value::Compound(props)) = entry.get("Properties") {
+        let props = props
+            .iter()
+            .filter_map(|(k, v)| match v {
+                Value::String(v) => Some((k.as_str(), v.as_str())),
+                _ => None,
+            })
+            .collect();
+        block.set_properties(props);
+    }
+    Ok(block.get_id())
+}
+
+/// A single region of a litematic, with its lowest corner relative to the schematic origin
+struct Region<'a> {
+    min: BlockPos,
+    size: BlockPos,
+    nbt: &'a HashMap<String, Value>,
+}
+
+impl<'a> Region<'a> {
+    fn read(nbt: &'a Value) -> Result<Region<'a>> {
+        let nbt = nbt_as!(nbt, Value::Compound);
+        let pos = read_vec(&nbt["Position"])?;
+        let size = read_vec(&nbt["Size"])?;
+        // Negative sizes mean the region extends backwards from its position
+        let corner = |pos: i32, size: i32| if size < 0 { pos + size + 1 } else { pos };
+        Ok(Region {
+            min: BlockPos::new(
+                corner(pos.x, size.x),
+                corner(pos.y, size.y),
+                corner(pos.z, size.z),
+            ),
+            size: BlockPos::new(size.x.abs(), size.y.abs(), size.z.abs()),
+            nbt,
+        })
+    }
+}
+
+pub fn load_litematic(nbt: &nbt::Blob) -> Result<WorldEditClipboard> {
+    let version = *nbt_as!(&nbt["Version"], Value::Int);
+    if !(4..=LITEMATIC_VERSION).contains(&version) {
+        bail!("unsupported litematic version: {}", version);
+    }
+
+    let regions = nbt_as!(&nbt["Regions"], Value::Compound)
+        .values()
+        .map(Region::read)
+        .collect::<Result<Vec<_>>>()?;
+    if regions.is_empty() {
+        bail!("litematic does not contain any regions");
+    }
+
+    let min = regions.iter().map(|r| r.min).reduce(BlockPos::min).unwrap();
+    let max = regions
+        .iter()
+        .map(|r| r.min + r.size)
+        .reduce(BlockPos::max)
+        .unwrap();
+    let size_x = (max.x - min.x) as u32;
+    let size_y = (max.y - min.y) as u32;
+    let size_z = (max.z - min.z) as u32;
+
+    let index_of = |pos: BlockPos| {
+        (pos.y as u32 * size_x * size_z + pos.z as u32 * size_x + pos.x as u32) as usize
+    };
+
+    let mut data = PalettedBitBuffer::new((size_x * size_y * size_z) as usize, 9);
+    let mut block_entities = FxHashMap::default();
+    for region in regions {
+        let palette = nbt_as!(&region.nbt["BlockStatePalette"], Value::List)
+            .iter()
+            .map(parse_palette_entry)
+            .collect::<Result<Vec<_>>>()?;
+        let states = nbt_as!(&region.nbt["BlockStates"], Value::LongArray);
+        let bits = bits_for_palette(palette.len());
+        let volume = (region.size.x * region.size.y * region.size.z) as usize;
+        if states.len() * 64 < volume * bits as usize {
+            bail!("litematic region block data is too short");
+        }
+
+        let offset = region.min - min;
+        let mut i = 0;
+        for y in 0..region.size.y {
+            for z in 0..region.size.z {
+                for x in 0..region.size.x {
+                    let palette_idx = get_packed(states, bits, i) as usize;
+                    i += 1;
+                    let Some(&block) = palette.get(palette_idx) else {
+                        bail!("invalid palette index in litematic: {}", palette_idx);
+                    };
+                    let pos = BlockPos::new(x, y, z) + offset;
+                    data.set_entry(index_of(pos), block);
+                }
+            }
+        }
+
+        if let Some(Value::List(tile_entities)) = region.nbt.get("TileEntities") {
+            for tile_entity in tile_entities {
+                let val = nbt_as!(tile_entity, Value::Compound);
+                let (Some(Value::Int(x)), Some(Value::Int(y)), Some(Value::Int(z))) =
+                    (val.get("x"), val.get("y"), val.get("z"))
+                else {
+                    continue;
+                };
+                let pos = BlockPos::new(*x, *y, *z) + offset;
+                // Tile entities outside of the schematic have no block to belong to
+                if pos.x < 0
+                    || pos.y < 0
+                    || pos.z < 0
+                    || pos.x as u32 >= size_x
+                    || pos.y as u32 >= size_y
+                    || pos.z as u32 >= size_z
+                {
+                    continue;
+                }
+                // Litematica strips the id from tile entities, so we use the block instead
+                let id = match val.get("id") {
+                    Some(Value::String(id)) => id.clone(),
+                    _ => Block::from_id(data.get_entry(index_of(pos)))
+                        .get_name()
+                        .to_string(),
+                };
+                if let Some(parsed) = BlockEntity::from_nbt(&id, val) {
+                    block_entities.insert(pos, parsed);
+                }
+            }
+        }
+    }
+
+    Ok(WorldEditClipboard {
+        size_x,
+        size_y,
+        size_z,
+        offset_x: 0,
+        offset_y: 0,
+        offset_z: 0,
+        data,
+        block_entities,
+    })
+}
+
+pub fn save_litematic(name: &str, clipboard: &WorldEditClipboard) -> Result<nbt::Blob> {
+    let size_x = clipboard.size_x;
+    let size_y = clipboard.size_y;
+    let size_z = clipboard.size_z;
+    let volume = (size_x * size_y * size_z) as usize;
+
+    // Litematica expects air to always be the first palette entry
+    let mut palette = vec![Block::Air {}.get_id()];
+    let mut indices = Vec::with_capacity(volume);
+    let mut total_blocks = 0;
+    for i in 0..volume {
+        let entry = clipboard.data.get_entry(i);
+        if entry != 0 {
+            total_blocks += 1;
+        }
+        let idx = match palette.iter().position(|id| *id == entry) {
+            Some(idx) => idx,
+            None => {
+                palette.push(entry);
+                palette.len() - 1
+            }
+        };
+        indices.push(idx as u32);
+    }
+
+    let bits = bits_for_palette(palette.len());
+    let mut states = vec![0i64; (volume * bits as usize).div_ceil(64)];
+    for (i, idx) in indices.into_iter().enumerate() {
+        set_packed(&mut states, bits, i, idx);
+    }
+
+    let palette = palette
+        .into_iter()
+        .map(|id| {
+            let block = Block::from_id(id);
+            let mut entry = map! {
+                "Name" => Value::String(format!("minecraft:{}", block.get_name()))
+            };
+            let props = block.properties();
+            if !props.is_empty() {
+                let props = props
+                    .into_iter()
+                    .map(|(k, v)| (k.to_string(), Value::String(v)))
+                    .collect();
+                entry.insert("Properties".to_string(), Value::Compound(props));
+            }
+            Value::Compound(entry)
+        })
+        .collect();
+
+    let mut tile_entities = Vec::new();
+    for (pos, block_entity) in &clipboard.block_entities {
+        if let Some(mut blob) = block_entity.to_nbt(false) {
+            blob.insert("x", Value::Int(pos.x))?;
+            blob.insert("y", Value::Int(pos.y))?;
+            blob.insert("z", Value::Int(pos.z))?;
+            tile_entities.push(Value::Compound(blob.content));
+        }
+    }
+
+    let region = map! {
+        "Position" => write_vec(0, 0, 0),
+        "Size" => write_vec(size_x as i32, size_y as i32, size_z as i32),
+        "BlockStatePalette" => Value::List(palette),
+        "BlockStates" => Value::LongArray(states),
+        "TileEntities" => Value::List(tile_entities),
+        "Entities" => Value::List(Vec::new()),
+        "PendingBlockTicks" => Value::List(Vec::new()),
+        "PendingFluidTicks" => Value::List(Vec::new())
+    };
+
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as i64;
+    let metadata = map! {
+        "Name" => Value::String(name.to_string()),
+        "Author" => Value::String("MCHPRS".to_string()),
+        "Description" => Value::String(String::new()),
+        "RegionCount" => Value::Int(1),
+        "TotalBlocks" => Value::Int(total_blocks),
+        "TotalVolume" => Value::Int(volume as i32),
+        "EnclosingSize" => write_vec(size_x as i32, size_y as i32, size_z as i32),
+        "TimeCreated" => Value::Long(now),
+        "TimeModified" => Value::Long(now)
+    };
+
+    let mut blob = nbt::Blob::new();
+    blob.insert("Version", Value::Int(LITEMATIC_VERSION))?;
+    blob.insert("SubVersion", Value::Int(LITEMATIC_SUB_VERSION))?;
+    blob.insert("MinecraftDataVersion", Value::Int(MC_DATA_VERSION))?;
+    blob.insert("Metadata", Value::Compound(metadata))?;
+    blob.insert(
+        "Regions",
+        Value::Compound(map! {
+            name => Value::Compound(region)
+        }),
+    )?;
+    Ok(blob)
+}
+
+#[test]
+fn round_trip() {
+    use mchprs_blocks::block_entities::{ContainerType, InventoryEntry};
+    use mchprs_blocks::blocks::{ComparatorMode, RedstoneComparator, RedstoneRepeater};
+    use mchprs_blocks::items::Item;
+    use mchprs_blocks::BlockDirection;
+
+    // Enough block states that the 5 bit entries cross the boundaries between longs
+    let mut blocks = vec![Block::Stone {}, Block::Glass {}, Block::Barrel {}];
+    for facing in [
+        BlockDirection::North,
+        BlockDirection::South,
+        BlockDirection::East,
+        BlockDirection::West,
+    ] {
+        for delay in 1..=4 {
+            blocks.push(Block::RedstoneRepeater {
+                repeater: RedstoneRepeater {
+                    delay,
+                    facing,
+                    ..Default::default()
+                },
+            });
+        }
+        blocks.push(Block::RedstoneComparator {
+            comparator: RedstoneComparator::new(facing, ComparatorMode::Subtract, false),
+        });
+    }
+
+    let (size_x, size_y, size_z) = (5, 3, 7);
+    let volume = (size_x * size_y * size_z) as usize;
+    let mut data = PalettedBitBuffer::new(volume, 9);
+    for i in 0..volume {
+        // Leave every fourth block as air
+        if i % 4 != 0 {
+            data.set_entry(i, blocks[i % blocks.len()].get_id());
+        }
+    }
+    let comparator = Block::RedstoneComparator {
+        comparator: RedstoneComparator::new(BlockDirection::North, ComparatorMode::Compare, false),
+    };
+    data.set_entry(1, comparator.get_id());
+    data.set_entry(volume - 1, Block::Barrel {}.get_id());
+    let mut block_entities = FxHashMap::default();
+    block_entities.insert(
+        BlockPos::new(1, 0, 0),
+        BlockEntity::Comparator { output_strength: 7 },
+    );
+    let inventory = vec![InventoryEntry {
+        id: Item::Redstone {}.get_id(),
+        slot: 3,
+        count: 12,
+        nbt: None,
+    }];
+    block_entities.insert(
+        BlockPos::new(4, 2, 6),
+        BlockEntity::container(inventory, ContainerType::Barrel),
+    );
+    let clipboard = WorldEditClipboard {
+        offset_x: 0,
+        offset_y: 0,
+        offset_z: 0,
+        size_x,
+        size_y,
+        size_z,
+        data,
+        block_entities,
+    };
+
+    let mut file = Vec::new();
+    save_litematic("round_trip", &clipboard)
+        .unwrap()
+        .to_gzip_writer(&mut file)
+        .unwrap();
+    let loaded =
+        load_litematic(&nbt::Blob::from_gzip_reader(&mut file.as_slice()).unwrap()).unwrap();
+
+    assert_eq!(
+        (loaded.size_x, loaded.size_y, loaded.size_z),
+        (size_x, size_y, size_z)
+    );
+    for i in 0..volume {
+        assert_eq!(loaded.data.get_entry(i), clipboard.data.get_entry(i));
+    }
+    let block_entities = |clipboard: &WorldEditClipboard| {
+        let mut block_entities: Vec<_> = clipboard
+            .block_entities
+            .iter()
+            .map(|(pos, block_entity)| (*pos, block_entity.to_nbt(false).map(|b| b.content)))
+            .collect();
+        block_entities.sort_by_key(|(pos, _)| (pos.x, pos.y, pos.z));
+        block_entities
+    };
+    assert_eq!(block_entities(&loaded), block_entities(&clipboard));
+}
+
+#[test]
+fn tile_entities_outside_region() {
+    let mut data = PalettedBitBuffer::new(1, 9);
+    data.set_entry(0, Block::Barrel {}.get_id());
+    let clipboard = WorldEditClipboard {
+        offset_x: 0,
+        offset_y: 0,
+        offset_z: 0,
+        size_x: 1,
+        size_y: 1,
+        size_z: 1,
+        data,
+        block_entities: FxHashMap::default(),
+    };
+
+    let mut blob = save_litematic("outside", &clipboard).unwrap();
+    let Value::Compound(mut regions) = blob["Regions"].clone() else {
+        unreachable!()
+    };
+    let Some(Value::Compound(region)) = regions.get_mut("outside") else {
+        unreachable!()
+    };
+    region.insert(
+        "TileEntities".to_string(),
+        Value::List(vec![
+            Value::Compound(map! { "x" => Value::Int(0), "y" => Value::Int(1) }),
+            Value::Compound(map! {
+                "x" => Value::Int(3),
+                "y" => Value::Int(-1),
+                "z" => Value::Int(0)
+            }),
+        ]),
+    );
+    blob.insert("Regions", Value::Compound(regions)).unwrap();
+
+    let loaded = load_litematic(&blob).unwrap();
+    assert!(loaded.block_entities.is_empty());
+}
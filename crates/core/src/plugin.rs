@@ -0,0 +1,255 @@
+//! Native plugins loaded from dynamic libraries in `./plugins` at startup.
+//!
+//! A plugin is a `cdylib` crate that depends on `mchprs_core`, implements [`Plugin`], and
+//! exports it with [`declare_plugin!`](crate::declare_plugin). Rust doesn't have a stable ABI,
+//! so a plugin has to be built with the same compiler and the same version of MCHPRS as the
+//! server that loads it. Plugins built against another version are refused.
+
+use crate::events::{self, Event, EventResult};
+use crate::plot::commands;
+use crate::plot::recording::Input;
+use crate::plot::Plot;
+use libloading::Library;
+use mchprs_blocks::blocks::Block;
+use mchprs_blocks::BlockPos;
+use mchprs_network::packets::clientbound::CCommandsNode;
+use mchprs_text::TextComponent;
+use mchprs_world::World;
+use once_cell::sync::OnceCell;
+use std::ffi::OsStr;
+use std::fs;
+use std::path::Path;
+use tracing::{error, info, warn};
+
+/// This is bumped whenever the [`Plugin`] trait or [`PluginDeclaration`] changes
+pub const PLUGIN_API_VERSION: u32 = 2;
+#[doc(hidden)]
+pub const CORE_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+const PLUGINS_DIR: &str = "./plugins";
+
+static PLUGINS: OnceCell<Vec<LoadedPlugin>> = OnceCell::new();
+
+pub use crate::events::PlayerInfo;
+
+/// Every hook has a default implementation, so plugins only need to implement the ones they
+/// use. Hooks are called from the server thread and from plot threads, so they must not block
+/// for long.
+pub trait Plugin: Send + Sync {
+    fn name(&self) -> &str;
+
+    /// Called once after the plugin is loaded
+    fn on_enable(&self) {}
+
+    /// Called when the server is stopping
+    fn on_disable(&self) {}
+
+    /// Called for every event published on the [event bus](crate::events)
+    fn on_event(&self, _event: &Event) -> EventResult {
+        EventResult::Continue
+    }
+
+    /// The names of the commands this plugin handles, without the leading slash. Commands the
+    /// server already has are refused.
+    fn commands(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    /// Called when a player runs one of the commands returned by [`Plugin::commands`]
+    fn on_command(&self, _ctx: &mut CommandContext<'_>, _command: &str, _args: &[&str]) {}
+}
+
+/// The symbol exported by [`declare_plugin!`](crate::declare_plugin)
+pub struct PluginDeclaration {
+    pub api_version: u32,
+    pub core_version: &'static str,
+    pub create: fn() -> Box<dyn Plugin>,
+}
+
+/// Exports a plugin from a `cdylib` crate. Takes a function that creates the plugin.
+#[macro_export]
+macro_rules! declare_plugin {
+    ($create:expr) => {
+        #[no_mangle]
+        pub static MCHPRS_PLUGIN: $crate::plugin::PluginDeclaration =
+            $crate::plugin::PluginDeclaration {
+                api_version: $crate::plugin::PLUGIN_API_VERSION,
+                core_version: $crate::plugin::CORE_VERSION,
+                create: $create,
+            };
+    };
+}
+
+/// Lets a plugin command act on the plot the player is in
+pub struct CommandContext<'a> {
+    plot: &'a mut Plot,
+    player: usize,
+}
+
+impl<'a> CommandContext<'a> {
+    pub fn player(&self) -> PlayerInfo {
+        PlayerInfo::of(&self.plot.players[self.player])
+    }
+
+    /// The coordinates of the plot the command was run in
+    pub fn plot(&self) -> (i32, i32) {
+        (self.plot.world.x, self.plot.world.z)
+    }
+
+    /// Sends a message to the player, formatted with `&` color codes
+    pub fn send_message(&self, message: &str) {
+        self.plot.players[self.player].send_chat_message(&TextComponent::from_legacy_text(message));
+    }
+
+    pub fn send_error_message(&self, message: &str) {
+        self.plot.players[self.player].send_error_message(message);
+    }
+
+    pub fn has_permission(&self, node: &str) -> bool {
+        self.plot.players[self.player].has_permission(node)
+    }
+
+    pub fn get_block(&self, pos: BlockPos) -> Block {
+        self.plot.world.get_block(pos)
+    }
+
+    /// Whether the player may build in the plot, like when the plot is theirs
+    pub fn can_build(&self) -> bool {
+        self.plot.can_interact(self.player)
+    }
+
+    /// Sets a block in the plot. Returns `false` if the position is outside of the plot, or if
+    /// the player can't build in the plot.
+    pub fn set_block(&mut self, pos: BlockPos, block: Block) -> bool {
+        if !self.can_build()
+            || !Plot::in_plot_bounds(
+                &self.plot.world.geometry,
+                self.plot.world.x,
+                self.plot.world.z,
+                pos.x,
+                pos.z,
+            )
+        {
+            return false;
+        }
+        self.plot.reset_redpiler();
+        self.plot.record(Input::SetBlock {
+            pos,
+            block: block.get_id(),
+        });
+        self.plot.world.set_block(pos, block)
+    }
+}
+
+struct LoadedPlugin {
+    // The plugin has to be dropped before the library its code lives in
+    plugin: Box<dyn Plugin>,
+    /// The plugin's commands that are not already taken by the server or another plugin
+    commands: Vec<&'static str>,
+    _library: Library,
+}
+
+fn load_plugin(path: &Path) -> Result<LoadedPlugin, String> {
+    // SAFETY: Loading a library runs its initialization code. Plugins are trusted by whoever
+    // put them in the plugins folder.
+    let library = unsafe { Library::new(path) }.map_err(|err| err.to_string())?;
+    // SAFETY: The symbol is declared as a `PluginDeclaration` by `declare_plugin!`
+    let declaration: &PluginDeclaration = unsafe {
+        let symbol = library
+            .get::<*const PluginDeclaration>(b"MCHPRS_PLUGIN\0")
+            .map_err(|_| "library does not export a plugin".to_string())?;
+        &**symbol
+    };
+    if declaration.api_version != PLUGIN_API_VERSION || declaration.core_version != CORE_VERSION {
+        return Err(format!(
+            "plugin was built for MCHPRS {} (plugin api {}), but this is MCHPRS {} (plugin api {})",
+            declaration.core_version, declaration.api_version, CORE_VERSION, PLUGIN_API_VERSION
+        ));
+    }
+    let plugin = (declaration.create)();
+    Ok(LoadedPlugin {
+        plugin,
+        commands: Vec::new(),
+        _library: library,
+    })
+}
+
+/// Loads and enables every plugin in the plugins folder
+pub(crate) fn load_plugins() {
+    let mut plugins: Vec<LoadedPlugin> = Vec::new();
+    if let Ok(entries) = fs::read_dir(PLUGINS_DIR) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension() != Some(OsStr::new(std::env::consts::DLL_EXTENSION)) {
+                continue;
+            }
+            match load_plugin(&path) {
+                Ok(mut loaded) => {
+                    for &command in loaded.plugin.commands() {
+                        let taken = plugins
+                            .iter()
+                            .any(|other| other.commands.contains(&command));
+                        if commands::is_builtin_command(command) || taken {
+                            warn!(
+                                "Plugin {} can't register /{}, which already exists",
+                                loaded.plugin.name(),
+                                command
+                            );
+                        } else {
+                            loaded.commands.push(command);
+                        }
+                    }
+                    info!("Loaded plugin {}", loaded.plugin.name());
+                    loaded.plugin.on_enable();
+                    plugins.push(loaded);
+                }
+                Err(err) => error!("Failed to load plugin {}: {}", path.display(), err),
+            }
+        }
+    }
+    if PLUGINS.set(plugins).is_err() {
+        warn!("Plugins were already loaded");
+        return;
+    }
+    for loaded in PLUGINS.get().into_iter().flatten() {
+        let plugin = &*loaded.plugin;
+        events::subscribe(move |event| plugin.on_event(event));
+    }
+}
+
+/// Calls `f` for every loaded plugin
+pub(crate) fn for_each(mut f: impl FnMut(&dyn Plugin)) {
+    for loaded in PLUGINS.get().into_iter().flatten() {
+        f(&*loaded.plugin);
+    }
+}
+
+/// Runs a plugin command. Returns `false` if no plugin handles the command.
+pub(crate) fn execute_command(
+    plot: &mut Plot,
+    player: usize,
+    command: &str,
+    args: &[&str],
+) -> bool {
+    let Some(loaded) = PLUGINS
+        .get()
+        .into_iter()
+        .flatten()
+        .find(|loaded| loaded.commands.contains(&command))
+    else {
+        return false;
+    };
+    let mut ctx = CommandContext { plot, player };
+    loaded.plugin.on_command(&mut ctx, command, args);
+    ctx.plot.world.flush_block_changes();
+    true
+}
+
+pub(crate) fn add_command_completions(nodes: &mut Vec<CCommandsNode>) {
+    for loaded in PLUGINS.get().into_iter().flatten() {
+        for &name in &loaded.commands {
+            // Plugins parse their own arguments
+            commands::add_free_form_command(nodes, name);
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use crate::floodgate;
 use crate::utils::HyphenatedUUID;
 use anyhow::Result;
 use serde::Deserialize;
@@ -11,7 +12,14 @@ pub struct PlayerProfile {
 }
 
 impl PlayerProfile {
+    /// Looks the player up with Mojang, or with Geyser for Bedrock players
     pub async fn lookup_by_username(username: &str) -> Result<PlayerProfile> {
+        if let Some(gamertag) = floodgate::bedrock_gamertag(username) {
+            return Ok(PlayerProfile {
+                uuid: HyphenatedUUID(floodgate::lookup_uuid(&gamertag).await?),
+                username: username.to_string(),
+            });
+        }
         let url = format!(
             "https://api.mojang.com/users/profiles/minecraft/{}",
             username
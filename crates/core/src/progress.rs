@@ -0,0 +1,87 @@
+//! Boss bars showing the progress of long running operations.
+
+use crate::lang::Text;
+use crate::player::Player;
+use mchprs_network::packets::clientbound::{
+    CBossBar, CBossBarAction, CBossBarColor, ClientBoundPacket,
+};
+use mchprs_network::PlayerPacketSender;
+use mchprs_text::{ColorCode, TextComponentBuilder};
+use std::time::{Duration, Instant};
+
+/// Operations that finish quicker than this never show a progress bar
+const SHOW_DELAY: Duration = Duration::from_millis(500);
+/// The minimum time between progress updates
+const UPDATE_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A boss bar shown to a player while an operation is running. The bar only appears once the
+/// operation has taken a noticeable amount of time, and is removed when this is dropped.
+pub struct ProgressBar {
+    sender: PlayerPacketSender,
+    uuid: u128,
+    title: String,
+    started: Instant,
+    last_update: Instant,
+    shown: bool,
+}
+
+impl ProgressBar {
+    pub fn new(player: &Player, title: impl Into<Text>) -> ProgressBar {
+        let now = Instant::now();
+        ProgressBar {
+            sender: PlayerPacketSender::new(&player.client),
+            uuid: rand::random(),
+            title: title.into().translate(&player.locale),
+            started: now,
+            last_update: now,
+            shown: false,
+        }
+    }
+
+    /// Sets the progress of the operation, from 0 to 1. This is cheap to call often, since
+    /// updates are only sent to the player every so often.
+    pub fn set_progress(&mut self, progress: f32) {
+        let now = Instant::now();
+        if now - self.started < SHOW_DELAY || now - self.last_update < UPDATE_INTERVAL {
+            return;
+        }
+        self.last_update = now;
+
+        let health = progress.clamp(0.0, 1.0);
+        let action = if self.shown {
+            CBossBarAction::UpdateHealth(health)
+        } else {
+            self.shown = true;
+            CBossBarAction::Add {
+                title: TextComponentBuilder::new(self.title.clone())
+                    .color_code(ColorCode::Gold)
+                    .finish(),
+                health,
+                color: CBossBarColor::Yellow,
+                division: 0,
+                flags: 0,
+            }
+        };
+        self.sender.send_packet(
+            &CBossBar {
+                uuid: self.uuid,
+                action,
+            }
+            .encode(),
+        );
+    }
+}
+
+impl Drop for ProgressBar {
+    fn drop(&mut self) {
+        if self.shown {
+            self.sender.send_packet(
+                &CBossBar {
+                    uuid: self.uuid,
+                    action: CBossBarAction::Remove,
+                }
+                .encode(),
+            );
+        }
+    }
+}
@@ -0,0 +1,266 @@
+//! Replays a recording made with `/record` with the base redstone implementation and with
+//! redpiler side by side, to find the first redstone tick after which they don't agree.
+
+use crate::bench::HeadlessPlot;
+use crate::plot::recording::{apply_input, Input, Recording};
+use anyhow::{bail, Result};
+use mchprs_blocks::blocks::Block;
+use mchprs_blocks::BlockPos;
+use mchprs_redpiler::CompilerOptions;
+use mchprs_world::memory::MemoryWorld;
+use mchprs_world::World;
+use rustc_hash::FxHashSet;
+use std::path::Path;
+use std::time::Instant;
+
+/// How many of the blocks that are different are printed
+const REPORT_BLOCKS: usize = 10;
+
+/// The blocks that weren't the same with both implementations after a redstone tick
+pub struct Divergence {
+    /// How many redstone ticks had run
+    pub tick: u64,
+    /// Each position that is different, with its block in the base implementation and with
+    /// redpiler
+    pub blocks: Vec<(BlockPos, Block, Block)>,
+    /// The last input before the ticks were different, with the tick it happened at
+    pub last_input: Option<(u64, Input)>,
+}
+
+struct Replay {
+    base: HeadlessPlot,
+    compiled: HeadlessPlot,
+    options: CompilerOptions,
+    tick: u64,
+}
+
+impl Replay {
+    fn new(recording: &Recording, options: CompilerOptions) -> Replay {
+        let (x, z) = (recording.plot_x, recording.plot_z);
+        Replay {
+            base: HeadlessPlot::from_data(recording.start.clone(), x, z),
+            compiled: HeadlessPlot::from_data(recording.start.clone(), x, z),
+            options,
+            tick: 0,
+        }
+    }
+
+    fn apply(&mut self, input: &Input) {
+        apply_input(&mut self.base, input);
+
+        // Like in a plot, redpiler handles these itself and anything else has to reset it
+        match *input {
+            Input::Use { pos }
+                if matches!(
+                    self.compiled.world().get_block(pos),
+                    Block::Lever { .. } | Block::StoneButton { .. }
+                ) =>
+            {
+                self.compile();
+                self.compiled.use_block(pos);
+            }
+            Input::PressurePlate { pos, powered } => {
+                self.compile();
+                self.compiled.set_pressure_plate(pos, powered);
+            }
+            Input::ContainerViewers(ref viewers) => {
+                self.compile();
+                self.compiled
+                    .set_container_viewers(viewers.iter().copied().collect());
+            }
+            _ => {
+                self.compiled.reset_redpiler();
+                apply_input(&mut self.compiled, input);
+            }
+        }
+    }
+
+    /// Compiles the plot again if an input reset redpiler
+    fn compile(&mut self) {
+        if !self.compiled.is_redpiler_active() {
+            self.compiled.start_redpiler(self.options.clone());
+        }
+    }
+
+    fn tick(&mut self) -> Vec<(BlockPos, Block, Block)> {
+        self.compile();
+        self.base.tick();
+        self.compiled.tick();
+        self.tick += 1;
+        self.compare()
+    }
+
+    /// Returns the blocks that are different, out of the ones that changed in either plot since
+    /// the last comparison
+    fn compare(&mut self) -> Vec<(BlockPos, Block, Block)> {
+        self.compiled.flush();
+        let mut changed = FxHashSet::default();
+        take_changes(self.base.world_mut(), &mut changed);
+        take_changes(self.compiled.world_mut(), &mut changed);
+
+        let mut different: Vec<_> = changed
+            .into_iter()
+            .filter_map(|pos| {
+                let base = self.base.world().get_block_raw(pos);
+                let compiled = self.compiled.world().get_block_raw(pos);
+                (base != compiled).then(|| (pos, Block::from_id(base), Block::from_id(compiled)))
+            })
+            .collect();
+        different.sort_by_key(|(pos, ..)| (pos.x, pos.y, pos.z));
+        different
+    }
+}
+
+/// Adds the positions of the blocks that changed since this was last called to `changed`
+fn take_changes(world: &mut MemoryWorld, changed: &mut FxHashSet<BlockPos>) {
+    for chunk in &mut world.chunks {
+        let (chunk_x, chunk_z) = (chunk.x, chunk.z);
+        for packet in chunk.multi_blocks() {
+            for record in &packet.records {
+                changed.insert(BlockPos::new(
+                    chunk_x * 16 + record.x as i32,
+                    packet.chunk_y as i32 * 16 + record.y as i32,
+                    chunk_z * 16 + record.z as i32,
+                ));
+            }
+        }
+        chunk.reset_multi_blocks();
+    }
+}
+
+/// Replays the recording with both implementations, returning where they first didn't agree
+pub fn replay(recording: &Recording, options: CompilerOptions) -> Option<Divergence> {
+    let mut replay = Replay::new(recording, options);
+    let mut inputs = recording.inputs.iter().peekable();
+    let mut last_input = None;
+    loop {
+        while let Some((tick, input)) = inputs.next_if(|(tick, _)| *tick <= replay.tick) {
+            replay.apply(input);
+            last_input = Some((*tick, input.clone()));
+        }
+
+        let done = replay.tick >= recording.ticks;
+        let blocks = if done {
+            replay.compare()
+        } else {
+            replay.tick()
+        };
+        if !blocks.is_empty() {
+            return Some(Divergence {
+                tick: replay.tick,
+                blocks,
+                last_input,
+            });
+        }
+        if done {
+            return None;
+        }
+    }
+}
+
+/// Loads the recording, replays it and prints where redpiler first did something different
+pub fn run(path: &Path, options: CompilerOptions) -> Result<()> {
+    let start = Instant::now();
+    let recording = Recording::load_from_file(path)?;
+    println!(
+        "Loaded {} in {:?}, with {} inputs over {} redstone ticks",
+        path.display(),
+        start.elapsed(),
+        recording.inputs.len(),
+        recording.ticks
+    );
+
+    let start = Instant::now();
+    let Some(divergence) = replay(&recording, options) else {
+        println!(
+            "Replayed in {:?}, redpiler did the same as the base redstone implementation",
+            start.elapsed()
+        );
+        return Ok(());
+    };
+
+    println!(
+        "After redstone tick {}, {} blocks are different:",
+        divergence.tick,
+        divergence.blocks.len()
+    );
+    for (pos, base, compiled) in divergence.blocks.iter().take(REPORT_BLOCKS) {
+        println!(
+            "  {}: {:?} without redpiler, {:?} with it",
+            pos, base, compiled
+        );
+    }
+    if divergence.blocks.len() > REPORT_BLOCKS {
+        println!("  and {} more", divergence.blocks.len() - REPORT_BLOCKS);
+    }
+    match &divergence.last_input {
+        Some((tick, input)) => println!("The last input was at tick {}: {}", tick, input),
+        None => println!("There were no inputs before then"),
+    }
+    bail!("redpiler diverged from the base redstone implementation")
+}
+
+#[test]
+fn replay_stays_in_sync() {
+    use mchprs_blocks::blocks::{Lever, LeverFace};
+    use mchprs_redstone::wire::make_cross;
+    use mchprs_save_data::plot_data::{ChunkData, PlotData, TimeOfDay, Tps, WorldSendRate};
+
+    let lever = BlockPos::new(1, 1, 1);
+    let lamp = BlockPos::new(4, 1, 1);
+    let mut plot = HeadlessPlot::empty();
+    let world = plot.world_mut();
+    for x in 1..=4 {
+        world.set_block(BlockPos::new(x, 0, 1), Block::Stone {});
+    }
+    world.set_block(
+        lever,
+        Block::Lever {
+            lever: Lever {
+                face: LeverFace::Floor,
+                ..Default::default()
+            },
+        },
+    );
+    world.set_block(lamp, Block::RedstoneLamp { lit: false });
+    let start = PlotData {
+        tps: Tps::Limited(10),
+        world_send_rate: WorldSendRate::default(),
+        chunk_data: world.chunks.iter_mut().map(ChunkData::new).collect(),
+        pending_ticks: Vec::new(),
+        time_of_day: TimeOfDay::default(),
+        entities: Vec::new(),
+    };
+
+    let wire = |x| Input::Place {
+        pos: BlockPos::new(x, 1, 1),
+        block: Block::RedstoneWire {
+            wire: make_cross(0),
+        }
+        .get_id(),
+        block_entity: None,
+    };
+    let recording = Recording {
+        plot_x: 0,
+        plot_z: 0,
+        start,
+        inputs: vec![
+            (0, wire(2)),
+            (0, wire(3)),
+            (2, Input::Use { pos: lever }),
+            (5, Input::Break { pos: lever }),
+        ],
+        ticks: 10,
+    };
+    assert!(replay(&recording, Default::default()).is_none());
+
+    let mut replay = Replay::new(&recording, Default::default());
+    for (_, input) in &recording.inputs[..3] {
+        replay.apply(input);
+    }
+    replay.tick();
+    assert_eq!(
+        replay.compiled.world().get_block(lamp),
+        Block::RedstoneLamp { lit: true }
+    );
+}
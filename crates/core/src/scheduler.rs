@@ -0,0 +1,263 @@
+//! Runs the tasks in the `[[schedule]]` section of the config at intervals or times of day.
+
+use crate::config::{ScheduledTask, TaskAction, CONFIG};
+use crate::permissions;
+use crate::plot::format_wait;
+use crate::server::Message;
+use chrono::{DateTime, Local, NaiveTime, TimeDelta};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{Receiver, Sender};
+use std::thread;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+const BACKUPS_DIR: &str = "./backups";
+/// How long a backup waits for plots to save before copying the world anyway
+const SAVE_TIMEOUT: Duration = Duration::from_secs(30);
+/// The permission the owner of a task needs to schedule commands
+const COMMAND_PERMISSION: &str = "mchprs.schedule";
+
+enum Schedule {
+    Every(TimeDelta),
+    Daily(NaiveTime),
+}
+
+impl Schedule {
+    fn parse(task: &ScheduledTask) -> Result<Schedule, String> {
+        match (&task.every, &task.at) {
+            (Some(every), None) => parse_interval(every).map(Schedule::Every),
+            (None, Some(at)) => NaiveTime::parse_from_str(at, "%H:%M")
+                .map(Schedule::Daily)
+                .map_err(|_| format!("invalid time of day \"{}\", expected HH:MM", at)),
+            _ => Err("a task needs either `every` or `at`".to_string()),
+        }
+    }
+
+    fn next_after(&self, now: DateTime<Local>) -> DateTime<Local> {
+        match self {
+            Schedule::Every(interval) => now + *interval,
+            Schedule::Daily(time) => {
+                let mut date = now.date_naive();
+                loop {
+                    // A time can be skipped or repeated when the clocks change
+                    if let Some(next) = date.and_time(*time).and_local_timezone(Local).earliest() {
+                        if next > now {
+                            return next;
+                        }
+                    }
+                    date = date.succ_opt().unwrap();
+                }
+            }
+        }
+    }
+}
+
+/// Parses intervals like `90s`, `30m`, `12h` or `1d`
+fn parse_interval(interval: &str) -> Result<TimeDelta, String> {
+    let invalid = || {
+        format!(
+            "invalid interval \"{}\", expected a number followed by s, m, h or d",
+            interval
+        )
+    };
+    let (split, unit) = interval.char_indices().last().ok_or_else(invalid)?;
+    let amount: i64 = interval[..split].parse().map_err(|_| invalid())?;
+    let unit_seconds = match unit {
+        's' => 1,
+        'm' => 60,
+        'h' => 60 * 60,
+        'd' => 60 * 60 * 24,
+        _ => return Err(invalid()),
+    };
+    amount
+        .checked_mul(unit_seconds)
+        .filter(|&seconds| seconds > 0)
+        .and_then(TimeDelta::try_seconds)
+        .ok_or_else(invalid)
+}
+
+/// Returns the owner of a task that runs a command, after checking that they are allowed to
+/// schedule commands
+fn command_owner(task: &ScheduledTask) -> Result<Option<u128>, String> {
+    if !matches!(task.action, TaskAction::Command { .. }) {
+        return Ok(None);
+    }
+    let Some(owner) = task.owner else {
+        return Err("a command task needs an `owner` to run as".to_string());
+    };
+    // Everyone has every permission without LuckPerms
+    if CONFIG.luckperms.is_some() {
+        let permissions = permissions::load_player_cache(owner.0)
+            .map_err(|err| format!("failed to load the permissions of {}: {}", owner, err))?;
+        if !matches!(permissions.get_node_val(COMMAND_PERMISSION), Some(val) if val > 0) {
+            return Err(format!(
+                "the owner {} does not have the {} permission",
+                owner, COMMAND_PERMISSION
+            ));
+        }
+    }
+    Ok(Some(owner.0))
+}
+
+struct Task {
+    action: TaskAction,
+    /// Who commands run as, only set for commands
+    owner: Option<u128>,
+    schedule: Schedule,
+    next_run: DateTime<Local>,
+    /// The restart warnings that were already sent for the next run
+    warned: Vec<u64>,
+}
+
+impl Task {
+    fn schedule_next(&mut self, now: DateTime<Local>) {
+        self.next_run = self.schedule.next_after(now);
+        // Don't warn players of a restart that is closer than the warning says
+        let left = (self.next_run - now).num_seconds().max(0) as u64;
+        self.warned.clear();
+        if let TaskAction::Restart { warnings } = &self.action {
+            self.warned
+                .extend(warnings.iter().filter(|&&warning| warning > left));
+        }
+    }
+}
+
+fn run_tasks(mut tasks: Vec<Task>, server: Sender<Message>) {
+    loop {
+        let now = Local::now();
+        for task in &mut tasks {
+            if let TaskAction::Restart { warnings } = &task.action {
+                let left = (task.next_run - now).num_seconds().max(0) as u64;
+                for &warning in warnings {
+                    if left <= warning && !task.warned.contains(&warning) {
+                        task.warned.push(warning);
+                        let time = format_wait(Duration::from_secs(warning));
+                        let message = text!("restart.warning", time = time);
+                        let _ = server.send(Message::Notice(message));
+                    }
+                }
+            }
+
+            if now < task.next_run {
+                continue;
+            }
+            task.schedule_next(now);
+            let message = match &task.action {
+                TaskAction::Announce { message } => Message::Announce(message.clone()),
+                TaskAction::Backup { keep } => Message::Backup(*keep),
+                TaskAction::Restart { .. } => {
+                    info!("Restarting the server as scheduled");
+                    Message::Shutdown
+                }
+                TaskAction::Command { command } => {
+                    // Command tasks always have an owner, it is checked when they are created
+                    let owner = task.owner.unwrap();
+                    Message::RunCommand(owner, command.trim_start_matches('/').to_string())
+                }
+            };
+            if server.send(message).is_err() {
+                return;
+            }
+        }
+        thread::sleep(Duration::from_secs(1));
+    }
+}
+
+pub fn init(tasks: &[ScheduledTask], server: Sender<Message>) {
+    let now = Local::now();
+    let mut parsed = Vec::new();
+    for (i, task) in tasks.iter().enumerate() {
+        match Schedule::parse(task).and_then(|schedule| Ok((schedule, command_owner(task)?))) {
+            Ok((schedule, owner)) => {
+                let mut task = Task {
+                    action: task.action.clone(),
+                    owner,
+                    schedule,
+                    next_run: now,
+                    warned: Vec::new(),
+                };
+                task.schedule_next(now);
+                parsed.push(task);
+            }
+            Err(err) => error!("Ignoring scheduled task {}: {}", i + 1, err),
+        }
+    }
+    if parsed.is_empty() {
+        return;
+    }
+    info!("Scheduled {} tasks", parsed.len());
+
+    thread::Builder::new()
+        .name("scheduler".to_string())
+        .spawn(move || run_tasks(parsed, server))
+        .unwrap();
+}
+
+fn copy_dir(from: &Path, to: &Path) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
+
+/// Deletes the oldest backups so that at most `keep` are left
+fn prune_backups(keep: usize) -> io::Result<()> {
+    let mut backups: Vec<PathBuf> = fs::read_dir(BACKUPS_DIR)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_dir())
+        .collect();
+    // The names contain the date, so they sort from oldest to newest
+    backups.sort();
+    let excess = backups.len().saturating_sub(keep);
+    for backup in &backups[..excess] {
+        fs::remove_dir_all(backup)?;
+    }
+    Ok(())
+}
+
+/// Copies the world into the backups folder once `plot_count` plots have confirmed they saved
+pub fn start_backup(plot_count: usize, saved: Receiver<()>, keep: Option<usize>) {
+    thread::Builder::new()
+        .name("backup".to_string())
+        .spawn(move || {
+            for _ in 0..plot_count {
+                if saved.recv_timeout(SAVE_TIMEOUT).is_err() {
+                    warn!("Not all plots saved in time, backing up anyway");
+                    break;
+                }
+            }
+
+            let name = Local::now().format("world-%Y-%m-%d-%H%M%S").to_string();
+            let target = Path::new(BACKUPS_DIR).join(name);
+            match copy_dir(Path::new("./world"), &target) {
+                Ok(()) => info!("Backed up the world to {}", target.display()),
+                Err(err) => error!("Failed to back up the world: {}", err),
+            }
+            if let Some(keep) = keep {
+                if let Err(err) = prune_backups(keep) {
+                    error!("Failed to delete old backups: {}", err);
+                }
+            }
+        })
+        .unwrap();
+}
+
+#[test]
+fn intervals() {
+    assert_eq!(parse_interval("90s"), Ok(TimeDelta::seconds(90)));
+    assert_eq!(parse_interval("12h"), Ok(TimeDelta::hours(12)));
+    assert_eq!(parse_interval("1d"), Ok(TimeDelta::days(1)));
+    assert!(parse_interval("").is_err());
+    assert!(parse_interval("0m").is_err());
+    assert!(parse_interval("5é").is_err());
+    assert!(parse_interval("9223372036854775807d").is_err());
+}
@@ -1,36 +1,45 @@
+use crate::admin_api::{AdminRequest, PlayerSummary};
 use crate::config::CONFIG;
-use crate::player::{Gamemode, PacketSender, Player};
+use crate::events::{self, Event, PlayerInfo};
+use crate::lang::{Text, DEFAULT_LOCALE, MAX_LOCALE_LENGTH};
+use crate::player::{Gamemode, MessageSender, PacketSender, Player, PlayerPos, SavedPlayer};
 use crate::plot::commands::DECLARE_COMMANDS;
-use crate::plot::{self, database, Plot, PLOT_BLOCK_HEIGHT};
-use crate::utils::HyphenatedUUID;
-use crate::{permissions, utils};
+use crate::plot::{self, database, geometry, Plot};
+use crate::plugin;
+use crate::utils::{self, HyphenatedUUID};
+use crate::worlds::{self, DEFAULT_WORLD};
+use crate::{admin_api, discord, floodgate, metrics, permissions, scheduler, webmap};
 use backtrace::Backtrace;
 use bus::Bus;
 use hmac::{Hmac, Mac};
+use mchprs_blocks::items::ItemStack;
 use mchprs_network::packets::clientbound::{
-    CConfigurationPluginMessage, CDisconnectLogin, CFinishConfiguration, CGameEvent,
+    CConfigurationPluginMessage, CDisconnect, CDisconnectLogin, CFinishConfiguration, CGameEvent,
     CGameEventType, CLogin, CLoginPluginRequest, CLoginSuccess, CPlayerInfoActions,
     CPlayerInfoAddPlayer, CPlayerInfoUpdate, CPlayerInfoUpdatePlayer, CPong, CRegistryBiome,
     CRegistryBiomeEffects, CRegistryData, CRegistryDataCodec, CRegistryDimensionType, CResponse,
-    CSetCompression, CSetContainerContent, CSetHeldItem, CSynchronizePlayerPosition,
-    ClientBoundPacket, UpdateTime,
+    CSetCompression, ClientBoundPacket, UpdateTime,
 };
 use mchprs_network::packets::serverbound::{
-    SAcknowledgeFinishConfiguration, SHandshake, SLoginAcknowledged, SLoginPluginResponse,
-    SLoginStart, SPing, SRequest, ServerBoundPacketHandler, VelocityResponseData,
+    SAcknowledgeFinishConfiguration, SClientInformation, SHandshake, SLoginAcknowledged,
+    SLoginPluginResponse, SLoginStart, SPing, SRequest, ServerBoundPacketHandler,
+    VelocityResponseData,
 };
-use mchprs_network::packets::{PacketEncoderExt, PlayerProperty, SlotData, COMPRESSION_THRESHOLD};
-use mchprs_network::{NetworkServer, NetworkState, PlayerPacketSender};
+use mchprs_network::packets::{PacketEncoderExt, PlayerProperty, COMPRESSION_THRESHOLD};
+use mchprs_network::{NetworkServer, NetworkState};
 use mchprs_text::TextComponent;
 use mchprs_utils::map;
 use rustc_hash::FxHashMap;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use sha2::Sha256;
+use std::collections::BTreeMap;
 use std::fs::{self, File};
 use std::io::Cursor;
 use std::path::Path;
+use std::sync::atomic::Ordering;
 use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tracing::{debug, error, info, warn};
 
@@ -54,14 +63,57 @@ pub enum Message {
     PlayerTeleportOther(Player, String),
     /// This message is sent to the server thread when a player changes their gamemode.
     PlayerUpdateGamemode(u128, Gamemode),
-    /// This message is sent to the server thread when a plot unloads itself.
-    PlotUnload(i32, i32),
+    /// This message is sent to the server thread when a plot unloads itself. It contains the
+    /// name of the world and the plot coordinates.
+    PlotUnload(String, i32, i32),
     /// This message is sent to the server thread when a player runs /whitelist add.
-    WhitelistAdd(u128, String, PlayerPacketSender),
+    WhitelistAdd(u128, String, MessageSender),
     /// This message is sent to the server thread when a player runs /whitelist remove.
-    WhitelistRemove(u128, PlayerPacketSender),
+    WhitelistRemove(u128, MessageSender),
     /// This message is sent to the server thread when a player runs /stop.
     Shutdown,
+    /// This message is sent to the server thread when someone sends a message in the Discord
+    /// channel. It contains the name of the author and the message.
+    DiscordChat(String, String),
+    /// This message is sent to the server thread by the admin API.
+    AdminRequest(AdminRequest),
+    /// This message is sent to the server thread by the scheduler to announce something in chat.
+    Announce(String),
+    /// This message is sent to the server thread by the scheduler to broadcast a message that
+    /// is translated for every player, like a restart warning.
+    Notice(Text),
+    /// This message is sent to the server thread by the scheduler to back up the world. It
+    /// contains the number of backups to keep.
+    Backup(Option<usize>),
+    /// This message is sent to the server thread by the scheduler to run a command as the owner
+    /// of a task. It contains the uuid of the owner and the command without the leading slash.
+    RunCommand(u128, String),
+    /// This message is sent to the server thread when a player runs /give for a player in
+    /// another plot. It contains the name of the receiver and the item.
+    GiveItem(String, ItemStack, MessageSender),
+    /// This message is sent to the server thread when a player runs /kick, or /kickall in which
+    /// case there is no target and everyone except the staff member is kicked.
+    Kick {
+        target: Option<String>,
+        reason: Option<String>,
+        staff_uuid: u128,
+        staff_name: String,
+        sender: MessageSender,
+    },
+    /// This message is sent to the server thread when a player runs /list.
+    ListPlayers(MessageSender),
+    /// This message is sent to the server thread when a player runs /seen. It contains the
+    /// name of the player to look up.
+    Seen(String, MessageSender),
+    /// This message is sent to the server thread when a player runs /whois. It contains the
+    /// name of the player to look up.
+    WhoIs(String, MessageSender),
+    /// This message is sent to the server thread when a player runs /near with a radius, to
+    /// look for players in the other plots.
+    Near(NearQuery),
+    /// This message is sent to the server thread when a plot fails to save itself or one of its
+    /// players, so the staff that are online can be told.
+    NotifyStaff(Text),
 }
 
 /// `BroadcastMessage` gets broadcasted from the server thread to all the plot threads.
@@ -83,6 +135,30 @@ pub enum BroadcastMessage {
     /// This message is broadcasted when the server is stopping, either through the stop
     /// command or through the ctrl+c handler.
     Shutdown,
+    /// This message is broadcasted when a player gets kicked. It contains the uuid of the
+    /// player and the reason.
+    KickPlayer(u128, Text),
+    /// This message is broadcasted to run a command as a player. It contains the uuid of the
+    /// player and the command without the leading slash.
+    RunCommand(u128, String),
+    /// This message is broadcasted before the world is backed up. Plots save themselves and
+    /// then send on the channel.
+    Save(Sender<()>),
+    /// This message is broadcasted when a player is given an item with /give. It contains the
+    /// uuid of the receiver and the item.
+    GiveItem(u128, ItemStack),
+    /// This message is broadcasted when a player runs /whois. The plot the player is in
+    /// answers with their details.
+    WhoIs(u128, Arc<MessageSender>),
+    /// This message is broadcasted for server announcements in chat, which are translated for
+    /// every player.
+    Notice(Text),
+    /// This message is broadcasted when a player runs /near with a radius. Every plot replies
+    /// with the players it has within the radius.
+    Near(NearQuery),
+    /// This message is broadcasted when something went wrong that staff should know about, like
+    /// a plot failing to save. It is shown to players with `mchprs.notify.errors`.
+    StaffNotice(Text),
 }
 
 /// `PrivMessage` gets send from the server thread directly to a plot thread.
@@ -103,8 +179,19 @@ pub struct PlayerJoinInfo {
     pub properties: Vec<PlayerProperty>,
 }
 
+/// The player who ran /near and where they were
+#[derive(Debug, Clone)]
+pub struct NearQuery {
+    pub uuid: u128,
+    pub world: String,
+    pub pos: PlayerPos,
+    pub radius: f64,
+    pub sender: Arc<MessageSender>,
+}
+
 #[derive(Debug, Clone)]
 struct PlayerListEntry {
+    world: String,
     plot_x: i32,
     plot_z: i32,
     username: String,
@@ -113,6 +200,7 @@ struct PlayerListEntry {
 }
 
 struct PlotListEntry {
+    world: String,
     plot_x: i32,
     plot_z: i32,
     priv_message_sender: mpsc::Sender<PrivMessage>,
@@ -133,10 +221,13 @@ pub struct MinecraftServer {
     online_players: FxHashMap<u128, PlayerListEntry>,
     running_plots: Vec<PlotListEntry>,
     whitelist: Option<Vec<WhitelistEntry>>,
+    /// Set once every plot has been saved after a shutdown
+    stopped: bool,
 }
 
 impl MinecraftServer {
-    /// Start the server
+    /// Start the server. Returns after a graceful shutdown, so that the caller can clean up
+    /// before the process exits.
     pub fn run() {
         std::panic::set_hook(Box::new(|panic_info| {
             let backtrace = Backtrace::new();
@@ -146,9 +237,16 @@ impl MinecraftServer {
         info!("Starting server...");
         let start_time = Instant::now();
 
+        // This has to happen before the plot folders are created, which tell apart worlds from
+        // before their size was saved
+        plot::geometry::init(CONFIG.plot_scale, CONFIG.world_height)
+            .expect("Error loading the size of the world");
+
         // Create world folders if they don't exist yet
         fs::create_dir_all("./world/players").unwrap();
-        fs::create_dir_all("./world/plots").unwrap();
+        for world in worlds::names() {
+            fs::create_dir_all(worlds::plots_dir(world)).unwrap();
+        }
         fs::create_dir_all("./schems").unwrap();
 
         plot::database::init();
@@ -179,6 +277,36 @@ impl MinecraftServer {
             permissions::init(permissions_config.clone()).unwrap();
         }
 
+        if let Some(metrics_config) = &CONFIG.metrics {
+            if metrics_config.enabled {
+                metrics::init(&metrics_config.bind_address);
+            }
+        }
+
+        if let Some(web_map_config) = &CONFIG.web_map {
+            if web_map_config.enabled {
+                webmap::init(web_map_config);
+            }
+        }
+
+        if let Some(admin_api_config) = &CONFIG.admin_api {
+            if admin_api_config.enabled {
+                admin_api::init(admin_api_config, plot_tx.clone());
+            }
+        }
+
+        if let Some(discord_config) = &CONFIG.discord {
+            if discord_config.enabled {
+                discord::init(discord_config, plot_tx.clone());
+            }
+        }
+
+        if let Some(schedule) = &CONFIG.schedule {
+            scheduler::init(schedule, plot_tx.clone());
+        }
+
+        plugin::load_plugins();
+
         // Create server struct
         let mut server = MinecraftServer {
             network: NetworkServer::new(bind_addr),
@@ -188,62 +316,121 @@ impl MinecraftServer {
             online_players: FxHashMap::default(),
             running_plots: Vec::new(),
             whitelist,
+            stopped: false,
         };
 
-        // Load the spawn area plot on server start
-        // This plot should be always active
-        let (spawn_tx, spawn_rx) = mpsc::channel();
-        Plot::load_and_run(
-            0,
-            0,
-            server.broadcaster.add_rx(),
-            server.plot_sender.clone(),
-            spawn_rx,
-            true,
-            None,
-        );
-        server.running_plots.push(PlotListEntry {
-            plot_x: 0,
-            plot_z: 0,
-            priv_message_sender: spawn_tx,
-        });
+        // Load the spawn area plot of every world on server start
+        // These plots should be always active
+        for world in worlds::names() {
+            let (spawn_tx, spawn_rx) = mpsc::channel();
+            Plot::load_and_run(
+                world.to_string(),
+                0,
+                0,
+                server.broadcaster.add_rx(),
+                server.plot_sender.clone(),
+                spawn_rx,
+                true,
+                None,
+            );
+            server.running_plots.push(PlotListEntry {
+                world: world.to_string(),
+                plot_x: 0,
+                plot_z: 0,
+                priv_message_sender: spawn_tx,
+            });
+        }
 
         info!("Done! Start took {:?}", start_time.elapsed());
 
-        loop {
+        while !server.stopped {
             server.update();
             std::thread::sleep(Duration::from_millis(2));
         }
     }
 
     /// Updates the player's location on the `online_players` list
-    fn update_player_entry(&mut self, uuid: u128, plot_x: i32, plot_z: i32) {
+    fn update_player_entry(&mut self, uuid: u128, world: &str, plot_x: i32, plot_z: i32) {
         let player = self.online_players.get_mut(&uuid);
         if let Some(player) = player {
+            player.world = world.to_string();
             player.plot_x = plot_x;
             player.plot_z = plot_z;
         }
     }
 
     /// Removes the plot entry from the `running_plots` list
-    fn handle_plot_unload(&mut self, plot_x: i32, plot_z: i32) {
+    fn handle_plot_unload(&mut self, world: &str, plot_x: i32, plot_z: i32) {
         let index = self
             .running_plots
             .iter()
-            .position(|p| p.plot_x == plot_x && p.plot_z == plot_z);
+            .position(|p| p.world == world && p.plot_x == plot_x && p.plot_z == plot_z);
         if let Some(index) = index {
             self.running_plots.remove(index);
         }
     }
 
+    fn find_online_player(&self, username: &str) -> Option<u128> {
+        self.online_players
+            .iter()
+            .find(|(_, player)| player.username.eq_ignore_ascii_case(username))
+            .map(|(uuid, _)| *uuid)
+    }
+
+    fn handle_admin_request(&mut self, request: AdminRequest) {
+        match request {
+            AdminRequest::ListPlayers(reply) => {
+                let players = self
+                    .online_players
+                    .iter()
+                    .map(|(uuid, player)| PlayerSummary {
+                        uuid: HyphenatedUUID(*uuid),
+                        username: player.username.clone(),
+                        world: player.world.clone(),
+                        plot_x: player.plot_x,
+                        plot_z: player.plot_z,
+                        gamemode: player.gamemode,
+                    })
+                    .collect();
+                let _ = reply.send(players);
+            }
+            AdminRequest::KickPlayer {
+                username,
+                reason,
+                reply,
+            } => {
+                let uuid = self.find_online_player(&username);
+                if let Some(uuid) = uuid {
+                    info!("Kicking {} through the admin API: {}", username, reason);
+                    self.broadcaster
+                        .broadcast(BroadcastMessage::KickPlayer(uuid, reason.into()));
+                }
+                let _ = reply.send(uuid.is_some());
+            }
+            AdminRequest::RunCommand {
+                username,
+                command,
+                reply,
+            } => {
+                let uuid = self.find_online_player(&username);
+                if let Some(uuid) = uuid {
+                    info!("Running /{} as {} through the admin API", command, username);
+                    self.broadcaster
+                        .broadcast(BroadcastMessage::RunCommand(uuid, command));
+                }
+                let _ = reply.send(uuid.is_some());
+            }
+        }
+    }
+
     fn graceful_shutdown(&mut self) {
         info!("Commencing graceful shutdown...");
         self.broadcaster.broadcast(BroadcastMessage::Shutdown);
         // Wait for all plots to save and unload
         while !self.running_plots.is_empty() {
             while let Ok(message) = self.receiver.try_recv() {
-                if let Message::PlotUnload(plot_x, plot_z) = message {
-                    self.handle_plot_unload(plot_x, plot_z);
+                if let Message::PlotUnload(world, plot_x, plot_z) = message {
+                    self.handle_plot_unload(&world, plot_x, plot_z);
                 }
                 std::thread::sleep(Duration::from_millis(2));
             }
@@ -253,14 +440,18 @@ impl MinecraftServer {
             fs::write("whitelist.json", serde_json::to_string(whitelist).unwrap()).unwrap();
         }
 
-        std::process::exit(0);
+        plugin::for_each(|plugin| plugin.on_disable());
+        info!("Shutdown complete");
+        self.stopped = true;
     }
 
     fn send_player_to_plot(&mut self, player: Player, new_entry: bool) {
-        let (plot_x, plot_z) = player.pos.plot_pos();
+        let world = player.world.clone();
+        let (plot_x, plot_z) = player.pos.plot_pos(&geometry(&world));
 
         if new_entry {
             let player_list_entry = PlayerListEntry {
+                world: world.clone(),
                 plot_x,
                 plot_z,
                 username: player.username.clone(),
@@ -268,17 +459,19 @@ impl MinecraftServer {
                 gamemode: player.gamemode,
             };
             self.online_players.insert(player.uuid, player_list_entry);
+            metrics::PLAYERS_ONLINE.store(self.online_players.len(), Ordering::Relaxed);
         } else {
-            self.update_player_entry(player.uuid, plot_x, plot_z);
+            self.update_player_entry(player.uuid, &world, plot_x, plot_z);
         }
 
         let plot_loaded = self
             .running_plots
             .iter()
-            .any(|p| p.plot_x == plot_x && p.plot_z == plot_z);
+            .any(|p| p.world == world && p.plot_x == plot_x && p.plot_z == plot_z);
         if !plot_loaded {
             let (priv_tx, priv_rx) = mpsc::channel();
             Plot::load_and_run(
+                world.clone(),
                 plot_x,
                 plot_z,
                 self.broadcaster.add_rx(),
@@ -288,6 +481,7 @@ impl MinecraftServer {
                 Some(player),
             );
             self.running_plots.push(PlotListEntry {
+                world,
                 plot_x,
                 plot_z,
                 priv_message_sender: priv_tx,
@@ -296,11 +490,16 @@ impl MinecraftServer {
             let plot_list_entry = self
                 .running_plots
                 .iter()
-                .find(|p| p.plot_x == plot_x && p.plot_z == plot_z)
+                .find(|p| p.world == world && p.plot_x == plot_x && p.plot_z == plot_z)
                 .unwrap();
-            let _ = plot_list_entry
+            let result = plot_list_entry
                 .priv_message_sender
                 .send(PrivMessage::PlayerEnterPlot(player));
+            if let Err(mpsc::SendError(PrivMessage::PlayerEnterPlot(player))) = result {
+                // The plot thread stopped before we got its unload message
+                self.handle_plot_unload(&world, plot_x, plot_z);
+                self.send_player_to_plot(player, false);
+            }
         }
     }
 
@@ -310,7 +509,33 @@ impl MinecraftServer {
         let uuid = client.uuid.unwrap();
         let username = client.username.clone().unwrap();
         let properties = client.properties.clone();
-        let player = Player::load_player(uuid, username, properties, client.into());
+        let locale = client.locale.clone();
+        let saved = match SavedPlayer::load(uuid, &username) {
+            Ok(saved) => saved,
+            Err(err) => {
+                // Letting them join would overwrite their data when they leave
+                error!("Failed to load the player data of {}: {:?}", username, err);
+                let reason = text!("login.load_error")
+                    .translate(locale.as_deref().unwrap_or(DEFAULT_LOCALE));
+                let disconnect = CDisconnect {
+                    reason: reason.into(),
+                }
+                .encode();
+                client.send_packet(&disconnect);
+                client.close_connection();
+                self.broadcaster
+                    .broadcast(BroadcastMessage::StaffNotice(text!(
+                        "server.player_load_error",
+                        player = username,
+                        error = format!("{:#}", err)
+                    )));
+                return;
+            }
+        };
+        let mut player = Player::load_player(saved, uuid, username, properties, client.into());
+        if let Some(locale) = locale {
+            player.locale = locale;
+        }
 
         let join_game = CLogin {
             entity_id: player.entity_id as i32,
@@ -339,17 +564,7 @@ impl MinecraftServer {
         player.client.send_packet(&entity_metadata);
 
         // Send the player's position and rotation.
-        let player_pos_and_look = CSynchronizePlayerPosition {
-            x: player.pos.x,
-            y: player.pos.y,
-            z: player.pos.z,
-            yaw: player.yaw,
-            pitch: player.pitch,
-            flags: 0,
-            teleport_id: 0,
-        }
-        .encode();
-        player.client.send_packet(&player_pos_and_look);
+        player.teleport_facing(player.pos, player.yaw, player.pitch);
 
         // Send the player list to the newly connected player.
         // (This is the list you see when you press tab in-game)
@@ -387,27 +602,8 @@ impl MinecraftServer {
         .encode();
         player.client.send_packet(&player_info);
 
-        // Send the player's inventory
-        let slot_data: Vec<Option<SlotData>> = player
-            .inventory
-            .iter()
-            .map(|op| op.as_ref().map(utils::encode_slot_data))
-            .collect();
-        let window_items = CSetContainerContent {
-            window_id: 0,
-            state_id: 0,
-            slot_data,
-            carried_item: None,
-        }
-        .encode();
-        player.client.send_packet(&window_items);
-
-        // Send the player's selected item slot
-        let held_item_change = CSetHeldItem {
-            slot: player.selected_slot as i8,
-        }
-        .encode();
-        player.client.send_packet(&held_item_change);
+        // Send the player's inventory and selected item slot
+        player.send_inventory();
 
         player.client.send_packet(&DECLARE_COMMANDS);
 
@@ -477,7 +673,7 @@ impl MinecraftServer {
             if !whitelisted {
                 let disconnect = CDisconnectLogin {
                     reason: json!({
-                        "text": "You are not whitelisted on this server"
+                        "text": text!("login.not_whitelisted").translate(DEFAULT_LOCALE)
                     })
                     .to_string(),
                 }
@@ -505,10 +701,69 @@ impl MinecraftServer {
         clients[client_idx].send_packet(&login_success);
     }
 
+    /// Lists the online players, grouped by the plot they are in
+    fn send_player_list(&self, sender: &MessageSender) {
+        let mut plots: BTreeMap<(&str, i32, i32), Vec<&str>> = BTreeMap::new();
+        for player in self.online_players.values() {
+            plots
+                .entry((&player.world, player.plot_x, player.plot_z))
+                .or_default()
+                .push(&player.username);
+        }
+        sender.send_system_message(text!("list.header", count = self.online_players.len()));
+        for ((world, plot_x, plot_z), mut usernames) in plots {
+            usernames.sort_unstable_by_key(|username| username.to_lowercase());
+            let usernames = usernames.join(", ");
+            if world == DEFAULT_WORLD {
+                sender.send_system_message(text!(
+                    "list.plot",
+                    x = plot_x,
+                    z = plot_z,
+                    players = usernames
+                ));
+            } else {
+                sender.send_system_message(text!(
+                    "list.world_plot",
+                    world = world,
+                    x = plot_x,
+                    z = plot_z,
+                    players = usernames
+                ));
+            }
+        }
+    }
+
+    fn send_last_seen(&self, username: &str, sender: &MessageSender) {
+        if let Some(uuid) = self.find_online_player(username) {
+            let username = &self.online_players[&uuid].username;
+            sender.send_system_message(text!("seen.online", player = username));
+            return;
+        }
+        let Some(seen) = database::get_last_seen(username) else {
+            sender.send_error_message(text!("seen.never_joined"));
+            return;
+        };
+        let now = utils::unix_time();
+        let ago = |time| utils::format_time_ago(now - time).translate(sender.locale());
+        let message = match (seen.last_login, seen.last_logout) {
+            (_, Some(logout)) => text!("seen.offline", player = seen.username, time = ago(logout)),
+            (Some(login), None) => {
+                text!(
+                    "seen.last_joined",
+                    player = seen.username,
+                    time = ago(login)
+                )
+            }
+            (None, None) => text!("seen.unknown", player = seen.username),
+        };
+        sender.send_system_message(message);
+    }
+
     fn handle_message(&mut self, message: Message) {
         match message {
             Message::PlayerJoined(player) => {
                 info!("{} joined the game", player.username);
+                events::publish(&Event::PlayerJoin(PlayerInfo::of(&player)));
                 // Send player info to plots
                 let player_join_info = PlayerJoinInfo {
                     username: player.username.clone(),
@@ -516,7 +771,9 @@ impl MinecraftServer {
                     gamemode: player.gamemode,
                     properties: player.properties.clone(),
                 };
-                database::ensure_user(&format!("{:032x}", player.uuid), &player.username);
+                let uuid = format!("{:032x}", player.uuid);
+                database::ensure_user(&uuid, &player.username);
+                database::set_last_login(&uuid, utils::unix_time());
                 self.broadcaster
                     .broadcast(BroadcastMessage::PlayerJoinedInfo(player_join_info));
                 self.send_player_to_plot(player, true);
@@ -524,11 +781,19 @@ impl MinecraftServer {
             Message::PlayerLeft(uuid) => {
                 if let Some((_, player)) = self.online_players.remove_entry(&uuid) {
                     info!("{} left the game", player.username);
+                    database::set_last_logout(&format!("{:032x}", uuid), utils::unix_time());
+                    events::publish(&Event::PlayerQuit(PlayerInfo {
+                        uuid,
+                        username: player.username,
+                    }));
                 }
+                metrics::PLAYERS_ONLINE.store(self.online_players.len(), Ordering::Relaxed);
                 self.broadcaster
                     .broadcast(BroadcastMessage::PlayerLeft(uuid));
             }
-            Message::PlotUnload(plot_x, plot_z) => self.handle_plot_unload(plot_x, plot_z),
+            Message::PlotUnload(world, plot_x, plot_z) => {
+                self.handle_plot_unload(&world, plot_x, plot_z)
+            }
             Message::ChatInfo(uuid, username, message) => {
                 info!("<{}> {}", username, message);
                 self.broadcaster.broadcast(BroadcastMessage::Chat(
@@ -540,6 +805,147 @@ impl MinecraftServer {
                             .replace("{message}", &message),
                     ),
                 ));
+                discord::send_chat(&username, &message);
+            }
+            Message::AdminRequest(request) => self.handle_admin_request(request),
+            Message::DiscordChat(author, message) => {
+                info!("[Discord] <{}> {}", author, message);
+                let discord_format = &CONFIG.discord.as_ref().unwrap().format;
+                self.broadcaster.broadcast(BroadcastMessage::Chat(
+                    0,
+                    TextComponent::from_legacy_text(
+                        &discord_format
+                            .replace("{username}", &author)
+                            .replace("{message}", &message),
+                    ),
+                ));
+            }
+            Message::Announce(message) => {
+                info!("[Announcement] {}", message);
+                self.broadcaster.broadcast(BroadcastMessage::Chat(
+                    0,
+                    TextComponent::from_legacy_text(&message),
+                ));
+            }
+            Message::Notice(message) => {
+                info!("[Notice] {}", message.translate(DEFAULT_LOCALE));
+                self.broadcaster
+                    .broadcast(BroadcastMessage::Notice(message));
+            }
+            Message::Backup(keep) => {
+                info!("Backing up the world");
+                let (saved_tx, saved_rx) = mpsc::channel();
+                self.broadcaster.broadcast(BroadcastMessage::Save(saved_tx));
+                scheduler::start_backup(self.running_plots.len(), saved_rx, keep);
+            }
+            Message::RunCommand(uuid, command) => match self.online_players.get(&uuid) {
+                Some(player) => {
+                    info!("Running /{} as {} as scheduled", command, player.username);
+                    self.broadcaster
+                        .broadcast(BroadcastMessage::RunCommand(uuid, command));
+                }
+                None => warn!(
+                    "Not running /{} as scheduled, the owner of the task is not online",
+                    command
+                ),
+            },
+            Message::GiveItem(username, item, sender) => {
+                let username_lower = username.to_lowercase();
+                if let Some(uuid) = self
+                    .online_players
+                    .iter()
+                    .find(|(_, p)| p.username.to_lowercase() == username_lower)
+                    .map(|(uuid, _)| *uuid)
+                {
+                    self.broadcaster
+                        .broadcast(BroadcastMessage::GiveItem(uuid, item));
+                } else {
+                    sender.send_error_message(text!("command.player_not_found"));
+                }
+            }
+            Message::Kick {
+                target,
+                reason,
+                staff_uuid,
+                staff_name,
+                sender,
+            } => {
+                let disconnect_reason = match &reason {
+                    Some(reason) => text!("kick.reason", staff = staff_name, reason = reason),
+                    None => text!("kick.no_reason", staff = staff_name),
+                };
+                match target {
+                    Some(target) => {
+                        let Some(uuid) = self.find_online_player(&target) else {
+                            sender.send_error_message(text!("command.player_not_found"));
+                            return;
+                        };
+                        let username = self.online_players[&uuid].username.clone();
+                        info!(
+                            "{} kicked {}: {}",
+                            staff_name,
+                            username,
+                            disconnect_reason.translate(DEFAULT_LOCALE)
+                        );
+                        self.broadcaster
+                            .broadcast(BroadcastMessage::KickPlayer(uuid, disconnect_reason));
+                        if CONFIG.broadcast_kicks {
+                            let message = match &reason {
+                                Some(reason) => text!(
+                                    "kick.broadcast",
+                                    player = username,
+                                    staff = staff_name,
+                                    reason = reason
+                                ),
+                                None => text!(
+                                    "kick.broadcast_no_reason",
+                                    player = username,
+                                    staff = staff_name
+                                ),
+                            };
+                            self.broadcaster
+                                .broadcast(BroadcastMessage::Notice(message));
+                        } else {
+                            sender.send_system_message(text!("kick.done", player = username));
+                        }
+                    }
+                    None => {
+                        let uuids: Vec<u128> = self
+                            .online_players
+                            .keys()
+                            .copied()
+                            .filter(|&uuid| uuid != staff_uuid)
+                            .collect();
+                        info!(
+                            "{} kicked {} players: {}",
+                            staff_name,
+                            uuids.len(),
+                            disconnect_reason.translate(DEFAULT_LOCALE)
+                        );
+                        for &uuid in &uuids {
+                            self.broadcaster.broadcast(BroadcastMessage::KickPlayer(
+                                uuid,
+                                disconnect_reason.clone(),
+                            ));
+                        }
+                        sender.send_system_message(text!("kick.all_done", count = uuids.len()));
+                    }
+                }
+            }
+            Message::ListPlayers(sender) => self.send_player_list(&sender),
+            Message::Seen(username, sender) => self.send_last_seen(&username, &sender),
+            Message::WhoIs(username, sender) => {
+                if let Some(uuid) = self.find_online_player(&username) {
+                    self.broadcaster
+                        .broadcast(BroadcastMessage::WhoIs(uuid, Arc::new(sender)));
+                } else {
+                    sender.send_error_message(text!("command.player_not_found"));
+                }
+            }
+            Message::Near(query) => self.broadcaster.broadcast(BroadcastMessage::Near(query)),
+            Message::NotifyStaff(message) => {
+                self.broadcaster
+                    .broadcast(BroadcastMessage::StaffNotice(message));
             }
             Message::PlayerLeavePlot(player) => {
                 self.send_player_to_plot(player, false);
@@ -547,37 +953,40 @@ impl MinecraftServer {
             Message::Shutdown => {
                 self.graceful_shutdown();
             }
-            Message::PlayerTeleportOther(player, other_username) => {
+            Message::PlayerTeleportOther(mut player, other_username) => {
                 let username_lower = other_username.to_lowercase();
                 if let Some((_, other_player)) = self
                     .online_players
                     .iter()
                     .find(|(_, p)| p.username.to_lowercase().starts_with(&username_lower))
                 {
+                    let world = other_player.world.clone();
                     let plot_x = other_player.plot_x;
                     let plot_z = other_player.plot_z;
 
                     let plot_loaded = self
                         .running_plots
                         .iter()
-                        .any(|p| p.plot_x == plot_x && p.plot_z == plot_z);
+                        .any(|p| p.world == world && p.plot_x == plot_x && p.plot_z == plot_z);
                     if !plot_loaded {
-                        player
-                            .send_system_message("Their plot wasn't loaded. How did this happen??");
+                        player.send_system_message(text!("tp.plot_not_loaded"));
                         self.send_player_to_plot(player, false);
                     } else {
-                        self.update_player_entry(player.uuid, plot_x, plot_z);
+                        self.update_player_entry(player.uuid, &world, plot_x, plot_z);
+                        player.world = world;
                         let plot_list_entry = self
                             .running_plots
                             .iter()
-                            .find(|p| p.plot_x == plot_x && p.plot_z == plot_z)
+                            .find(|p| {
+                                p.world == player.world && p.plot_x == plot_x && p.plot_z == plot_z
+                            })
                             .unwrap();
                         let _ = plot_list_entry
                             .priv_message_sender
                             .send(PrivMessage::PlayerTeleportOther(player, other_username));
                     }
                 } else {
-                    player.send_system_message("Player not found!");
+                    player.send_system_message(text!("command.player_not_found"));
                     self.send_player_to_plot(player, false);
                 }
             }
@@ -590,8 +999,7 @@ impl MinecraftServer {
             }
             Message::WhitelistAdd(uuid, username, sender) => {
                 if let Some(whitelist) = &mut self.whitelist {
-                    let msg = format!("{} was sucessfully added to the whitelist.", &username);
-                    sender.send_system_message(&msg);
+                    sender.send_system_message(text!("whitelist.added", player = username));
                     let uuid = HyphenatedUUID(uuid);
                     debug!("Added to whitelist: {} ({})", username, uuid);
 
@@ -600,7 +1008,7 @@ impl MinecraftServer {
                         uuid,
                     });
                 } else {
-                    sender.send_error_message("Whitelist is not enabled!");
+                    sender.send_error_message(text!("whitelist.disabled"));
                 }
             }
             Message::WhitelistRemove(uuid, sender) => {
@@ -609,21 +1017,20 @@ impl MinecraftServer {
                     whitelist.retain(|entry| {
                         let matches = entry.uuid.0 == uuid;
                         if matches {
-                            let msg = format!(
-                                "{} was sucessfully removed from the whitelist.",
-                                &entry.name
-                            );
-                            sender.send_system_message(&msg);
+                            sender.send_system_message(text!(
+                                "whitelist.removed",
+                                player = entry.name
+                            ));
                             debug!("Removed from whitelist: {}", HyphenatedUUID(uuid));
                             found = true;
                         }
                         !matches
                     });
                     if !found {
-                        sender.send_error_message("That player is not whitelisted on this server.");
+                        sender.send_error_message(text!("whitelist.not_whitelisted"));
                     }
                 } else {
-                    sender.send_error_message("Whitelist is not enabled!");
+                    sender.send_error_message(text!("whitelist.disabled"));
                 }
             }
         }
@@ -632,6 +1039,9 @@ impl MinecraftServer {
     fn update(&mut self) {
         while let Ok(message) = self.receiver.try_recv() {
             self.handle_message(message);
+            if self.stopped {
+                return;
+            }
         }
         self.network.update();
 
@@ -670,8 +1080,11 @@ impl ServerBoundPacketHandler for MinecraftServer {
         if next_state == NetworkState::Login && handshake.protocol_version != PROTOCOL_VERSION {
             warn!("A player tried to connect using the wrong version");
             let disconnect = CDisconnectLogin {
-                reason: json!({ "text": format!("Version mismatch, I'm on {}!", MC_VERSION) })
-                    .to_string(),
+                reason: json!({
+                    "text": text!("login.version_mismatch", version = MC_VERSION)
+                        .translate(DEFAULT_LOCALE)
+                })
+                .to_string(),
             }
             .encode();
             client.send_packet(&disconnect);
@@ -746,8 +1159,9 @@ impl ServerBoundPacketHandler for MinecraftServer {
             bed_works: false,
             respawn_anchor_works: false,
             min_y: 0,
-            height: PLOT_BLOCK_HEIGHT,
-            logical_height: PLOT_BLOCK_HEIGHT,
+            // Every world is as high as the default world
+            height: geometry(DEFAULT_WORLD).block_height,
+            logical_height: geometry(DEFAULT_WORLD).block_height,
             infiniburn: "#minecraft:infiniburn_overworld".to_owned(),
             effects: "#minecraft:overworld".to_owned(),
             ambient_light: 1.0,
@@ -795,6 +1209,15 @@ impl ServerBoundPacketHandler for MinecraftServer {
         client.send_packet(&CFinishConfiguration.encode());
     }
 
+    fn handle_client_information(&mut self, packet: SClientInformation, client_idx: usize) {
+        let client = &mut self.network.handshaking_clients[client_idx];
+        if packet.locale.len() > MAX_LOCALE_LENGTH {
+            client.close_connection();
+            return;
+        }
+        client.locale = Some(packet.locale.to_lowercase());
+    }
+
     fn handle_acknowledge_finish_configuration(
         &mut self,
         _ackowledge_finish_configuration: SAcknowledgeFinishConfiguration,
@@ -837,8 +1260,18 @@ impl ServerBoundPacketHandler for MinecraftServer {
             return;
         };
 
+        let mut properties = velocity_response.properties;
+        if floodgate::is_bedrock_uuid(velocity_response.uuid) {
+            info!(
+                "{} is joining from Bedrock through Floodgate",
+                velocity_response.username
+            );
+            properties = floodgate::java_properties(properties);
+        }
+        // The proxy may have renamed the player, like Floodgate does with its prefix
+        clients[client_idx].username = Some(velocity_response.username);
         clients[client_idx].uuid = Some(velocity_response.uuid);
-        clients[client_idx].properties = velocity_response.properties;
+        clients[client_idx].properties = properties;
         self.complete_player_login(client_idx);
     }
 }
@@ -0,0 +1,267 @@
+//! A parser for stringified NBT, the `{Key:"value",Count:3b}` syntax used in commands.
+
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+pub type SnbtResult<T> = Result<T, String>;
+
+struct Parser<'a> {
+    input: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+}
+
+impl<'a> Parser<'a> {
+    fn new(input: &'a str) -> Parser<'a> {
+        Parser {
+            input,
+            chars: input.char_indices().peekable(),
+        }
+    }
+
+    fn pos(&mut self) -> usize {
+        self.chars.peek().map_or(self.input.len(), |&(i, _)| i)
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.skip_whitespace();
+        self.chars.peek().map(|&(_, c)| c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+    }
+
+    fn error<T>(&mut self, message: &str) -> SnbtResult<T> {
+        Err(format!("{} at position {}", message, self.pos()))
+    }
+
+    fn expect(&mut self, expected: char) -> SnbtResult<()> {
+        if self.peek() == Some(expected) {
+            self.chars.next();
+            Ok(())
+        } else {
+            self.error(&format!("expected '{}'", expected))
+        }
+    }
+
+    fn is_unquoted_char(c: char) -> bool {
+        c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | '+')
+    }
+
+    fn read_unquoted(&mut self) -> &'a str {
+        self.skip_whitespace();
+        let start = self.pos();
+        while self
+            .chars
+            .next_if(|&(_, c)| Self::is_unquoted_char(c))
+            .is_some()
+        {}
+        let end = self.pos();
+        &self.input[start..end]
+    }
+
+    fn read_quoted(&mut self, quote: char) -> SnbtResult<String> {
+        let mut string = String::new();
+        loop {
+            match self.chars.next() {
+                Some((_, '\\')) => match self.chars.next() {
+                    Some((_, c)) if c == quote || c == '\\' => string.push(c),
+                    _ => return self.error("invalid escape sequence"),
+                },
+                Some((_, c)) if c == quote => return Ok(string),
+                Some((_, c)) => string.push(c),
+                None => return self.error("unterminated string"),
+            }
+        }
+    }
+
+    fn read_string(&mut self) -> SnbtResult<String> {
+        match self.peek() {
+            Some(quote @ ('"' | '\'')) => {
+                self.chars.next();
+                self.read_quoted(quote)
+            }
+            _ => {
+                let string = self.read_unquoted();
+                if string.is_empty() {
+                    return self.error("expected a string");
+                }
+                Ok(string.to_string())
+            }
+        }
+    }
+
+    fn read_value(&mut self) -> SnbtResult<nbt::Value> {
+        match self.peek() {
+            Some('{') => self.read_compound().map(nbt::Value::Compound),
+            Some('[') => self.read_list(),
+            Some('"' | '\'') => self.read_string().map(nbt::Value::String),
+            Some(_) => {
+                let token = self.read_unquoted();
+                if token.is_empty() {
+                    return self.error("expected a value");
+                }
+                Ok(parse_primitive(token))
+            }
+            None => self.error("expected a value"),
+        }
+    }
+
+    fn read_compound(&mut self) -> SnbtResult<nbt::Map<String, nbt::Value>> {
+        self.expect('{')?;
+        let mut compound = nbt::Map::new();
+        if self.peek() == Some('}') {
+            self.chars.next();
+            return Ok(compound);
+        }
+        loop {
+            let key = self.read_string()?;
+            self.expect(':')?;
+            let value = self.read_value()?;
+            compound.insert(key, value);
+            match self.peek() {
+                Some(',') => {
+                    self.chars.next();
+                }
+                Some('}') => {
+                    self.chars.next();
+                    return Ok(compound);
+                }
+                _ => return self.error("expected ',' or '}'"),
+            }
+        }
+    }
+
+    /// Reads the elements of a list or array up to and including the closing bracket
+    fn read_elements(&mut self) -> SnbtResult<Vec<nbt::Value>> {
+        let mut elements = Vec::new();
+        if self.peek() == Some(']') {
+            self.chars.next();
+            return Ok(elements);
+        }
+        loop {
+            elements.push(self.read_value()?);
+            match self.peek() {
+                Some(',') => {
+                    self.chars.next();
+                }
+                Some(']') => {
+                    self.chars.next();
+                    return Ok(elements);
+                }
+                _ => return self.error("expected ',' or ']'"),
+            }
+        }
+    }
+
+    fn read_list(&mut self) -> SnbtResult<nbt::Value> {
+        self.expect('[')?;
+        // Typed arrays look like `[I;1,2,3]`
+        let mut lookahead = self.chars.clone();
+        if let (Some((_, ty)), Some((_, ';'))) = (lookahead.next(), lookahead.next()) {
+            self.chars.next();
+            self.chars.next();
+            let elements = self.read_elements()?;
+            return match ty {
+                'B' => elements
+                    .into_iter()
+                    .map(|value| match value {
+                        nbt::Value::Byte(b) => Ok(b),
+                        _ => Err("byte arrays can only contain bytes".to_string()),
+                    })
+                    .collect::<SnbtResult<_>>()
+                    .map(nbt::Value::ByteArray),
+                'I' => elements
+                    .into_iter()
+                    .map(|value| match value {
+                        nbt::Value::Int(i) => Ok(i),
+                        _ => Err("int arrays can only contain ints".to_string()),
+                    })
+                    .collect::<SnbtResult<_>>()
+                    .map(nbt::Value::IntArray),
+                'L' => elements
+                    .into_iter()
+                    .map(|value| match value {
+                        nbt::Value::Long(l) => Ok(l),
+                        _ => Err("long arrays can only contain longs".to_string()),
+                    })
+                    .collect::<SnbtResult<_>>()
+                    .map(nbt::Value::LongArray),
+                _ => Err(format!("invalid array type '{}'", ty)),
+            };
+        }
+
+        let elements = self.read_elements()?;
+        if let Some(first) = elements.first() {
+            let id = first.id();
+            if elements.iter().any(|value| value.id() != id) {
+                return Err("all elements of a list must have the same type".to_string());
+            }
+        }
+        Ok(nbt::Value::List(elements))
+    }
+}
+
+/// Parses an unquoted token as a number or boolean. Anything else is a string.
+fn parse_primitive(token: &str) -> nbt::Value {
+    let (number, suffix) = match token.char_indices().last() {
+        Some((i, c)) if c.is_ascii_alphabetic() => (&token[..i], Some(c.to_ascii_lowercase())),
+        _ => (token, None),
+    };
+    let value = match suffix {
+        Some('b') => number.parse().ok().map(nbt::Value::Byte),
+        Some('s') => number.parse().ok().map(nbt::Value::Short),
+        Some('l') => number.parse().ok().map(nbt::Value::Long),
+        Some('f') => number.parse().ok().map(nbt::Value::Float),
+        Some('d') => number.parse().ok().map(nbt::Value::Double),
+        None if number.contains('.') => number.parse().ok().map(nbt::Value::Double),
+        None => number.parse().ok().map(nbt::Value::Int),
+        _ => None,
+    };
+    value.unwrap_or_else(|| match token {
+        "true" => nbt::Value::Byte(1),
+        "false" => nbt::Value::Byte(0),
+        _ => nbt::Value::String(token.to_string()),
+    })
+}
+
+/// Parses a compound tag, returning it and the rest of the input after the closing brace
+pub fn parse_compound(input: &str) -> SnbtResult<(nbt::Blob, &str)> {
+    let mut parser = Parser::new(input);
+    let compound = parser.read_compound()?;
+    let rest = &input[parser.pos()..];
+    Ok((nbt::Blob::with_content(compound), rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_item_tag() {
+        let (blob, rest) = parse_compound(
+            r#"{display:{Name:'{"text":"Reset"}'},Count:3b,Items:[{Slot:0b,id:"minecraft:redstone"}],Ids:[I;1,2]} 5"#,
+        )
+        .unwrap();
+        assert_eq!(rest, " 5");
+        assert_eq!(blob["Count"], nbt::Value::Byte(3));
+        assert_eq!(blob["Ids"], nbt::Value::IntArray(vec![1, 2]));
+        let nbt::Value::Compound(display) = &blob["display"] else {
+            panic!("display is not a compound");
+        };
+        assert_eq!(
+            display["Name"],
+            nbt::Value::String(r#"{"text":"Reset"}"#.to_string())
+        );
+        let nbt::Value::List(items) = &blob["Items"] else {
+            panic!("Items is not a list");
+        };
+        assert_eq!(items.len(), 1);
+    }
+
+    #[test]
+    fn reject_mixed_list() {
+        assert!(parse_compound("{a:[1,2b]}").is_err());
+        assert!(parse_compound("{a:1").is_err());
+    }
+}
@@ -0,0 +1,82 @@
+//! The place where new players join, and where `/spawn` takes players. It is set in game with
+//! `/setspawn` and saved in `world/spawn.toml`, which can also be edited while the server is
+//! stopped.
+
+use crate::worlds::{self, DEFAULT_WORLD};
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::sync::Mutex;
+use tracing::warn;
+
+const SPAWN_PATH: &str = "./world/spawn.toml";
+
+static SPAWN: Lazy<Mutex<Spawn>> = Lazy::new(|| Mutex::new(Spawn::load()));
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Spawn {
+    pub world: String,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+    #[serde(default)]
+    pub yaw: f32,
+    #[serde(default)]
+    pub pitch: f32,
+}
+
+impl Default for Spawn {
+    /// Where players joined before the spawn could be set
+    fn default() -> Spawn {
+        Spawn {
+            world: DEFAULT_WORLD.to_string(),
+            x: 128.0,
+            y: 128.0,
+            z: 128.0,
+            yaw: 0.0,
+            pitch: 0.0,
+        }
+    }
+}
+
+impl Spawn {
+    fn load() -> Spawn {
+        let Ok(contents) = fs::read_to_string(SPAWN_PATH) else {
+            return Default::default();
+        };
+        let spawn: Spawn = match toml::from_str(&contents) {
+            Ok(spawn) => spawn,
+            Err(err) => {
+                warn!("There was an error loading world/spawn.toml: {}", err);
+                return Default::default();
+            }
+        };
+        if !worlds::exists(&spawn.world) {
+            warn!(
+                "The spawn is in the world {}, which does not exist",
+                spawn.world
+            );
+            return Default::default();
+        }
+        spawn
+    }
+}
+
+pub fn get() -> Spawn {
+    SPAWN.lock().unwrap().clone()
+}
+
+/// Moves the spawn and saves it
+pub fn set(spawn: Spawn) -> Result<()> {
+    fs::write(SPAWN_PATH, toml::to_string(&spawn)?)?;
+    *SPAWN.lock().unwrap() = spawn;
+    Ok(())
+}
+
+#[test]
+fn spawn_without_rotation() {
+    let spawn: Spawn = toml::from_str("world = \"world\"\nx = 0.5\ny = 64.0\nz = 0.5").unwrap();
+    assert_eq!(spawn.y, 64.0);
+    assert_eq!(spawn.yaw, 0.0);
+}
@@ -1,13 +1,36 @@
+use crate::lang::Text;
 use mchprs_blocks::block_entities::InventoryEntry;
 use mchprs_blocks::items::{Item, ItemStack};
 use mchprs_network::packets::SlotData;
 use serde::de::Visitor;
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 use std::io::Cursor;
 use std::num::ParseIntError;
 use std::str::FromStr;
+use std::time::SystemTime;
 
-#[derive(Debug)]
+/// The current time in seconds since the unix epoch
+pub fn unix_time() -> i64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs() as i64)
+}
+
+/// Describes how long ago something happened, like `3 hours ago`
+pub fn format_time_ago(seconds: i64) -> Text {
+    match seconds.max(0) {
+        s if s < 60 => text!("time.just_now"),
+        s if s < 2 * 60 => text!("time.minute_ago"),
+        s if s < 60 * 60 => text!("time.minutes_ago", amount = s / 60),
+        s if s < 2 * 60 * 60 => text!("time.hour_ago"),
+        s if s < 24 * 60 * 60 => text!("time.hours_ago", amount = s / (60 * 60)),
+        s if s < 2 * 24 * 60 * 60 => text!("time.day_ago"),
+        s => text!("time.days_ago", amount = s / (24 * 60 * 60)),
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
 pub struct HyphenatedUUID(pub u128);
 
 impl std::fmt::Display for HyphenatedUUID {
@@ -75,6 +98,28 @@ pub fn encode_slot_data(item: &ItemStack) -> SlotData {
     }
 }
 
+pub fn decode_slot_data(slot_data: SlotData) -> ItemStack {
+    ItemStack {
+        count: slot_data.item_count as u8,
+        item_type: Item::from_id(slot_data.item_id as u32),
+        nbt: slot_data.nbt.map(nbt::Blob::with_content),
+    }
+}
+
+pub fn stack_to_inventory_entry(slot: i8, item: &ItemStack) -> InventoryEntry {
+    let nbt = item.nbt.as_ref().map(|blob| {
+        let mut data = Vec::new();
+        blob.to_writer(&mut data).unwrap();
+        data
+    });
+    InventoryEntry {
+        count: item.count as i8,
+        id: item.item_type.get_id(),
+        slot,
+        nbt,
+    }
+}
+
 pub fn inventory_entry_to_stack(entry: &InventoryEntry) -> ItemStack {
     let nbt = entry
         .nbt
@@ -86,3 +131,64 @@ pub fn inventory_entry_to_stack(entry: &InventoryEntry) -> ItemStack {
         nbt,
     }
 }
+
+/// Writes the pages of a book and quill. Signing it turns it into a written book, whose pages are
+/// text components rather than the plain text they were written as.
+pub fn write_book(book: &mut ItemStack, pages: Vec<String>, signature: Option<(String, &str)>) {
+    let mut tag = book.nbt.take().unwrap_or_else(nbt::Blob::new);
+    let pages = match signature {
+        Some((title, author)) => {
+            book.item_type = Item::WrittenBook {};
+            let tag = &mut tag.content;
+            tag.insert("title".to_string(), nbt::Value::String(title));
+            tag.insert("author".to_string(), nbt::Value::String(author.to_string()));
+            // The pages are plain text, so there is nothing in them for the client to resolve
+            tag.insert("resolved".to_string(), nbt::Value::Byte(1));
+            pages
+                .into_iter()
+                .map(|page| nbt::Value::String(json!({ "text": page }).to_string()))
+                .collect()
+        }
+        None => pages.into_iter().map(nbt::Value::String).collect(),
+    };
+    tag.content
+        .insert("pages".to_string(), nbt::Value::List(pages));
+    book.nbt = Some(tag);
+}
+
+#[test]
+fn signing_a_book() {
+    let mut book = ItemStack {
+        item_type: Item::WritableBook {},
+        count: 1,
+        nbt: None,
+    };
+    write_book(&mut book, vec!["ADD r1 r2".to_string()], None);
+    let pages = |book: &ItemStack| book.nbt.as_ref().unwrap().content["pages"].clone();
+    assert_eq!(
+        pages(&book),
+        nbt::Value::List(vec![nbt::Value::String("ADD r1 r2".to_string())])
+    );
+
+    let signature = Some(("ISA".to_string(), "Builder"));
+    write_book(&mut book, vec!["ADD \"r1\"".to_string()], signature);
+    assert_eq!(book.item_type, Item::WrittenBook {});
+    assert_eq!(
+        pages(&book),
+        nbt::Value::List(vec![nbt::Value::String(
+            r#"{"text":"ADD \"r1\""}"#.to_string()
+        )])
+    );
+    let tag = &book.nbt.as_ref().unwrap().content;
+    assert_eq!(tag["title"], nbt::Value::String("ISA".to_string()));
+    assert_eq!(tag["author"], nbt::Value::String("Builder".to_string()));
+}
+
+#[test]
+fn time_ago() {
+    let ago = |seconds| format_time_ago(seconds).translate("en_us");
+    assert_eq!(ago(10), "just now");
+    assert_eq!(ago(60), "1 minute ago");
+    assert_eq!(ago(3 * 60 * 60 + 5), "3 hours ago");
+    assert_eq!(ago(50 * 60 * 60), "2 days ago");
+}
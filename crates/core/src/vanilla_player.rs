@@ -0,0 +1,180 @@
+//! Reads the player files a vanilla server keeps in the `playerdata` folder of its world, so
+//! players of a creative server that moved to MCHPRS keep their inventory and position. A player
+//! is only imported the first time they join, after that they have an MCHPRS player file.
+
+use anyhow::{Context, Result};
+use mchprs_blocks::block_entities::{BlockEntity, InventoryEntry};
+use nbt::Value;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, ErrorKind};
+use std::path::Path;
+
+pub struct VanillaPlayer {
+    pub position: [f64; 3],
+    pub yaw: f32,
+    pub pitch: f32,
+    /// The items in the slots of the player's inventory window
+    pub inventory: Vec<InventoryEntry>,
+    pub selected_slot: i32,
+    pub spectator: bool,
+    pub on_ground: bool,
+    pub flying: bool,
+    /// The speeds relative to the vanilla defaults, like `/speed` sets them
+    pub fly_speed: f32,
+    pub walk_speed: f32,
+}
+
+/// The slot of the inventory window that shows a slot of the saved inventory, which numbers the
+/// hotbar first and the armor and offhand slots separately
+fn window_slot(slot: i8) -> Option<i8> {
+    match slot {
+        0..=8 => Some(slot + 36),
+        9..=35 => Some(slot),
+        // From the boots to the helmet
+        100..=103 => Some(108 - slot),
+        -106 => Some(45),
+        _ => None,
+    }
+}
+
+impl VanillaPlayer {
+    /// Reads a player file, returning `None` if it doesn't exist
+    pub fn read(path: &Path) -> Result<Option<VanillaPlayer>> {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(err) => {
+                return Err(err).with_context(|| format!("failed to open {}", path.display()))
+            }
+        };
+        let nbt = nbt::Blob::from_gzip_reader(&mut BufReader::new(file))
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        let player = VanillaPlayer::from_nbt(&nbt.content)
+            .with_context(|| format!("{} is missing the position", path.display()))?;
+        Ok(Some(player))
+    }
+
+    fn from_nbt(nbt: &HashMap<String, Value>) -> Option<VanillaPlayer> {
+        let pos = match nbt.get("Pos") {
+            Some(Value::List(pos)) => pos.as_slice(),
+            _ => return None,
+        };
+        let [Value::Double(x), Value::Double(y), Value::Double(z)] = pos else {
+            return None;
+        };
+        let (yaw, pitch) = match nbt.get("Rotation") {
+            Some(Value::List(rotation)) => match rotation.as_slice() {
+                [Value::Float(yaw), Value::Float(pitch)] => (*yaw, *pitch),
+                _ => (0.0, 0.0),
+            },
+            _ => (0.0, 0.0),
+        };
+
+        let mut inventory = Vec::new();
+        if let Some(Value::List(items)) = nbt.get("Inventory") {
+            for item in items {
+                let Value::Compound(item) = item else {
+                    continue;
+                };
+                let Some(Value::Byte(slot)) = item.get("Slot") else {
+                    continue;
+                };
+                let entry = window_slot(*slot).and_then(|slot| BlockEntity::load_item(item, slot));
+                inventory.extend(entry);
+            }
+        }
+
+        let byte = |nbt: &HashMap<String, Value>, name: &str| match nbt.get(name) {
+            Some(Value::Byte(value)) => Some(*value != 0),
+            _ => None,
+        };
+        let float = |nbt: &HashMap<String, Value>, name: &str| match nbt.get(name) {
+            Some(Value::Float(value)) => Some(*value),
+            _ => None,
+        };
+        let empty = HashMap::new();
+        let abilities = match nbt.get("abilities") {
+            Some(Value::Compound(abilities)) => abilities,
+            _ => &empty,
+        };
+        Some(VanillaPlayer {
+            position: [*x, *y, *z],
+            yaw,
+            pitch,
+            inventory,
+            selected_slot: match nbt.get("SelectedItemSlot") {
+                Some(Value::Int(slot @ 0..=8)) => *slot,
+                _ => 0,
+            },
+            // Everyone plays in creative, except for spectators
+            spectator: matches!(nbt.get("playerGameType"), Some(Value::Int(3))),
+            on_ground: byte(nbt, "OnGround").unwrap_or(true),
+            flying: byte(abilities, "flying").unwrap_or(false),
+            fly_speed: float(abilities, "flySpeed").map_or(1.0, |speed| speed / 0.05),
+            walk_speed: float(abilities, "walkSpeed").map_or(1.0, |speed| speed / 0.1),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mchprs_blocks::items::Item;
+    use mchprs_utils::map;
+
+    #[test]
+    fn inventory_slots() {
+        let item = |slot: i8, id: &str| {
+            Value::Compound(map! {
+                "Slot" => Value::Byte(slot),
+                "id" => Value::String(id.to_string()),
+                "Count" => Value::Byte(1)
+            })
+        };
+        let pos = vec![
+            Value::Double(10.5),
+            Value::Double(64.0),
+            Value::Double(-3.5),
+        ];
+        let nbt = map! {
+            "Pos" => Value::List(pos),
+            "Rotation" => Value::List(vec![Value::Float(90.0), Value::Float(-10.0)]),
+            "SelectedItemSlot" => Value::Int(2),
+            "playerGameType" => Value::Int(1),
+            "abilities" => Value::Compound(map! {
+                "flying" => Value::Byte(1),
+                "flySpeed" => Value::Float(0.1)
+            }),
+            "Inventory" => Value::List(vec![
+                item(0, "minecraft:redstone"),
+                item(9, "minecraft:repeater"),
+                item(103, "minecraft:glass"),
+                item(-106, "minecraft:stone")
+            ])
+        };
+        let player = VanillaPlayer::from_nbt(&nbt).unwrap();
+        assert_eq!(player.position, [10.5, 64.0, -3.5]);
+        assert_eq!((player.yaw, player.pitch), (90.0, -10.0));
+        assert_eq!(player.selected_slot, 2);
+        assert!(!player.spectator);
+        assert!(player.flying);
+        assert_eq!(player.fly_speed, 2.0);
+        assert_eq!(player.walk_speed, 1.0);
+
+        let slots: Vec<_> = player
+            .inventory
+            .iter()
+            .map(|entry| (entry.slot, Item::from_id(entry.id)))
+            .collect();
+        assert_eq!(
+            slots,
+            [
+                (36, Item::Redstone {}),
+                (9, Item::Repeater {}),
+                (5, Item::Glass {}),
+                (45, Item::Stone {})
+            ]
+        );
+    }
+}
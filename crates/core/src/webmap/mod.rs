@@ -0,0 +1,268 @@
+//! A web page that shows a live top-down view of a plot, for watching a display from a browser.
+//!
+//! The page is served over HTTP, and connects back over a WebSocket to
+//! `/ws/<world>/<plot x>/<plot z>`.
+//! Plot threads pick up new viewers, send them the whole plot once, and then only send the
+//! columns that changed when block changes are flushed.
+//!
+//! Every message starts with a byte for its type:
+//! - `0`: The whole plot, followed by the width as a little endian `u16` and RGB pixels.
+//! - `1`: Changed columns, followed by the count as a little endian `u32` and for every column
+//!   its x and z as little endian `u16`s and its RGB color.
+
+pub mod render;
+
+use crate::config::WebMapConfig;
+use crate::plot::PlotWorld;
+use once_cell::sync::Lazy;
+use rustc_hash::{FxHashMap, FxHashSet};
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::{error, info, warn};
+use tungstenite::Message as WsMessage;
+
+const INDEX_HTML: &str = include_str!("index.html");
+/// How often plots send changes to their viewers
+const UPDATE_INTERVAL: Duration = Duration::from_millis(100);
+/// How often the server checks if a viewer is still connected when nothing changes
+const PING_INTERVAL: Duration = Duration::from_secs(10);
+
+const FULL_FRAME: u8 = 0;
+const CHANGED_COLUMNS: u8 = 1;
+
+struct Viewer {
+    id: u64,
+    sender: Sender<Vec<u8>>,
+}
+
+static NEXT_VIEWER_ID: AtomicU64 = AtomicU64::new(0);
+/// The number of pending viewers, so plots don't need to take the lock when there are none
+static PENDING_COUNT: AtomicUsize = AtomicUsize::new(0);
+/// Viewers that connected and haven't been picked up by their plot yet, by world and plot
+/// position
+static PENDING_VIEWERS: Lazy<Mutex<FxHashMap<(String, i32, i32), Vec<Viewer>>>> =
+    Lazy::new(Default::default);
+
+/// The state of the map of a plot that has viewers
+pub struct MapFeed {
+    /// The width of the plot in blocks
+    width: u16,
+    image: Vec<u8>,
+    /// Chunks that changed since the last update, relative to the plot
+    changed: FxHashSet<(u16, u16)>,
+    viewers: Vec<Viewer>,
+    last_update: Instant,
+}
+
+impl MapFeed {
+    fn new(world: &PlotWorld) -> MapFeed {
+        MapFeed {
+            width: world.geometry.block_width as u16,
+            image: render::render_plot(world),
+            changed: FxHashSet::default(),
+            viewers: Vec::new(),
+            last_update: Instant::now(),
+        }
+    }
+
+    fn full_frame(&self) -> Vec<u8> {
+        let mut frame = Vec::with_capacity(self.image.len() + 3);
+        frame.push(FULL_FRAME);
+        frame.extend_from_slice(&self.width.to_le_bytes());
+        frame.extend_from_slice(&self.image);
+        frame
+    }
+
+    /// Marks a chunk as changed, with coordinates relative to the plot
+    pub fn record_chunk_change(&mut self, chunk_x: i32, chunk_z: i32) {
+        self.changed.insert((chunk_x as u16, chunk_z as u16));
+    }
+}
+
+fn take_pending_viewers(world: &str, plot_x: i32, plot_z: i32) -> Vec<Viewer> {
+    if PENDING_COUNT.load(Ordering::Relaxed) == 0 {
+        return Vec::new();
+    }
+    let viewers = PENDING_VIEWERS
+        .lock()
+        .unwrap()
+        .remove(&(world.to_string(), plot_x, plot_z))
+        .unwrap_or_default();
+    PENDING_COUNT.fetch_sub(viewers.len(), Ordering::Relaxed);
+    viewers
+}
+
+/// Re-renders the changed parts of the plot and sends them to its viewers. Returns `None` once
+/// the plot has no viewers left.
+pub fn update_feed(world_name: &str, world: &PlotWorld, feed: Option<MapFeed>) -> Option<MapFeed> {
+    if matches!(&feed, Some(feed) if feed.last_update.elapsed() < UPDATE_INTERVAL) {
+        return feed;
+    }
+    let new_viewers = take_pending_viewers(world_name, world.x, world.z);
+    let mut feed = match feed {
+        Some(feed) => feed,
+        None if new_viewers.is_empty() => return None,
+        None => MapFeed::new(world),
+    };
+    feed.last_update = Instant::now();
+
+    // Changes are recorded by chunk, so every column of a changed chunk is rendered again
+    let width = world.geometry.block_width as usize;
+    let mut changed_columns = Vec::new();
+    for (chunk_x, chunk_z) in feed.changed.drain() {
+        let chunk =
+            &world.chunks[chunk_x as usize * world.geometry.width as usize + chunk_z as usize];
+        for z in 0..16 {
+            for x in 0..16 {
+                let color = render::column_color(chunk, x, z);
+                let column_x = chunk_x as usize * 16 + x as usize;
+                let column_z = chunk_z as usize * 16 + z as usize;
+                let idx = (column_z * width + column_x) * 3;
+                if feed.image[idx..idx + 3] != color {
+                    feed.image[idx..idx + 3].copy_from_slice(&color);
+                    changed_columns.push((column_x as u16, column_z as u16, color));
+                }
+            }
+        }
+    }
+
+    if !changed_columns.is_empty() {
+        let mut frame = Vec::with_capacity(5 + changed_columns.len() * 7);
+        frame.push(CHANGED_COLUMNS);
+        frame.extend_from_slice(&(changed_columns.len() as u32).to_le_bytes());
+        for (x, z, color) in changed_columns {
+            frame.extend_from_slice(&x.to_le_bytes());
+            frame.extend_from_slice(&z.to_le_bytes());
+            frame.extend_from_slice(&color);
+        }
+        feed.viewers
+            .retain(|viewer| viewer.sender.send(frame.clone()).is_ok());
+    }
+
+    if !new_viewers.is_empty() {
+        let frame = feed.full_frame();
+        for viewer in new_viewers {
+            if viewer.sender.send(frame.clone()).is_ok() {
+                feed.viewers.push(viewer);
+            }
+        }
+    }
+
+    if feed.viewers.is_empty() {
+        None
+    } else {
+        Some(feed)
+    }
+}
+
+fn serve_page(mut stream: TcpStream) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        INDEX_HTML.len(),
+        INDEX_HTML
+    )?;
+    stream.flush()
+}
+
+/// Parses the world and plot from a request line like `GET /ws/world/1/-2 HTTP/1.1`
+fn parse_plot(request_line: &str) -> Option<(String, i32, i32)> {
+    let path = request_line.split_whitespace().nth(1)?;
+    let mut segments = path.strip_prefix("/ws/")?.split('/');
+    let world = segments.next()?.to_string();
+    let x = segments.next()?.parse().ok()?;
+    let z = segments.next()?.parse().ok()?;
+    Some((world, x, z))
+}
+
+fn serve_viewer(stream: TcpStream, plot: (String, i32, i32)) -> io::Result<()> {
+    let mut socket =
+        tungstenite::accept(stream).map_err(|err| io::Error::other(err.to_string()))?;
+    let (sender, receiver) = mpsc::channel();
+    let id = NEXT_VIEWER_ID.fetch_add(1, Ordering::Relaxed);
+    let mut pending = PENDING_VIEWERS.lock().unwrap();
+    pending
+        .entry(plot.clone())
+        .or_default()
+        .push(Viewer { id, sender });
+    PENDING_COUNT.fetch_add(1, Ordering::Relaxed);
+    drop(pending);
+
+    let result = loop {
+        let message = match receiver.recv_timeout(PING_INTERVAL) {
+            Ok(frame) => WsMessage::Binary(frame),
+            Err(RecvTimeoutError::Timeout) => WsMessage::Ping(Vec::new()),
+            // The plot unloaded
+            Err(RecvTimeoutError::Disconnected) => break Ok(()),
+        };
+        if let Err(err) = socket.send(message) {
+            break Err(err);
+        }
+    };
+
+    // The plot might not have picked up the viewer yet
+    if let Some(viewers) = PENDING_VIEWERS.lock().unwrap().get_mut(&plot) {
+        let len = viewers.len();
+        viewers.retain(|viewer| viewer.id != id);
+        PENDING_COUNT.fetch_sub(len - viewers.len(), Ordering::Relaxed);
+    }
+    let _ = socket.close(None);
+    match result {
+        Ok(()) | Err(tungstenite::Error::ConnectionClosed) => Ok(()),
+        Err(err) => Err(io::Error::other(err.to_string())),
+    }
+}
+
+fn handle_connection(stream: TcpStream) -> io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    // Look at the request without consuming it, so the WebSocket handshake can read it again
+    let mut buf = [0; 1024];
+    let len = stream.peek(&mut buf)?;
+    let request = String::from_utf8_lossy(&buf[..len]);
+    let request_line = request.lines().next().unwrap_or_default();
+    match parse_plot(request_line) {
+        Some(plot) => {
+            stream.set_read_timeout(None)?;
+            serve_viewer(stream, plot)
+        }
+        None => {
+            // Every other path gets the page
+            let mut stream = stream;
+            let _ = stream.read(&mut buf)?;
+            serve_page(stream)
+        }
+    }
+}
+
+pub fn init(config: &WebMapConfig) {
+    let listener = match TcpListener::bind(&config.bind_address) {
+        Ok(listener) => listener,
+        Err(err) => {
+            error!("Failed to bind web map to {}: {}", config.bind_address, err);
+            return;
+        }
+    };
+    info!("Serving the web map on http://{}", config.bind_address);
+
+    thread::Builder::new()
+        .name("webmap".to_string())
+        .spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(stream) = stream else {
+                    continue;
+                };
+                // Viewers stay connected, so each one gets its own thread
+                thread::spawn(move || {
+                    if let Err(err) = handle_connection(stream) {
+                        warn!("Error serving web map connection: {}", err);
+                    }
+                });
+            }
+        })
+        .unwrap();
+}
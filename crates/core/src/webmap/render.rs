@@ -0,0 +1,123 @@
+//! Renders plots from above, one pixel per block column.
+
+use crate::plot::PlotWorld;
+use mchprs_blocks::blocks::Block;
+use mchprs_blocks::BlockColorVariant;
+use mchprs_world::storage::Chunk;
+
+pub type Rgb = [u8; 3];
+
+/// The color of columns that only contain air
+const BACKGROUND: Rgb = [24, 24, 28];
+
+fn dye_color(color: BlockColorVariant) -> Rgb {
+    match color {
+        BlockColorVariant::White => [233, 236, 236],
+        BlockColorVariant::Orange => [240, 118, 19],
+        BlockColorVariant::Magenta => [189, 68, 179],
+        BlockColorVariant::LightBlue => [58, 175, 217],
+        BlockColorVariant::Yellow => [248, 198, 39],
+        BlockColorVariant::Lime => [112, 185, 25],
+        BlockColorVariant::Pink => [237, 141, 172],
+        BlockColorVariant::Gray => [62, 68, 71],
+        BlockColorVariant::LightGray => [142, 142, 134],
+        BlockColorVariant::Cyan => [21, 137, 145],
+        BlockColorVariant::Purple => [121, 42, 172],
+        BlockColorVariant::Blue => [53, 57, 157],
+        BlockColorVariant::Brown => [114, 71, 40],
+        BlockColorVariant::Green => [84, 109, 27],
+        BlockColorVariant::Red => [161, 39, 34],
+        BlockColorVariant::Black => [20, 21, 25],
+    }
+}
+
+/// Returns the color of a block seen from above, or `None` if it can be seen through
+pub fn block_color(block: Block) -> Option<Rgb> {
+    Some(match block {
+        Block::Air {} | Block::Glass {} => return None,
+        Block::RedstoneLamp { lit: true } => [255, 214, 120],
+        Block::RedstoneLamp { lit: false } => [104, 66, 38],
+        Block::RedstoneWire { wire } => [75 + wire.power * 12, 0, 0],
+        Block::RedstoneTorch { lit } | Block::RedstoneWallTorch { lit, .. } => {
+            if lit {
+                [255, 40, 20]
+            } else {
+                [90, 28, 20]
+            }
+        }
+        Block::RedstoneBlock {} => [175, 24, 5],
+        Block::RedstoneRepeater { .. } | Block::RedstoneComparator { .. } => [165, 160, 158],
+        Block::Concrete { color } | Block::Wool { color } | Block::StainedGlass { color } => {
+            dye_color(color)
+        }
+        Block::ColoredTerracotta { color } => dye_color(color).map(|c| (c as u16 * 3 / 4) as u8),
+        Block::Terracotta {} => [152, 94, 67],
+        Block::Stone {}
+        | Block::StoneButton { .. }
+        | Block::StonePressurePlate { .. }
+        | Block::StoneBricks {} => [125, 125, 125],
+        Block::SmoothStoneSlab { .. } => [160, 160, 160],
+        Block::Lever { .. } | Block::TripwireHook { .. } | Block::Furnace { .. } => [110, 110, 110],
+        Block::Observer { .. } | Block::Hopper { .. } | Block::Cauldron { .. } => [70, 70, 72],
+        Block::Quartz {} | Block::SmoothQuartz {} | Block::QuartzSlab { .. } => [235, 229, 222],
+        Block::Sandstone {} | Block::Sand {} => [219, 207, 163],
+        Block::Glowstone {} => [250, 210, 120],
+        Block::Sign { .. } | Block::WallSign { .. } => [162, 130, 78],
+        Block::Barrel { .. }
+        | Block::TrappedChest { .. }
+        | Block::Lectern { .. }
+        | Block::Composter { .. }
+        | Block::NoteBlock { .. } => [120, 86, 50],
+        Block::Target {} => [226, 170, 158],
+        Block::SlimeBlock {} => [112, 192, 92],
+        Block::HoneyBlock {} => [250, 188, 58],
+        Block::DaylightDetector { .. } => [190, 175, 150],
+        Block::SeaPickle { .. } => [90, 110, 40],
+        Block::Cake { .. } => [238, 228, 218],
+        Block::CoalBlock {} => [16, 16, 16],
+        Block::IronTrapdoor { .. } | Block::IronBlock {} => [220, 220, 220],
+        Block::GoldBlock {} => [246, 208, 61],
+        Block::EmeraldBlock {} => [42, 203, 87],
+        Block::Clay {} => [160, 166, 179],
+        Block::PackedIce {} => [141, 180, 250],
+        Block::BoneBlock {} => [229, 225, 207],
+        Block::SoulSand {} => [81, 62, 50],
+        Block::Pumpkin {} => [198, 118, 24],
+        Block::HayBlock { .. } => [166, 136, 38],
+        Block::EndPortalFrame { .. } => [91, 120, 97],
+        _ => [128, 128, 128],
+    })
+}
+
+/// Returns the color of the highest visible block in a column of a chunk
+pub fn column_color(chunk: &Chunk, x: u32, z: u32) -> Rgb {
+    for section in chunk.sections.iter().rev() {
+        if section.block_count() == 0 {
+            continue;
+        }
+        for y in (0..16).rev() {
+            let block = Block::from_id(section.get_block(x, y, z));
+            if let Some(color) = block_color(block) {
+                return color;
+            }
+        }
+    }
+    BACKGROUND
+}
+
+/// Renders the whole plot into an RGB image, with x going right and z going down
+pub fn render_plot(world: &PlotWorld) -> Vec<u8> {
+    let width = world.geometry.block_width as usize;
+    let mut image = vec![0; width * width * 3];
+    for chunk in &world.chunks {
+        let chunk_x = (chunk.x - world.x * world.geometry.width) as usize;
+        let chunk_z = (chunk.z - world.z * world.geometry.width) as usize;
+        for z in 0..16 {
+            for x in 0..16 {
+                let idx = ((chunk_z * 16 + z) * width + chunk_x * 16 + x) * 3;
+                image[idx..idx + 3].copy_from_slice(&column_color(chunk, x as u32, z as u32));
+            }
+        }
+    }
+    image
+}
@@ -0,0 +1,76 @@
+//! Named plot worlds. Each world has its own plot grid and is stored in its own folder, while
+//! player data and the plot database are shared.
+
+use crate::config::{PlotTemplateConfig, WorldConfig, WorldGenerator, CONFIG};
+use once_cell::sync::Lazy;
+use std::path::PathBuf;
+
+/// The world players start in. It is stored directly in `./world`, like before there were
+/// multiple worlds.
+pub const DEFAULT_WORLD: &str = "world";
+
+fn config(world: &str) -> Option<&'static WorldConfig> {
+    CONFIG
+        .worlds
+        .as_ref()
+        .and_then(|worlds| worlds.iter().find(|config| config.name == world))
+}
+
+/// Returns the names of all the worlds, starting with the default one
+pub fn names() -> impl Iterator<Item = &'static str> {
+    let configured = CONFIG
+        .worlds
+        .iter()
+        .flatten()
+        .map(|config| config.name.as_str());
+    std::iter::once(DEFAULT_WORLD).chain(configured.filter(|&name| name != DEFAULT_WORLD))
+}
+
+pub fn exists(world: &str) -> bool {
+    world == DEFAULT_WORLD || config(world).is_some()
+}
+
+/// The folder `world` is saved in
+pub fn dir(world: &str) -> PathBuf {
+    if world == DEFAULT_WORLD {
+        PathBuf::from("./world")
+    } else {
+        PathBuf::from(format!("./world/worlds/{}", world))
+    }
+}
+
+/// The folder the plots of `world` are saved in
+pub fn plots_dir(world: &str) -> PathBuf {
+    dir(world).join("plots")
+}
+
+pub fn permission(world: &str) -> Option<&'static str> {
+    config(world).and_then(|config| config.permission.as_deref())
+}
+
+/// The template new plots in `world` are generated from
+pub fn template(world: &str) -> &'static PlotTemplateConfig {
+    static DEFAULT_TEMPLATE: Lazy<PlotTemplateConfig> = Lazy::new(Default::default);
+    config(world)
+        .and_then(|config| config.template.as_ref())
+        .or(CONFIG.plot_template.as_ref())
+        .unwrap_or(&DEFAULT_TEMPLATE)
+}
+
+pub fn generator(world: &str) -> WorldGenerator {
+    config(world)
+        .map(|config| config.generator)
+        .unwrap_or_default()
+}
+
+pub fn auto_redpiler(world: &str) -> bool {
+    config(world)
+        .and_then(|config| config.auto_redpiler)
+        .unwrap_or(CONFIG.auto_redpiler)
+}
+
+pub fn plot_unload_time(world: &str) -> i64 {
+    config(world)
+        .and_then(|config| config.plot_unload_time)
+        .unwrap_or(CONFIG.plot_unload_time)
+}
@@ -0,0 +1,20 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use mchprs_network::packets::read_packet;
+use mchprs_network::NetworkState;
+use std::io::Cursor;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+
+// The first byte decides whether compression is enabled, the rest is read as packets until one
+// fails to decode, like a client connection would be
+fuzz_target!(|data: &[u8]| {
+    let Some((&compressed, data)) = data.split_first() else {
+        return;
+    };
+    let compressed = Arc::new(AtomicBool::new(compressed & 1 == 1));
+    let mut state = NetworkState::Handshaking;
+    let mut reader = Cursor::new(data);
+    while read_packet(&mut reader, &compressed, &mut state).is_ok() {}
+});
@@ -49,6 +49,8 @@ pub struct HandshakingConn {
     pub uuid: Option<u128>,
     pub forwarding_message_id: Option<i32>,
     pub properties: Vec<PlayerProperty>,
+    /// The language the client reported while it was being configured
+    pub locale: Option<String>,
 }
 
 impl HandshakingConn {
@@ -88,8 +90,9 @@ impl PlayerConn {
         self.client.send_packet(data);
     }
 
-    pub fn receive_packets(&mut self) -> Vec<Box<dyn ServerBoundPacket>> {
-        self.client.receive_packets(&mut self.alive)
+    /// Takes the next packet the client sent, if there is one
+    pub fn receive_packet(&mut self) -> Option<Box<dyn ServerBoundPacket>> {
+        self.client.receive_packet(&mut self.alive)
     }
 
     pub fn alive(&self) -> bool {
@@ -131,25 +134,22 @@ impl NetworkClient {
         }
     }
 
-    pub fn receive_packets(&self, alive: &mut bool) -> Vec<Box<dyn ServerBoundPacket>> {
-        let mut packets = Vec::new();
-        loop {
-            let packet = self.packets.try_recv();
-            match packet {
-                Ok(packet) => packets.push(packet),
-                Err(mpsc::TryRecvError::Empty) => break,
-                _ => {
-                    *alive = false;
-                    break;
-                }
+    pub fn receive_packet(&self, alive: &mut bool) -> Option<Box<dyn ServerBoundPacket>> {
+        match self.packets.try_recv() {
+            Ok(packet) => Some(packet),
+            Err(mpsc::TryRecvError::Empty) => None,
+            Err(mpsc::TryRecvError::Disconnected) => {
+                *alive = false;
+                None
             }
         }
-        packets
+    }
+
+    pub fn receive_packets(&self, alive: &mut bool) -> Vec<Box<dyn ServerBoundPacket>> {
+        std::iter::from_fn(|| self.receive_packet(alive)).collect()
     }
 
     pub fn send_packet(&self, data: &PacketEncoder) {
-        // TODO: every call to `send_packet` with the same PacketEncoder will
-        // lead to re-encoding the packet. It might be good to cache this.
         if self.compressed.load(Ordering::Relaxed) {
             let _ = data.write_compressed(&self.stream);
         } else {
@@ -215,6 +215,7 @@ impl NetworkServer {
                     uuid: None,
                     forwarding_message_id: None,
                     properties: vec![],
+                    locale: None,
                 }),
                 Err(mpsc::TryRecvError::Empty) => break,
                 Err(mpsc::TryRecvError::Disconnected) => {
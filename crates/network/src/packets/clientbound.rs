@@ -361,6 +361,69 @@ impl ClientBoundPacket for CBlockUpdate {
     }
 }
 
+#[derive(Debug, Clone, Copy)]
+pub enum CBossBarColor {
+    Pink,
+    Blue,
+    Red,
+    Green,
+    Yellow,
+    Purple,
+    White,
+}
+
+pub enum CBossBarAction {
+    Add {
+        title: TextComponent,
+        health: f32,
+        color: CBossBarColor,
+        /// 0 for no notches, 1 for 6, 2 for 10, 3 for 12, 4 for 20
+        division: i32,
+        flags: u8,
+    },
+    Remove,
+    UpdateHealth(f32),
+    UpdateTitle(TextComponent),
+}
+
+pub struct CBossBar {
+    pub uuid: u128,
+    pub action: CBossBarAction,
+}
+
+impl ClientBoundPacket for CBossBar {
+    fn encode(&self) -> PacketEncoder {
+        let mut buf = Vec::new();
+        buf.write_uuid(self.uuid);
+        match &self.action {
+            CBossBarAction::Add {
+                title,
+                health,
+                color,
+                division,
+                flags,
+            } => {
+                buf.write_varint(0);
+                buf.write_text_component(title);
+                buf.write_float(*health);
+                buf.write_varint(*color as i32);
+                buf.write_varint(*division);
+                buf.write_unsigned_byte(*flags);
+            }
+            CBossBarAction::Remove => buf.write_varint(1),
+            CBossBarAction::UpdateHealth(health) => {
+                buf.write_varint(2);
+                buf.write_float(*health);
+            }
+            CBossBarAction::UpdateTitle(title) => {
+                buf.write_varint(3);
+                buf.write_text_component(title);
+            }
+        }
+        PacketEncoder::new(buf, 0x0A)
+    }
+}
+
 pub struct CCommandSuggestionsResponseMatch {
     pub match_: String,
     pub tooltip: Option<TextComponent>,
@@ -401,6 +464,7 @@ pub enum CDeclareCommandsNodeParser {
     Float(f32, f32),
     BlockPos,
     BlockState,
+    ItemStack,
     String(i32),
 }
 
@@ -416,6 +480,7 @@ impl CDeclareCommandsNodeParser {
             Vec3 => buf.write_varint(10),       // minecraft:vec3
             BlockPos => buf.write_varint(8),    // minecraft:block_pos
             BlockState => buf.write_varint(12), // minecraft:block_state
+            ItemStack => buf.write_varint(14),  // minecraft:item_stack
             Integer(min, max) => {
                 buf.write_varint(3); // brigadier:integer
                 buf.write_byte(3); // Supply min and max value
@@ -479,6 +544,18 @@ impl ClientBoundPacket for CCommands {
     }
 }
 
+pub struct CCloseContainer {
+    pub window_id: u8,
+}
+
+impl ClientBoundPacket for CCloseContainer {
+    fn encode(&self) -> PacketEncoder {
+        let mut buf = Vec::new();
+        buf.write_unsigned_byte(self.window_id);
+        PacketEncoder::new(buf, 0x12)
+    }
+}
+
 pub struct CSetContainerContent {
     pub window_id: u8,
     pub state_id: i32,
@@ -500,6 +577,22 @@ impl ClientBoundPacket for CSetContainerContent {
     }
 }
 
+pub struct CSetContainerProperty {
+    pub window_id: u8,
+    pub property: i16,
+    pub value: i16,
+}
+
+impl ClientBoundPacket for CSetContainerProperty {
+    fn encode(&self) -> PacketEncoder {
+        let mut buf = Vec::new();
+        buf.write_unsigned_byte(self.window_id);
+        buf.write_short(self.property);
+        buf.write_short(self.value);
+        PacketEncoder::new(buf, 0x14)
+    }
+}
+
 pub struct CSetContainerSlot {
     pub window_id: u8,
     pub state_id: i32,
@@ -579,6 +672,33 @@ impl ClientBoundPacket for CGameEvent {
     }
 }
 
+pub struct CInitializeWorldBorder {
+    pub x: f64,
+    pub z: f64,
+    pub old_diameter: f64,
+    pub new_diameter: f64,
+    /// How many milliseconds it takes to go from the old to the new diameter
+    pub speed: i64,
+    pub portal_teleport_boundary: i32,
+    pub warning_blocks: i32,
+    pub warning_time: i32,
+}
+
+impl ClientBoundPacket for CInitializeWorldBorder {
+    fn encode(&self) -> PacketEncoder {
+        let mut buf = Vec::new();
+        buf.write_double(self.x);
+        buf.write_double(self.z);
+        buf.write_double(self.old_diameter);
+        buf.write_double(self.new_diameter);
+        buf.write_varlong(self.speed);
+        buf.write_varint(self.portal_teleport_boundary);
+        buf.write_varint(self.warning_blocks);
+        buf.write_varint(self.warning_time);
+        PacketEncoder::new(buf, 0x23)
+    }
+}
+
 pub struct CKeepAlive {
     pub id: i64,
 }
@@ -597,6 +717,45 @@ pub struct CChunkDataSection {
     pub biomes: PalettedContainer,
 }
 
+impl CChunkDataSection {
+    /// Encodes the section like it is sent in [`CChunkData`], so the encoded form can be kept
+    /// until the section changes
+    pub fn encode(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.write_short(self.block_count);
+        let containers = [&self.block_states, &self.biomes];
+        for container in containers {
+            data.write_unsigned_byte(container.bits_per_entry);
+
+            // Palette
+            if container.bits_per_entry == 0 {
+                // Single valued palette
+                let palette = container
+                    .palette
+                    .as_ref()
+                    .expect("container with 0 bits per entry should have palette");
+                let item = *palette
+                    .first()
+                    .expect("container with 0 bits per entry should have palette with one entry");
+                data.write_varint(item);
+            } else if let Some(palette) = &container.palette {
+                // Indirect palette
+                data.write_varint(palette.len() as i32);
+                for palette_entry in palette {
+                    data.write_varint(*palette_entry);
+                }
+            }
+
+            // Data Array
+            data.write_varint(container.data_array.len() as i32);
+            for long in &container.data_array {
+                data.write_long(*long as i64);
+            }
+        }
+        data
+    }
+}
+
 pub struct CChunkDataBlockEntity {
     pub x: i8,
     pub z: i8,
@@ -610,7 +769,9 @@ pub struct CChunkData {
     pub chunk_x: i32,
     pub chunk_z: i32,
     pub heightmaps: NBTCompound,
-    pub chunk_sections: Vec<CChunkDataSection>,
+    pub num_sections: usize,
+    /// Every section encoded with [`CChunkDataSection::encode`], one after another
+    pub chunk_section_data: Vec<u8>,
     pub block_entities: Vec<CChunkDataBlockEntity>,
 }
 
@@ -620,41 +781,8 @@ impl ClientBoundPacket for CChunkData {
         buf.write_int(self.chunk_x);
         buf.write_int(self.chunk_z);
         buf.write_nbt(&self.heightmaps);
-        let mut data = Vec::new();
-        for chunk_section in &self.chunk_sections {
-            data.write_short(chunk_section.block_count);
-            let containers = [&chunk_section.block_states, &chunk_section.biomes];
-            for container in containers {
-                data.write_unsigned_byte(container.bits_per_entry);
-
-                // Palette
-                if container.bits_per_entry == 0 {
-                    // Single valued palette
-                    let palette = container
-                        .palette
-                        .as_ref()
-                        .expect("container with 0 bits per entry should have palette");
-                    let item = *palette.first().expect(
-                        "container with 0 bits per entry should have palette with one entry",
-                    );
-                    data.write_varint(item);
-                } else if let Some(palette) = &container.palette {
-                    // Indirect palette
-                    data.write_varint(palette.len() as i32);
-                    for palette_entry in palette {
-                        data.write_varint(*palette_entry);
-                    }
-                }
-
-                // Data Array
-                data.write_varint(container.data_array.len() as i32);
-                for long in &container.data_array {
-                    data.write_long(*long as i64);
-                }
-            }
-        }
-        buf.write_varint(data.len() as i32);
-        buf.write_bytes(&data);
+        buf.write_varint(self.chunk_section_data.len() as i32);
+        buf.write_bytes(&self.chunk_section_data);
         // Number of block entities
         buf.write_varint(self.block_entities.len() as i32);
         for block_entity in &self.block_entities {
@@ -672,7 +800,7 @@ impl ClientBoundPacket for CChunkData {
         // Block Light Mask
         buf.write_varint(0);
 
-        let bits = bits![u64, Lsb0; 1].repeat(self.chunk_sections.len() + 2);
+        let bits = bits![u64, Lsb0; 1].repeat(self.num_sections + 2);
         let longs = bits.as_raw_slice();
         // Empty Sky Light Mask
         buf.write_varint(longs.len() as i32);
@@ -848,6 +976,19 @@ impl ClientBoundPacket for CEntityRotation {
     }
 }
 
+/// Opens the written book in the player's hand, which the client only does when told to
+pub struct COpenBook {
+    pub hand: i32,
+}
+
+impl ClientBoundPacket for COpenBook {
+    fn encode(&self) -> PacketEncoder {
+        let mut buf = Vec::new();
+        buf.write_varint(self.hand);
+        PacketEncoder::new(buf, 0x30)
+    }
+}
+
 pub struct COpenScreen {
     pub window_id: i32,
     pub window_type: i32,
@@ -14,10 +14,14 @@ use serverbound::*;
 use std::io::{self, Cursor, Read, Write};
 use std::net::TcpStream;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
-use tracing::{error, trace};
+use std::sync::{Arc, OnceLock};
+use tracing::{error, trace, trace_span};
 
 pub const COMPRESSION_THRESHOLD: usize = 256;
+/// The longest packet the vanilla server accepts, the largest length that fits in 3 VarInt bytes
+const MAX_PACKET_LENGTH: usize = 2097151;
+/// The longest packet the vanilla server accepts once it is decompressed
+const MAX_DECOMPRESSED_LENGTH: usize = 8388608;
 
 #[derive(Debug)]
 pub struct SlotData {
@@ -47,6 +51,11 @@ pub enum PacketDecodeError {
     Io(io::Error),
     FromUtf8(std::string::FromUtf8Error),
     Nbt(nbt::Error),
+    VarIntTooLong,
+    /// A length prefix was negative or longer than the packet can be
+    InvalidLength(i64),
+    /// A value that isn't one of the values of an enum in the protocol
+    InvalidEnum(i32),
 }
 
 impl From<nbt::Error> for PacketDecodeError {
@@ -74,15 +83,25 @@ fn read_compressed<T: PacketDecoderExt>(
     reader: &mut T,
     network_state: &mut NetworkState,
 ) -> DecodeResult<Box<dyn ServerBoundPacket>> {
-    let decompressed_length = reader.read_varint()? as usize;
+    let decompressed_length = reader.read_length()?;
+    if decompressed_length > MAX_DECOMPRESSED_LENGTH {
+        return Err(PacketDecodeError::InvalidLength(decompressed_length as i64));
+    }
     let data = PacketDecoderExt::read_to_end(reader)?;
     // `data` is not compressed if `decompressed_length` is 0
     if decompressed_length == 0 {
         read_decompressed(&mut Cursor::new(data), network_state)
     } else {
-        let mut decompresser = ZlibDecoder::new(data.as_slice());
+        let decompresser = ZlibDecoder::new(data.as_slice());
         let mut decompressed_data = Vec::with_capacity(decompressed_length);
-        decompresser.read_to_end(&mut decompressed_data)?;
+        // Anything past the length the client gave is left out, so it can't make us inflate
+        // more than that
+        decompresser
+            .take(decompressed_length as u64)
+            .read_to_end(&mut decompressed_data)?;
+        if decompressed_data.len() != decompressed_length {
+            return Err(PacketDecodeError::InvalidLength(decompressed_length as i64));
+        }
         read_decompressed(&mut Cursor::new(decompressed_data), network_state)
     }
 }
@@ -118,11 +137,17 @@ fn read_decompressed<T: PacketDecoderExt>(
             Box::new(SAcknowledgeFinishConfiguration::decode(reader)?)
         }
         _ => match packet_id {
+            0x00 => Box::new(SConfirmTeleportation::decode(reader)?),
             0x04 => Box::new(SChatCommand::decode(reader)?),
             0x05 => Box::new(SChatMessage::decode(reader)?),
             0x09 => Box::new(SClientInformation::decode(reader)?),
             0x0A => Box::new(SCommandSuggestionsRequest::decode(reader)?),
+            0x0C => Box::new(SClickContainerButton::decode(reader)?),
+            0x0D => Box::new(SClickContainer::decode(reader)?),
+            0x0E => Box::new(SCloseContainer::decode(reader)?),
             0x10 => Box::new(SPluginMessage::decode(reader)?),
+            0x11 => Box::new(SEditBook::decode(reader)?),
+            0x13 => Box::new(SInteract::decode(reader)?),
             0x15 => Box::new(SKeepAlive::decode(reader)?),
             0x17 => Box::new(SSetPlayerPosition::decode(reader)?),
             0x18 => Box::new(SSetPlayerPositionAndRotation::decode(reader)?),
@@ -136,6 +161,7 @@ fn read_decompressed<T: PacketDecoderExt>(
             0x32 => Box::new(SUpdateSign::decode(reader)?),
             0x33 => Box::new(SSwingArm::decode(reader)?),
             0x35 => Box::new(SUseItemOn::decode(reader)?),
+            0x36 => Box::new(SUseItem::decode(reader)?),
             _ => Box::new(SUnknown),
         },
     };
@@ -149,8 +175,12 @@ pub fn read_packet<T: PacketDecoderExt>(
     compressed: &Arc<AtomicBool>,
     network_state: &mut NetworkState,
 ) -> DecodeResult<Box<dyn ServerBoundPacket>> {
-    let length = reader.read_varint()?;
-    let data = reader.read_bytes(length as usize)?;
+    let length = reader.read_length()?;
+    if length > MAX_PACKET_LENGTH {
+        return Err(PacketDecodeError::InvalidLength(length as i64));
+    }
+    let data = reader.read_bytes(length)?;
+    let _span = trace_span!("decode_packet", length).entered();
     let mut cursor = Cursor::new(data);
     if compressed.load(Ordering::Relaxed) {
         read_compressed(&mut cursor, network_state)
@@ -171,9 +201,14 @@ pub trait PacketDecoderExt: Read + Sized {
         Ok(self.read_i8()?)
     }
 
+    /// Reads exactly `bytes` bytes. The buffer grows as the data comes in, so a length prefix
+    /// that is far longer than the data can't make us allocate it all up front.
     fn read_bytes(&mut self, bytes: usize) -> DecodeResult<Vec<u8>> {
-        let mut read = vec![0; bytes];
-        self.read_exact(&mut read)?;
+        let mut read = Vec::new();
+        Read::read_to_end(&mut self.by_ref().take(bytes as u64), &mut read)?;
+        if read.len() != bytes {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
         Ok(read)
     }
 
@@ -206,48 +241,42 @@ pub trait PacketDecoderExt: Read + Sized {
     }
 
     fn read_varint(&mut self) -> DecodeResult<i32> {
-        let mut num_read = 0;
         let mut result = 0i32;
-        let mut read;
-        loop {
-            read = self.read_byte()? as u8;
+        for num_read in 0..5 {
+            let read = self.read_unsigned_byte()?;
             let value = (read & 0b0111_1111) as i32;
             result |= value << (7 * num_read);
 
-            num_read += 1;
-            if num_read > 5 {
-                panic!("VarInt is too big!");
-            }
             if read & 0b1000_0000 == 0 {
-                break;
+                return Ok(result);
             }
         }
-        Ok(result)
+        Err(PacketDecodeError::VarIntTooLong)
     }
 
     fn read_varlong(&mut self) -> DecodeResult<i64> {
-        let mut num_read = 0;
         let mut result = 0i64;
-        let mut read;
-        loop {
-            read = self.read_byte()? as u8;
+        for num_read in 0..10 {
+            let read = self.read_unsigned_byte()?;
             let value = (read & 0b0111_1111) as i64;
             result |= value << (7 * num_read);
 
-            num_read += 1;
-            if num_read > 5 {
-                panic!("VarInt is too big!");
-            }
             if read & 0b1000_0000 == 0 {
-                break;
+                return Ok(result);
             }
         }
-        Ok(result)
+        Err(PacketDecodeError::VarIntTooLong)
     }
 
-    fn read_string(&mut self) -> DecodeResult<String> {
+    /// Reads a VarInt length prefix, which can't be negative
+    fn read_length(&mut self) -> DecodeResult<usize> {
         let length = self.read_varint()?;
-        Ok(String::from_utf8(self.read_bytes(length as usize)?)?)
+        usize::try_from(length).map_err(|_| PacketDecodeError::InvalidLength(length as i64))
+    }
+
+    fn read_string(&mut self) -> DecodeResult<String> {
+        let length = self.read_length()?;
+        Ok(String::from_utf8(self.read_bytes(length)?)?)
     }
 
     fn read_to_end(&mut self) -> DecodeResult<Vec<u8>> {
@@ -438,12 +467,18 @@ impl PacketEncoderExt for Vec<u8> {}
 pub struct PacketEncoder {
     buffer: Vec<u8>,
     packet_id: u32,
+    /// The compressed packet, so a packet sent to many players is only compressed once
+    compressed: OnceLock<Vec<u8>>,
 }
 
 impl PacketEncoder {
     fn new(buffer: Vec<u8>, packet_id: u32) -> PacketEncoder {
         trace!("Encoding packet with id {:#02x}", packet_id);
-        PacketEncoder { buffer, packet_id }
+        PacketEncoder {
+            buffer,
+            packet_id,
+            compressed: OnceLock::new(),
+        }
     }
 
     // This function is separate because it is needed when writing packet headers
@@ -464,6 +499,18 @@ impl PacketEncoder {
     }
 
     pub fn write_compressed(&self, mut w: impl Write) -> io::Result<()> {
+        let compressed = match self.compressed.get() {
+            Some(compressed) => compressed,
+            None => {
+                let mut compressed = Vec::new();
+                self.compress(&mut compressed)?;
+                self.compressed.get_or_init(|| compressed)
+            }
+        };
+        w.write_all(compressed)
+    }
+
+    fn compress(&self, mut w: impl Write) -> io::Result<()> {
         // TODO: zero allocation
         let packet_id = PacketEncoder::varint(self.packet_id as i32);
         let data = [packet_id.as_slice(), self.buffer.as_slice()].concat();
@@ -1,4 +1,4 @@
-use super::{DecodeResult, PacketDecoderExt, PlayerProperty, SlotData};
+use super::{DecodeResult, PacketDecodeError, PacketDecoderExt, PlayerProperty, SlotData};
 
 pub trait ServerBoundPacketHandler {
     // Handshaking
@@ -19,6 +19,8 @@ pub trait ServerBoundPacketHandler {
     ) {
     }
     // Play
+    fn handle_confirm_teleportation(&mut self, _packet: SConfirmTeleportation, _player_idx: usize) {
+    }
     fn handle_chat_command(&mut self, _packet: SChatCommand, _player_idx: usize) {}
     fn handle_chat_message(&mut self, _packet: SChatMessage, _player_idx: usize) {}
     fn handle_command_suggestions_request(
@@ -28,6 +30,8 @@ pub trait ServerBoundPacketHandler {
     ) {
     }
     fn handle_plugin_message(&mut self, _packet: SPluginMessage, _player_idx: usize) {}
+    fn handle_edit_book(&mut self, _packet: SEditBook, _player_idx: usize) {}
+    fn handle_interact(&mut self, _packet: SInteract, _player_idx: usize) {}
     fn handle_keep_alive(&mut self, _packet: SKeepAlive, _player_idx: usize) {}
     fn handle_set_player_position(&mut self, _packet: SSetPlayerPosition, _player_idx: usize) {}
     fn handle_set_player_position_and_rotation(
@@ -43,10 +47,19 @@ pub trait ServerBoundPacketHandler {
     fn handle_player_command(&mut self, _packet: SPlayerCommand, _player_idx: usize) {}
     fn handle_swing_arm(&mut self, _packet: SSwingArm, _player_idx: usize) {}
     fn handle_use_item_on(&mut self, _packet: SUseItemOn, _player_idx: usize) {}
+    fn handle_use_item(&mut self, _packet: SUseItem, _player_idx: usize) {}
     fn handle_set_held_item(&mut self, _packet: SSetHeldItem, _player_idx: usize) {}
     fn handle_set_creative_mode_slot(&mut self, _packet: SSetCreativeModeSlot, _player_idx: usize) {
     }
     fn handle_update_sign(&mut self, _packet: SUpdateSign, _player_idx: usize) {}
+    fn handle_click_container_button(
+        &mut self,
+        _packet: SClickContainerButton,
+        _player_idx: usize,
+    ) {
+    }
+    fn handle_click_container(&mut self, _packet: SClickContainer, _player_idx: usize) {}
+    fn handle_close_container(&mut self, _packet: SCloseContainer, _player_idx: usize) {}
     fn handle_unknown(&mut self, _packet: SUnknown, _player_idx: usize) {}
 }
 
@@ -58,6 +71,18 @@ pub trait ServerBoundPacket: Send + std::fmt::Debug {
     fn handle(self: Box<Self>, handler: &mut dyn ServerBoundPacketHandler, player_idx: usize);
 }
 
+fn read_slot<T: PacketDecoderExt>(decoder: &mut T) -> DecodeResult<Option<SlotData>> {
+    Ok(if decoder.read_bool()? {
+        Some(SlotData {
+            item_id: decoder.read_varint()?,
+            item_count: decoder.read_byte()?,
+            nbt: decoder.read_nbt_compound()?,
+        })
+    } else {
+        None
+    })
+}
+
 #[derive(Debug)]
 pub struct SUnknown;
 
@@ -255,6 +280,23 @@ impl ServerBoundPacket for SAcknowledgeFinishConfiguration {
     }
 }
 
+#[derive(Debug)]
+pub struct SConfirmTeleportation {
+    pub teleport_id: i32,
+}
+
+impl ServerBoundPacket for SConfirmTeleportation {
+    fn decode<T: PacketDecoderExt>(decoder: &mut T) -> DecodeResult<Self> {
+        Ok(SConfirmTeleportation {
+            teleport_id: decoder.read_varint()?,
+        })
+    }
+
+    fn handle(self: Box<Self>, handler: &mut dyn ServerBoundPacketHandler, player_idx: usize) {
+        handler.handle_confirm_teleportation(*self, player_idx);
+    }
+}
+
 #[derive(Debug)]
 pub struct SChatCommandArgumentSignature {
     pub argument_name: String,
@@ -285,7 +327,8 @@ impl ServerBoundPacket for SChatCommand {
             });
         }
         let message_count = decoder.read_varint()?;
-        let acknowledged = decoder.read_bytes(3)?.try_into().unwrap();
+        let mut acknowledged = [0; 3];
+        decoder.read_exact(&mut acknowledged)?;
         Ok(SChatCommand {
             command,
             timestamp,
@@ -323,7 +366,8 @@ impl ServerBoundPacket for SChatMessage {
             None
         };
         let message_count = decoder.read_varint()?;
-        let acknowledged = decoder.read_bytes(3)?.try_into().unwrap();
+        let mut acknowledged = [0; 3];
+        decoder.read_exact(&mut acknowledged)?;
         Ok(SChatMessage {
             message,
             timestamp,
@@ -358,6 +402,83 @@ impl ServerBoundPacket for SCommandSuggestionsRequest {
     }
 }
 
+#[derive(Debug)]
+pub struct SClickContainerButton {
+    pub window_id: u8,
+    pub button_id: u8,
+}
+
+impl ServerBoundPacket for SClickContainerButton {
+    fn decode<T: PacketDecoderExt>(decoder: &mut T) -> DecodeResult<Self> {
+        Ok(SClickContainerButton {
+            window_id: decoder.read_unsigned_byte()?,
+            button_id: decoder.read_unsigned_byte()?,
+        })
+    }
+
+    fn handle(self: Box<Self>, handler: &mut dyn ServerBoundPacketHandler, player_idx: usize) {
+        handler.handle_click_container_button(*self, player_idx);
+    }
+}
+
+#[derive(Debug)]
+pub struct SClickContainer {
+    pub window_id: u8,
+    pub state_id: i32,
+    pub slot: i16,
+    pub button: i8,
+    pub mode: i32,
+    /// The slots the click changed and their new contents, as predicted by the client
+    pub changed_slots: Vec<(i16, Option<SlotData>)>,
+    pub carried_item: Option<SlotData>,
+}
+
+impl ServerBoundPacket for SClickContainer {
+    fn decode<T: PacketDecoderExt>(decoder: &mut T) -> DecodeResult<Self> {
+        let window_id = decoder.read_unsigned_byte()?;
+        let state_id = decoder.read_varint()?;
+        let slot = decoder.read_short()?;
+        let button = decoder.read_byte()?;
+        let mode = decoder.read_varint()?;
+        let changed_len = decoder.read_varint()?;
+        let mut changed_slots = Vec::new();
+        for _ in 0..changed_len {
+            changed_slots.push((decoder.read_short()?, read_slot(decoder)?));
+        }
+        let carried_item = read_slot(decoder)?;
+        Ok(SClickContainer {
+            window_id,
+            state_id,
+            slot,
+            button,
+            mode,
+            changed_slots,
+            carried_item,
+        })
+    }
+
+    fn handle(self: Box<Self>, handler: &mut dyn ServerBoundPacketHandler, player_idx: usize) {
+        handler.handle_click_container(*self, player_idx);
+    }
+}
+
+#[derive(Debug)]
+pub struct SCloseContainer {
+    pub window_id: u8,
+}
+
+impl ServerBoundPacket for SCloseContainer {
+    fn decode<T: PacketDecoderExt>(decoder: &mut T) -> DecodeResult<Self> {
+        Ok(SCloseContainer {
+            window_id: decoder.read_unsigned_byte()?,
+        })
+    }
+
+    fn handle(self: Box<Self>, handler: &mut dyn ServerBoundPacketHandler, player_idx: usize) {
+        handler.handle_close_container(*self, player_idx);
+    }
+}
+
 #[derive(Debug)]
 pub struct SPluginMessage {
     pub channel: String,
@@ -377,6 +498,102 @@ impl ServerBoundPacket for SPluginMessage {
     }
 }
 
+/// The most pages a book can be sent with
+const MAX_BOOK_PAGES: usize = 200;
+const MAX_BOOK_PAGE_LENGTH: usize = 8192;
+const MAX_BOOK_TITLE_LENGTH: usize = 128;
+
+#[derive(Debug)]
+pub struct SEditBook {
+    /// The hotbar slot holding the book, or 40 for the offhand
+    pub slot: i32,
+    pub pages: Vec<String>,
+    /// The title the book was signed with, if it was signed
+    pub title: Option<String>,
+}
+
+impl ServerBoundPacket for SEditBook {
+    fn decode<T: PacketDecoderExt>(decoder: &mut T) -> DecodeResult<Self> {
+        let slot = decoder.read_varint()?;
+        let count = decoder.read_length()?;
+        if count > MAX_BOOK_PAGES {
+            return Err(PacketDecodeError::InvalidLength(count as i64));
+        }
+        let mut pages = Vec::with_capacity(count);
+        for _ in 0..count {
+            let page = decoder.read_string()?;
+            if page.chars().count() > MAX_BOOK_PAGE_LENGTH {
+                return Err(PacketDecodeError::InvalidLength(page.len() as i64));
+            }
+            pages.push(page);
+        }
+        let title = if decoder.read_bool()? {
+            let title = decoder.read_string()?;
+            if title.chars().count() > MAX_BOOK_TITLE_LENGTH {
+                return Err(PacketDecodeError::InvalidLength(title.len() as i64));
+            }
+            Some(title)
+        } else {
+            None
+        };
+        Ok(SEditBook { slot, pages, title })
+    }
+
+    fn handle(self: Box<Self>, handler: &mut dyn ServerBoundPacketHandler, player_idx: usize) {
+        handler.handle_edit_book(*self, player_idx);
+    }
+}
+
+#[derive(Debug)]
+pub enum SInteractType {
+    Interact {
+        hand: i32,
+    },
+    Attack,
+    /// Sent before `Interact` with where on the entity the player clicked
+    InteractAt {
+        target_x: f32,
+        target_y: f32,
+        target_z: f32,
+        hand: i32,
+    },
+}
+
+#[derive(Debug)]
+pub struct SInteract {
+    pub entity_id: i32,
+    pub ty: SInteractType,
+    pub sneaking: bool,
+}
+
+impl ServerBoundPacket for SInteract {
+    fn decode<T: PacketDecoderExt>(decoder: &mut T) -> DecodeResult<Self> {
+        let entity_id = decoder.read_varint()?;
+        let ty = match decoder.read_varint()? {
+            0 => SInteractType::Interact {
+                hand: decoder.read_varint()?,
+            },
+            1 => SInteractType::Attack,
+            2 => SInteractType::InteractAt {
+                target_x: decoder.read_float()?,
+                target_y: decoder.read_float()?,
+                target_z: decoder.read_float()?,
+                hand: decoder.read_varint()?,
+            },
+            ty => return Err(PacketDecodeError::InvalidEnum(ty)),
+        };
+        Ok(SInteract {
+            entity_id,
+            ty,
+            sneaking: decoder.read_bool()?,
+        })
+    }
+
+    fn handle(self: Box<Self>, handler: &mut dyn ServerBoundPacketHandler, player_idx: usize) {
+        handler.handle_interact(*self, player_idx);
+    }
+}
+
 #[derive(Debug)]
 pub struct SKeepAlive {
     pub id: i64,
@@ -611,6 +828,26 @@ impl ServerBoundPacket for SUseItemOn {
     }
 }
 
+/// Sent when the player right clicks with an item, whether or not they are looking at a block
+#[derive(Debug)]
+pub struct SUseItem {
+    pub hand: i32,
+    pub sequence: i32,
+}
+
+impl ServerBoundPacket for SUseItem {
+    fn decode<T: PacketDecoderExt>(decoder: &mut T) -> DecodeResult<Self> {
+        Ok(SUseItem {
+            hand: decoder.read_varint()?,
+            sequence: decoder.read_varint()?,
+        })
+    }
+
+    fn handle(self: Box<Self>, handler: &mut dyn ServerBoundPacketHandler, player_idx: usize) {
+        handler.handle_use_item(*self, player_idx);
+    }
+}
+
 #[derive(Debug)]
 pub struct SSetHeldItem {
     pub slot: i16,
@@ -637,15 +874,7 @@ pub struct SSetCreativeModeSlot {
 impl ServerBoundPacket for SSetCreativeModeSlot {
     fn decode<T: PacketDecoderExt>(decoder: &mut T) -> DecodeResult<Self> {
         let slot = decoder.read_short()?;
-        let clicked_item = if decoder.read_bool()? {
-            Some(SlotData {
-                item_id: decoder.read_varint()?,
-                item_count: decoder.read_byte()?,
-                nbt: decoder.read_nbt_compound()?,
-            })
-        } else {
-            None
-        };
+        let clicked_item = read_slot(decoder)?;
         Ok(SSetCreativeModeSlot { slot, clicked_item })
     }
 
@@ -118,6 +118,7 @@ fn compile_node(
         CNodeType::Button => NodeType::Button,
         CNodeType::Lever => NodeType::Lever,
         CNodeType::PressurePlate => NodeType::PressurePlate,
+        CNodeType::TrappedChest => NodeType::TrappedChest,
         CNodeType::Trapdoor => NodeType::Trapdoor,
         CNodeType::Wire => NodeType::Wire,
         CNodeType::Constant => NodeType::Constant,
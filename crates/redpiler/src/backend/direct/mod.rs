@@ -230,6 +230,18 @@ impl JITBackend for DirectBackend {
         }
     }
 
+    fn set_trapped_chest(&mut self, pos: BlockPos, viewers: u8) {
+        let node_id = self.pos_map[&pos];
+        let node = &self.nodes[node_id];
+        match node.ty {
+            NodeType::TrappedChest => {
+                let power = viewers.min(15);
+                self.set_node(node_id, power > 0, power);
+            }
+            _ => warn!("Tried to set trapped chest viewers for a {:?}", node.ty),
+        }
+    }
+
     fn tick(&mut self) {
         let mut queues = self.scheduler.queues_this_tick();
 
@@ -370,6 +382,7 @@ impl fmt::Display for DirectBackend {
                 NodeType::Button => "Button".to_string(),
                 NodeType::Lever => "Lever".to_string(),
                 NodeType::PressurePlate => "PressurePlate".to_string(),
+                NodeType::TrappedChest => format!("TrappedChest({})", node.output_power),
                 NodeType::Trapdoor => "Trapdoor".to_string(),
                 NodeType::Wire => "Wire".to_string(),
                 NodeType::Constant => format!("Constant({})", node.output_power),
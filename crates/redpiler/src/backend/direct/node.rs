@@ -122,6 +122,7 @@ pub enum NodeType {
     Button,
     Lever,
     PressurePlate,
+    TrappedChest,
     Trapdoor,
     Wire,
     Constant,
@@ -20,6 +20,7 @@ pub enum NodeType {
     Button,
     Lever,
     PressurePlate,
+    TrappedChest,
     Trapdoor,
     Wire,
     Constant,
@@ -175,6 +175,19 @@ impl Compiler {
         debug!("Compile completed in {:?}", start.elapsed());
     }
 
+    /// Runs the compiler passes on the world and returns the resulting graph, without
+    /// compiling a backend for it. This is used to export the graph for inspection.
+    pub fn compile_graph<W: World>(
+        world: &W,
+        bounds: (BlockPos, BlockPos),
+        options: &CompilerOptions,
+    ) -> Vec<redpiler_graph::Node> {
+        let input = CompilerInput { world, bounds };
+        let pass_manager = make_default_pass_manager::<W>();
+        let graph = pass_manager.run_passes(options, &input, Default::default());
+        passes::convert_graph(&graph)
+    }
+
     pub fn reset<W: World>(&mut self, world: &mut W, bounds: (BlockPos, BlockPos)) {
         if self.is_active {
             self.is_active = false;
@@ -221,6 +234,11 @@ impl Compiler {
         self.backend().set_pressure_plate(pos, powered);
     }
 
+    /// Sets how many players are looking into the trapped chest at `pos`
+    pub fn set_trapped_chest(&mut self, pos: BlockPos, viewers: u8) {
+        self.backend().set_trapped_chest(pos, viewers);
+    }
+
     pub fn flush<W: World>(&mut self, world: &mut W) {
         let io_only = self.options.io_only;
         self.backend().flush(world, io_only);
@@ -322,7 +322,10 @@ impl SSRangeAnalysis {
             | NodeType::NoteBlock { .. } => SSRange::constant(0),
             NodeType::Torch => SSRange::constant(15),
             NodeType::Constant => SSRange::constant(state.output_strength),
-            NodeType::Button | NodeType::Lever | NodeType::PressurePlate => SSRange::FULL,
+            NodeType::Button
+            | NodeType::Lever
+            | NodeType::PressurePlate
+            | NodeType::TrappedChest => SSRange::FULL,
         }
     }
 }
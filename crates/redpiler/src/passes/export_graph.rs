@@ -63,6 +63,7 @@ fn convert_node(
             CNodeType::Button => NodeType::Button,
             CNodeType::Lever => NodeType::Lever,
             CNodeType::PressurePlate => NodeType::PressurePlate,
+            CNodeType::TrappedChest => NodeType::TrappedChest,
             CNodeType::Trapdoor => NodeType::Trapdoor,
             CNodeType::Wire => NodeType::Wire,
             CNodeType::Constant => NodeType::Constant,
@@ -90,6 +91,19 @@ fn convert_node(
     }
 }
 
+/// Converts the graph into the format of the [`redpiler_graph`] crate
+pub fn convert_graph(graph: &CompileGraph) -> Vec<Node> {
+    let mut nodes_map = FxHashMap::with_capacity_and_hasher(graph.node_count(), Default::default());
+    for node in graph.node_indices() {
+        nodes_map.insert(node, nodes_map.len());
+    }
+
+    graph
+        .node_indices()
+        .map(|idx| convert_node(graph, idx, &nodes_map))
+        .collect_vec()
+}
+
 pub struct ExportGraph;
 
 impl<W: World> Pass<W> for ExportGraph {
@@ -100,17 +114,7 @@ impl<W: World> Pass<W> for ExportGraph {
         _: &CompilerInput<'_, W>,
         _: &mut AnalysisInfos,
     ) {
-        let mut nodes_map =
-            FxHashMap::with_capacity_and_hasher(graph.node_count(), Default::default());
-        for node in graph.node_indices() {
-            nodes_map.insert(node, nodes_map.len());
-        }
-
-        let nodes = graph
-            .node_indices()
-            .map(|idx| convert_node(graph, idx, &nodes_map))
-            .collect_vec();
-
+        let nodes = convert_graph(graph);
         fs::write("redpiler_graph.bc", serialize(nodes.as_slice()).unwrap()).unwrap();
     }
 
@@ -89,7 +89,7 @@ fn for_pos<W: World>(
 
     let is_input = matches!(
         ty,
-        NodeType::Button | NodeType::Lever | NodeType::PressurePlate
+        NodeType::Button | NodeType::Lever | NodeType::PressurePlate | NodeType::TrappedChest
     );
     let is_output = matches!(
         ty,
@@ -156,8 +156,13 @@ fn identify_block<W: World>(
         Block::StonePressurePlate { powered } => {
             (NodeType::PressurePlate, NodeState::simple(powered))
         }
+        Block::TrappedChest { .. } => (
+            NodeType::TrappedChest,
+            NodeState::ss(world.container_viewers(pos).min(15)),
+        ),
         Block::IronTrapdoor { powered, .. } => (NodeType::Trapdoor, NodeState::simple(powered)),
         Block::RedstoneBlock {} => (NodeType::Constant, NodeState::ss(15)),
+        Block::DaylightDetector { power, .. } => (NodeType::Constant, NodeState::ss(power)),
         Block::NoteBlock {
             instrument: _,
             note,
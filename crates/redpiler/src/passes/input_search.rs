@@ -4,7 +4,9 @@
 //! This pass is *mandatory*. Without it, there would be no links between nodes.
 
 use super::Pass;
-use crate::compile_graph::{CompileGraph, CompileLink, LinkType, NodeIdx};
+use crate::compile_graph::{
+    CompileGraph, CompileLink, CompileNode, LinkType, NodeIdx, NodeState, NodeType,
+};
 use crate::passes::AnalysisInfos;
 use crate::{CompilerInput, CompilerOptions};
 use mchprs_blocks::blocks::{Block, ButtonFace, LeverFace};
@@ -65,9 +67,11 @@ impl<'a, W: World> InputSearchState<'a, W> {
             Block::RedstoneTorch { .. } => true,
             Block::RedstoneWallTorch { facing, .. } if facing.block_face() != side => true,
             Block::RedstoneBlock {} => true,
+            Block::DaylightDetector { .. } => true,
             Block::Lever { .. } => true,
             Block::StoneButton { .. } => true,
             Block::StonePressurePlate { .. } => true,
+            Block::TrappedChest { .. } => true,
             Block::RedstoneRepeater { repeater } if repeater.facing.block_face() == side => true,
             Block::RedstoneComparator { comparator } if comparator.facing.block_face() == side => {
                 true
@@ -81,6 +85,7 @@ impl<'a, W: World> InputSearchState<'a, W> {
             Block::RedstoneTorch { .. } if side == BlockFace::Bottom => true,
             Block::RedstoneWallTorch { .. } if side == BlockFace::Bottom => true,
             Block::StonePressurePlate { .. } if side == BlockFace::Top => true,
+            Block::TrappedChest { .. } if side == BlockFace::Top => true,
             Block::Lever { lever } => match side {
                 BlockFace::Top => lever.face == LeverFace::Floor,
                 BlockFace::Bottom => lever.face == LeverFace::Ceiling,
@@ -272,7 +277,10 @@ impl<'a, W: World> InputSearchState<'a, W> {
         let side_block = self.world.get_block(side_pos);
         if (mchprs_redstone::is_diode(side_block)
             && self.provides_weak_power(side_block, side.block_face()))
-            || matches!(side_block, Block::RedstoneBlock { .. })
+            || matches!(
+                side_block,
+                Block::RedstoneBlock { .. } | Block::DaylightDetector { .. }
+            )
         {
             self.graph
                 .add_edge(self.pos_map[&side_pos], id, CompileLink::side(0));
@@ -317,7 +325,20 @@ impl<'a, W: World> InputSearchState<'a, W> {
 
                 let input_pos = pos.offset(facing.block_face());
                 let input_block = self.world.get_block(input_pos);
-                if comparator::has_override(input_block) {
+                if let Block::TrappedChest { .. } = input_block {
+                    // The trapped chest node carries the power from its viewers, so its contents
+                    // get their own constant
+                    let ss = comparator::get_override(input_block, self.world, input_pos);
+                    let constant = self.graph.add_node(CompileNode {
+                        ty: NodeType::Constant,
+                        block: None,
+                        state: NodeState::ss(ss),
+                        is_input: false,
+                        is_output: false,
+                        annotations: Default::default(),
+                    });
+                    self.graph.add_edge(constant, id, CompileLink::default(0));
+                } else if comparator::has_override(input_block) {
                     self.graph
                         .add_edge(self.pos_map[&input_pos], id, CompileLink::default(0));
                 } else {
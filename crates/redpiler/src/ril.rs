@@ -167,6 +167,7 @@ fn dump_node(f: &mut fmt::Formatter<'_>, ctx: &FmtContext<'_>) -> fmt::Result {
         NodeType::Button => write!(f, "button {}", node.state.powered),
         NodeType::Lever => write!(f, "lever {}", node.state.powered),
         NodeType::PressurePlate => write!(f, "pressure_plate {}", node.state.powered),
+        NodeType::TrappedChest => write!(f, "trapped_chest {}", node.state.output_strength),
         NodeType::Trapdoor => write!(
             f,
             "trapdoor {}, {}",
@@ -245,6 +246,7 @@ enum ComponentType {
     Button,
     Lever,
     PressurePlate,
+    TrappedChest,
     Trapdoor,
     Wire,
     Constant,
@@ -372,6 +374,7 @@ impl<'a> Lexer<'a> {
             "button" => Token::ComponentType(ComponentType::Button),
             "lever" => Token::ComponentType(ComponentType::Lever),
             "pressure_plate" => Token::ComponentType(ComponentType::PressurePlate),
+            "trapped_chest" => Token::ComponentType(ComponentType::TrappedChest),
             "trapdoor" => Token::ComponentType(ComponentType::Trapdoor),
             "wire" => Token::ComponentType(ComponentType::Wire),
             "constant" => Token::ComponentType(ComponentType::Constant),
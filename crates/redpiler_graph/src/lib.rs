@@ -1,5 +1,6 @@
 use bincode::{BincodeRead, Result};
 use serde::{Deserialize, Serialize};
+use std::fmt::Write;
 
 pub type NodeId = usize;
 
@@ -42,6 +43,7 @@ pub enum NodeType {
     Wire,
     Constant,
     NoteBlock,
+    TrappedChest,
 }
 
 #[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
@@ -86,3 +88,39 @@ where
 {
     bincode::deserialize_from(reader)
 }
+
+/// Formats the graph in the DOT language, to be rendered with Graphviz. Nodes are labelled with
+/// their type and position, powered nodes are filled red, and side inputs are dashed.
+pub fn to_dot(nodes: &[Node]) -> String {
+    let mut out = String::from("digraph {\n");
+    for (id, node) in nodes.iter().enumerate() {
+        let mut label = format!("{:?}", node.ty);
+        if let Some((pos, _)) = node.block {
+            let _ = write!(label, "\\n{}, {}, {}", pos.x, pos.y, pos.z);
+        }
+        if node.state.output_strength > 0 {
+            let _ = write!(label, "\\nss {}", node.state.output_strength);
+        }
+        let style = if node.state.powered {
+            ", style=filled, fillcolor=\"#ff8080\""
+        } else {
+            ""
+        };
+        let _ = writeln!(out, "    n{} [label=\"{}\"{}];", id, label, style);
+    }
+    for (id, node) in nodes.iter().enumerate() {
+        for link in &node.inputs {
+            let style = match link.ty {
+                LinkType::Default => "",
+                LinkType::Side => ", style=dashed",
+            };
+            let _ = writeln!(
+                out,
+                "    n{} -> n{} [label=\"{}\"{}];",
+                link.to, id, link.weight, style
+            );
+        }
+    }
+    out.push_str("}\n");
+    out
+}
@@ -31,6 +31,8 @@ pub fn has_override(block: Block) -> bool {
         Block::Barrel { .. }
             | Block::Furnace { .. }
             | Block::Hopper { .. }
+            | Block::TrappedChest { .. }
+            | Block::Lectern { .. }
             | Block::Cauldron { .. }
             | Block::Composter { .. }
             | Block::Cake { .. }
@@ -40,7 +42,10 @@ pub fn has_override(block: Block) -> bool {
 
 pub fn get_override(block: Block, world: &impl World, pos: BlockPos) -> u8 {
     match block {
-        Block::Barrel { .. } | Block::Furnace { .. } | Block::Hopper { .. } => {
+        Block::Barrel { .. }
+        | Block::Furnace { .. }
+        | Block::Hopper { .. }
+        | Block::TrappedChest { .. } => {
             match world.get_block_entity(pos) {
                 Some(BlockEntity::Container {
                     comparator_override,
@@ -54,6 +59,13 @@ pub fn get_override(block: Block, world: &impl World, pos: BlockPos) -> u8 {
                 None => 0,
             }
         }
+        Block::Lectern { .. } => match world.get_block_entity(pos) {
+            Some(BlockEntity::Lectern {
+                comparator_override,
+                ..
+            }) => *comparator_override,
+            _ => 0,
+        },
         Block::Cauldron { level } => level,
         Block::Composter { level } => level,
         Block::Cake { bites } => 14 - 2 * bites,
@@ -70,13 +82,16 @@ pub fn get_far_input(world: &impl World, pos: BlockPos, facing: BlockDirection)
         return None;
     }
 
-    let far_input_pos = input_pos.offset(face);
+    get_far_override(world, input_pos.offset(face), face)
+}
+
+/// Returns the signal of the block or item frame a comparator reads through a solid block,
+/// whichever is stronger
+fn get_far_override(world: &impl World, far_input_pos: BlockPos, face: BlockFace) -> Option<u8> {
     let far_input_block = world.get_block(far_input_pos);
-    if has_override(far_input_block) {
-        Some(get_override(far_input_block, world, far_input_pos))
-    } else {
-        None
-    }
+    let block_override =
+        has_override(far_input_block).then(|| get_override(far_input_block, world, far_input_pos));
+    block_override.max(world.item_frame_override(far_input_pos, face))
 }
 
 fn calculate_input_strength(comp: RedstoneComparator, world: &impl World, pos: BlockPos) -> u8 {
@@ -86,13 +101,8 @@ fn calculate_input_strength(comp: RedstoneComparator, world: &impl World, pos: B
     if has_override(input_block) {
         get_override(input_block, world, input_pos)
     } else if base_input_strength < 15 && input_block.is_solid() {
-        let far_input_pos = input_pos.offset(comp.facing.block_face());
-        let far_input_block = world.get_block(far_input_pos);
-        if has_override(far_input_block) {
-            get_override(far_input_block, world, far_input_pos)
-        } else {
-            base_input_strength
-        }
+        let face = comp.facing.block_face();
+        get_far_override(world, input_pos.offset(face), face).unwrap_or(base_input_strength)
     } else {
         base_input_strength
     }
@@ -0,0 +1,36 @@
+use std::f32::consts::{PI, TAU};
+
+/// The fraction of the day the sun has moved through, at `time` ticks into the day
+fn sun_progress(time: u32) -> f32 {
+    let d = (time as f64 / 24000.0 - 0.25).rem_euclid(1.0);
+    let e = 0.5 - (d * std::f64::consts::PI).cos() / 2.0;
+    ((d * 2.0 + e) / 3.0) as f32
+}
+
+/// Returns the signal strength of a daylight detector under the open sky, `time` ticks into
+/// the day. Weather is never taken into account, as plots don't have any.
+pub fn get_power(time: u32, inverted: bool) -> u8 {
+    let sun_angle = sun_progress(time) * TAU;
+    let brightness = 0.5 + 2.0 * (sun_angle.cos() as f64).clamp(-0.25, 0.25);
+    let sky_darken = ((1.0 - brightness) * 11.0) as i32;
+    let mut power = 15 - sky_darken;
+    if inverted {
+        power = 15 - power;
+    } else if power > 0 {
+        // Weaken the signal while the sun is low
+        let target = if sun_angle < PI { 0.0 } else { TAU };
+        let angle = sun_angle + (target - sun_angle) * 0.2;
+        power = (power as f32 * angle.cos()).round() as i32;
+    }
+    power.clamp(0, 15) as u8
+}
+
+#[test]
+fn daylight_power() {
+    // Noon
+    assert_eq!(get_power(6000, false), 15);
+    assert_eq!(get_power(6000, true), 0);
+    // Midnight
+    assert_eq!(get_power(18000, false), 0);
+    assert_eq!(get_power(18000, true), 11);
+}
@@ -3,7 +3,9 @@
 //! scenerio (i.e. regular buiding)
 
 pub mod comparator;
+pub mod daylight;
 pub mod noteblock;
+pub mod push;
 pub mod repeater;
 pub mod wire;
 
@@ -31,6 +33,8 @@ fn get_weak_power(
         Block::RedstoneWallTorch { lit: true, facing } if facing.block_face() != side => 15,
         Block::RedstoneBlock {} => 15,
         Block::StonePressurePlate { powered: true } => 15,
+        Block::TrappedChest { .. } => world.container_viewers(pos).min(15),
+        Block::DaylightDetector { power, .. } => power,
         Block::Lever { lever } if lever.powered => 15,
         Block::StoneButton { button } if button.powered => 15,
         Block::RedstoneRepeater { repeater }
@@ -91,6 +95,9 @@ fn get_strong_power(
             } && button.powered,
         ),
         Block::StonePressurePlate { powered: true } if side == BlockFace::Top => 15,
+        Block::TrappedChest { .. } if side == BlockFace::Top => {
+            world.container_viewers(pos).min(15)
+        }
         Block::RedstoneWire { .. } => get_weak_power(block, world, pos, side, dust_power),
         Block::RedstoneRepeater { .. } => get_weak_power(block, world, pos, side, dust_power),
         Block::RedstoneComparator { .. } => get_weak_power(block, world, pos, side, dust_power),
@@ -351,6 +358,20 @@ pub fn is_diode(block: Block) -> bool {
     )
 }
 
+/// Whether [`on_use`] handles using the block, so it might change it
+pub fn is_usable(block: Block) -> bool {
+    matches!(
+        block,
+        Block::RedstoneRepeater { .. }
+            | Block::RedstoneComparator { .. }
+            | Block::DaylightDetector { .. }
+            | Block::Lever { .. }
+            | Block::StoneButton { .. }
+            | Block::RedstoneWire { .. }
+            | Block::NoteBlock { .. }
+    )
+}
+
 /// Returns true if the action was handled
 pub fn on_use(block: Block, world: &mut impl World, pos: BlockPos) -> bool {
     match block {
@@ -370,6 +391,13 @@ pub fn on_use(block: Block, world: &mut impl World, pos: BlockPos) -> bool {
             world.set_block(pos, Block::RedstoneComparator { comparator });
             true
         }
+        Block::DaylightDetector { inverted, .. } => {
+            let inverted = !inverted;
+            let power = daylight::get_power(world.time_of_day(), inverted);
+            world.set_block(pos, Block::DaylightDetector { inverted, power });
+            update_surrounding_blocks(world, pos);
+            true
+        }
         Block::Lever { mut lever } => {
             lever.powered = !lever.powered;
             world.set_block(pos, Block::Lever { lever });
@@ -0,0 +1,317 @@
+//! Works out which blocks a piston moves, following the rules of vanilla's
+//! `PistonStructureResolver`. Slime and honey blocks drag the blocks next to them along, but
+//! don't stick to each other.
+//!
+//! Pistons aren't implemented yet, so nothing calls this until they are.
+
+use mchprs_blocks::blocks::Block;
+use mchprs_blocks::{BlockFacing, BlockPos};
+use mchprs_world::World;
+
+/// The most blocks a piston can move at once
+pub const PUSH_LIMIT: usize = 12;
+
+/// In the order vanilla checks them, which decides the order of the moved blocks
+const DIRECTIONS: [BlockFacing; 6] = [
+    BlockFacing::Down,
+    BlockFacing::Up,
+    BlockFacing::North,
+    BlockFacing::South,
+    BlockFacing::West,
+    BlockFacing::East,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PushReaction {
+    Normal,
+    /// The block breaks when it is pushed, and isn't pulled
+    Destroy,
+    /// The block can't be moved and stops the piston
+    Block,
+    /// The block can be pushed, but not pulled
+    PushOnly,
+}
+
+pub fn push_reaction(block: Block) -> PushReaction {
+    match block {
+        Block::RedstoneWire { .. }
+        | Block::RedstoneTorch { .. }
+        | Block::RedstoneWallTorch { .. }
+        | Block::RedstoneRepeater { .. }
+        | Block::RedstoneComparator { .. }
+        | Block::Lever { .. }
+        | Block::StoneButton { .. }
+        | Block::StonePressurePlate { .. }
+        | Block::TripwireHook { .. }
+        | Block::Sign { .. }
+        | Block::WallSign { .. }
+        | Block::SeaPickle { .. }
+        | Block::Cake { .. }
+        | Block::Pumpkin { .. } => PushReaction::Destroy,
+        // Daylight detectors have a block entity in vanilla
+        Block::EndPortalFrame { .. } | Block::DaylightDetector { .. } => PushReaction::Block,
+        block if block.has_block_entity() => PushReaction::Block,
+        _ => PushReaction::Normal,
+    }
+}
+
+pub fn is_sticky(block: Block) -> bool {
+    matches!(block, Block::SlimeBlock {} | Block::HoneyBlock {})
+}
+
+fn can_stick_to_each_other(a: Block, b: Block) -> bool {
+    match (a, b) {
+        (Block::SlimeBlock {}, Block::HoneyBlock {})
+        | (Block::HoneyBlock {}, Block::SlimeBlock {}) => false,
+        _ => is_sticky(a) || is_sticky(b),
+    }
+}
+
+fn same_axis(a: BlockFacing, b: BlockFacing) -> bool {
+    a == b || a == b.opposite()
+}
+
+/// The blocks moved by a piston
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct PushStructure {
+    /// The blocks to move, in the order vanilla finds them
+    pub to_push: Vec<BlockPos>,
+    /// The blocks that break instead of moving
+    pub to_destroy: Vec<BlockPos>,
+}
+
+struct Resolver<'a, W: World> {
+    world: &'a W,
+    piston_pos: BlockPos,
+    piston_direction: BlockFacing,
+    push_direction: BlockFacing,
+    structure: PushStructure,
+}
+
+impl<W: World> Resolver<'_, W> {
+    fn is_pushable(
+        &self,
+        block: Block,
+        pos: BlockPos,
+        move_direction: BlockFacing,
+        allow_destroy: bool,
+        piston_facing: BlockFacing,
+    ) -> bool {
+        if pos.y < 0 || (pos.y == 0 && move_direction == BlockFacing::Down) {
+            return false;
+        }
+        match push_reaction(block) {
+            PushReaction::Normal => true,
+            PushReaction::Destroy => allow_destroy,
+            PushReaction::Block => false,
+            PushReaction::PushOnly => move_direction == piston_facing,
+        }
+    }
+
+    /// Adds the block at `from_pos`, the sticky blocks pulled along behind it, and the blocks
+    /// it pushes in front of it. Returns false if the piston can't move them.
+    fn add_block_line(&mut self, from_pos: BlockPos, direction: BlockFacing) -> bool {
+        let mut block = self.world.get_block(from_pos);
+        if matches!(block, Block::Air {})
+            || !self.is_pushable(block, from_pos, self.push_direction, false, direction)
+            || from_pos == self.piston_pos
+            || self.structure.to_push.contains(&from_pos)
+        {
+            return true;
+        }
+
+        let behind = self.push_direction.opposite();
+        let mut line_len = 1;
+        if line_len + self.structure.to_push.len() > PUSH_LIMIT {
+            return false;
+        }
+        while is_sticky(block) {
+            let pos = behind.offset_pos(from_pos, line_len as i32);
+            let previous = block;
+            block = self.world.get_block(pos);
+            if matches!(block, Block::Air {})
+                || !can_stick_to_each_other(previous, block)
+                || !self.is_pushable(block, pos, self.push_direction, false, behind)
+                || pos == self.piston_pos
+            {
+                break;
+            }
+            line_len += 1;
+            if line_len + self.structure.to_push.len() > PUSH_LIMIT {
+                return false;
+            }
+        }
+
+        for i in (0..line_len).rev() {
+            self.structure
+                .to_push
+                .push(behind.offset_pos(from_pos, i as i32));
+        }
+        let mut added = line_len;
+
+        let mut ahead = 1;
+        loop {
+            let pos = self.push_direction.offset_pos(from_pos, ahead);
+            if let Some(collision) = self.structure.to_push.iter().position(|&p| p == pos) {
+                // The new blocks run into ones that are already moving, so they have to be
+                // moved before them
+                self.structure.to_push[collision..].rotate_right(added);
+                for i in 0..=collision + added {
+                    let pos = self.structure.to_push[i];
+                    if is_sticky(self.world.get_block(pos)) && !self.add_branching_blocks(pos) {
+                        return false;
+                    }
+                }
+                return true;
+            }
+
+            let block = self.world.get_block(pos);
+            if matches!(block, Block::Air {}) {
+                return true;
+            }
+            if !self.is_pushable(block, pos, self.push_direction, true, self.push_direction)
+                || pos == self.piston_pos
+            {
+                return false;
+            }
+            if push_reaction(block) == PushReaction::Destroy {
+                self.structure.to_destroy.push(pos);
+                return true;
+            }
+            if self.structure.to_push.len() >= PUSH_LIMIT {
+                return false;
+            }
+            self.structure.to_push.push(pos);
+            added += 1;
+            ahead += 1;
+        }
+    }
+
+    /// Adds the blocks stuck to the sides of the sticky block at `from_pos`
+    fn add_branching_blocks(&mut self, from_pos: BlockPos) -> bool {
+        let block = self.world.get_block(from_pos);
+        for direction in DIRECTIONS {
+            if same_axis(direction, self.push_direction) {
+                continue;
+            }
+            let pos = direction.offset_pos(from_pos, 1);
+            if can_stick_to_each_other(self.world.get_block(pos), block)
+                && !self.add_block_line(pos, direction)
+            {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Finds the blocks that a piston at `piston_pos` facing `facing` moves when it extends, or
+/// pulls when a sticky piston retracts. Returns `None` if the blocks can't be moved, because
+/// there are too many or one of them is immovable.
+pub fn resolve(
+    world: &impl World,
+    piston_pos: BlockPos,
+    facing: BlockFacing,
+    extending: bool,
+) -> Option<PushStructure> {
+    let (push_direction, start_pos) = if extending {
+        (facing, facing.offset_pos(piston_pos, 1))
+    } else {
+        // Skip over the piston head
+        (facing.opposite(), facing.offset_pos(piston_pos, 2))
+    };
+    let mut resolver = Resolver {
+        world,
+        piston_pos,
+        piston_direction: facing,
+        push_direction,
+        structure: PushStructure::default(),
+    };
+
+    let block = world.get_block(start_pos);
+    if !resolver.is_pushable(
+        block,
+        start_pos,
+        push_direction,
+        false,
+        resolver.piston_direction,
+    ) {
+        if extending && push_reaction(block) == PushReaction::Destroy {
+            resolver.structure.to_destroy.push(start_pos);
+            return Some(resolver.structure);
+        }
+        return None;
+    }
+    if !resolver.add_block_line(start_pos, push_direction) {
+        return None;
+    }
+
+    // The list grows while the sticky blocks in it are checked
+    let mut i = 0;
+    while i < resolver.structure.to_push.len() {
+        let pos = resolver.structure.to_push[i];
+        if is_sticky(world.get_block(pos)) && !resolver.add_branching_blocks(pos) {
+            return None;
+        }
+        i += 1;
+    }
+    Some(resolver.structure)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mchprs_world::memory::MemoryWorld;
+
+    const PISTON: BlockPos = BlockPos::new(0, 10, 0);
+
+    fn at(x: i32, y: i32) -> BlockPos {
+        BlockPos::new(x, y, 0)
+    }
+
+    #[test]
+    fn push_limit() {
+        let mut world = MemoryWorld::new(1, 1, 1);
+        for x in 1..=PUSH_LIMIT as i32 {
+            world.set_block(at(x, 10), Block::Stone {});
+        }
+        let structure = resolve(&world, PISTON, BlockFacing::East, true).unwrap();
+        assert_eq!(structure.to_push.len(), PUSH_LIMIT);
+
+        world.set_block(at(PUSH_LIMIT as i32 + 1, 10), Block::Stone {});
+        assert_eq!(resolve(&world, PISTON, BlockFacing::East, true), None);
+    }
+
+    #[test]
+    fn immovable_and_destroyed_blocks() {
+        let mut world = MemoryWorld::new(1, 1, 1);
+        world.set_block(at(1, 10), Block::Stone {});
+        world.set_block(at(2, 10), Block::Barrel {});
+        assert_eq!(resolve(&world, PISTON, BlockFacing::East, true), None);
+
+        world.set_block(at(2, 10), Block::RedstoneBlock {});
+        world.set_block(at(3, 10), Block::Cake { bites: 0 });
+        let structure = resolve(&world, PISTON, BlockFacing::East, true).unwrap();
+        assert_eq!(structure.to_push, vec![at(1, 10), at(2, 10)]);
+        assert_eq!(structure.to_destroy, vec![at(3, 10)]);
+    }
+
+    #[test]
+    fn sticky_blocks() {
+        let mut world = MemoryWorld::new(1, 1, 1);
+        world.set_block(at(1, 10), Block::SlimeBlock {});
+        world.set_block(at(1, 11), Block::Stone {});
+        // Honey doesn't stick to slime, and nothing sticks to the stone
+        world.set_block(at(1, 9), Block::HoneyBlock {});
+        world.set_block(at(1, 12), Block::Stone {});
+        let structure = resolve(&world, PISTON, BlockFacing::East, true).unwrap();
+        assert_eq!(structure.to_push, vec![at(1, 10), at(1, 11)]);
+
+        // A sticky piston pulls the slime and what is stuck to it
+        let mut world = MemoryWorld::new(1, 1, 1);
+        world.set_block(at(2, 10), Block::SlimeBlock {});
+        world.set_block(at(2, 11), Block::Stone {});
+        let structure = resolve(&world, PISTON, BlockFacing::East, false).unwrap();
+        assert_eq!(structure.to_push, vec![at(2, 10), at(2, 11)]);
+    }
+}
@@ -100,3 +100,33 @@ pub fn tick(mut rep: RedstoneRepeater, world: &mut impl World, pos: BlockPos) {
         on_state_change(rep, world, pos);
     }
 }
+
+#[test]
+fn repeater_delay() {
+    use mchprs_world::memory::MemoryWorld;
+
+    let mut world = MemoryWorld::new(1, 1, 1);
+    let pos = BlockPos::new(5, 0, 0);
+    let rep = RedstoneRepeater {
+        delay: 3,
+        facing: BlockDirection::West,
+        locked: false,
+        powered: false,
+    };
+    world.set_block(pos, Block::RedstoneRepeater { repeater: rep });
+    world.set_block(pos.offset(BlockFace::West), Block::RedstoneBlock {});
+    super::update(world.get_block(pos), &mut world, pos);
+
+    let powered = |world: &MemoryWorld| {
+        matches!(
+            world.get_block(pos),
+            Block::RedstoneRepeater { repeater } if repeater.powered
+        )
+    };
+    for _ in 0..2 {
+        world.tick(super::tick);
+        assert!(!powered(&world));
+    }
+    world.tick(super::tick);
+    assert!(powered(&world));
+}
@@ -111,6 +111,8 @@ fn can_connect_to(block: Block, side: BlockDirection) -> bool {
         | Block::TripwireHook { .. }
         | Block::StoneButton { .. }
         | Block::Target { .. }
+        | Block::TrappedChest { .. }
+        | Block::DaylightDetector { .. }
         | Block::Lever { .. } => true,
         Block::RedstoneRepeater { repeater } => {
             repeater.facing == side || repeater.facing == side.opposite()
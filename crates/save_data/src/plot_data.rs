@@ -3,12 +3,12 @@ mod fixer;
 use self::fixer::FixInfo;
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use mchprs_blocks::block_entities::BlockEntity;
-use mchprs_blocks::BlockPos;
+use mchprs_blocks::{BlockFace, BlockPos};
 use mchprs_world::storage::{Chunk, ChunkSection};
 use mchprs_world::TickEntry;
 use rustc_hash::FxHashMap;
 use serde::{Deserialize, Serialize};
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::{Read, Write};
 use std::path::Path;
 use std::{fmt, io};
@@ -18,7 +18,10 @@ use thiserror::Error;
 /// 0: Initial plot data file with header (MC 1.18.2)
 /// 1: Add world send rate
 /// 2: Update to MC 1.20.4
-pub const VERSION: u32 = 2;
+/// 3: Compress plot data with zstd
+/// 4: Add time of day
+/// 5: Add item frames and armor stands
+pub const VERSION: u32 = 5;
 
 #[derive(Error, Debug)]
 pub enum PlotLoadError {
@@ -60,6 +63,8 @@ pub enum PlotSaveError {
 }
 
 static PLOT_MAGIC: &[u8; 8] = b"\x86MCHPRS\x00";
+/// The size of the magic and version number at the start of every plot file
+const HEADER_LEN: u64 = PLOT_MAGIC.len() as u64 + 4;
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
 pub struct ChunkSectionData {
@@ -116,19 +121,15 @@ impl ChunkData {
     }
 
     pub fn load(self, x: i32, z: i32) -> Chunk {
-        Chunk {
-            x,
-            z,
-            sections: self
-                .sections
-                .into_iter()
-                .map(|section| match section {
-                    Some(section) => section.load(),
-                    None => Default::default(),
-                })
-                .collect(),
-            block_entities: self.block_entities,
-        }
+        let sections = self
+            .sections
+            .into_iter()
+            .map(|section| match section {
+                Some(section) => section.load(),
+                None => Default::default(),
+            })
+            .collect();
+        Chunk::new(x, z, sections, self.block_entities)
     }
 }
 
@@ -147,6 +148,17 @@ impl Default for WorldSendRate {
     }
 }
 
+/// The time of day in ticks, which stays the same until it is changed with `/plot set time`
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeOfDay(pub u32);
+
+impl Default for TimeOfDay {
+    fn default() -> Self {
+        // Noon
+        Self(6000)
+    }
+}
+
 impl fmt::Display for Tps {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -156,12 +168,48 @@ impl fmt::Display for Tps {
     }
 }
 
+/// An entity players decorate a plot with. Positions are relative to the lowest corner of the
+/// plot, so plots can be copied to other places like the template is.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum EntityData {
+    ItemFrame {
+        /// The block the frame is in, next to the block it hangs on
+        pos: BlockPos,
+        facing: BlockFace,
+        /// The id of the item in the frame
+        item: Option<u32>,
+        rotation: u8,
+    },
+    ArmorStand {
+        x: f64,
+        y: f64,
+        z: f64,
+        yaw: f32,
+        name: Option<String>,
+    },
+}
+
+/// Parses the coordinates of a plot written as `x,z`, like the tools take them on the command line
+pub fn parse_plot_pos(s: &str) -> Result<(i32, i32), String> {
+    let Some((x, z)) = s.split_once(',') else {
+        return Err(format!("expected a plot in the form x,z but got `{}`", s));
+    };
+    let parse = |c: &str| {
+        c.trim()
+            .parse::<i32>()
+            .map_err(|_| format!("invalid plot coordinate `{}`", c))
+    };
+    Ok((parse(x)?, parse(z)?))
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct PlotData {
     pub tps: Tps,
     pub world_send_rate: WorldSendRate,
     pub chunk_data: Vec<ChunkData>,
     pub pending_ticks: Vec<TickEntry>,
+    pub time_of_day: TimeOfDay,
+    pub entities: Vec<EntityData>,
 }
 
 impl PlotData {
@@ -184,19 +232,24 @@ impl PlotData {
             return Err(PlotLoadError::TooNew(version));
         }
 
-        let mut buf = Vec::new();
-        file.read_to_end(&mut buf)?;
+        let buf = zstd::decode_all(file)?;
         Ok(bincode::deserialize(&buf)?)
     }
 
+    /// Writes the plot next to `path` first and then moves it over the old file, so a failed
+    /// save, like one on a full disk, leaves the last save as it was.
     pub fn save_to_file(&self, path: impl AsRef<Path>) -> Result<(), PlotSaveError> {
-        let mut file = File::create(path)?;
+        let path = path.as_ref();
+        let data = bincode::serialize(self)?;
+        let data = zstd::encode_all(data.as_slice(), zstd::DEFAULT_COMPRESSION_LEVEL)?;
 
+        let tmp_path = path.with_extension("tmp");
+        let mut file = File::create(&tmp_path)?;
         file.write_all(PLOT_MAGIC)?;
         file.write_u32::<LittleEndian>(VERSION)?;
-        let data = bincode::serialize(self)?;
         file.write_all(&data)?;
         file.sync_data()?;
+        fs::rename(tmp_path, path)?;
         Ok(())
     }
 }
@@ -7,9 +7,12 @@
 //! seperate download. As our save format changes in the future, the fixer
 //! module may become quite big.
 
-use super::{PlotData, PlotLoadError};
+use super::{ChunkData, PlotData, PlotLoadError, TimeOfDay, Tps, WorldSendRate, HEADER_LEN};
 use crate::plot_data::VERSION;
-use std::fs;
+use mchprs_world::TickEntry;
+use serde::Deserialize;
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
 use std::path::Path;
 use tracing::debug;
 
@@ -23,24 +26,99 @@ fn make_backup(path: impl AsRef<Path>) -> Result<(), PlotLoadError> {
     let path = path.as_ref();
     let mut backup_path = path.with_extension("bak");
     if backup_path.exists() {
-        let num = 1;
+        let mut num = 1;
         loop {
             backup_path = path.with_extension(format!("bak.{}", num));
             if !backup_path.exists() {
                 break;
             }
+            num += 1;
         }
     }
     fs::rename(path, backup_path)?;
     Ok(())
 }
 
+/// The plot data of versions 2 and 3, before plots had a time of day
+#[derive(Deserialize)]
+struct PlotDataV3 {
+    tps: Tps,
+    world_send_rate: WorldSendRate,
+    chunk_data: Vec<ChunkData>,
+    pending_ticks: Vec<TickEntry>,
+}
+
+impl From<PlotDataV3> for PlotData {
+    fn from(data: PlotDataV3) -> Self {
+        PlotData {
+            tps: data.tps,
+            world_send_rate: data.world_send_rate,
+            chunk_data: data.chunk_data,
+            pending_ticks: data.pending_ticks,
+            time_of_day: TimeOfDay::default(),
+            entities: Vec::new(),
+        }
+    }
+}
+
+/// The plot data of version 4, before plots had entities
+#[derive(Deserialize)]
+struct PlotDataV4 {
+    tps: Tps,
+    world_send_rate: WorldSendRate,
+    chunk_data: Vec<ChunkData>,
+    pending_ticks: Vec<TickEntry>,
+    time_of_day: TimeOfDay,
+}
+
+impl From<PlotDataV4> for PlotData {
+    fn from(data: PlotDataV4) -> Self {
+        PlotData {
+            tps: data.tps,
+            world_send_rate: data.world_send_rate,
+            chunk_data: data.chunk_data,
+            pending_ticks: data.pending_ticks,
+            time_of_day: data.time_of_day,
+            entities: Vec::new(),
+        }
+    }
+}
+
+fn read_body(path: impl AsRef<Path>) -> Result<Vec<u8>, PlotLoadError> {
+    let mut file = File::open(path)?;
+    file.seek(SeekFrom::Start(HEADER_LEN))?;
+    let mut buf = Vec::new();
+    file.read_to_end(&mut buf)?;
+    Ok(buf)
+}
+
+/// Version 2 plots are stored like version 3, just without compression.
+fn load_v2(path: impl AsRef<Path>) -> Result<PlotData, PlotLoadError> {
+    let data: PlotDataV3 = bincode::deserialize(&read_body(path)?)?;
+    Ok(data.into())
+}
+
+fn load_v3(path: impl AsRef<Path>) -> Result<PlotData, PlotLoadError> {
+    let buf = zstd::decode_all(read_body(path)?.as_slice())?;
+    let data: PlotDataV3 = bincode::deserialize(&buf)?;
+    Ok(data.into())
+}
+
+fn load_v4(path: impl AsRef<Path>) -> Result<PlotData, PlotLoadError> {
+    let buf = zstd::decode_all(read_body(path)?.as_slice())?;
+    let data: PlotDataV4 = bincode::deserialize(&buf)?;
+    Ok(data.into())
+}
+
 pub fn try_fix(path: impl AsRef<Path>, info: FixInfo) -> Result<Option<PlotData>, PlotLoadError> {
     debug!("Trying to fix plot with {:?}", info);
     let result: Option<PlotData> = match info {
         FixInfo::OldVersion {
             version: version @ 0..=1,
         } => return Err(PlotLoadError::ConversionUnavailable(version)),
+        FixInfo::OldVersion { version: 2 } => Some(load_v2(&path)?),
+        FixInfo::OldVersion { version: 3 } => Some(load_v3(&path)?),
+        FixInfo::OldVersion { version: 4 } => Some(load_v4(&path)?),
         _ => None,
     };
 
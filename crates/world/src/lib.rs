@@ -1,8 +1,9 @@
+pub mod memory;
 pub mod storage;
 
 use mchprs_blocks::block_entities::BlockEntity;
 use mchprs_blocks::blocks::Block;
-use mchprs_blocks::BlockPos;
+use mchprs_blocks::{BlockFace, BlockPos};
 use serde::{Deserialize, Serialize};
 use storage::Chunk;
 
@@ -65,10 +66,32 @@ pub trait World {
     /// Returns true if there is a tick entry with `pos`
     fn pending_tick_at(&mut self, pos: BlockPos) -> bool;
 
+    /// Sends the blocks that changed since this was last called to anyone who can see them.
+    /// Block entities have to be set after this, or they won't be shown for the new blocks.
+    fn flush_block_changes(&mut self) {}
+
     fn is_cursed(&self) -> bool {
         false
     }
 
+    /// Returns the time of day in ticks, which daylight detectors output a signal for
+    fn time_of_day(&self) -> u32 {
+        6000
+    }
+
+    /// Returns the number of players that have the container at `pos` open
+    #[allow(unused_variables)]
+    fn container_viewers(&self, pos: BlockPos) -> u8 {
+        0
+    }
+
+    /// Returns the signal a comparator reads from the item frame at `pos` facing `facing`, if
+    /// there is one
+    #[allow(unused_variables)]
+    fn item_frame_override(&self, pos: BlockPos, facing: BlockFace) -> Option<u8> {
+        None
+    }
+
     #[allow(unused_variables)]
     fn play_sound(
         &mut self,
@@ -0,0 +1,217 @@
+//! A world that only lives in memory, for running redstone and world editing logic without a
+//! plot or any players, like in tests.
+
+use crate::storage::Chunk;
+use crate::{TickEntry, TickPriority, World};
+use mchprs_blocks::block_entities::BlockEntity;
+use mchprs_blocks::blocks::Block;
+use mchprs_blocks::{BlockFace, BlockPos};
+use rustc_hash::FxHashMap;
+
+/// A rectangle of chunks, starting at chunk 0, 0 unless it was made with
+/// [`MemoryWorld::with_origin`]. Blocks outside of it are air and can't be set.
+#[derive(Clone)]
+pub struct MemoryWorld {
+    /// The chunk coordinates of the chunk with the lowest x and z
+    pub x: i32,
+    pub z: i32,
+    /// The width of the world along the x axis, in chunks
+    pub width: i32,
+    /// The width of the world along the z axis, in chunks
+    pub depth: i32,
+    pub chunks: Vec<Chunk>,
+    pub to_be_ticked: Vec<TickEntry>,
+    pub time_of_day: u32,
+    /// The signal comparators read from the item frame at a position and facing
+    pub item_frames: FxHashMap<(BlockPos, BlockFace), u8>,
+    /// How many players have the container at a position open
+    pub container_viewers: FxHashMap<BlockPos, u8>,
+}
+
+impl MemoryWorld {
+    pub fn new(width: i32, depth: i32, num_sections: usize) -> MemoryWorld {
+        MemoryWorld::with_origin(0, 0, width, depth, num_sections)
+    }
+
+    /// Creates a world whose first chunk is at chunk `x`, `z`, which lets it have negative
+    /// coordinates
+    pub fn with_origin(x: i32, z: i32, width: i32, depth: i32, num_sections: usize) -> MemoryWorld {
+        let mut chunks = Vec::new();
+        for chunk_x in x..x + width {
+            for chunk_z in z..z + depth {
+                chunks.push(Chunk::empty(chunk_x, chunk_z, num_sections));
+            }
+        }
+        MemoryWorld {
+            x,
+            z,
+            width,
+            depth,
+            chunks,
+            to_be_ticked: Vec::new(),
+            time_of_day: 6000,
+            item_frames: FxHashMap::default(),
+            container_viewers: FxHashMap::default(),
+        }
+    }
+
+    fn chunk_index(&self, x: i32, z: i32) -> Option<usize> {
+        let (x, z) = (x - self.x, z - self.z);
+        if !(0..self.width).contains(&x) || !(0..self.depth).contains(&z) {
+            return None;
+        }
+        Some((x * self.depth + z) as usize)
+    }
+
+    /// Returns the chunk a block is in and the position of the block within it
+    fn chunk_for_block(&self, pos: BlockPos) -> Option<(usize, BlockPos)> {
+        let idx = self.chunk_index(pos.x >> 4, pos.z >> 4)?;
+        let height = self.chunks[idx].sections.len() as i32 * 16;
+        if !(0..height).contains(&pos.y) {
+            return None;
+        }
+        Some((idx, BlockPos::new(pos.x & 0xF, pos.y, pos.z & 0xF)))
+    }
+
+    /// Runs the ticks that are due in the next game tick, in the same order as a plot does.
+    /// `tick_fn` is usually `mchprs_redstone::tick`.
+    pub fn tick(&mut self, mut tick_fn: impl FnMut(Block, &mut MemoryWorld, BlockPos)) {
+        self.to_be_ticked
+            .sort_by_key(|e| (e.ticks_left, e.tick_priority));
+        for pending in &mut self.to_be_ticked {
+            pending.ticks_left = pending.ticks_left.saturating_sub(1);
+        }
+        while self.to_be_ticked.first().map_or(1, |e| e.ticks_left) == 0 {
+            let entry = self.to_be_ticked.remove(0);
+            tick_fn(self.get_block(entry.pos), self, entry.pos);
+        }
+    }
+}
+
+impl World for MemoryWorld {
+    fn get_block_raw(&self, pos: BlockPos) -> u32 {
+        match self.chunk_for_block(pos) {
+            Some((idx, local)) => {
+                self.chunks[idx].get_block(local.x as u32, local.y as u32, local.z as u32)
+            }
+            None => 0,
+        }
+    }
+
+    fn set_block_raw(&mut self, pos: BlockPos, block: u32) -> bool {
+        match self.chunk_for_block(pos) {
+            Some((idx, local)) => {
+                self.chunks[idx].set_block(local.x as u32, local.y as u32, local.z as u32, block)
+            }
+            None => false,
+        }
+    }
+
+    fn delete_block_entity(&mut self, pos: BlockPos) {
+        if let Some((idx, local)) = self.chunk_for_block(pos) {
+            self.chunks[idx].delete_block_entity(local);
+        }
+    }
+
+    fn get_block_entity(&self, pos: BlockPos) -> Option<&BlockEntity> {
+        let (idx, local) = self.chunk_for_block(pos)?;
+        self.chunks[idx].get_block_entity(local)
+    }
+
+    fn set_block_entity(&mut self, pos: BlockPos, block_entity: BlockEntity) {
+        if let Some((idx, local)) = self.chunk_for_block(pos) {
+            self.chunks[idx].set_block_entity(local, block_entity);
+        }
+    }
+
+    fn get_chunk(&self, x: i32, z: i32) -> Option<&Chunk> {
+        self.chunks.get(self.chunk_index(x, z)?)
+    }
+
+    fn get_chunk_mut(&mut self, x: i32, z: i32) -> Option<&mut Chunk> {
+        let idx = self.chunk_index(x, z)?;
+        self.chunks.get_mut(idx)
+    }
+
+    fn schedule_tick(&mut self, pos: BlockPos, delay: u32, priority: TickPriority) {
+        self.to_be_ticked.push(TickEntry {
+            pos,
+            ticks_left: delay,
+            tick_priority: priority,
+        });
+    }
+
+    fn pending_tick_at(&mut self, pos: BlockPos) -> bool {
+        self.to_be_ticked.iter().any(|e| e.pos == pos)
+    }
+
+    fn time_of_day(&self) -> u32 {
+        self.time_of_day
+    }
+
+    fn container_viewers(&self, pos: BlockPos) -> u8 {
+        self.container_viewers.get(&pos).copied().unwrap_or(0)
+    }
+
+    fn item_frame_override(&self, pos: BlockPos, facing: BlockFace) -> Option<u8> {
+        self.item_frames.get(&(pos, facing)).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blocks_and_bounds() {
+        let mut world = MemoryWorld::new(2, 1, 1);
+        let pos = BlockPos::new(20, 3, 5);
+        assert!(world.set_block(pos, Block::Stone {}));
+        assert!(!world.set_block(pos, Block::Stone {}));
+        assert_eq!(world.get_block(pos), Block::Stone {});
+        assert_eq!(
+            world.get_chunk(1, 0).unwrap().get_block(4, 3, 5),
+            world.get_block_raw(pos)
+        );
+
+        // Outside of the chunks and above the build height
+        for pos in [
+            BlockPos::new(-1, 3, 5),
+            BlockPos::new(20, 3, 16),
+            BlockPos::new(20, 16, 5),
+        ] {
+            assert!(!world.set_block(pos, Block::Stone {}));
+            assert_eq!(world.get_block(pos), Block::Air {});
+        }
+    }
+
+    #[test]
+    fn negative_coordinates() {
+        let mut world = MemoryWorld::with_origin(-1, -1, 2, 2, 1);
+        let pos = BlockPos::new(-1, 0, -16);
+        assert!(world.set_block(pos, Block::Stone {}));
+        assert_eq!(world.get_block(pos), Block::Stone {});
+        assert_eq!(
+            world.get_chunk(-1, -1).unwrap().get_block(15, 0, 0),
+            world.get_block_raw(pos)
+        );
+        assert!(!world.set_block(BlockPos::new(-17, 0, 0), Block::Stone {}));
+        assert!(world.get_chunk(1, 0).is_none());
+    }
+
+    #[test]
+    fn ticks_run_in_order() {
+        let mut world = MemoryWorld::new(1, 1, 1);
+        let at = |x| BlockPos::new(x, 0, 0);
+        world.schedule_tick(at(0), 2, TickPriority::Normal);
+        world.schedule_tick(at(1), 1, TickPriority::Normal);
+        world.schedule_tick(at(2), 1, TickPriority::Highest);
+
+        let mut ticked = Vec::new();
+        world.tick(|_, _, pos| ticked.push(pos.x));
+        assert_eq!(ticked, [2, 1]);
+        world.tick(|_, _, pos| ticked.push(pos.x));
+        assert_eq!(ticked, [2, 1, 0]);
+        assert!(world.to_be_ticked.is_empty());
+    }
+}
@@ -2,6 +2,8 @@ use mchprs_blocks::block_entities::BlockEntity;
 use mchprs_blocks::BlockPos;
 use rustc_hash::FxHashMap;
 use std::mem;
+#[cfg(feature = "networking")]
+use std::sync::{Arc, OnceLock};
 
 #[cfg(feature = "networking")]
 use mchprs_network::packets::{
@@ -230,21 +232,60 @@ impl PalettedBitBuffer {
         self.data.bits_per_entry as u8
     }
 
+    /// The palette only grows while entries are set, so it can still have values that were
+    /// overwritten since. The container that is sent gets a palette with only the values that
+    /// are used, and as few bits as it needs.
     #[cfg(feature = "networking")]
     fn encode_packet(&self) -> PalettedContainer {
-        if self.use_palette && self.palette.len() == 1 {
-            PalettedContainer {
+        let entries = self.entries();
+        let mut palette = Vec::new();
+        let mut palette_indices = FxHashMap::default();
+        let indices: Vec<u32> = (0..entries)
+            .map(|i| {
+                let val = self.get_entry(i);
+                *palette_indices.entry(val).or_insert_with(|| {
+                    palette.push(val as i32);
+                    palette.len() as u32 - 1
+                })
+            })
+            .collect();
+
+        if palette.len() == 1 {
+            return PalettedContainer {
                 bits_per_entry: 0,
-                data_array: vec![0],
-                palette: Some(vec![self.palette[0] as i32]),
+                data_array: Vec::new(),
+                palette: Some(palette),
+            };
+        }
+        // ceil(log2(x)), see `Chunk::encode_packet`
+        let bits = 32 - (palette.len() as u32 - 1).leading_zeros();
+        if (bits as u64) < self.direct_threshold {
+            // The client never uses less than 4 bits for an indirect palette
+            let bits = bits.max(4) as u8;
+            let mut data = BitBuffer::create(bits, entries);
+            for (i, index) in indices.into_iter().enumerate() {
+                data.set_entry(i, index);
             }
-        } else {
+            PalettedContainer {
+                bits_per_entry: bits,
+                data_array: data.longs,
+                palette: Some(palette),
+            }
+        } else if !self.use_palette {
             PalettedContainer {
                 bits_per_entry: self.data.bits_per_entry as u8,
                 data_array: self.data.longs.clone(),
-                palette: self
-                    .use_palette
-                    .then(|| self.palette.clone().into_iter().map(|x| x as i32).collect()),
+                palette: None,
+            }
+        } else {
+            let mut data = BitBuffer::create(15, entries);
+            for i in 0..entries {
+                data.set_entry(i, self.get_entry(i));
+            }
+            PalettedContainer {
+                bits_per_entry: 15,
+                data_array: data.longs,
+                palette: None,
             }
         }
     }
@@ -258,6 +299,9 @@ pub struct ChunkSection {
     multi_block: CUpdateSectionBlocks,
     changed_blocks: [i16; 16 * 16 * 16],
     changed: bool,
+    /// The section encoded for the chunk data packet, until it is changed
+    #[cfg(feature = "networking")]
+    encoded: OnceLock<Vec<u8>>,
 }
 
 impl ChunkSection {
@@ -281,6 +325,8 @@ impl ChunkSection {
             },
             changed_blocks: [-1; 16 * 16 * 16],
             changed: false,
+            #[cfg(feature = "networking")]
+            encoded: OnceLock::new(),
         }
     }
 
@@ -310,10 +356,16 @@ impl ChunkSection {
         if changed {
             self.changed = true;
             self.changed_blocks[idx] = block as i16;
+            self.clear_encoded();
         }
         changed
     }
 
+    fn clear_encoded(&mut self) {
+        #[cfg(feature = "networking")]
+        self.encoded.take();
+    }
+
     pub fn data(&self) -> &[u64] {
         self.buffer.data()
     }
@@ -336,19 +388,25 @@ impl ChunkSection {
             new_buffer.set_entry(i, self.buffer.get_entry(i));
         }
         self.buffer = new_buffer;
+        self.clear_encoded();
     }
 
+    /// Returns the section encoded for the chunk data packet. It is only encoded again after
+    /// the section changes.
     #[cfg(feature = "networking")]
-    fn encode_packet(&self) -> CChunkDataSection {
-        CChunkDataSection {
-            block_count: self.block_count as i16,
-            block_states: self.buffer.encode_packet(),
-            biomes: PalettedContainer {
-                bits_per_entry: 0,
-                data_array: vec![],
-                palette: Some(vec![0]),
-            },
-        }
+    fn encoded(&self) -> &[u8] {
+        self.encoded.get_or_init(|| {
+            CChunkDataSection {
+                block_count: self.block_count as i16,
+                block_states: self.buffer.encode_packet(),
+                biomes: PalettedContainer {
+                    bits_per_entry: 0,
+                    data_array: vec![],
+                    palette: Some(vec![0]),
+                },
+            }
+            .encode()
+        })
     }
 
     fn flush(&mut self) {
@@ -358,6 +416,7 @@ impl ChunkSection {
                     self.buffer.set_entry(i, *block as u32);
                 }
             }
+            self.clear_encoded();
         }
     }
 
@@ -380,6 +439,7 @@ impl ChunkSection {
             }
             self.changed = false;
             self.changed_blocks = [-1; 16 * 16 * 16];
+            self.clear_encoded();
         }
         &self.multi_block
     }
@@ -399,6 +459,8 @@ impl Default for ChunkSection {
             },
             changed_blocks: [-1; 16 * 16 * 16],
             changed: false,
+            #[cfg(feature = "networking")]
+            encoded: OnceLock::new(),
         }
     }
 }
@@ -408,12 +470,48 @@ pub struct Chunk {
     pub sections: Vec<ChunkSection>,
     pub x: i32,
     pub z: i32,
+    /// These should be changed with `set_block_entity` and `delete_block_entity`, which make sure
+    /// the chunk data packet is encoded again
     pub block_entities: FxHashMap<BlockPos, BlockEntity>,
+    /// The chunk data packet, until the chunk is changed. It is shared by every player the chunk
+    /// is sent to.
+    #[cfg(feature = "networking")]
+    encoded: OnceLock<Arc<PacketEncoder>>,
 }
 
 impl Chunk {
+    pub fn new(
+        x: i32,
+        z: i32,
+        sections: Vec<ChunkSection>,
+        block_entities: FxHashMap<BlockPos, BlockEntity>,
+    ) -> Chunk {
+        Chunk {
+            sections,
+            x,
+            z,
+            block_entities,
+            #[cfg(feature = "networking")]
+            encoded: OnceLock::new(),
+        }
+    }
+
+    /// Returns the chunk data packet for the chunk. It is only encoded again after the chunk
+    /// changes.
     #[cfg(feature = "networking")]
-    pub fn encode_packet(&self) -> PacketEncoder {
+    pub fn encode_packet(&self) -> Arc<PacketEncoder> {
+        self.encoded
+            .get_or_init(|| Arc::new(self.encode_packet_uncached()))
+            .clone()
+    }
+
+    fn clear_encoded(&mut self) {
+        #[cfg(feature = "networking")]
+        self.encoded.take();
+    }
+
+    #[cfg(feature = "networking")]
+    fn encode_packet_uncached(&self) -> PacketEncoder {
         let block_height = self.sections.len() * 16;
         // Integer arithmetic trick: ceil(log2(x)) can be calculated with 32 - (x -
         // 1).leading_zeros(). See also: https://wiki.vg/Protocol#Chunk_Data_and_Update_Light
@@ -426,9 +524,9 @@ impl Chunk {
             }
         }
 
-        let mut chunk_sections = Vec::new();
+        let mut chunk_section_data = Vec::new();
         for section in &self.sections {
-            chunk_sections.push(section.encode_packet());
+            chunk_section_data.extend_from_slice(section.encoded());
         }
         let mut heightmaps = nbt::Map::new();
         let heightmap_longs: Vec<i64> = heightmap_buffer
@@ -453,7 +551,8 @@ impl Chunk {
             }
         }
         CChunkData {
-            chunk_sections,
+            num_sections: self.sections.len(),
+            chunk_section_data,
             chunk_x: self.x,
             chunk_z: self.z,
             heightmaps,
@@ -464,22 +563,23 @@ impl Chunk {
 
     #[cfg(feature = "networking")]
     pub fn encode_empty_packet(x: i32, z: i32, num_sections: usize) -> PacketEncoder {
+        let empty_section = CChunkDataSection {
+            block_count: 0,
+            block_states: PalettedContainer {
+                bits_per_entry: 0,
+                data_array: vec![],
+                palette: Some(vec![0]),
+            },
+            biomes: PalettedContainer {
+                bits_per_entry: 0,
+                data_array: vec![],
+                palette: Some(vec![0]),
+            },
+        }
+        .encode();
         CChunkData {
-            chunk_sections: (0..num_sections)
-                .map(|_| CChunkDataSection {
-                    block_count: 0,
-                    block_states: PalettedContainer {
-                        bits_per_entry: 0,
-                        data_array: vec![0],
-                        palette: Some(vec![0]),
-                    },
-                    biomes: PalettedContainer {
-                        bits_per_entry: 0,
-                        data_array: vec![0],
-                        palette: Some(vec![0]),
-                    },
-                })
-                .collect(),
+            num_sections,
+            chunk_section_data: empty_section.repeat(num_sections),
             chunk_x: x,
             chunk_z: z,
             heightmaps: nbt::Map::new(),
@@ -506,7 +606,11 @@ impl Chunk {
     pub fn set_block(&mut self, x: u32, y: u32, z: u32, block_id: u32) -> bool {
         let section_y = (y >> 4) as usize;
         let section = &mut self.sections[section_y];
-        section.set_block(x, y & 0xF, z, block_id)
+        let changed = section.set_block(x, y & 0xF, z, block_id);
+        if changed {
+            self.clear_encoded();
+        }
+        changed
     }
 
     pub fn get_block(&self, x: u32, y: u32, z: u32) -> u32 {
@@ -522,30 +626,42 @@ impl Chunk {
     }
 
     pub fn delete_block_entity(&mut self, pos: BlockPos) {
-        self.block_entities.remove(&pos);
+        if self.block_entities.remove(&pos).is_some() {
+            self.clear_encoded();
+        }
     }
 
     pub fn set_block_entity(&mut self, pos: BlockPos, block_entity: BlockEntity) {
         self.block_entities.insert(pos, block_entity);
+        self.clear_encoded();
     }
 
     pub fn compress(&mut self) {
         self.sections
             .iter_mut()
             .for_each(|section| section.compress());
+        self.clear_encoded();
     }
 
     pub fn empty(x: i32, z: i32, num_sections: usize) -> Chunk {
-        Chunk {
-            sections: (0..num_sections).map(|_| Default::default()).collect(),
+        Chunk::new(
             x,
             z,
-            block_entities: FxHashMap::default(),
-        }
+            (0..num_sections).map(|_| Default::default()).collect(),
+            FxHashMap::default(),
+        )
+    }
+
+    /// Whether any section has changes that haven't been written to its buffer yet
+    fn has_changes(&self) -> bool {
+        self.sections.iter().any(|section| section.changed)
     }
 
     #[cfg(feature = "networking")]
     pub fn multi_blocks(&mut self) -> impl Iterator<Item = &CUpdateSectionBlocks> {
+        if self.has_changes() {
+            self.clear_encoded();
+        }
         let x = self.x;
         let z = self.z;
         self.sections
@@ -566,8 +682,69 @@ impl Chunk {
     }
 
     pub fn flush(&mut self) {
+        if self.has_changes() {
+            self.clear_encoded();
+        }
         for section in &mut self.sections {
             section.flush();
         }
     }
 }
+
+#[cfg(feature = "networking")]
+#[test]
+fn section_palette_shrinks() {
+    let mut section = ChunkSection::default();
+    for i in 0..20 {
+        section.set_block(i % 16, i / 16, 0, i + 1);
+    }
+    section.flush();
+    // 20 blocks and air
+    assert_eq!(section.buffer.encode_packet().bits_per_entry, 5);
+    let encoded = section.encoded().to_vec();
+
+    for i in 0..20 {
+        section.set_block(i % 16, i / 16, 0, 0);
+    }
+    section.flush();
+    assert_ne!(section.encoded(), encoded);
+    let container = section.buffer.encode_packet();
+    assert_eq!(container.bits_per_entry, 0);
+    assert_eq!(container.palette, Some(vec![0]));
+    assert!(container.data_array.is_empty());
+}
+
+#[cfg(feature = "networking")]
+#[test]
+fn chunk_packet_is_encoded_again_after_changes() {
+    use mchprs_blocks::block_entities::ContainerType;
+
+    let mut chunk = Chunk::empty(0, 0, 4);
+    let packet = chunk.encode_packet();
+    assert!(Arc::ptr_eq(&packet, &chunk.encode_packet()));
+
+    // Setting a block to what it already is doesn't change anything
+    assert!(!chunk.set_block(1, 2, 3, 0));
+    assert!(Arc::ptr_eq(&packet, &chunk.encode_packet()));
+
+    chunk.set_block(1, 2, 3, 1);
+    let with_block = chunk.encode_packet();
+    assert!(!Arc::ptr_eq(&packet, &with_block));
+    chunk.flush();
+    assert!(!Arc::ptr_eq(&with_block, &chunk.encode_packet()));
+
+    let with_block = chunk.encode_packet();
+    let pos = BlockPos::new(1, 2, 3);
+    chunk.set_block_entity(
+        pos,
+        BlockEntity::Container {
+            comparator_override: 0,
+            inventory: Vec::new(),
+            ty: ContainerType::Barrel,
+        },
+    );
+    let with_block_entity = chunk.encode_packet();
+    assert!(!Arc::ptr_eq(&with_block, &with_block_entity));
+    chunk.delete_block_entity(pos);
+    assert!(!Arc::ptr_eq(&with_block_entity, &chunk.encode_packet()));
+}
@@ -4,8 +4,12 @@ mod regions;
 use anyhow::Result;
 use chrono::{DateTime, Utc};
 use clap::Parser;
+use mchprs_blocks::BlockPos;
+use mchprs_save_data::plot_data::parse_plot_pos;
+use regions::ExportArea;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
 pub const MC_VERSION: &str = "1.20.4";
 pub const MC_DATA_VERSION: i32 = 3700;
@@ -19,6 +23,18 @@ struct Args {
 
     /// Path to minecraft saves directory. A new save will be created.
     output_path: PathBuf,
+
+    /// Only export the given plots, as `x,z`. Can be specified multiple times.
+    #[arg(long, value_parser = parse_plot_pos, allow_hyphen_values = true)]
+    plot: Vec<(i32, i32)>,
+
+    /// First corner of an area to export, as `x,y,z`. Blocks outside of it are left out.
+    #[arg(long, value_parser = BlockPos::from_str, allow_hyphen_values = true, requires = "to")]
+    from: Option<BlockPos>,
+
+    /// Second corner of an area to export, as `x,y,z`.
+    #[arg(long, value_parser = BlockPos::from_str, allow_hyphen_values = true, requires = "from")]
+    to: Option<BlockPos>,
 }
 
 fn main() -> Result<()> {
@@ -29,8 +45,13 @@ fn main() -> Result<()> {
     let output_path = Path::new(&args.output_path).join(&level_name);
     fs::create_dir(&output_path)?;
 
+    let area = ExportArea {
+        plots: args.plot,
+        selection: args.from.zip(args.to).map(|(a, b)| (a.min(b), a.max(b))),
+    };
+
     level::write_level_dat(&level_name, &output_path)?;
-    regions::generate_regions(&args.world_path, &output_path)?;
+    regions::generate_regions(&args.world_path, &output_path, &area)?;
 
     Ok(())
 }
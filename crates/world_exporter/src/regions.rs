@@ -2,9 +2,9 @@ use super::MC_DATA_VERSION;
 use anyhow::Result;
 use mchprs_blocks::blocks::Block;
 use mchprs_blocks::BlockPos;
-use mchprs_save_data::plot_data::{ChunkData, PlotData};
+use mchprs_save_data::plot_data::{parse_plot_pos, ChunkData, PlotData};
 use mchprs_utils::map;
-use mchprs_world::storage::{ChunkSection, PalettedBitBuffer};
+use mchprs_world::storage::{Chunk, ChunkSection, PalettedBitBuffer};
 use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
@@ -48,8 +48,72 @@ fn encode_section(section: &ChunkSection) -> HashMap<String, nbt::Value> {
     }
 }
 
-fn serialize_chunk(chunk_x: i32, chunk_z: i32, chunk: ChunkData) -> Result<Vec<u8>> {
-    let chunk = chunk.load(chunk_x, chunk_z);
+/// Limits what part of the world gets exported
+pub struct ExportArea {
+    /// The plots to export. If empty, every plot is exported.
+    pub plots: Vec<(i32, i32)>,
+    /// The lowest and highest corner of the area to export
+    pub selection: Option<(BlockPos, BlockPos)>,
+}
+
+impl ExportArea {
+    fn contains_plot(&self, plot_x: i32, plot_z: i32) -> bool {
+        self.plots.is_empty() || self.plots.contains(&(plot_x, plot_z))
+    }
+
+    fn contains_chunk(&self, chunk_x: i32, chunk_z: i32) -> bool {
+        match self.selection {
+            Some((first, second)) => {
+                (first.x >> 4..=second.x >> 4).contains(&chunk_x)
+                    && (first.z >> 4..=second.z >> 4).contains(&chunk_z)
+            }
+            None => true,
+        }
+    }
+
+    fn contains_block(&self, pos: BlockPos) -> bool {
+        match self.selection {
+            Some((first, second)) => pos.min(first) == first && pos.max(second) == second,
+            None => true,
+        }
+    }
+}
+
+/// Removes every block in the chunk that is outside of the selected area
+fn clip_chunk(chunk: &mut Chunk, area: &ExportArea) {
+    if area.selection.is_none() {
+        return;
+    }
+    let height = chunk.sections.len() as u32 * 16;
+    for y in 0..height {
+        for z in 0..16 {
+            for x in 0..16 {
+                let pos = BlockPos::new(chunk.x * 16 + x as i32, y as i32, chunk.z * 16 + z as i32);
+                if !area.contains_block(pos) {
+                    chunk.set_block(x, y, z, 0);
+                }
+            }
+        }
+    }
+    let (chunk_x, chunk_z) = (chunk.x, chunk.z);
+    chunk.block_entities.retain(|pos, _| {
+        area.contains_block(BlockPos::new(
+            chunk_x * 16 + pos.x,
+            pos.y,
+            chunk_z * 16 + pos.z,
+        ))
+    });
+    chunk.flush();
+}
+
+fn serialize_chunk(
+    chunk_x: i32,
+    chunk_z: i32,
+    chunk: ChunkData,
+    area: &ExportArea,
+) -> Result<Vec<u8>> {
+    let mut chunk = chunk.load(chunk_x, chunk_z);
+    clip_chunk(&mut chunk, area);
     let mut nbt = nbt::Blob::new();
     nbt.insert("DataVersion", nbt::Value::Int(MC_DATA_VERSION))?;
     nbt.insert("xPos", nbt::Value::Int(chunk_x))?;
@@ -110,7 +174,7 @@ struct Region {
     chunks: HashMap<(u8, u8), Vec<u8>>,
 }
 
-pub fn generate_regions(world_path: &Path, output_path: &Path) -> Result<()> {
+pub fn generate_regions(world_path: &Path, output_path: &Path, area: &ExportArea) -> Result<()> {
     let plots_path = world_path.join("plots");
     let plots_dir = fs::read_dir(plots_path)?;
 
@@ -127,11 +191,13 @@ pub fn generate_regions(world_path: &Path, output_path: &Path) -> Result<()> {
         };
         let Some((plot_x, plot_z)) = file_name
             .strip_prefix('p')
-            .and_then(|name| name.split_once(','))
-            .and_then(|(x, y)| Some((x.parse::<i32>().ok()?, y.parse::<i32>().ok()?)))
+            .and_then(|name| parse_plot_pos(name).ok())
         else {
             continue;
         };
+        if !area.contains_plot(plot_x, plot_z) {
+            continue;
+        }
 
         let plot_data = PlotData::load_from_file(dir_entry.path())?;
         let plot_chunk_width = (plot_data.chunk_data.len() as f64).sqrt() as i32;
@@ -146,9 +212,12 @@ pub fn generate_regions(world_path: &Path, output_path: &Path) -> Result<()> {
             let chunk_idx = chunk_idx as i32;
             let chunk_x = chunk_idx / plot_chunk_width + plot_x * plot_chunk_width;
             let chunk_z = chunk_idx % plot_chunk_width + plot_z * plot_chunk_width;
+            if !area.contains_chunk(chunk_x, chunk_z) {
+                continue;
+            }
             let region_pos = (chunk_x >> 5, chunk_z >> 5);
             let pos_in_region = ((chunk_x & 31) as u8, (chunk_z & 31) as u8);
-            let data = serialize_chunk(chunk_x, chunk_z, chunk_data)?;
+            let data = serialize_chunk(chunk_x, chunk_z, chunk_data, area)?;
             let region = regions.entry(region_pos).or_default();
             region.chunks.insert(pos_in_region, data);
         }
@@ -0,0 +1,337 @@
+use super::regions::RegionReader;
+use anyhow::{bail, Context, Result};
+use mchprs_blocks::block_entities::BlockEntity;
+use mchprs_blocks::blocks::Block;
+use mchprs_blocks::BlockPos;
+use mchprs_save_data::plot_data::{ChunkData, PlotData};
+use mchprs_world::storage::Chunk;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// The lowest y level of a vanilla overworld since 1.18
+const VANILLA_MIN_Y: i32 = -64;
+/// The first data version where block states are no longer packed across longs (20w17a)
+const MIN_DATA_VERSION: i32 = 2529;
+
+pub struct ImportOptions {
+    /// The lowest corner of the area in vanilla coordinates
+    pub first: BlockPos,
+    /// The highest corner of the area in vanilla coordinates
+    pub second: BlockPos,
+    /// Where the lowest corner should be put, relative to the plot
+    pub dest: BlockPos,
+    pub ignore_air: bool,
+}
+
+#[derive(Default)]
+struct ImportStats {
+    imported: usize,
+    out_of_bounds: usize,
+    missing_chunks: usize,
+    /// Block names that have no MCHPRS equivalent and how many times each was found
+    unsupported: BTreeMap<String, usize>,
+}
+
+/// Maps a palette entry (`{Name: "minecraft:...", Properties: {...}}`) to a block state id.
+/// If the block is not supported, its name is returned in the error variant.
+fn map_palette_entry(entry: &nbt::Value) -> Result<Result<u32, String>> {
+    let nbt::Value::Compound(entry) = entry else {
+        bail!("invalid palette entry");
+    };
+    let Some(nbt::Value::String(name)) = entry.get("Name") else {
+        bail!("palette entry is missing a name");
+    };
+    let short_name = name.trim_start_matches("minecraft:");
+    let Some(mut block) = Block::from_name(short_name) else {
+        return Ok(Err(name.clone()));
+    };
+    if let Some(nbt::Value::Compound(props)) = entry.get("Properties") {
+        let props = props
+            .iter()
+            .filter_map(|(k, v)| match v {
+                nbt::Value::String(v) => Some((k.as_str(), v.as_str())),
+                _ => None,
+            })
+            .collect();
+        block.set_properties(props);
+    }
+    Ok(Ok(block.get_id()))
+}
+
+/// Decodes the block states of a chunk section into 4096 palette indices (yzx order)
+fn decode_block_states(palette_len: usize, data: Option<&[i64]>) -> Result<Vec<usize>> {
+    let Some(data) = data else {
+        // A section with a single palette entry does not store any data
+        return Ok(vec![0; 4096]);
+    };
+    let bits = (usize::BITS - (palette_len.max(1) - 1).leading_zeros()).max(4) as usize;
+    let entries_per_long = 64 / bits;
+    if data.len() < 4096usize.div_ceil(entries_per_long) {
+        bail!("chunk section block data is too short");
+    }
+    let mask = (1u64 << bits) - 1;
+    Ok((0..4096)
+        .map(|i| {
+            let long = data[i / entries_per_long] as u64;
+            ((long >> ((i % entries_per_long) * bits)) & mask) as usize
+        })
+        .collect())
+}
+
+struct PlotTarget {
+    plot: PlotData,
+    chunks: Vec<Chunk>,
+    width: i32,
+    height: i32,
+}
+
+impl PlotTarget {
+    fn new(plot: PlotData) -> PlotTarget {
+        let width = (plot.chunk_data.len() as f64).sqrt() as i32;
+        let chunks: Vec<Chunk> = plot
+            .chunk_data
+            .drain(..)
+            .enumerate()
+            .map(|(i, c)| c.load(i as i32 / width, i as i32 % width))
+            .collect();
+        let height = chunks.first().map_or(0, |c| c.sections.len() as i32 * 16);
+        PlotTarget {
+            plot,
+            chunks,
+            width,
+            height,
+        }
+    }
+
+    /// Returns the chunk and chunk-local position for a plot-local position
+    fn locate(&mut self, pos: BlockPos) -> Option<(&mut Chunk, BlockPos)> {
+        let block_width = self.width * 16;
+        if !(0..block_width).contains(&pos.x)
+            || !(0..block_width).contains(&pos.z)
+            || !(0..self.height).contains(&pos.y)
+        {
+            return None;
+        }
+        let idx = ((pos.x >> 4) * self.width + (pos.z >> 4)) as usize;
+        Some((
+            &mut self.chunks[idx],
+            BlockPos::new(pos.x & 0xF, pos.y, pos.z & 0xF),
+        ))
+    }
+
+    fn finish(mut self) -> PlotData {
+        self.plot.chunk_data = self.chunks.iter_mut().map(ChunkData::new).collect();
+        self.plot
+    }
+}
+
+fn import_chunk(
+    chunk_nbt: &nbt::Blob,
+    chunk_x: i32,
+    chunk_z: i32,
+    options: &ImportOptions,
+    target: &mut PlotTarget,
+    stats: &mut ImportStats,
+) -> Result<()> {
+    use nbt::Value;
+
+    let data_version = match chunk_nbt.get("DataVersion") {
+        Some(Value::Int(version)) => *version,
+        _ => 0,
+    };
+    if data_version < MIN_DATA_VERSION {
+        bail!(
+            "chunk {},{} has data version {}, worlds older than 1.16 must be upgraded in vanilla first",
+            chunk_x,
+            chunk_z,
+            data_version
+        );
+    }
+
+    // Before 1.18, chunk data was nested in a `Level` compound with capitalized names
+    let (root, sections_key, block_states_key, palette_key, data_key, block_entities_key) =
+        match chunk_nbt.get("Level") {
+            Some(Value::Compound(level)) => (
+                level,
+                "Sections",
+                None,
+                "Palette",
+                "BlockStates",
+                "TileEntities",
+            ),
+            _ => (
+                &chunk_nbt.content,
+                "sections",
+                Some("block_states"),
+                "palette",
+                "data",
+                "block_entities",
+            ),
+        };
+
+    let offset = options.dest - options.first;
+    let Some(Value::List(sections)) = root.get(sections_key) else {
+        return Ok(());
+    };
+    for section in sections {
+        let Value::Compound(section) = section else {
+            continue;
+        };
+        let section_y = match section.get("Y") {
+            Some(Value::Byte(y)) => *y as i32,
+            _ => continue,
+        };
+        let block_states = match block_states_key {
+            Some(key) => match section.get(key) {
+                Some(Value::Compound(block_states)) => block_states,
+                _ => continue,
+            },
+            None => section,
+        };
+        let Some(Value::List(palette)) = block_states.get(palette_key) else {
+            continue;
+        };
+        let data = match block_states.get(data_key) {
+            Some(Value::LongArray(data)) => Some(data.as_slice()),
+            _ => None,
+        };
+
+        let palette = palette
+            .iter()
+            .map(map_palette_entry)
+            .collect::<Result<Vec<_>>>()?;
+        let indices = decode_block_states(palette.len(), data)
+            .with_context(|| format!("error decoding chunk {},{}", chunk_x, chunk_z))?;
+
+        for (i, palette_idx) in indices.into_iter().enumerate() {
+            let pos = BlockPos::new(
+                (chunk_x << 4) | (i & 0xF) as i32,
+                (section_y << 4) + (i >> 8) as i32,
+                (chunk_z << 4) | ((i >> 4) & 0xF) as i32,
+            );
+            if pos.min(options.first) != options.first || pos.max(options.second) != options.second
+            {
+                continue;
+            }
+            let Some(entry) = palette.get(palette_idx) else {
+                bail!("chunk {},{} has an invalid palette index", chunk_x, chunk_z);
+            };
+            let block_id = match entry {
+                Ok(id) => *id,
+                Err(name) => {
+                    *stats.unsupported.entry(name.clone()).or_default() += 1;
+                    Block::Air {}.get_id()
+                }
+            };
+            if options.ignore_air && block_id == (Block::Air {}).get_id() {
+                continue;
+            }
+            let Some((chunk, local)) = target.locate(pos + offset) else {
+                stats.out_of_bounds += 1;
+                continue;
+            };
+            chunk.set_block(local.x as u32, local.y as u32, local.z as u32, block_id);
+            chunk.delete_block_entity(local);
+            stats.imported += 1;
+        }
+    }
+
+    let Some(Value::List(block_entities)) = root.get(block_entities_key) else {
+        return Ok(());
+    };
+    for block_entity in block_entities {
+        let Value::Compound(block_entity) = block_entity else {
+            continue;
+        };
+        let (Some(Value::Int(x)), Some(Value::Int(y)), Some(Value::Int(z))) = (
+            block_entity.get("x"),
+            block_entity.get("y"),
+            block_entity.get("z"),
+        ) else {
+            continue;
+        };
+        let pos = BlockPos::new(*x, *y, *z);
+        if pos.min(options.first) != options.first || pos.max(options.second) != options.second {
+            continue;
+        }
+        let Some(Value::String(id)) = block_entity.get("id") else {
+            continue;
+        };
+        if let Some(parsed) = BlockEntity::from_nbt(id, block_entity) {
+            if let Some((chunk, local)) = target.locate(pos + offset) {
+                chunk.set_block_entity(local, parsed);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+pub fn import_area(region_path: &Path, plot_path: &Path, options: ImportOptions) -> Result<()> {
+    let plot = if plot_path.exists() {
+        PlotData::load_from_file(plot_path)?
+    } else {
+        let template_path = plot_path.with_file_name("pTEMPLATE");
+        if !template_path.exists() {
+            bail!(
+                "plot file {:?} does not exist, visit the plot on the server once to create it",
+                plot_path
+            );
+        }
+        PlotData::load_from_file(template_path)?
+    };
+    let mut target = PlotTarget::new(plot);
+    let mut stats = ImportStats::default();
+    let mut reader = RegionReader::new(region_path);
+
+    if options.first.y < VANILLA_MIN_Y {
+        println!(
+            "warning: the selected area goes below y={}, which vanilla worlds do not have",
+            VANILLA_MIN_Y
+        );
+    }
+
+    for chunk_x in (options.first.x >> 4)..=(options.second.x >> 4) {
+        for chunk_z in (options.first.z >> 4)..=(options.second.z >> 4) {
+            match reader.read_chunk(chunk_x, chunk_z)? {
+                Some(chunk_nbt) => import_chunk(
+                    &chunk_nbt,
+                    chunk_x,
+                    chunk_z,
+                    &options,
+                    &mut target,
+                    &mut stats,
+                )?,
+                None => stats.missing_chunks += 1,
+            }
+        }
+    }
+
+    target.finish().save_to_file(plot_path)?;
+
+    println!("imported {} blocks into {:?}", stats.imported, plot_path);
+    if stats.missing_chunks > 0 {
+        println!(
+            "warning: {} chunks in the selected area have not been generated and were skipped",
+            stats.missing_chunks
+        );
+    }
+    if stats.out_of_bounds > 0 {
+        println!(
+            "warning: {} blocks did not fit inside of the plot and were skipped",
+            stats.out_of_bounds
+        );
+    }
+    if !stats.unsupported.is_empty() {
+        println!(
+            "warning: the following blocks are not supported by MCHPRS and were replaced with air:"
+        );
+        let mut unsupported: Vec<_> = stats.unsupported.into_iter().collect();
+        unsupported.sort_by(|a, b| b.1.cmp(&a.1));
+        for (name, count) in unsupported {
+            println!("  {}: {}", name, count);
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,69 @@
+mod import;
+mod regions;
+
+use anyhow::{bail, Result};
+use clap::Parser;
+use mchprs_blocks::BlockPos;
+use mchprs_save_data::plot_data::parse_plot_pos;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// MCHPRS world import tool
+///
+/// Copies an area of a vanilla Anvil world into an MCHPRS plot. The server
+/// should not be running while importing, otherwise the plot may be
+/// overwritten when it unloads.
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct Args {
+    /// Path to the vanilla world directory (the one containing `region`).
+    vanilla_path: PathBuf,
+
+    /// Path to MCHPRS world directory.
+    world_path: PathBuf,
+
+    /// The plot to import into, as `x,z`.
+    #[arg(value_parser = parse_plot_pos, allow_hyphen_values = true)]
+    plot: (i32, i32),
+
+    /// First corner of the area to import in vanilla world coordinates, as `x,y,z`.
+    #[arg(long, value_parser = BlockPos::from_str, allow_hyphen_values = true)]
+    from: BlockPos,
+
+    /// Second corner of the area to import in vanilla world coordinates, as `x,y,z`.
+    #[arg(long, value_parser = BlockPos::from_str, allow_hyphen_values = true)]
+    to: BlockPos,
+
+    /// Where the lowest corner of the area will be placed, relative to the plot corner.
+    #[arg(long, value_parser = BlockPos::from_str, default_value = "1,8,1")]
+    dest: BlockPos,
+
+    /// Do not overwrite existing blocks in the plot with air.
+    #[arg(long)]
+    ignore_air: bool,
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+
+    let region_path = args.vanilla_path.join("region");
+    if !region_path.is_dir() {
+        bail!("could not find region directory in {:?}", args.vanilla_path);
+    }
+
+    let (plot_x, plot_z) = args.plot;
+    let plot_path = args
+        .world_path
+        .join("plots")
+        .join(format!("p{},{}", plot_x, plot_z));
+
+    let options = import::ImportOptions {
+        first: args.from.min(args.to),
+        second: args.from.max(args.to),
+        dest: args.dest,
+        ignore_air: args.ignore_air,
+    };
+    import::import_area(&region_path, &plot_path, options)?;
+
+    Ok(())
+}
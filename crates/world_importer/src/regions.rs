@@ -0,0 +1,95 @@
+use anyhow::{bail, Context, Result};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+/// Reads chunks out of the `r.{x}.{z}.mca` files in a region directory.
+/// Region files are read in their entirety the first time a chunk in them is requested.
+pub struct RegionReader {
+    region_path: PathBuf,
+    regions: HashMap<(i32, i32), Option<Vec<u8>>>,
+}
+
+impl RegionReader {
+    pub fn new(region_path: &Path) -> RegionReader {
+        RegionReader {
+            region_path: region_path.to_owned(),
+            regions: HashMap::new(),
+        }
+    }
+
+    fn region_data(&mut self, region_x: i32, region_z: i32) -> Result<Option<&[u8]>> {
+        if !self.regions.contains_key(&(region_x, region_z)) {
+            let file_name = format!("r.{}.{}.mca", region_x, region_z);
+            let path = self.region_path.join(&file_name);
+            let data = if path.exists() {
+                println!("reading region file: {}", file_name);
+                Some(fs::read(path)?)
+            } else {
+                None
+            };
+            self.regions.insert((region_x, region_z), data);
+        }
+        Ok(self.regions[&(region_x, region_z)].as_deref())
+    }
+
+    /// Returns the NBT data of a chunk, or `None` if the chunk was never generated.
+    pub fn read_chunk(&mut self, chunk_x: i32, chunk_z: i32) -> Result<Option<nbt::Blob>> {
+        let Some(region) = self.region_data(chunk_x >> 5, chunk_z >> 5)? else {
+            return Ok(None);
+        };
+
+        let header_offset = 4 * ((chunk_x & 31) as usize + (chunk_z & 31) as usize * 32);
+        if region.len() < 0x2000 {
+            bail!(
+                "region file containing chunk {},{} is truncated",
+                chunk_x,
+                chunk_z
+            );
+        }
+        let sector_offset = ((region[header_offset] as usize) << 16)
+            | ((region[header_offset + 1] as usize) << 8)
+            | region[header_offset + 2] as usize;
+        if sector_offset == 0 {
+            return Ok(None);
+        }
+
+        let start = sector_offset * 0x1000;
+        let Some(header) = region.get(start..start + 5) else {
+            bail!(
+                "chunk {},{} points outside of its region file",
+                chunk_x,
+                chunk_z
+            );
+        };
+        let len = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        let compression = header[4];
+        let Some(data) = region.get(start + 5..start + 4 + len) else {
+            bail!("chunk {},{} data is truncated", chunk_x, chunk_z);
+        };
+
+        let mut reader = Cursor::new(data);
+        let blob = match compression {
+            1 => nbt::Blob::from_gzip_reader(&mut reader),
+            2 => nbt::Blob::from_zlib_reader(&mut reader),
+            3 => nbt::Blob::from_reader(&mut reader),
+            // The high bit indicates the chunk is stored in a separate `c.{x}.{z}.mcc` file
+            c if c & 0x80 != 0 => {
+                bail!(
+                    "chunk {},{} is stored externally, which is unsupported",
+                    chunk_x,
+                    chunk_z
+                )
+            }
+            c => bail!(
+                "chunk {},{} uses unknown compression type {}",
+                chunk_x,
+                chunk_z,
+                c
+            ),
+        };
+        let blob = blob.with_context(|| format!("error reading chunk {},{}", chunk_x, chunk_z))?;
+        Ok(Some(blob))
+    }
+}
@@ -1,21 +1,174 @@
+use anyhow::Result;
+use clap::{Parser, Subcommand};
+use mchprs_blocks::BlockPos;
+use mchprs_core::bench::{self, BenchOptions};
+use mchprs_core::convert;
+use mchprs_core::midi;
+use mchprs_core::replay;
 use mchprs_core::server::MinecraftServer;
+use mchprs_redpiler::CompilerOptions;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use tracing::debug;
-use tracing_subscriber::filter::LevelFilter;
+use tracing_chrome::{ChromeLayer, ChromeLayerBuilder, FlushGuard};
+use tracing_subscriber::filter::{Filtered, LevelFilter, Targets};
 use tracing_subscriber::fmt::writer::MakeWriterExt;
-use tracing_subscriber::EnvFilter;
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::{EnvFilter, Registry};
+
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Write a Chrome trace of plot ticks, packet decoding and worldedit operations to this
+    /// file. It can be opened in Perfetto or `chrome://tracing`.
+    #[arg(long, global = true)]
+    trace: Option<PathBuf>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Run a schematic without starting the server and print how fast it ticks
+    Bench {
+        /// Path to the schematic to run.
+        schematic: PathBuf,
+
+        /// The number of redstone ticks to run.
+        #[arg(long, default_value_t = 10000)]
+        ticks: u64,
+
+        /// Use the base redstone implementation instead of redpiler.
+        #[arg(long)]
+        no_redpiler: bool,
+
+        /// Enable redpiler optimizations.
+        #[arg(long, conflicts_with = "no_redpiler")]
+        optimize: bool,
+
+        /// Use the block at `x,y,z` before ticking, relative to the lowest corner of the schematic.
+        #[arg(long = "use", value_parser = BlockPos::from_str, allow_hyphen_values = true)]
+        use_blocks: Vec<BlockPos>,
+    },
+    /// Convert between `.schem` and `.litematic` schematics and plot save files
+    Convert {
+        /// The file to convert. Files that aren't `.schem` or `.litematic` are read as plot
+        /// save files, like the ones in `world/plots`.
+        input: PathBuf,
+
+        /// Where to write the converted file, in the format of its extension.
+        output: PathBuf,
+    },
+    /// Generate a note block contraption that plays a MIDI file, and save it as a schematic
+    Midi {
+        /// Path to the MIDI file.
+        input: PathBuf,
+
+        /// Where to write the schematic, as `.schem` or `.litematic`.
+        output: PathBuf,
+    },
+    /// Replay a recording made with `/record` with and without redpiler, and show where they
+    /// first have different blocks
+    Replay {
+        /// Path to the recording, usually in the `recordings` folder.
+        recording: PathBuf,
+
+        /// Enable redpiler optimizations.
+        #[arg(long)]
+        optimize: bool,
+    },
+}
+
+type TraceLayer = Filtered<ChromeLayer<Registry>, Targets, Registry>;
+
+/// Creates the layer writing the Chrome trace. The trace is only completely written once the
+/// guard is dropped, so it must be kept alive until the program exits.
+fn trace_layer(path: Option<&Path>) -> (Option<TraceLayer>, Option<FlushGuard>) {
+    let Some(path) = path else {
+        return (None, None);
+    };
+    let (layer, guard) = ChromeLayerBuilder::new()
+        .file(path)
+        .include_args(true)
+        .build();
+    // Dependencies are left out to keep the trace readable
+    let filter = Targets::new().with_target("mchprs", LevelFilter::TRACE);
+    (Some(layer.with_filter(filter)), Some(guard))
+}
+
+/// Logs warnings to stdout for commands that run without the server
+fn init_command_logging(trace_layer: Option<TraceLayer>) {
+    let env_filter = EnvFilter::builder()
+        .with_default_directive(LevelFilter::WARN.into())
+        .with_env_var("MCHPRS_LOG")
+        .from_env_lossy();
+    tracing_subscriber::registry()
+        .with(trace_layer)
+        .with(tracing_subscriber::fmt::layer().with_filter(env_filter))
+        .init();
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+    let (trace_layer, _trace_guard) = trace_layer(args.trace.as_deref());
+
+    match args.command {
+        Some(Command::Bench {
+            schematic,
+            ticks,
+            no_redpiler,
+            optimize,
+            use_blocks,
+        }) => {
+            init_command_logging(trace_layer);
+            let redpiler = (!no_redpiler).then(|| CompilerOptions {
+                optimize,
+                ..Default::default()
+            });
+            let options = BenchOptions {
+                ticks,
+                redpiler,
+                use_blocks,
+            };
+            return bench::run(&schematic, options);
+        }
+        Some(Command::Convert { input, output }) => {
+            init_command_logging(trace_layer);
+            return convert::run(&input, &output);
+        }
+        Some(Command::Midi { input, output }) => {
+            init_command_logging(trace_layer);
+            return midi::run(&input, &output);
+        }
+        Some(Command::Replay {
+            recording,
+            optimize,
+        }) => {
+            init_command_logging(trace_layer);
+            let options = CompilerOptions {
+                optimize,
+                ..Default::default()
+            };
+            return replay::run(&recording, options);
+        }
+        None => {}
+    }
 
-fn main() {
     // Setup logging
     let logfile = tracing_appender::rolling::daily("./logs", "mchprs.log");
     let env_filter = EnvFilter::builder()
         .with_default_directive(LevelFilter::INFO.into())
         .with_env_var("MCHPRS_LOG")
         .from_env_lossy();
-    tracing_subscriber::fmt()
-        .with_writer(logfile.and(std::io::stdout))
-        .with_env_filter(env_filter)
+    tracing_subscriber::registry()
+        .with(trace_layer)
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_writer(logfile.and(std::io::stdout))
+                .with_filter(env_filter),
+        )
         .init();
 
     // Move old log file into logs folder
@@ -30,4 +183,6 @@ fn main() {
     }
 
     MinecraftServer::run();
+    // Returning drops the trace guard, which writes the rest of the trace
+    Ok(())
 }
@@ -0,0 +1,134 @@
+//! Compares the Chunk Data packets sent for known chunks against snapshots of their bytes, so
+//! changes to how chunks and their palettes are encoded can't change what clients receive
+//! without anyone noticing. Like a plot does before sending its chunks, the tests flush the
+//! blocks they set, which are only in the section buffers after that.
+//!
+//! The snapshots are hex dumps in `tests/snapshots/chunk_packets`. After a change that is meant
+//! to change the packets, run the tests with `MCHPRS_BLESS=1` to write them again and review the
+//! difference. Snapshots that don't exist yet are written too, except when the `CI` environment
+//! variable is set.
+
+use mchprs_blocks::blocks::{Block, RedstoneRepeater};
+use mchprs_blocks::BlockDirection;
+use mchprs_network::packets::PacketEncoder;
+use mchprs_world::storage::Chunk;
+use std::env;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+
+const BYTES_PER_LINE: usize = 32;
+
+fn hex_dump(bytes: &[u8]) -> String {
+    let mut dump = String::new();
+    for line in bytes.chunks(BYTES_PER_LINE) {
+        for byte in line {
+            dump.push_str(&format!("{:02x}", byte));
+        }
+        dump.push('\n');
+    }
+    dump
+}
+
+fn check_snapshot(name: &str, packet: &PacketEncoder) {
+    let mut bytes = Vec::new();
+    packet.write_uncompressed(&mut bytes).unwrap();
+    let actual = hex_dump(&bytes);
+
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/snapshots/chunk_packets")
+        .join(format!("{}.hex", name));
+    let bless = env::var_os("MCHPRS_BLESS").is_some();
+    match fs::read_to_string(&path) {
+        Ok(expected) if !bless => {
+            if let Some((line, (expected, actual))) = expected
+                .lines()
+                .zip(actual.lines())
+                .enumerate()
+                .find(|(_, (expected, actual))| expected != actual)
+            {
+                panic!(
+                    "the {} packet is different from {} at byte {}:\n\
+                     expected {}\n\
+                     found    {}\n\
+                     If this is intended, run the tests with MCHPRS_BLESS=1 to update it.",
+                    name,
+                    path.display(),
+                    line * BYTES_PER_LINE,
+                    expected,
+                    actual
+                );
+            }
+            assert_eq!(
+                expected.len(),
+                actual.len(),
+                "the {} packet is a different length than {}",
+                name,
+                path.display()
+            );
+        }
+        Err(err) if err.kind() != ErrorKind::NotFound => {
+            panic!("couldn't read {}: {}", path.display(), err)
+        }
+        _ => {
+            assert!(
+                bless || env::var_os("CI").is_none(),
+                "the snapshot {} is missing",
+                path.display()
+            );
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, actual).unwrap();
+        }
+    }
+}
+
+#[test]
+fn empty_chunk() {
+    check_snapshot("empty", &Chunk::encode_empty_packet(0, 0, 4));
+    check_snapshot("empty_sections", &Chunk::empty(-1, 2, 4).encode_packet());
+}
+
+#[test]
+fn single_block() {
+    let mut chunk = Chunk::empty(0, 0, 4);
+    chunk.set_block(3, 5, 7, Block::Stone {}.get_id());
+    chunk.flush();
+    check_snapshot("single_block", &chunk.encode_packet());
+}
+
+#[test]
+fn redstone_on_a_floor() {
+    let mut chunk = Chunk::empty(-3, -5, 4);
+    for x in 0..16 {
+        for z in 0..16 {
+            chunk.set_block(x, 16, z, Block::Sandstone {}.get_id());
+        }
+    }
+    for (x, delay) in (2..6).zip(1..=4) {
+        let repeater = RedstoneRepeater {
+            delay,
+            facing: BlockDirection::West,
+            ..Default::default()
+        };
+        chunk.set_block(x, 17, 8, Block::RedstoneRepeater { repeater }.get_id());
+    }
+    chunk.set_block(6, 17, 8, Block::RedstoneLamp { lit: false }.get_id());
+    chunk.set_block(15, 63, 15, Block::Glass {}.get_id());
+    chunk.flush();
+    check_snapshot("redstone_on_a_floor", &chunk.encode_packet());
+}
+
+/// Each section has a different number of block states, so each one needs a palette with a
+/// different number of bits, up to the global palette
+#[test]
+fn palette_sizes() {
+    let mut chunk = Chunk::empty(0, 0, 4);
+    for (section, states) in [2, 17, 256, 300].into_iter().enumerate() {
+        for i in 0..states {
+            let (x, y, z) = (i % 16, section as u32 * 16 + i / 256, (i / 16) % 16);
+            chunk.set_block(x, y, z, i + 1);
+        }
+    }
+    chunk.flush();
+    check_snapshot("palette_sizes", &chunk.encode_packet());
+}
@@ -0,0 +1,77 @@
+mod harness;
+use harness::*;
+
+use mchprs_blocks::blocks::Block;
+use mchprs_blocks::items::Item;
+use mchprs_blocks::{BlockFace, BlockPos};
+
+#[test]
+fn login_and_commands() {
+    let server = TestServer::start();
+    let mut alice = server.connect("Alice");
+    assert_eq!(alice.pos, (128.0, 128.0, 128.0));
+    alice.wait_for_message("Entering plot (0, 0)");
+
+    alice.command("plot info");
+    alice.wait_for_message("Plot is not owned by anyone.");
+    alice.command("plot claim");
+    alice.wait_for_message("Claimed plot 0,0");
+    alice.command("plot info");
+    alice.wait_for_message("Plot owner is: ");
+}
+
+#[test]
+fn placed_blocks_are_sent_to_everyone() {
+    let server = TestServer::start();
+    let mut alice = server.connect("Alice");
+    let mut bob = server.connect("Bob");
+    alice.wait_for_message("Entering plot (0, 0)");
+    bob.wait_for_message("Entering plot (0, 0)");
+
+    alice.hold(Item::Stone {});
+    alice.use_item_on(BlockPos::new(130, 127, 128), BlockFace::Top);
+    let placed = BlockPos::new(130, 128, 128);
+    alice.wait_for_block(placed, Block::Stone {});
+    bob.wait_for_block(placed, Block::Stone {});
+}
+
+#[test]
+fn moving_into_another_plot() {
+    let server = TestServer::start();
+    let mut alice = server.connect("Alice");
+    alice.wait_for_message("Entering plot (0, 0)");
+
+    alice.move_to(640.0, 128.0, 128.0);
+    alice.wait_for_message("Entering plot (1, 0)");
+    alice.move_to(128.0, 128.0, 128.0);
+    alice.wait_for_message("Entering plot (0, 0)");
+}
+
+#[test]
+fn impossible_moves_are_undone() {
+    let server = TestServer::start();
+    let mut alice = server.connect("Alice");
+    alice.wait_for_message("Entering plot (0, 0)");
+
+    alice.jump_to(640.0, 128.0, 128.0);
+    alice.wait_until("being moved back", |alice| {
+        alice.pos == (128.0, 128.0, 128.0)
+    });
+}
+
+#[test]
+fn invalid_packets_disconnect_only_the_sender() {
+    let server = TestServer::start();
+    let mut alice = server.connect("Alice");
+    let mut bob = server.connect("Bob");
+    alice.wait_for_message("Entering plot (0, 0)");
+    bob.wait_for_message("Entering plot (0, 0)");
+
+    alice.select_slot(9);
+    let reason = alice.wait_for_disconnect();
+    assert!(reason.contains("invalid packet"), "{}", reason);
+
+    // The plot kept running for everyone else
+    bob.command("plot info");
+    bob.wait_for_message("Plot is not owned by anyone.");
+}
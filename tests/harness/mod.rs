@@ -0,0 +1,393 @@
+//! Runs the server in a temporary folder and connects scripted clients to it, which speak just
+//! enough of the protocol to log in, move around, place blocks and run commands. The clients
+//! keep track of the chat messages and block changes they are sent so tests can wait for them.
+
+use flate2::read::ZlibDecoder;
+use mchprs_blocks::blocks::Block;
+use mchprs_blocks::items::Item;
+use mchprs_blocks::{BlockFace, BlockPos};
+use mchprs_network::packets::{PacketDecoderExt, PacketEncoderExt};
+use std::collections::HashMap;
+use std::io::{Cursor, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use std::{fs, process, thread};
+
+const PROTOCOL_VERSION: i32 = 765;
+/// How long to wait for the server to start or for something to be sent before failing the test
+const TIMEOUT: Duration = Duration::from_secs(30);
+/// How far `move_to` moves in each packet, which is well under what the server accepts
+const MOVE_STEP: f64 = 50.0;
+
+static SERVER_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// A server process with its own world, which is stopped and deleted when this is dropped
+pub struct TestServer {
+    process: Child,
+    dir: PathBuf,
+    port: u16,
+}
+
+impl TestServer {
+    pub fn start() -> TestServer {
+        // The port is only free until the listener is dropped, but nothing else should be taking
+        // ports this quickly
+        let port = TcpListener::bind("127.0.0.1:0")
+            .and_then(|listener| listener.local_addr())
+            .expect("no free port for the test server")
+            .port();
+
+        let id = SERVER_COUNT.fetch_add(1, Ordering::Relaxed);
+        let dir = std::env::temp_dir().join(format!("mchprs-test-{}-{}", process::id(), id));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("Config.toml"),
+            format!("bind_address = \"127.0.0.1:{}\"\n", port),
+        )
+        .unwrap();
+
+        let process = Command::new(env!("CARGO_BIN_EXE_mchprs"))
+            .current_dir(&dir)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .spawn()
+            .expect("failed to start the server");
+        let server = TestServer { process, dir, port };
+
+        let start = Instant::now();
+        while TcpStream::connect(("127.0.0.1", port)).is_err() {
+            assert!(
+                start.elapsed() < TIMEOUT,
+                "the server didn't start listening"
+            );
+            thread::sleep(Duration::from_millis(50));
+        }
+        server
+    }
+
+    pub fn connect(&self, username: &str) -> FakeClient {
+        FakeClient::connect(self.port, username)
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        let _ = self.process.kill();
+        let _ = self.process.wait();
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+struct Packet {
+    id: i32,
+    data: Cursor<Vec<u8>>,
+}
+
+pub struct FakeClient {
+    stream: TcpStream,
+    compressed: bool,
+    sequence: i32,
+    pub username: String,
+    pub pos: (f64, f64, f64),
+    /// The plain text of every chat message received, in order
+    pub messages: Vec<String>,
+    /// The blocks the server has changed, by their state id
+    pub blocks: HashMap<BlockPos, u32>,
+}
+
+impl FakeClient {
+    fn connect(port: u16, username: &str) -> FakeClient {
+        let stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        stream.set_read_timeout(Some(TIMEOUT)).unwrap();
+        let mut client = FakeClient {
+            stream,
+            compressed: false,
+            sequence: 0,
+            username: username.to_string(),
+            pos: (0.0, 0.0, 0.0),
+            messages: Vec::new(),
+            blocks: HashMap::new(),
+        };
+        client.login();
+        client
+    }
+
+    fn login(&mut self) {
+        let mut handshake = Vec::new();
+        handshake.write_varint(PROTOCOL_VERSION);
+        handshake.write_string(255, "127.0.0.1");
+        handshake.write_unsigned_short(self.stream.peer_addr().unwrap().port());
+        handshake.write_varint(2);
+        self.send(0x00, handshake);
+
+        let mut login_start = Vec::new();
+        login_start.write_string(16, &self.username);
+        login_start.write_uuid(0);
+        self.send(0x00, login_start);
+
+        loop {
+            let mut packet = self.receive();
+            match packet.id {
+                0x00 => panic!("disconnected while logging in: {}", read_text(&mut packet)),
+                0x02 => break,
+                0x03 => self.compressed = true,
+                _ => {}
+            }
+        }
+        self.send(0x03, Vec::new());
+
+        // Configuration
+        while self.receive().id != 0x02 {}
+        self.send(0x02, Vec::new());
+
+        self.wait_until("the spawn position", |client| client.pos != (0.0, 0.0, 0.0));
+    }
+
+    fn send(&mut self, id: i32, payload: Vec<u8>) {
+        let mut data = Vec::new();
+        data.write_varint(id);
+        data.write_bytes(&payload);
+        let mut frame = Vec::new();
+        if self.compressed {
+            // Data length: 0 because it isn't compressed
+            frame.write_varint(data.len() as i32 + 1);
+            frame.write_varint(0);
+        } else {
+            frame.write_varint(data.len() as i32);
+        }
+        frame.write_bytes(&data);
+        self.stream.write_all(&frame).unwrap();
+    }
+
+    fn receive(&mut self) -> Packet {
+        let data = self
+            .stream
+            .read_varint()
+            .and_then(|length| self.stream.read_bytes(length as usize));
+        let data = match data {
+            Ok(data) => data,
+            Err(err) => panic!("{} stopped receiving packets: {:?}", self.username, err),
+        };
+        let mut data = Cursor::new(data);
+        if self.compressed && data.read_varint().unwrap() != 0 {
+            let mut decompressed = Vec::new();
+            ZlibDecoder::new(data)
+                .read_to_end(&mut decompressed)
+                .unwrap();
+            data = Cursor::new(decompressed);
+        }
+        Packet {
+            id: data.read_varint().unwrap(),
+            data,
+        }
+    }
+
+    /// Reads play packets, answering keep alives and teleports, until `done` returns true
+    pub fn wait_until(&mut self, what: &str, done: impl Fn(&FakeClient) -> bool) {
+        let start = Instant::now();
+        while !done(self) {
+            if start.elapsed() > TIMEOUT {
+                panic!("{} timed out waiting for {}", self.username, what);
+            }
+            let mut packet = self.receive();
+            self.handle(&mut packet);
+        }
+    }
+
+    fn handle(&mut self, packet: &mut Packet) {
+        let data = &mut packet.data;
+        match packet.id {
+            0x09 => {
+                let (x, y, z) = data.read_position().unwrap();
+                let block = data.read_varint().unwrap() as u32;
+                self.blocks.insert(BlockPos::new(x, y, z), block);
+            }
+            0x1B => panic!("{} was disconnected: {}", self.username, read_text(packet)),
+            0x24 => {
+                let id = data.read_long().unwrap();
+                let mut keep_alive = Vec::new();
+                keep_alive.write_long(id);
+                self.send(0x15, keep_alive);
+            }
+            0x3E => {
+                let x = data.read_double().unwrap();
+                let y = data.read_double().unwrap();
+                let z = data.read_double().unwrap();
+                self.pos = (x, y, z);
+                data.read_float().unwrap();
+                data.read_float().unwrap();
+                data.read_unsigned_byte().unwrap();
+                let mut confirm = Vec::new();
+                confirm.write_varint(data.read_varint().unwrap());
+                self.send(0x00, confirm);
+            }
+            0x47 => {
+                let section = data.read_long().unwrap();
+                let (chunk_x, chunk_y, chunk_z) =
+                    (section >> 42, section << 44 >> 44, section << 22 >> 42);
+                for _ in 0..data.read_varint().unwrap() {
+                    let record = data.read_varlong().unwrap();
+                    let pos = BlockPos::new(
+                        (chunk_x * 16 + ((record >> 8) & 0xF)) as i32,
+                        (chunk_y * 16 + (record & 0xF)) as i32,
+                        (chunk_z * 16 + ((record >> 4) & 0xF)) as i32,
+                    );
+                    self.blocks.insert(pos, (record >> 12) as u32);
+                }
+            }
+            0x69 => {
+                let message = read_text(packet);
+                self.messages.push(message);
+            }
+            _ => {}
+        }
+    }
+
+    /// Reads packets until the server disconnects the client, returning the reason it gave
+    pub fn wait_for_disconnect(&mut self) -> String {
+        let start = Instant::now();
+        loop {
+            if start.elapsed() > TIMEOUT {
+                panic!("{} was never disconnected", self.username);
+            }
+            let mut packet = self.receive();
+            if packet.id == 0x1B {
+                return read_text(&mut packet);
+            }
+            self.handle(&mut packet);
+        }
+    }
+
+    /// Waits for a chat message containing `text` and returns it
+    pub fn wait_for_message(&mut self, text: &str) -> String {
+        let start = self.messages.len();
+        let what = format!("a message containing {:?}", text);
+        self.wait_until(&what, |client| {
+            client.messages[start..].iter().any(|m| m.contains(text))
+        });
+        self.messages[start..]
+            .iter()
+            .find(|m| m.contains(text))
+            .unwrap()
+            .clone()
+    }
+
+    pub fn wait_for_block(&mut self, pos: BlockPos, block: Block) {
+        let what = format!("{:?} at {:?}", block, pos);
+        self.wait_until(&what, |client| {
+            client.blocks.get(&pos) == Some(&block.get_id())
+        });
+    }
+
+    /// Runs a command, without the leading slash
+    pub fn command(&mut self, command: &str) {
+        let mut packet = Vec::new();
+        packet.write_string(256, command);
+        // Timestamp and salt
+        packet.write_long(0);
+        packet.write_long(0);
+        // No argument signatures, message count and acknowledged messages
+        packet.write_varint(0);
+        packet.write_varint(0);
+        packet.write_bytes(&[0; 3]);
+        self.send(0x04, packet);
+    }
+
+    /// Moves to the position in steps that are short enough for the server to accept
+    pub fn move_to(&mut self, x: f64, y: f64, z: f64) {
+        let (from_x, from_y, from_z) = self.pos;
+        let distance = ((x - from_x).powi(2) + (y - from_y).powi(2) + (z - from_z).powi(2)).sqrt();
+        let steps = (distance / MOVE_STEP).ceil().max(1.0) as u32;
+        for step in 1..=steps {
+            let t = step as f64 / steps as f64;
+            self.jump_to(
+                from_x + (x - from_x) * t,
+                from_y + (y - from_y) * t,
+                from_z + (z - from_z) * t,
+            );
+        }
+    }
+
+    /// Sends the position in a single packet, however far away it is
+    pub fn jump_to(&mut self, x: f64, y: f64, z: f64) {
+        let mut packet = Vec::new();
+        packet.write_double(x);
+        packet.write_double(y);
+        packet.write_double(z);
+        packet.write_bool(false);
+        self.send(0x17, packet);
+        self.pos = (x, y, z);
+    }
+
+    /// Puts an item in the selected hotbar slot
+    pub fn hold(&mut self, item: Item) {
+        let mut packet = Vec::new();
+        packet.write_short(36);
+        packet.write_bool(true);
+        packet.write_varint(item.get_id() as i32);
+        packet.write_byte(1);
+        // No NBT
+        packet.write_byte(0);
+        self.send(0x2F, packet);
+    }
+
+    /// Selects a hotbar slot, which vanilla clients only do for slots 0 to 8
+    pub fn select_slot(&mut self, slot: i16) {
+        let mut packet = Vec::new();
+        packet.write_short(slot);
+        self.send(0x2C, packet);
+    }
+
+    /// Right clicks on a face of the block at `pos`, which places the held block next to it
+    pub fn use_item_on(&mut self, pos: BlockPos, face: BlockFace) {
+        self.sequence += 1;
+        let mut packet = Vec::new();
+        // Main hand
+        packet.write_varint(0);
+        packet.write_position(pos.x, pos.y, pos.z);
+        packet.write_varint(face as i32);
+        packet.write_float(0.5);
+        packet.write_float(0.5);
+        packet.write_float(0.5);
+        packet.write_bool(false);
+        packet.write_varint(self.sequence);
+        self.send(0x35, packet);
+    }
+}
+
+/// Reads a text component and returns its text without any formatting
+fn read_text(packet: &mut Packet) -> String {
+    fn plain_text(value: &nbt::Value, text: &mut String) {
+        match value {
+            nbt::Value::String(s) => text.push_str(s),
+            nbt::Value::Compound(compound) => {
+                if let Some(nbt::Value::String(s)) = compound.get("text") {
+                    text.push_str(s);
+                }
+                if let Some(nbt::Value::List(extra)) = compound.get("extra") {
+                    for value in extra {
+                        plain_text(value, text);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let data = &mut packet.data;
+    // Login disconnects still use JSON
+    if packet.id == 0x00 {
+        return data.read_string().unwrap_or_default();
+    }
+    let id = data.read_unsigned_byte().unwrap();
+    let mut text = String::new();
+    match nbt::Value::from_reader(id, data) {
+        Ok(value) => plain_text(&value, &mut text),
+        Err(err) => text = format!("<invalid text component: {}>", err),
+    }
+    text
+}
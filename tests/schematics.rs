@@ -0,0 +1,239 @@
+//! Runs every schematic in `tests/schematics` with the base redstone implementation and with
+//! redpiler, checking that its outputs do what the test case next to it says.
+//!
+//! Each `name.schem` has a `name.toml` with the positions of its inputs, which are levers, and
+//! its outputs, which can be lamps, torches, repeaters or comparators. Positions are relative to
+//! the lowest corner of the schematic. States are written as a string of `0`s and `1`s, one for
+//! each input or output in order. A test case has a truth table, a timeline, or both:
+//!
+//! ```toml
+//! inputs = [[0, 1, 0], [0, 1, 2]]
+//! outputs = [[4, 1, 1]]
+//! # How many redstone ticks each row of the truth table is given to settle
+//! settle_ticks = 10
+//! # The inputs are set row by row, then the outputs are checked once the circuit settled
+//! truth_table = ["00 -> 0", "10 -> 1"]
+//!
+//! # The inputs are set and then the outputs are checked after `tick` redstone ticks have run
+//! [[timeline]]
+//! tick = 0
+//! set = "11"
+//! expect = "0"
+//! ```
+
+use mchprs_blocks::blocks::Block;
+use mchprs_blocks::BlockPos;
+use mchprs_core::bench::HeadlessPlot;
+use mchprs_redpiler::{BackendVariant, CompilerOptions};
+use mchprs_world::World;
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TestCase {
+    inputs: Vec<[i32; 3]>,
+    outputs: Vec<[i32; 3]>,
+    #[serde(default = "default_settle_ticks")]
+    settle_ticks: u64,
+    #[serde(default)]
+    truth_table: Vec<String>,
+    #[serde(default)]
+    timeline: Vec<TimelineEntry>,
+}
+
+fn default_settle_ticks() -> u64 {
+    10
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TimelineEntry {
+    tick: u64,
+    set: Option<String>,
+    expect: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Backend {
+    Redstone,
+    Redpiler(BackendVariant),
+}
+
+const BACKENDS: [Backend; 2] = [Backend::Redstone, Backend::Redpiler(BackendVariant::Direct)];
+
+fn parse_states(states: &str, count: usize) -> Result<Vec<bool>, String> {
+    let states = states
+        .chars()
+        .map(|c| match c {
+            '0' => Ok(false),
+            '1' => Ok(true),
+            _ => Err(format!("{:?} is not a state, only 0 and 1 are", c)),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if states.len() != count {
+        return Err(format!("expected {} states, found {}", count, states.len()));
+    }
+    Ok(states)
+}
+
+fn is_powered(block: Block) -> Option<bool> {
+    Some(match block {
+        Block::RedstoneLamp { lit } => lit,
+        Block::RedstoneTorch { lit } => lit,
+        Block::RedstoneWallTorch { lit, .. } => lit,
+        Block::RedstoneRepeater { repeater } => repeater.powered,
+        Block::RedstoneComparator { comparator } => comparator.powered,
+        _ => return None,
+    })
+}
+
+struct Runner<'a> {
+    plot: HeadlessPlot,
+    case: &'a TestCase,
+    tick: u64,
+}
+
+impl<'a> Runner<'a> {
+    fn new(schematic: &Path, case: &'a TestCase, backend: Backend) -> Result<Runner<'a>, String> {
+        let mut plot = HeadlessPlot::from_schematic(schematic).map_err(|err| err.to_string())?;
+        if let Backend::Redpiler(backend_variant) = backend {
+            plot.start_redpiler(CompilerOptions {
+                backend_variant,
+                ..Default::default()
+            });
+        }
+        Ok(Runner {
+            plot,
+            case,
+            tick: 0,
+        })
+    }
+
+    fn set_inputs(&mut self, states: &[bool]) -> Result<(), String> {
+        self.plot.flush();
+        for (&[x, y, z], &state) in self.case.inputs.iter().zip(states) {
+            let pos = BlockPos::new(x, y, z);
+            match self.plot.world().get_block(pos) {
+                Block::Lever { lever } if lever.powered != state => self.plot.use_block(pos),
+                Block::Lever { .. } => {}
+                block => return Err(format!("the input at {} is {:?}, not a lever", pos, block)),
+            }
+        }
+        Ok(())
+    }
+
+    fn check_outputs(&mut self, expected: &[bool]) -> Result<(), String> {
+        self.plot.flush();
+        for (&[x, y, z], &expected) in self.case.outputs.iter().zip(expected) {
+            let pos = BlockPos::new(x, y, z);
+            let block = self.plot.world().get_block(pos);
+            match is_powered(block) {
+                Some(powered) if powered == expected => {}
+                Some(powered) => {
+                    return Err(format!(
+                        "after {} ticks, the output at {} was {} instead of {}",
+                        self.tick, pos, powered as u8, expected as u8
+                    ))
+                }
+                None => return Err(format!("the output at {} is {:?}", pos, block)),
+            }
+        }
+        Ok(())
+    }
+
+    fn tick_until(&mut self, tick: u64) {
+        while self.tick < tick {
+            self.plot.tick();
+            self.tick += 1;
+        }
+    }
+
+    fn run_truth_table(&mut self) -> Result<(), String> {
+        let (inputs, outputs) = (self.case.inputs.len(), self.case.outputs.len());
+        for row in &self.case.truth_table {
+            let (set, expect) = row
+                .split_once("->")
+                .ok_or_else(|| format!("{:?} is missing a ->", row))?;
+            self.set_inputs(&parse_states(set.trim(), inputs)?)?;
+            self.tick_until(self.tick + self.case.settle_ticks);
+            self.check_outputs(&parse_states(expect.trim(), outputs)?)
+                .map_err(|err| format!("in row {:?}, {}", row, err))?;
+        }
+        Ok(())
+    }
+
+    fn run_timeline(&mut self) -> Result<(), String> {
+        let (inputs, outputs) = (self.case.inputs.len(), self.case.outputs.len());
+        for entry in &self.case.timeline {
+            if entry.tick < self.tick {
+                return Err(format!(
+                    "tick {} comes after tick {}",
+                    entry.tick, self.tick
+                ));
+            }
+            self.tick_until(entry.tick);
+            if let Some(set) = &entry.set {
+                self.set_inputs(&parse_states(set, inputs)?)?;
+            }
+            if let Some(expect) = &entry.expect {
+                self.check_outputs(&parse_states(expect, outputs)?)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Runs the truth table and the timeline, each starting from the schematic as it was saved
+fn run_case(schematic: &Path, case: &TestCase, backend: Backend) -> Result<(), String> {
+    if !case.truth_table.is_empty() {
+        Runner::new(schematic, case, backend)?.run_truth_table()?;
+    }
+    if !case.timeline.is_empty() {
+        Runner::new(schematic, case, backend)?.run_timeline()?;
+    }
+    Ok(())
+}
+
+#[test]
+fn schematics() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/schematics");
+    let mut schematics: Vec<_> = fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "schem"))
+        .collect();
+    schematics.sort();
+    assert!(!schematics.is_empty(), "no schematics in {}", dir.display());
+
+    let mut failures = Vec::new();
+    for schematic in &schematics {
+        let name = schematic.file_stem().unwrap().to_string_lossy();
+        let case = fs::read_to_string(schematic.with_extension("toml"))
+            .map_err(|err| err.to_string())
+            .and_then(|case| toml::from_str::<TestCase>(&case).map_err(|err| err.to_string()));
+        let case = match case {
+            Ok(case) if case.truth_table.is_empty() && case.timeline.is_empty() => {
+                failures.push(format!("{}: has no truth table or timeline", name));
+                continue;
+            }
+            Ok(case) => case,
+            Err(err) => {
+                failures.push(format!("{}: {}", name, err));
+                continue;
+            }
+        };
+        for backend in BACKENDS {
+            if let Err(err) = run_case(schematic, &case, backend) {
+                failures.push(format!("{} with {:?}: {}", name, backend, err));
+            }
+        }
+    }
+    assert!(
+        failures.is_empty(),
+        "{} schematic tests failed:\n{}",
+        failures.len(),
+        failures.join("\n")
+    );
+}
@@ -12,7 +12,7 @@ fn repeater_t_flip_flop(backend: TestBackend) {
     // W RN RE
     // L
 
-    let mut world = TestWorld::new(1);
+    let mut world = TestWorld::new(1, 1, 1);
 
     let output_pos = pos(1, 1, 2);
     let lever_pos = pos(0, 1, 0);
@@ -50,7 +50,7 @@ fn pulse_gen_2t(backend: TestBackend) {
     let output_pos = pos(4, 1, 1);
     let lever_pos = pos(0, 1, 1);
 
-    let mut world = TestWorld::new(1);
+    let mut world = TestWorld::new(1, 1, 1);
 
     make_wire(&mut world, pos(1, 1, 0));
     make_repeater(&mut world, pos(2, 1, 0), 2, BlockDirection::West);
@@ -80,7 +80,7 @@ fn pulse_gen_1t(backend: TestBackend) {
     let output_pos = pos(5, 1, 1);
     let lever_pos = pos(0, 1, 1);
 
-    let mut world = TestWorld::new(1);
+    let mut world = TestWorld::new(1, 1, 1);
 
     make_wire(&mut world, pos(1, 1, 0));
     make_repeater(&mut world, pos(2, 1, 0), 2, BlockDirection::West);